@@ -0,0 +1,128 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::process::{Command, Stdio};
+use crate::task::Task;
+
+/// A shell command run on a task from the task menu
+#[derive(Clone, Debug, PartialEq)]
+pub struct Action {
+    /// The name shown in the menu
+    pub name : String,
+    /// The command, where `{content}`, `{todotxt}`, `{due}` and `{priority}` are replaced by the values of the task
+    pub template : String
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    /// Read an action written `name=command`
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, template)) if !name.trim().is_empty() && !template.trim().is_empty() => {
+                Ok(Action { name : name.trim().to_string(), template : template.trim().to_string() })
+            },
+            _ => Err(format!("\"{}\" is not an action, expected name=command", s))
+        }
+    }
+}
+
+/// Quote a text for `sh`, so it is a single word whatever it contains
+///
+/// Arguments:
+///
+/// * `text` - the text to quote
+pub fn shell_escape(text : &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+impl Action {
+    /// Return the command run on a task, with the placeholders replaced by the quoted values of the task
+    ///
+    /// A missing due date or priority is an empty word, the unknown placeholders are kept
+    ///
+    /// Arguments:
+    ///
+    /// * `task` - the task
+    pub fn command(&self, task : &Task) -> String {
+        lazy_static! {
+            static ref RE_PLACEHOLDER : Regex = Regex::new(r"\{(?P<name>content|todotxt|due|priority)\}").unwrap();
+        }
+        // The values are replaced in a single pass so a value containing a placeholder is not replaced again
+        RE_PLACEHOLDER.replace_all(&self.template, |cap : &Captures| {
+            let value = match &cap["name"] {
+                "content" => task.get_content().to_string(),
+                "todotxt" => task.to_string(),
+                "due" => task.get_due().map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+                _ => task.get_priority().map(String::from).unwrap_or_default()
+            };
+            shell_escape(&value)
+        }).into_owned()
+    }
+
+    /// Run the command on a task with `sh -c` and wait for it
+    ///
+    /// Return an error with the exit code and the first line of the error output if the command fails
+    ///
+    /// Arguments:
+    ///
+    /// * `task` - the task
+    pub fn run(&self, task : &Task) -> Result<(), String> {
+        let output = Command::new("sh")
+                        .arg("-c")
+                        .arg(self.command(task))
+                        .stdin(Stdio::null())
+                        .output()
+                        .map_err(|e| format!("Can't run \"{}\": {}", self.name, e))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let code = output.status.code().map(|c| c.to_string()).unwrap_or_else(|| String::from("none"));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut msg = format!("\"{}\" failed with the exit code {}", self.name, code);
+        if let Some(line) = stderr.lines().find(|l| !l.trim().is_empty()) {
+            msg.push_str(&format!("\n{}", line));
+        }
+        Err(msg)
+    }
+}
+
+#[cfg(test)]
+mod actions_tests {
+    use super::*;
+
+    fn action(s : &str) -> Action {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(action("calendar = gcalcli add {content}"), Action { name : String::from("calendar"), template : String::from("gcalcli add {content}") });
+        assert_eq!(action("tw=task add a=b").template, "task add a=b");
+        assert!("no command".parse::<Action>().is_err());
+        assert!("=echo".parse::<Action>().is_err());
+        assert!("name= ".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn templating() {
+        let task = "(A) say \"hi\" it's $HOME `ls` {due} due:2024-05-01".parse::<Task>().unwrap();
+        let send = action("send=echo {content} | tee {todotxt} {priority} {due} {unknown}");
+        assert_eq!(send.command(&task),
+                   "echo 'say \"hi\" it'\\''s $HOME `ls` {due}' | tee '(A) say \"hi\" it'\\''s $HOME `ls` {due} due:2024-05-01' 'A' '2024-05-01' {unknown}");
+        let task = "plain".parse::<Task>().unwrap();
+        assert_eq!(action("a=x {due}{priority}").command(&task), "x ''''");
+    }
+
+    #[test]
+    fn dry_run() {
+        // The shell gets back the content unchanged
+        let path = std::env::temp_dir().join(format!("rofitodo_action_{}.txt", std::process::id()));
+        let task = "say \"hi\" it's $HOME 100$ `ls` $(pwd) \\n".parse::<Task>().unwrap();
+        action(&format!("save=printf %s {{content}} > '{}'", path.display())).run(&task).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), task.get_content().as_str());
+        std::fs::remove_file(&path).unwrap();
+
+        let err = action("fail=echo oops >&2; exit 4").run(&task).unwrap_err();
+        assert_eq!(err, "\"fail\" failed with the exit code 4\noops");
+    }
+}
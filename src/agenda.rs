@@ -0,0 +1,114 @@
+use std::borrow::Borrow;
+use chrono::{Duration, NaiveDate};
+use crate::task::Task;
+
+/// Group tasks by their due date, for each of the `days` days from `start`
+///
+/// Every day is returned, with no task if nothing is due on it, and the tasks keep their order.
+/// The tasks without due date or due outside of the days are left out
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks to group
+/// * `start` - the first day
+/// * `days` - the number of days
+pub fn group_by_due<T : Borrow<Task>>(tasks : &[T], start : NaiveDate, days : usize) -> Vec<(NaiveDate, Vec<&T>)> {
+    (0..days).map(|day| {
+        let date = start + Duration::days(day as i64);
+        (date, tasks.iter().filter(|t| *(*t).borrow().get_due() == Some(date)).collect())
+    }).collect()
+}
+
+/// The tasks of a project, see `group_by_project`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProjectGroup {
+    /// The project tag, `None` for the tasks without project
+    pub project : Option<String>,
+    /// The indexes of the tasks of the project in the grouped list
+    pub indexes : Vec<usize>
+}
+
+/// Group tasks by project tag
+///
+/// A task with several projects is in the group of each of them, the tasks without project are in a last group.
+/// The groups are sorted by project and the tasks keep their order
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks to group
+pub fn group_by_project<T : Borrow<Task>>(tasks : &[T]) -> Vec<ProjectGroup> {
+    let mut groups : Vec<ProjectGroup> = Vec::new();
+    let mut no_project = Vec::new();
+    for (index, task) in tasks.iter().enumerate() {
+        let projects = task.borrow().get_project_tags();
+        if projects.is_empty() {
+            no_project.push(index);
+        }
+        for project in projects {
+            match groups.iter_mut().find(|g| g.project.as_ref() == Some(project)) {
+                Some(group) => group.indexes.push(index),
+                None => groups.push(ProjectGroup { project : Some(project.clone()), indexes : vec![index] })
+            }
+        }
+    }
+    groups.sort_by(|a, b| a.project.cmp(&b.project));
+    if !no_project.is_empty() {
+        groups.push(ProjectGroup { project : None, indexes : no_project });
+    }
+    groups
+}
+
+#[cfg(test)]
+mod agenda_tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn date(y : i32, m : u32, d : u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn contents<T : Borrow<Task>>(tasks : &[&T]) -> Vec<String> {
+        tasks.iter().map(|t| (*t).borrow().get_content().clone()).collect()
+    }
+
+    #[test]
+    fn group_days() {
+        let lines = ["late due:2024-05-05", "monday due:2024-05-06", "no date", "wednesday due:2024-05-08", "monday again due:2024-05-06", "next week due:2024-05-13"];
+        let tasks = lines.iter().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let groups = group_by_due(&tasks, date(2024, 5, 6), 7);
+        assert_eq!(groups.len(), 7);
+        assert_eq!(groups[0].0, date(2024, 5, 6));
+        assert_eq!(groups[6].0, date(2024, 5, 12));
+        assert_eq!(contents(&groups[0].1), vec!["monday", "monday again"]);
+        assert!(groups[1].1.is_empty());
+        assert_eq!(contents(&groups[2].1), vec!["wednesday"]);
+        assert_eq!(groups.iter().map(|(_, tasks)| tasks.len()).sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn group_shared_tasks() {
+        let tasks = vec![Rc::new("call mom due:2024-12-31".parse::<Task>().unwrap())];
+        let groups = group_by_due(&tasks, date(2024, 12, 30), 3);
+        assert_eq!(groups.iter().map(|(d, _)| *d).collect::<Vec<_>>(), vec![date(2024, 12, 30), date(2024, 12, 31), date(2025, 1, 1)]);
+        assert!(Rc::ptr_eq(groups[1].1[0], &tasks[0]));
+        assert!(group_by_due(&tasks, date(2024, 12, 30), 0).is_empty());
+    }
+
+    #[test]
+    fn group_projects() {
+        let lines = ["paint the fence +house", "sell the bike +garage +yard", "call mom", "fix the door +house +garage", "read"];
+        let tasks = lines.iter().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let groups = group_by_project(&tasks);
+        let project = |name : &str| Some(String::from(name));
+        assert_eq!(groups, vec![
+            ProjectGroup { project : project("garage"), indexes : vec![1, 3] },
+            ProjectGroup { project : project("house"), indexes : vec![0, 3] },
+            ProjectGroup { project : project("yard"), indexes : vec![1] },
+            ProjectGroup { project : None, indexes : vec![2, 4] }
+        ]);
+        // The indexes resolve to the grouped tasks
+        assert_eq!(tasks[groups[0].indexes[1]].get_content(), "fix the door +house +garage");
+        assert_eq!(group_by_project(&tasks[..1]), vec![ProjectGroup { project : project("house"), indexes : vec![0] }]);
+        assert!(group_by_project::<Task>(&[]).is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::task::{Task, SortTaskBy, SortDirection};
+use crate::sibling::SiblingFile;
+use crate::storage::Cipher;
+
+/// The `done.txt` file where the completed tasks are archived
+pub struct Archive {
+    /// The lines of the archive and its path
+    file : SiblingFile
+}
+
+impl Archive {
+    /// Return the path of the archive of a task list
+    ///
+    /// `todo.txt` is archived in `done.txt` in the same directory, the other lists have their own archive
+    /// so several lists can share a directory (`work.done.txt` for `work.txt`)
+    ///
+    /// Arguments:
+    ///
+    /// * `todo_file` - the path of the task list
+    pub fn path_for(todo_file : &Path) -> PathBuf {
+        SiblingFile::path_for(todo_file, "done")
+    }
+
+    /// Load an archive, a missing file is an empty archive
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the archive
+    /// * `cipher` - the encryption of the archive if its name ends in `.gpg`, see `TaskList::archive`
+    pub fn load(path : &Path, cipher : Option<Rc<dyn Cipher>>) -> Result<Self, String> {
+        Ok(Archive { file : SiblingFile::load(path, cipher)? })
+    }
+
+    /// Add a completed task at the end of the archive and save it
+    ///
+    /// Return the line added to the archive
+    ///
+    /// Arguments:
+    ///
+    /// * `task` - the completed task
+    pub fn put(&mut self, task : &Task) -> Result<String, String> {
+        let line = task.to_string();
+        self.file.lines.push(line.clone());
+        self.file.save()?;
+        Ok(line)
+    }
+
+    /// Add completed tasks at the end of the archive and save it once
+    ///
+    /// Arguments:
+    ///
+    /// * `tasks` - the completed tasks, in their order in the archive
+    pub fn put_all<'a>(&mut self, tasks : impl IntoIterator<Item=&'a Task>) -> Result<(), String> {
+        self.file.lines.extend(tasks.into_iter().map(|task| task.to_string()));
+        self.file.save()
+    }
+
+    /// Return the archived tasks with their line index, the last completed first,
+    /// and the number of lines which could not be parsed
+    pub fn tasks(&self) -> (Vec<(usize, Task)>, usize) {
+        let mut tasks = Vec::new();
+        let mut skipped = 0;
+        for (index, line) in self.file.lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match line.parse::<Task>() {
+                Ok(task) => tasks.push((index, task)),
+                Err(_) => skipped += 1
+            }
+        }
+        tasks.sort_by(|a, b| a.1.comp_sorted(&b.1, &SortTaskBy::CompletionDate, SortDirection::Descending));
+        (tasks, skipped)
+    }
+
+    /// Return a line of the archive
+    ///
+    /// Arguments:
+    ///
+    /// * `index` - the index of the line
+    pub fn line(&self, index : usize) -> Option<&str> {
+        self.file.lines.get(index).map(|l| l.as_str())
+    }
+
+    /// Remove a task from the archive and save it
+    ///
+    /// Return the removed task
+    ///
+    /// Arguments:
+    ///
+    /// * `index` - the line index of the task
+    pub fn take(&mut self, index : usize) -> Result<Task, String> {
+        let line = self.file.lines.get(index).ok_or_else(|| String::from("no such archived task"))?;
+        let task = line.parse::<Task>()?;
+        self.file.lines.remove(index);
+        self.file.save()?;
+        Ok(task)
+    }
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    #[test]
+    fn path_for() {
+        assert_eq!(Archive::path_for(Path::new("/home/me/todo.txt")), PathBuf::from("/home/me/done.txt"));
+        assert_eq!(Archive::path_for(Path::new("./work.txt")), PathBuf::from("./work.done.txt"));
+        assert_eq!(Archive::path_for(Path::new("/home/me/todo.txt.gpg")), PathBuf::from("/home/me/done.txt.gpg"));
+    }
+
+    #[test]
+    fn missing_archive() {
+        let archive = Archive::load(&temp_path("done_missing.txt"), None).unwrap();
+        let (tasks, skipped) = archive.tasks();
+        assert!(tasks.is_empty());
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn list_and_restore() {
+        let path = temp_path("done_restore.txt");
+        std::fs::write(&path, "x 2024-01-02 2023-12-01 first +garage pri:B\nx 2024-01-05 2024-01-01 second\nx 2024-13-45 broken\n\nx 2024-01-03 2023-12-31 third due:2024-01-10\n").unwrap();
+        let mut archive = Archive::load(&path, None).unwrap();
+        let (tasks, skipped) = archive.tasks();
+        assert_eq!(skipped, 1);
+        let contents = tasks.iter().map(|(_, t)| t.get_content()).collect::<Vec<_>>();
+        assert_eq!(contents, vec!["second", "third", "first +garage"]);
+
+        let mut task = archive.take(tasks[2].0).unwrap();
+        task.set_not_completed();
+        assert_eq!(task.to_string(), "(B) 2023-12-01 first +garage");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x 2024-01-05 2024-01-01 second\nx 2024-13-45 broken\n\nx 2024-01-03 2023-12-31 third due:2024-01-10\n");
+
+        let mut archive = Archive::load(&path, None).unwrap();
+        let (tasks, _) = archive.tasks();
+        let task = archive.take(tasks[1].0).unwrap();
+        assert_eq!(task.get_custom_tag("due"), Some("2024-01-10"));
+        assert_eq!(task.creation_date, chrono::NaiveDate::from_ymd_opt(2023, 12, 31));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn archive_task() {
+        let path = temp_path("done_put.txt");
+        std::fs::write(&path, "x 2024-01-02 first\n").unwrap();
+        let task = "x 2024-01-05 2024-01-01 sell the bike +garage".parse::<Task>().unwrap();
+        assert_eq!(Archive::load(&path, None).unwrap().put(&task).unwrap(), "x 2024-01-05 2024-01-01 sell the bike +garage");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x 2024-01-02 first\nx 2024-01-05 2024-01-01 sell the bike +garage\n");
+        let tasks = ["x 2024-01-06 2024-01-01 water plants", "x 2024-01-07 2024-01-01 pay rent"].iter().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        Archive::load(&path, None).unwrap().put_all(&tasks).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().skip(2).collect::<Vec<_>>(), vec![tasks[0].to_string(), tasks[1].to_string()]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::{NaiveDate, NaiveDateTime};
+use crate::storage;
+use crate::task::Task;
+use crate::undo::Op;
+
+/// The format of the time starting each line of the log
+const TIME_FORMAT : &str = "%Y-%m-%dT%H:%M:%S";
+
+/// An append-only log of the changes of the task lists, a line per change
+///
+/// The lines are written from the operations recorded in the undo history, like
+/// `2024-05-06T10:03:12 complete "pay rent"`
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditLog {
+    /// The path of the log
+    path : PathBuf,
+    /// The size in bytes after which the log is moved to its rotated file
+    max_size : u64
+}
+
+impl AuditLog {
+    /// Create a log, the file is created by the first change
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the log
+    /// * `max_size` - the size in bytes after which the log is moved to `rofitodo.log.1` and a new log is started
+    pub fn new(path : &Path, max_size : u64) -> Self {
+        AuditLog { path : path.to_path_buf(), max_size }
+    }
+
+    /// Return the path of the log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the path of the previous log, `rofitodo.log.1` for `rofitodo.log`
+    pub fn rotated_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".1");
+        PathBuf::from(path)
+    }
+
+    /// Add the lines of an operation at the end of the log, nothing is written in dry run
+    ///
+    /// The log is moved to its rotated file first if it is larger than the maximum size
+    ///
+    /// Arguments:
+    ///
+    /// * `time` - the time of the operation
+    /// * `prefix` - the text before each change, like `undo `
+    /// * `op` - the operation
+    pub fn write(&self, time : NaiveDateTime, prefix : &str, op : &Op) -> Result<(), String> {
+        let lines = entries(op);
+        if lines.is_empty() || storage::dry_run() {
+            return Ok(());
+        }
+        let error = |e : std::io::Error| format!("Could not write the log {} : {}", self.path.display(), e);
+        if fs::metadata(&self.path).is_ok_and(|m| m.len() >= self.max_size) {
+            fs::rename(&self.path, self.rotated_path()).map_err(error)?;
+        }
+        let time = time.format(TIME_FORMAT);
+        let text = lines.iter().map(|line| format!("{} {}{}\n", time, prefix, line)).collect::<String>();
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path).map_err(error)?;
+        file.write_all(text.as_bytes()).map_err(error)?;
+        file.flush().map_err(error)
+    }
+
+    /// Return the lines of the log and of its rotated file, the oldest first
+    ///
+    /// Arguments:
+    ///
+    /// * `since` - only return the lines of this day and after
+    pub fn read(&self, since : Option<NaiveDate>) -> Result<Vec<String>, String> {
+        let mut lines = Vec::new();
+        for path in [self.rotated_path(), self.path.clone()] {
+            match fs::read_to_string(&path) {
+                Ok(content) => lines.extend(content.lines().map(String::from)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                Err(e) => return Err(format!("Could not read the log {} : {}", path.display(), e))
+            }
+        }
+        if let Some(since) = since {
+            lines.retain(|line| line.get(..10).and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()).is_some_and(|day| day >= since));
+        }
+        Ok(lines)
+    }
+}
+
+/// Return the changes of tasks of an operation as lines of the log, like `edit "old" -> "new"`
+///
+/// The lines of the archive and of the trash are only logged through the tasks removed from or added to the list, a
+/// group of operations is logged by its description
+///
+/// Arguments:
+///
+/// * `op` - the operation
+pub fn entries(op : &Op) -> Vec<String> {
+    match op {
+        Op::Add(task) => vec![format!("add {}", quoted(task))],
+        Op::Remove(task) => vec![format!("delete {}", quoted(task))],
+        Op::Replace(old, new) => vec![match (old.completion, new.completion) {
+            (false, true) => format!("complete {}", quoted(old)),
+            (true, false) => format!("reopen {}", quoted(new)),
+            _ => format!("edit {} -> {}", quoted(old), quoted(new))
+        }],
+        Op::TakeLine(_, _) | Op::PutLine(_, _) => vec![],
+        Op::Batch(ops) => ops.iter().flat_map(entries).collect(),
+        Op::Group(description, _) => vec![description.clone()]
+    }
+}
+
+/// Return the todo.txt line of a task between double quotes
+fn quoted(task : &Task) -> String {
+    format!("\"{}\"", task)
+}
+
+#[cfg(test)]
+mod audit_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    fn task(line : &str) -> Task {
+        line.parse().unwrap()
+    }
+
+    fn time(text : &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, TIME_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn op_entries() {
+        let mut done = task("pay rent");
+        done.set_completed_on(NaiveDate::from_ymd_opt(2024, 5, 6).unwrap());
+        assert_eq!(entries(&Op::Replace(task("pay rent"), done.clone())), vec!["complete \"pay rent\""]);
+        assert_eq!(entries(&Op::Replace(done, task("pay rent"))), vec!["reopen \"pay rent\""]);
+        assert_eq!(entries(&Op::Add(task("(A) call plumber due:2024-05-10"))), vec!["add \"(A) call plumber due:2024-05-10\""]);
+        assert_eq!(entries(&Op::Replace(task("call mom"), task("call mom @phone"))), vec!["edit \"call mom\" -> \"call mom @phone\""]);
+        // A task moved to the trash is deleted from the list
+        let trashed = Op::Batch(vec![Op::PutLine(PathBuf::from("trash.txt"), String::from("buy milk deleted:2024-05-06")), Op::Remove(task("buy milk"))]);
+        assert_eq!(entries(&trashed), vec!["delete \"buy milk\""]);
+        assert!(entries(&Op::TakeLine(PathBuf::from("done.txt"), String::from("x old"))).is_empty());
+        let group = Op::Group(String::from("complete project +home (2 tasks)"), vec![Op::Remove(task("a +home")), Op::Remove(task("b +home"))]);
+        assert_eq!(entries(&group), vec!["complete project +home (2 tasks)"]);
+    }
+
+    #[test]
+    fn write_and_read() {
+        let log = AuditLog::new(&temp_path("audit_read.log"), 1024);
+        log.write(time("2024-04-30T18:00:00"), "", &Op::Add(task("pay rent"))).unwrap();
+        log.write(time("2024-05-06T10:03:12"), "undo ", &Op::Batch(vec![Op::Add(task("a")), Op::Remove(task("b"))])).unwrap();
+        assert_eq!(fs::read_to_string(log.path()).unwrap(),
+                   "2024-04-30T18:00:00 add \"pay rent\"\n2024-05-06T10:03:12 undo add \"a\"\n2024-05-06T10:03:12 undo delete \"b\"\n");
+        assert_eq!(log.read(NaiveDate::from_ymd_opt(2024, 5, 1)).unwrap(), vec!["2024-05-06T10:03:12 undo add \"a\"", "2024-05-06T10:03:12 undo delete \"b\""]);
+        assert_eq!(log.read(None).unwrap().len(), 3);
+        fs::remove_file(log.path()).unwrap();
+    }
+
+    #[test]
+    fn rotation() {
+        let log = AuditLog::new(&temp_path("audit_rotation.log"), 60);
+        for name in ["first task", "second task", "third task"] {
+            log.write(time("2024-05-06T10:00:00"), "", &Op::Add(task(name))).unwrap();
+        }
+        // The first two lines reached the maximum size, the third one started a new log
+        assert_eq!(fs::read_to_string(log.path()).unwrap(), "2024-05-06T10:00:00 add \"third task\"\n");
+        assert_eq!(fs::read_to_string(log.rotated_path()).unwrap().lines().count(), 2);
+        assert_eq!(log.read(None).unwrap().last().unwrap(), "2024-05-06T10:00:00 add \"third task\"");
+        fs::remove_file(log.path()).unwrap();
+        fs::remove_file(log.rotated_path()).unwrap();
+    }
+}
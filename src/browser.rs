@@ -0,0 +1,20 @@
+use std::process::{Command, Stdio};
+
+/// Open a URL in the browser without waiting for it
+///
+/// `$BROWSER` is used when set, `xdg-open` otherwise
+///
+/// Arguments:
+///
+/// * `url` - the URL to open
+pub fn open(url : &str) -> Result<(), String> {
+    let browser = std::env::var("BROWSER").ok().filter(|b| !b.trim().is_empty()).unwrap_or_else(|| String::from("xdg-open"));
+    Command::new(browser.trim())
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Can't open {} with {}: {}", url, browser, e))
+}
@@ -0,0 +1,256 @@
+use chrono::NaiveDate;
+use std::fmt;
+use crate::task::Task;
+
+/// An error returned when a `TaskBuilder` can't build its task
+#[derive(Clone, Debug, PartialEq)]
+pub enum BuildError {
+    /// The task has no content
+    EmptyContent,
+    /// The priority is not an uppercase letter
+    InvalidPriority(char),
+    /// The task is completed but has no creation date, which todo.txt forbids
+    CompletedWithoutCreation,
+    /// A tag can't be written in a todo.txt line
+    InvalidTag {
+        /// The key of the tag, `+` or `@` for a project or context tag
+        key : String,
+        /// The value of the tag
+        value : String
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::EmptyContent => write!(f, "the task is empty"),
+            BuildError::InvalidPriority(p) => write!(f, "invalid priority {}, a priority goes from A to Z", p),
+            BuildError::CompletedWithoutCreation => write!(f, "a completed task needs a creation date"),
+            BuildError::InvalidTag { key, value } => write!(f, "invalid value {} for tag {}", value, key)
+        }
+    }
+}
+
+impl std::error::Error for BuildError { }
+
+impl From<BuildError> for String {
+    fn from(e : BuildError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Build a `Task` from its parts
+///
+/// The project and context tags are added at the end of the content, the custom tags after it,
+/// and `build` checks the task can be written as a todo.txt line:
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use rofitodo::TaskBuilder;
+///
+/// let task = TaskBuilder::new()
+///     .content("call plumber")
+///     .priority('A')
+///     .created(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap())
+///     .due(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap())
+///     .project("house")
+///     .context("phone")
+///     .tag("est", "2h")
+///     .build()
+///     .unwrap();
+/// assert_eq!(task.to_string(), "(A) 2024-01-15 call plumber +house @phone est:2h due:2024-03-01");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TaskBuilder {
+    content : String,
+    priority : Option<char>,
+    due : Option<NaiveDate>,
+    created : Option<NaiveDate>,
+    completed : Option<NaiveDate>,
+    projects : Vec<String>,
+    contexts : Vec<String>,
+    tags : Vec<(String, String)>
+}
+
+impl TaskBuilder {
+    /// Create a builder of a task without content, dates or tags
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the content of the task, its project and context tags are read from it
+    ///
+    /// Arguments:
+    ///
+    /// * `content` - the content of the task, without its dates and custom tags
+    pub fn content(mut self, content : &str) -> Self {
+        self.content = String::from(content);
+        self
+    }
+
+    /// Set the priority of the task, from A to Z
+    pub fn priority(mut self, priority : char) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Set the due date of the task
+    pub fn due(mut self, date : NaiveDate) -> Self {
+        self.due = Some(date);
+        self
+    }
+
+    /// Set the creation date of the task
+    pub fn created(mut self, date : NaiveDate) -> Self {
+        self.created = Some(date);
+        self
+    }
+
+    /// Complete the task at a date, the priority is kept in the `pri` custom tag
+    pub fn completed(mut self, date : NaiveDate) -> Self {
+        self.completed = Some(date);
+        self
+    }
+
+    /// Add a project tag at the end of the content
+    ///
+    /// Arguments:
+    ///
+    /// * `tag` - the name of the tag, without `+`
+    pub fn project(mut self, tag : &str) -> Self {
+        self.projects.push(String::from(tag));
+        self
+    }
+
+    /// Add a context tag at the end of the content
+    ///
+    /// Arguments:
+    ///
+    /// * `tag` - the name of the tag, without `@`
+    pub fn context(mut self, tag : &str) -> Self {
+        self.contexts.push(String::from(tag));
+        self
+    }
+
+    /// Add a custom `key:value` tag, the due and threshold dates and the recurrence are read from their tag
+    ///
+    /// Arguments:
+    ///
+    /// * `key` - the key of the tag
+    /// * `value` - the value of the tag
+    pub fn tag(mut self, key : &str, value : &str) -> Self {
+        self.tags.push((String::from(key), String::from(value)));
+        self
+    }
+
+    /// Check the parts of the task and build it
+    pub fn build(self) -> Result<Task, BuildError> {
+        if self.content.trim().is_empty() {
+            return Err(BuildError::EmptyContent);
+        }
+        match self.priority {
+            Some(p) if !p.is_ascii_uppercase() => return Err(BuildError::InvalidPriority(p)),
+            _ => ()
+        }
+        if self.completed.is_some() && self.created.is_none() {
+            return Err(BuildError::CompletedWithoutCreation);
+        }
+        for (prefix, tag) in self.projects.iter().map(|t| ('+', t)).chain(self.contexts.iter().map(|t| ('@', t))) {
+            if tag.is_empty() || tag.contains(char::is_whitespace) {
+                return Err(BuildError::InvalidTag { key : prefix.to_string(), value : tag.clone() });
+            }
+        }
+        for (key, value) in self.tags.iter() {
+            if Task::check_custom_tag(key, value).is_err() {
+                return Err(BuildError::InvalidTag { key : key.clone(), value : value.clone() });
+            }
+        }
+        Ok(self.assemble())
+    }
+
+    /// Build the task without checking its parts
+    pub(crate) fn assemble(self) -> Task {
+        let mut task = Task::empty();
+        task.set_content(self.content);
+        task.creation_date = self.created;
+        if let Some(date) = self.completed {
+            task.completion = true;
+            task.completion_date = Some(date);
+        }
+        // The priority of a completed task goes to its `pri` tag
+        if let Some(p) = self.priority {
+            task.set_priority(Some(p)).expect("the priority is a letter");
+        }
+        for tag in self.projects.iter() {
+            task.add_project_tag(tag);
+        }
+        for tag in self.contexts.iter() {
+            task.add_context_tag(tag);
+        }
+        for (key, value) in self.tags.iter() {
+            task.set_custom_tag(key, value).expect("the tags are checked by `build`");
+        }
+        if self.due.is_some() {
+            task.set_due(self.due);
+        }
+        task
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use super::*;
+
+    fn date(day : u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    #[test]
+    fn build_task() {
+        let task = TaskBuilder::new().content("pay rent +home").priority('B').due(date(31)).tag("rec", "1m").build().unwrap();
+        assert_eq!(task.to_string(), "(B) pay rent +home rec:1m due:2024-01-31");
+        assert_eq!(task.get_project_tags(), &vec![String::from("home")]);
+        assert_eq!(task.creation_date, None);
+        assert!(task.next_occurrence().is_some());
+        // The tag already in the content is not repeated
+        let task = TaskBuilder::new().content("pay rent +home").project("home").context("bank").build().unwrap();
+        assert_eq!(task.get_content(), "pay rent +home @bank");
+        assert_eq!(task.get_context_tags(), &vec![String::from("bank")]);
+        assert_eq!(task.to_string().parse::<Task>().unwrap().get_content(), task.get_content());
+
+        let task = TaskBuilder::new().content("call mom").priority('A').created(date(2)).completed(date(3)).build().unwrap();
+        assert_eq!(task.to_string(), "x 2024-01-03 2024-01-02 call mom pri:A");
+        assert_eq!(task.get_priority(), Some('A'));
+    }
+
+    #[test]
+    fn empty_content() {
+        assert_eq!(TaskBuilder::new().priority('A').build().unwrap_err(), BuildError::EmptyContent);
+        assert_eq!(TaskBuilder::new().content("  ").project("home").build().unwrap_err(), BuildError::EmptyContent);
+    }
+
+    #[test]
+    fn invalid_priority() {
+        assert_eq!(TaskBuilder::new().content("call mom").priority('a').build().unwrap_err(), BuildError::InvalidPriority('a'));
+        assert_eq!(TaskBuilder::new().content("call mom").priority('1').build().unwrap_err().to_string(),
+                   "invalid priority 1, a priority goes from A to Z");
+    }
+
+    #[test]
+    fn completed_without_creation() {
+        assert_eq!(TaskBuilder::new().content("call mom").completed(date(3)).build().unwrap_err(), BuildError::CompletedWithoutCreation);
+    }
+
+    #[test]
+    fn invalid_tags() {
+        let invalid = |key : &str, value : &str| BuildError::InvalidTag { key : String::from(key), value : String::from(value) };
+        assert_eq!(TaskBuilder::new().content("call mom").project("my house").build().unwrap_err(), invalid("+", "my house"));
+        assert_eq!(TaskBuilder::new().content("call mom").context("").build().unwrap_err(), invalid("@", ""));
+        assert_eq!(TaskBuilder::new().content("call mom").tag("est", "2 h").build().unwrap_err(), invalid("est", "2 h"));
+        assert_eq!(TaskBuilder::new().content("call mom").tag("url", "https://example.com").build().unwrap_err(), invalid("url", "https://example.com"));
+        assert_eq!(TaskBuilder::new().content("call mom").tag("due", "friday").build().unwrap_err(), invalid("due", "friday"));
+        assert_eq!(TaskBuilder::new().content("call mom").tag("at", "14:00").build().unwrap_err(), invalid("at", "14:00"));
+        let timed = TaskBuilder::new().content("call mom").tag("due", "2024-05-06T14:00").build().unwrap();
+        assert_eq!(timed.get_due_time(), chrono::NaiveTime::from_hms_opt(14, 0, 0));
+    }
+}
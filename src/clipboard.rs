@@ -0,0 +1,189 @@
+use lazy_static::lazy_static;
+use std::fmt;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use crate::rofi::in_path;
+
+/// An error while copying to the clipboard or pasting from it
+#[derive(Debug, PartialEq)]
+pub enum ClipboardError {
+    /// None of the tools of wl-clipboard, `xclip` and `xsel` is installed
+    NoTool,
+    /// The clipboard tool could not be run
+    Spawn { program : String, reason : String },
+    /// The clipboard tool failed, with its exit code
+    Failed { program : String, code : Option<i32> }
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClipboardError::NoTool => write!(f, "No clipboard tool found, install wl-clipboard, xclip or xsel"),
+            ClipboardError::Spawn { program, reason } => write!(f, "Can't run {}: {}", program, reason),
+            ClipboardError::Failed { program, code : Some(code) } => write!(f, "{} failed with the exit code {}", program, code),
+            ClipboardError::Failed { program, code : None } => write!(f, "{} was stopped", program)
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+/// A command copying its standard input to the clipboard, or printing the clipboard
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tool {
+    /// The program to run
+    pub program : String,
+    /// The arguments of the program
+    pub args : Vec<String>
+}
+
+impl Tool {
+    /// Create a tool from a program and its arguments
+    fn new(program : &str, args : &[&str]) -> Self {
+        Tool { program : String::from(program), args : args.iter().map(|a| a.to_string()).collect() }
+    }
+}
+
+/// Choose the clipboard tool
+///
+/// `wl-copy` is tried first on Wayland, `xclip` and `xsel` are used on X11 (and by XWayland when `wl-copy` is missing)
+///
+/// Arguments:
+///
+/// * `wayland` - true if running in a Wayland session
+/// * `installed` - return true if a program is installed
+pub fn detect(wayland : bool, installed : impl Fn(&str) -> bool) -> Option<Tool> {
+    first_installed(wayland, Tool::new("wl-copy", &[]), vec![Tool::new("xclip", &["-selection", "clipboard"]), Tool::new("xsel", &["--clipboard", "--input"])], installed)
+}
+
+/// Choose the tool printing the clipboard, in the order of `detect`
+///
+/// Arguments:
+///
+/// * `wayland` - true if running in a Wayland session
+/// * `installed` - return true if a program is installed
+pub fn detect_paste(wayland : bool, installed : impl Fn(&str) -> bool) -> Option<Tool> {
+    first_installed(wayland, Tool::new("wl-paste", &["--no-newline"]),
+                    vec![Tool::new("xclip", &["-selection", "clipboard", "-o"]), Tool::new("xsel", &["--clipboard", "--output"])], installed)
+}
+
+/// Return the first installed tool, the Wayland tool first on Wayland and last on X11
+fn first_installed(wayland : bool, wayland_tool : Tool, mut tools : Vec<Tool>, installed : impl Fn(&str) -> bool) -> Option<Tool> {
+    if wayland {
+        tools.insert(0, wayland_tool);
+    } else {
+        tools.push(wayland_tool);
+    }
+    tools.into_iter().find(|tool| installed(&tool.program))
+}
+
+/// Copy a text to the clipboard with a tool
+///
+/// Arguments:
+///
+/// * `tool` - the clipboard tool
+/// * `text` - the copied text
+pub fn copy_with(tool : &Tool, text : &str) -> Result<(), ClipboardError> {
+    let spawn_error = |e : std::io::Error| ClipboardError::Spawn { program : tool.program.clone(), reason : e.to_string() };
+    let mut child = Command::new(&tool.program)
+                        .args(&tool.args)
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .spawn()
+                        .map_err(spawn_error)?;
+    // The standard input is closed when dropped so the tool stops reading,
+    // a tool exiting before reading everything is reported by its exit code
+    match child.stdin.take().expect("the standard input is piped").write_all(text.as_bytes()) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(spawn_error(e)),
+        _ => ()
+    }
+    let status = child.wait().map_err(spawn_error)?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ClipboardError::Failed { program : tool.program.clone(), code : status.code() })
+    }
+}
+
+/// Return the text of the clipboard printed by a tool, an empty clipboard is an empty text
+///
+/// Arguments:
+///
+/// * `tool` - the clipboard tool
+pub fn paste_with(tool : &Tool) -> Result<String, ClipboardError> {
+    let spawn_error = |e : std::io::Error| ClipboardError::Spawn { program : tool.program.clone(), reason : e.to_string() };
+    let mut child = Command::new(&tool.program)
+                        .args(&tool.args)
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::null())
+                        .spawn()
+                        .map_err(spawn_error)?;
+    let mut bytes = Vec::new();
+    child.stdout.take().expect("the standard output is piped").read_to_end(&mut bytes).map_err(spawn_error)?;
+    let status = child.wait().map_err(spawn_error)?;
+    if status.success() {
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    } else {
+        Err(ClipboardError::Failed { program : tool.program.clone(), code : status.code() })
+    }
+}
+
+/// Return the text of the clipboard, with the tool found at the first paste
+pub fn paste() -> Result<String, ClipboardError> {
+    lazy_static! {
+        static ref TOOL : Option<Tool> = detect_paste(std::env::var_os("WAYLAND_DISPLAY").is_some(), in_path);
+    }
+    TOOL.as_ref().ok_or(ClipboardError::NoTool).and_then(paste_with)
+}
+
+/// Copy a text to the clipboard, with the tool found at the first copy
+///
+/// Arguments:
+///
+/// * `text` - the copied text
+pub fn copy(text : &str) -> Result<(), ClipboardError> {
+    lazy_static! {
+        static ref TOOL : Option<Tool> = detect(std::env::var_os("WAYLAND_DISPLAY").is_some(), in_path);
+    }
+    TOOL.as_ref().ok_or(ClipboardError::NoTool).and_then(|tool| copy_with(tool, text))
+}
+
+#[cfg(test)]
+mod clipboard_tests {
+    use super::*;
+
+    #[test]
+    fn detect_tool() {
+        let all = |_ : &str| true;
+        assert_eq!(detect(true, all).unwrap().program, "wl-copy");
+        assert_eq!(detect(false, all).unwrap(), Tool::new("xclip", &["-selection", "clipboard"]));
+        assert_eq!(detect(false, |p| p == "xsel").unwrap().program, "xsel");
+        assert_eq!(detect(false, |p| p == "wl-copy").unwrap().program, "wl-copy");
+        assert_eq!(detect(true, |p| p == "xclip").unwrap().program, "xclip");
+        assert_eq!(detect(true, |_| false), None);
+        assert_eq!(detect_paste(true, all).unwrap(), Tool::new("wl-paste", &["--no-newline"]));
+        assert_eq!(detect_paste(false, all).unwrap(), Tool::new("xclip", &["-selection", "clipboard", "-o"]));
+        assert_eq!(detect_paste(false, |p| p == "xsel").unwrap().args, vec!["--clipboard", "--output"]);
+    }
+
+    #[test]
+    fn copy_text() {
+        let path = std::env::temp_dir().join(format!("rofitodo_clipboard_{}.txt", std::process::id()));
+        let tool = Tool::new("sh", &["-c", &format!("cat > '{}'", path.display())]);
+        copy_with(&tool, "(A) call mom \"now\" $HOME").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "(A) call mom \"now\" $HOME");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(copy_with(&Tool::new("sh", &["-c", "exit 3"]), "text"), Err(ClipboardError::Failed { program : String::from("sh"), code : Some(3) }));
+        assert!(matches!(copy_with(&Tool::new("rofitodo-missing-tool", &[]), "text"), Err(ClipboardError::Spawn { .. })));
+    }
+
+    #[test]
+    fn paste_text() {
+        assert_eq!(paste_with(&Tool::new("printf", &["call mom\\nabout the party"])), Ok(String::from("call mom\nabout the party")));
+        assert_eq!(paste_with(&Tool::new("true", &[])), Ok(String::new()));
+        assert_eq!(paste_with(&Tool::new("sh", &["-c", "exit 1"])), Err(ClipboardError::Failed { program : String::from("sh"), code : Some(1) }));
+    }
+}
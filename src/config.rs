@@ -0,0 +1,566 @@
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+use chrono::Weekday;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::actions::Action;
+use crate::task::SortTaskBy;
+use crate::template::{self, Template};
+use crate::task_templates::TaskTemplate;
+use crate::escalation::Rule;
+use crate::dates;
+use crate::locale::LANGUAGES;
+use crate::paths::expand_tilde;
+use crate::keys;
+
+/// The settings read from `config.toml`, a missing setting keeps the default value of its command line option
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The task file, `~/` is the home directory
+    #[serde(deserialize_with = "path")]
+    pub todo : Option<PathBuf>,
+    /// The file of the archived tasks of the task file
+    #[serde(deserialize_with = "path")]
+    pub done : Option<PathBuf>,
+    /// The trash of the deleted tasks of the task file
+    #[serde(deserialize_with = "path")]
+    pub trash : Option<PathBuf>,
+    /// The sort keys, by decreasing importance
+    #[serde(deserialize_with = "sort_keys")]
+    pub sort : Option<Vec<SortTaskBy>>,
+    /// Reverse the sort order
+    pub reverse : Option<bool>,
+    /// Show the completed tasks in the main menu, until it is changed in the menu
+    pub show_completed : Option<bool>,
+    /// How the due dates are shown, `absolute` or `relative`
+    #[serde(deserialize_with = "dates")]
+    pub dates : Option<String>,
+    /// The number of days from today after which the relative due dates are shown as dates
+    pub relative_days : Option<i64>,
+    /// The number of characters after which the lines of the tasks are cut
+    pub max_line_width : Option<usize>,
+    /// The template of the lines of the tasks, like `{priority} {content}[ due {due:%d %b}]`
+    #[serde(deserialize_with = "row_format")]
+    pub row_format : Option<Template>,
+    /// Prefix the lines of the tasks with their number in the menu
+    pub numbered_rows : Option<bool>,
+    /// Show the numbers of tasks, the filter and the sort order under the prompt of the main menu
+    pub summary : Option<bool>,
+    /// Write the labels of the recap of a task in plain ASCII instead of bold letters
+    pub ascii : Option<bool>,
+    /// The language of the menus, `en`, `fr` or `de`
+    #[serde(deserialize_with = "lang")]
+    pub lang : Option<String>,
+    /// The chrono format of the dates shown in the menus, like `%d.%m.%Y`
+    #[serde(deserialize_with = "date_format")]
+    pub date_format : Option<String>,
+    /// A TOML file of translations of the texts of the menus, replacing the texts of the language
+    #[serde(deserialize_with = "path")]
+    pub translations : Option<PathBuf>,
+    /// Ask to confirm the deletions and the other destructive actions
+    pub confirm : Option<bool>,
+    /// The number of backups of the task file to keep
+    pub backups : Option<usize>,
+    /// The number of days after which the completed tasks are archived at launch
+    pub auto_archive_days : Option<u32>,
+    /// The number of days after which a task to do is stale, 0 to never mark the tasks
+    pub stale_days : Option<u32>,
+    /// The priorities given to the tasks to do as their due date approaches, written `priority=days`
+    #[serde(deserialize_with = "escalate")]
+    pub escalate : Option<Vec<Rule>>,
+    /// Move the postponed and recurring due dates falling on the weekend to the next working day
+    pub skip_weekends : Option<bool>,
+    /// The days of the weekend, like `["fri", "sat"]`
+    #[serde(deserialize_with = "weekend")]
+    pub weekend : Option<Vec<Weekday>>,
+    /// Commit the task file in its git repository after each save
+    pub git_autocommit : Option<bool>,
+    /// Pull the git repository of the task file at launch
+    pub git_pull : Option<bool>,
+    /// The key the encrypted task files are encrypted for
+    pub gpg_recipient : Option<String>,
+    /// The gpg program decrypting and encrypting the task files
+    pub gpg_program : Option<String>,
+    /// The log of the changes of the tasks
+    #[serde(deserialize_with = "path")]
+    pub audit_log : Option<PathBuf>,
+    /// The size in kilobytes after which the log is rotated
+    pub audit_log_size : Option<u64>,
+    /// The menu shown at launch, `today` or `all`
+    #[serde(deserialize_with = "start_view")]
+    pub start_view : Option<String>,
+    /// The program showing the menus
+    #[serde(deserialize_with = "backend")]
+    pub backend : Option<String>,
+    /// The shell commands of the task menu, written `name=command`
+    #[serde(deserialize_with = "actions")]
+    pub actions : Option<Vec<Action>>,
+    /// The templates of the new tasks by name
+    #[serde(deserialize_with = "templates")]
+    pub templates : BTreeMap<String, TaskTemplate>,
+    /// The colors of the lists
+    pub colors : Colors,
+    /// The custom keys of rofi running an action on the highlighted task
+    pub keys : Keys
+}
+
+/// The colors of the `[colors]` table
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Colors {
+    /// The color of the due date of overdue tasks
+    pub overdue : Option<String>,
+    /// The color of the due date of tasks due today
+    pub today : Option<String>,
+    /// The color of the `(A)` priority
+    pub priority : Option<String>,
+    /// The colors of the priorities by letter
+    #[serde(deserialize_with = "priorities")]
+    pub priorities : BTreeMap<char, String>,
+    /// The colors of the project tags by name
+    pub project : BTreeMap<String, String>,
+    /// The colors of the context tags by name
+    pub context : BTreeMap<String, String>,
+    /// Color the whole line of the tasks instead of their tags
+    pub whole_row : Option<bool>,
+    /// Show the menus with colors and markup
+    pub enabled : Option<bool>
+}
+
+/// The keys of the `[keys]` table, an empty key removes the key of its action
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Keys {
+    /// The key marking the highlighted task as done
+    #[serde(deserialize_with = "key")]
+    pub complete : Option<String>,
+    /// The key deleting the highlighted task
+    #[serde(deserialize_with = "key")]
+    pub delete : Option<String>,
+    /// The key postponing the highlighted task
+    #[serde(deserialize_with = "key")]
+    pub postpone : Option<String>,
+    /// The key editing the highlighted task
+    #[serde(deserialize_with = "key")]
+    pub edit : Option<String>,
+    /// Show the keys in the message of the lists
+    pub help : Option<bool>
+}
+
+impl Keys {
+    /// Return the key of an action, `None` when it is not configured
+    pub fn get(&self, action : keys::KeyAction) -> Option<String> {
+        match action {
+            keys::KeyAction::Complete => self.complete.clone(),
+            keys::KeyAction::Delete => self.delete.clone(),
+            keys::KeyAction::Postpone => self.postpone.clone(),
+            keys::KeyAction::Edit => self.edit.clone()
+        }
+    }
+}
+
+fn key<'de, D : Deserializer<'de>>(d : D) -> Result<Option<String>, D::Error> {
+    let key = String::deserialize(d)?;
+    if !key.is_empty() && !keys::is_key(&key) {
+        return Err(D::Error::custom(format!("`{}` is not a key, expected a key like `Control+Return`", key)));
+    }
+    Ok(Some(key))
+}
+
+fn priorities<'de, D : Deserializer<'de>>(d : D) -> Result<BTreeMap<char, String>, D::Error> {
+    let priorities = BTreeMap::<char, String>::deserialize(d)?;
+    match priorities.keys().find(|p| !p.is_ascii_uppercase()) {
+        Some(p) => Err(D::Error::custom(format!("`{}` is not a priority, a priority goes from A to Z", p))),
+        None => Ok(priorities)
+    }
+}
+
+fn templates<'de, D : Deserializer<'de>>(d : D) -> Result<BTreeMap<String, TaskTemplate>, D::Error> {
+    BTreeMap::<String, String>::deserialize(d)?.into_iter()
+        .map(|(name, line)| Ok((name, line.parse::<TaskTemplate>().map_err(D::Error::custom)?)))
+        .collect()
+}
+
+fn path<'de, D : Deserializer<'de>>(d : D) -> Result<Option<PathBuf>, D::Error> {
+    let path = String::deserialize(d)?;
+    Ok(Some(expand_tilde(Path::new(&path), home::home_dir().as_deref())))
+}
+
+fn sort_keys<'de, D : Deserializer<'de>>(d : D) -> Result<Option<Vec<SortTaskBy>>, D::Error> {
+    Vec::<String>::deserialize(d)?.iter()
+        .map(|name| SortTaskBy::from_name(name).ok_or_else(|| D::Error::custom(format!("unknown sort key `{}`", name))))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Read a text which must be one of `allowed`
+fn one_of<'de, D : Deserializer<'de>>(d : D, allowed : &[&str]) -> Result<Option<String>, D::Error> {
+    let value = String::deserialize(d)?;
+    if allowed.contains(&value.as_str()) {
+        Ok(Some(value))
+    } else {
+        Err(D::Error::custom(format!("`{}` is not one of {}", value, allowed.join(", "))))
+    }
+}
+
+fn dates<'de, D : Deserializer<'de>>(d : D) -> Result<Option<String>, D::Error> {
+    one_of(d, &["absolute", "relative"])
+}
+
+fn row_format<'de, D : Deserializer<'de>>(d : D) -> Result<Option<Template>, D::Error> {
+    Template::parse(&String::deserialize(d)?).map(Some).map_err(D::Error::custom)
+}
+
+fn lang<'de, D : Deserializer<'de>>(d : D) -> Result<Option<String>, D::Error> {
+    one_of(d, &LANGUAGES)
+}
+
+fn date_format<'de, D : Deserializer<'de>>(d : D) -> Result<Option<String>, D::Error> {
+    let date_format = String::deserialize(d)?;
+    if !template::is_date_format(&date_format) {
+        return Err(D::Error::custom(format!("`{}` is not a date format", date_format)));
+    }
+    Ok(Some(date_format))
+}
+
+fn start_view<'de, D : Deserializer<'de>>(d : D) -> Result<Option<String>, D::Error> {
+    one_of(d, &["today", "all"])
+}
+
+fn backend<'de, D : Deserializer<'de>>(d : D) -> Result<Option<String>, D::Error> {
+    one_of(d, &["auto", "rofi", "wofi", "fuzzel", "bemenu", "dmenu", "stdio"])
+}
+
+fn escalate<'de, D : Deserializer<'de>>(d : D) -> Result<Option<Vec<Rule>>, D::Error> {
+    Vec::<String>::deserialize(d)?.iter()
+        .map(|rule| rule.parse::<Rule>().map_err(D::Error::custom))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn weekend<'de, D : Deserializer<'de>>(d : D) -> Result<Option<Vec<Weekday>>, D::Error> {
+    Vec::<String>::deserialize(d)?.iter()
+        .map(|day| dates::parse_weekday(day).map_err(D::Error::custom))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn actions<'de, D : Deserializer<'de>>(d : D) -> Result<Option<Vec<Action>>, D::Error> {
+    Vec::<String>::deserialize(d)?.iter()
+        .map(|action| action.parse::<Action>().map_err(D::Error::custom))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+impl Config {
+    /// Return the path of the configuration file, in `$XDG_CONFIG_HOME` or `~/.config`
+    pub fn path() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home::home_dir().map(|home| home.join(".config")))?;
+        Some(dir.join("rofitodo").join("config.toml"))
+    }
+
+    /// Load the configuration, the default one if the file is missing
+    ///
+    /// Return the configuration and the warnings about the unknown keys.
+    /// Fails naming the line and the key of a value which is not valid.
+    /// The relative paths are relative to the directory of the configuration file
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the configuration file
+    pub fn load(path : &Path) -> Result<(Self, Vec<String>), String> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                let (mut config, warnings) = Self::parse(&content).map_err(|e| format!("{} : {}", path.display(), e))?;
+                let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                for file in vec![&mut config.todo, &mut config.done, &mut config.trash, &mut config.audit_log, &mut config.translations].into_iter().flatten() {
+                    *file = dir.join(&*file);
+                }
+                Ok((config, warnings))
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok((Config::default(), vec![])),
+            Err(e) => Err(format!("Could not read {} : {}", path.display(), e))
+        }
+    }
+
+    /// Read a configuration from the content of a configuration file
+    ///
+    /// Return the configuration and the warnings about the unknown keys, which are written `table.key`
+    ///
+    /// Arguments:
+    ///
+    /// * `content` - the TOML content
+    pub fn parse(content : &str) -> Result<(Self, Vec<String>), String> {
+        let mut warnings = vec![];
+        let config = serde_ignored::deserialize(toml::Deserializer::new(content), |key| {
+            warnings.push(format!("unknown key `{}`, it is ignored", key));
+        }).map_err(|e| e.to_string().trim_end().to_string())?;
+        Ok((config, warnings))
+    }
+}
+
+impl crate::Cli {
+    /// Fill the options not given on the command line with the values of the configuration file
+    ///
+    /// The flags given on the command line are kept, the flags set in the configuration file are added
+    ///
+    /// Arguments:
+    ///
+    /// * `settings` - the configuration file
+    pub fn with_config(mut self, settings : &Config) -> Self {
+        self.reverse |= settings.reverse == Some(true);
+        self.no_confirm |= settings.confirm == Some(false);
+        self.relative_dates |= settings.dates.as_deref() == Some("relative");
+        self.relative_days = self.relative_days.or(settings.relative_days);
+        self.backups = self.backups.or(settings.backups);
+        self.auto_archive_days = self.auto_archive_days.or(settings.auto_archive_days);
+        self.stale_days = self.stale_days.or(settings.stale_days);
+        self.git_autocommit |= settings.git_autocommit == Some(true);
+        self.git_pull |= settings.git_pull == Some(true);
+        self.gpg_recipient = self.gpg_recipient.or_else(|| settings.gpg_recipient.clone());
+        self.gpg_program = self.gpg_program.or_else(|| settings.gpg_program.clone());
+        self.audit_log = self.audit_log.or_else(|| settings.audit_log.clone());
+        self.audit_log_size = self.audit_log_size.or(settings.audit_log_size);
+        self.start_view = self.start_view.or_else(|| settings.start_view.clone());
+        self.backend = self.backend.or_else(|| settings.backend.clone());
+        self.overdue_color = self.overdue_color.or_else(|| settings.colors.overdue.clone());
+        self.today_color = self.today_color.or_else(|| settings.colors.today.clone());
+        self.priority_color = self.priority_color.or_else(|| settings.colors.priority.clone());
+        self.max_line_width = self.max_line_width.or(settings.max_line_width);
+        self.row_format = self.row_format.or_else(|| settings.row_format.clone());
+        self.numbered_rows |= settings.numbered_rows == Some(true);
+        self.no_summary |= settings.summary == Some(false);
+        self.ascii |= settings.ascii == Some(true);
+        self.lang = self.lang.or_else(|| settings.lang.clone());
+        self.date_format = self.date_format.or_else(|| settings.date_format.clone());
+        self.color_rows |= settings.colors.whole_row == Some(true);
+        self.no_color |= settings.colors.enabled == Some(false);
+        if self.actions.is_empty() {
+            self.actions = settings.actions.clone().unwrap_or_default();
+        }
+        if self.escalate.is_empty() {
+            self.escalate = settings.escalate.clone().unwrap_or_default();
+        }
+        self.skip_weekends |= settings.skip_weekends == Some(true);
+        if self.weekend.is_empty() {
+            self.weekend = settings.weekend.clone().unwrap_or_default();
+        }
+        self
+    }
+}
+
+/// Read a translation file, its texts with their key, like `menu.mark_as_done` for `mark_as_done = "✔ erledigt"`
+/// in a `[menu]` table
+///
+/// Arguments:
+///
+/// * `path` - the path of the translation file
+pub fn load_translations(path : &Path) -> Result<Vec<(String, String)>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Could not read {} : {}", path.display(), e))?;
+    let table = content.parse::<toml::Table>().map_err(|e| format!("{} : {}", path.display(), e.to_string().trim_end()))?;
+    let mut texts = vec![];
+    add_texts(&mut texts, "", table).map_err(|key| format!("{} : the translation of `{}` is not a string", path.display(), key))?;
+    Ok(texts)
+}
+
+/// Add the texts of a table of a translation file, their key starting with `prefix`, fails with the key of a value
+/// which is not a text
+fn add_texts(texts : &mut Vec<(String, String)>, prefix : &str, table : toml::Table) -> Result<(), String> {
+    for (key, value) in table {
+        let key = format!("{}{}", prefix, key);
+        match value {
+            toml::Value::String(text) => texts.push((key, text)),
+            toml::Value::Table(table) => add_texts(texts, &format!("{}.", key), table)?,
+            _ => return Err(key)
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn full_config() {
+        let content = r##"
+# Settings of RofiTodo
+todo = "~/notes/todo.txt"
+done = "/tmp/done.txt"
+trash = '/tmp/trash.txt'
+sort = ["due", "priority"]   # the most urgent first
+reverse = false
+show_completed = true
+dates = "relative"
+relative_days = 30
+max_line_width = 80
+row_format = "[{priority} ]{content}[ # due {due:%d %b}]"
+numbered_rows = true
+summary = false
+ascii = true
+lang = "de"
+date_format = "%d.%m.%Y"
+translations = "/tmp/de.toml"
+confirm = false
+backups = 5
+auto_archive_days = 14
+stale_days = 60
+escalate = ["B=2", "A=0"]
+skip_weekends = true
+weekend = ["fri", "saturday"]
+git_autocommit = true
+git_pull = false
+gpg_recipient = "me@example.com"
+gpg_program = "gpg2"
+audit_log = "/tmp/rofitodo.log"
+audit_log_size = 256
+start_view = "all"
+backend = "fuzzel"
+actions = [
+    "calendar=gcalcli add --title {content}",  # a calendar event
+    'later=echo {todotxt} >> ~/later.txt',
+]
+
+[colors]
+overdue = "#ff0000"
+today = "orange"
+priority = "yellow"
+whole_row = true
+
+[colors.project]
+work = "#5294e2"
+home = "green"
+
+[colors.context]
+phone = "purple"
+
+[colors.priorities]
+B = "blue"
+
+[keys]
+complete = "Alt+Return"
+delete = ""
+help = true
+
+[templates]
+review = "review PR #{input} @work +reviews due:+2d"
+standup = "standup notes +team due:tomorrow"
+"##;
+        let (config, warnings) = Config::parse(content).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(config.todo, Some(home::home_dir().unwrap().join("notes/todo.txt")));
+        assert_eq!(config.done, Some(PathBuf::from("/tmp/done.txt")));
+        assert_eq!(config.trash, Some(PathBuf::from("/tmp/trash.txt")));
+        assert_eq!(config.sort, Some(vec![SortTaskBy::DueDate, SortTaskBy::Priority]));
+        assert_eq!((config.reverse, config.show_completed, config.confirm), (Some(false), Some(true), Some(false)));
+        assert_eq!((config.dates.as_deref(), config.relative_days, config.max_line_width), (Some("relative"), Some(30), Some(80)));
+        assert_eq!((config.numbered_rows, config.summary, config.ascii), (Some(true), Some(false), Some(true)));
+        assert_eq!((config.lang.as_deref(), config.date_format.as_deref()), (Some("de"), Some("%d.%m.%Y")));
+        assert_eq!(config.translations, Some(PathBuf::from("/tmp/de.toml")));
+        assert_eq!(config.row_format, Some(Template::parse("[{priority} ]{content}[ # due {due:%d %b}]").unwrap()));
+        assert_eq!((config.backups, config.start_view.as_deref(), config.backend.as_deref()), (Some(5), Some("all"), Some("fuzzel")));
+        assert_eq!((config.auto_archive_days, config.stale_days, config.git_autocommit, config.git_pull), (Some(14), Some(60), Some(true), Some(false)));
+        assert_eq!(config.escalate, Some(vec![Rule { priority : 'B', days : 2 }, Rule { priority : 'A', days : 0 }]));
+        assert_eq!((config.skip_weekends, config.weekend), (Some(true), Some(vec![Weekday::Fri, Weekday::Sat])));
+        assert_eq!((config.gpg_recipient.as_deref(), config.gpg_program.as_deref()), (Some("me@example.com"), Some("gpg2")));
+        assert_eq!((config.audit_log, config.audit_log_size), (Some(PathBuf::from("/tmp/rofitodo.log")), Some(256)));
+        let actions = config.actions.unwrap();
+        assert_eq!(actions.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), vec!["calendar", "later"]);
+        assert_eq!(actions[1].template, "echo {todotxt} >> ~/later.txt");
+        assert_eq!((config.colors.overdue.as_deref(), config.colors.today.as_deref(), config.colors.priority.as_deref()), (Some("#ff0000"), Some("orange"), Some("yellow")));
+        assert_eq!(config.colors.project.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect::<Vec<_>>(), vec![("home", "green"), ("work", "#5294e2")]);
+        assert_eq!(config.colors.context.get("phone").map(String::as_str), Some("purple"));
+        assert_eq!(config.colors.priorities.get(&'B').map(String::as_str), Some("blue"));
+        assert_eq!((config.colors.whole_row, config.colors.enabled), (Some(true), None));
+        assert_eq!((config.keys.complete.as_deref(), config.keys.delete.as_deref(), config.keys.edit.as_deref()), (Some("Alt+Return"), Some(""), None));
+        assert_eq!(config.keys.help, Some(true));
+        assert_eq!(config.templates.keys().collect::<Vec<_>>(), vec!["review", "standup"]);
+        assert_eq!(config.templates.get("review"), Some(&"review PR #{input} @work +reviews due:+2d".parse().unwrap()));
+    }
+
+    #[test]
+    fn dotted_keys() {
+        let (config, warnings) = Config::parse("colors.project.work = \"#5294e2\"\n").unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(config.colors.project.get("work").map(String::as_str), Some("#5294e2"));
+        let (config, _) = Config::parse("[colors]\npriorities.A = \"red\"\ncontext = { phone = \"purple\" }\n").unwrap();
+        assert_eq!(config.colors.priorities.get(&'A').map(String::as_str), Some("red"));
+        assert_eq!(config.colors.context.get("phone").map(String::as_str), Some("purple"));
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let (config, warnings) = Config::parse("backups = 2\ntheme = \"dark\"\n[colors]\nlow = \"grey\"\n[window]\nwidth = 40\n").unwrap();
+        assert_eq!(config, Config { backups : Some(2), ..Default::default() });
+        assert_eq!(warnings, vec!["unknown key `theme`, it is ignored", "unknown key `colors.low`, it is ignored", "unknown key `window`, it is ignored"]);
+    }
+
+    #[test]
+    fn invalid_config() {
+        // The error shows the line of the value, the last line is the reason
+        let error = |content : &str| Config::parse(content).unwrap_err();
+        let reason = |content : &str| error(content).lines().last().unwrap().to_string();
+        assert!(error("backups = 3\nconfirm = \"no\"").starts_with("TOML parse error at line 2, column 11\n  |\n2 | confirm = \"no\"\n"));
+        assert_eq!(reason("backups = 3\nconfirm = \"no\""), "invalid type: string \"no\", expected a boolean");
+        assert_eq!(reason("sort = [\"due\", \"size\"]"), "unknown sort key `size`");
+        assert_eq!(reason("dates = \"short\""), "`short` is not one of absolute, relative");
+        assert!(error("[colors]\ntoday = 3").contains("2 | today = 3\n"));
+        assert_eq!(reason("[colors]\ntoday = 3"), "invalid type: integer `3`, expected a string");
+        assert_eq!(reason("[colors.priorities]\nb = \"blue\""), "`b` is not a priority, a priority goes from A to Z");
+        assert_eq!(reason("[colors.project]\nwork = 3"), "invalid type: integer `3`, expected a string");
+        assert!(reason("actions = [\"open\"]").contains("is not an action"));
+        assert_eq!(reason("escalate = [\"b=2\"]"), "`b=2` is not a rule like `B=2`");
+        assert_eq!(reason("weekend = [\"sat\", \"holiday\"]"), "`holiday` is not a day of the week");
+        assert!(reason("summary = true\nrow_format = \"{content} {size}\"").starts_with("unknown placeholder `{size}`"));
+        assert!(reason("[keys]\ndelete = \"Control d\"").starts_with("`Control d` is not a key"));
+        assert_eq!(reason("[templates]\nreview = \"review due:+2x\""), "`due:+2x` is not a date");
+        assert_eq!(reason("lang = \"es\""), "`es` is not one of en, fr, de");
+        assert_eq!(reason("date_format = \"%d.%Q\""), "`%d.%Q` is not a date format");
+        assert_eq!(reason("row_format = \"{due:%Q}\""), "`%Q` is not a date format of `{due}`");
+        assert_eq!(reason("backups = -1"), "invalid value: integer `-1`, expected usize");
+        assert_eq!(reason("max_line_width = 80.5"), "invalid type: floating point `80.5`, expected usize");
+        assert!(error("todo = \"todo.txt").starts_with("TOML parse error at line 1"));
+        assert!(error("todo = \"todo.txt\"\ntodo = \"other.txt\"").starts_with("TOML parse error at line 2"));
+        assert!(error("backups").starts_with("TOML parse error at line 1"));
+    }
+
+    #[test]
+    fn translations() {
+        let path = std::env::temp_dir().join(format!("rofitodo_translations_{}.toml", std::process::id()));
+        std::fs::write(&path, "# German texts\n[menu]\nmark_as_done = \"✔ erledigt\"\n\n[label]\ntask = 'Todo'\n").unwrap();
+        assert_eq!(load_translations(&path), Ok(vec![(String::from("label.task"), String::from("Todo")),
+                                                     (String::from("menu.mark_as_done"), String::from("✔ erledigt"))]));
+        std::fs::write(&path, "[menu]\nadd = 3\n").unwrap();
+        assert_eq!(load_translations(&path), Err(format!("{} : the translation of `menu.add` is not a string", path.display())));
+        std::fs::remove_file(&path).unwrap();
+        assert!(load_translations(&path).unwrap_err().starts_with("Could not read "));
+    }
+
+    #[test]
+    fn load_file() {
+        let path = std::env::temp_dir().join(format!("rofitodo_config_{}.toml", std::process::id()));
+        assert_eq!(Config::load(&path), Ok((Config::default(), vec![])));
+        // The relative paths are relative to the configuration file
+        std::fs::write(&path, "todo = \"lists/todo.txt\"\ndone = \"/tmp/done.txt\"\n").unwrap();
+        let (config, _) = Config::load(&path).unwrap();
+        assert_eq!(config.todo, Some(std::env::temp_dir().join("lists/todo.txt")));
+        assert_eq!(config.done, Some(PathBuf::from("/tmp/done.txt")));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn command_line_first() {
+        use structopt::StructOpt;
+        let (settings, _) = Config::parse("backups = 5\nauto_archive_days = 14\nconfirm = false\nescalate = [\"A=0\"]\n").unwrap();
+        let args = crate::Cli::from_iter(["rofitodo", "--backups", "2", "--escalate", "B=2"]).with_config(&settings);
+        // The values of the command line are kept, the missing ones come from the configuration
+        assert_eq!((args.backups, args.auto_archive_days, args.no_confirm), (Some(2), Some(14), true));
+        assert_eq!(args.escalate, vec![Rule { priority : 'B', days : 2 }]);
+        let args = crate::Cli::from_iter(["rofitodo"]).with_config(&Config::default());
+        assert_eq!((args.backups, args.auto_archive_days, args.no_confirm), (None, None, false));
+    }
+}
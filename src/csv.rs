@@ -0,0 +1,135 @@
+use crate::task::Task;
+
+/// A column of the CSV export of the tasks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    /// The content of the task, with its project and context tags
+    Content,
+    /// The priority, from A to Z
+    Priority,
+    /// The due date
+    Due,
+    /// The creation date
+    Created,
+    /// The completion date
+    Completed,
+    /// The project tags, separated by `;`
+    Projects,
+    /// The context tags, separated by `;`
+    Contexts
+}
+
+impl Column {
+    /// The columns in their default order
+    pub const ALL : [Column; 7] = [Column::Content, Column::Priority, Column::Due, Column::Created, Column::Completed, Column::Projects, Column::Contexts];
+
+    /// Return the name of the column, as given to `--columns` and written in the header row
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Content => "content",
+            Column::Priority => "priority",
+            Column::Due => "due",
+            Column::Created => "created",
+            Column::Completed => "completed",
+            Column::Projects => "projects",
+            Column::Contexts => "contexts"
+        }
+    }
+
+    /// Return the column with a name, see `name`
+    pub fn from_name(name : &str) -> Option<Self> {
+        Column::ALL.iter().copied().find(|column| column.name() == name)
+    }
+
+    /// Read a list of column names, the default columns if the list is empty
+    ///
+    /// Return an error listing the valid columns for an unknown name
+    ///
+    /// Arguments:
+    ///
+    /// * `names` - the names of the columns, in their order in the export
+    pub fn parse_list(names : &[String]) -> Result<Vec<Self>, String> {
+        if names.is_empty() {
+            return Ok(Column::ALL.to_vec());
+        }
+        names.iter().map(|name| Column::from_name(name.trim()).ok_or_else(|| {
+            let valid = Column::ALL.iter().map(|c| c.name()).collect::<Vec<_>>().join(", ");
+            format!("Unknown column {}, the columns are {}", name, valid)
+        })).collect()
+    }
+
+    /// Return the value of the column for a task, the dates as `YYYY-MM-DD` and the tags separated by `;`
+    fn value(&self, task : &Task) -> String {
+        let date = |date : Option<chrono::NaiveDate>| date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default();
+        match self {
+            Column::Content => task.get_content().clone(),
+            Column::Priority => task.get_priority().map(String::from).unwrap_or_default(),
+            Column::Due => date(*task.get_due()),
+            Column::Created => date(task.creation_date),
+            Column::Completed => date(if task.completion { task.completion_date } else { None }),
+            Column::Projects => task.get_project_tags().join(";"),
+            Column::Contexts => task.get_context_tags().join(";")
+        }
+    }
+}
+
+/// Return the tasks as CSV, a header row with the names of the columns then a row per task
+///
+/// The rows end with CRLF and the fields are quoted as in RFC 4180
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks
+/// * `columns` - the columns, in their order
+pub fn to_csv<'a>(tasks : impl IntoIterator<Item=&'a Task>, columns : &[Column]) -> String {
+    let mut rows = vec![row(columns.iter().map(|c| c.name().to_string()))];
+    rows.extend(tasks.into_iter().map(|task| row(columns.iter().map(|c| c.value(task)))));
+    rows.concat()
+}
+
+/// Return a row of fields ended by CRLF
+fn row(fields : impl Iterator<Item=String>) -> String {
+    let mut row = fields.map(|field| quote(&field)).collect::<Vec<_>>().join(",");
+    row.push_str("\r\n");
+    row
+}
+
+/// Quote a field containing a comma, a quote or a line break, the quotes are doubled
+fn quote(field : &str) -> String {
+    if field.contains(&[',', '"', '\r', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod csv_tests {
+    use super::*;
+
+    #[test]
+    fn export() {
+        let mut tasks = ["(A) 2024-01-02 call mom @phone +family +home due:2024-01-10", "x 2024-01-05 2024-01-03 water plants pri:B"]
+                            .iter().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        tasks.push(Task::empty());
+        tasks[2].set_content(String::from("say \"hi\", then\nleave"));
+        assert_eq!(to_csv(&tasks, &Column::ALL), "content,priority,due,created,completed,projects,contexts\r\n\
+                                                   call mom @phone +family +home,A,2024-01-10,2024-01-02,,family;home,phone\r\n\
+                                                   water plants,B,,2024-01-03,2024-01-05,,\r\n\
+                                                   \"say \"\"hi\"\", then\nleave\",,,,,,\r\n");
+        assert_eq!(to_csv(&tasks[1..2], &[Column::Completed, Column::Content]), "completed,content\r\n2024-01-05,water plants\r\n");
+    }
+
+    #[test]
+    fn empty_list() {
+        assert_eq!(to_csv(&[], &Column::ALL), "content,priority,due,created,completed,projects,contexts\r\n");
+    }
+
+    #[test]
+    fn columns() {
+        assert_eq!(Column::parse_list(&[]), Ok(Column::ALL.to_vec()));
+        assert_eq!(Column::parse_list(&[String::from("due"), String::from("content")]), Ok(vec![Column::Due, Column::Content]));
+        assert_eq!(Column::parse_list(&[String::from("due"), String::from("tags")]),
+                   Err(String::from("Unknown column tags, the columns are content, priority, due, created, completed, projects, contexts")));
+    }
+}
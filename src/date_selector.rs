@@ -1,6 +1,7 @@
 use chrono::NaiveDate;
 use chrono::Datelike;
 use crate::rofi::{Rofi, RofiParams};
+use crate::dates::parse_due;
 
 /// Give the number of day in a month
 /// 
@@ -47,11 +48,11 @@ pub fn date_selector(rofi_config : &RofiParams, default_date : NaiveDate) -> Opt
         if year_list.contains(&selected_year) {
             year = year_list.iter().position(|r| r.eq(&selected_year)).unwrap() as i32 + now.year();
             break;
-        } else if selected_year.eq("") {
+        } else if selected_year.is_empty() {
             return None;
         }
     }
-    let month_list : Vec<String> = vec!["Janvier","Février","Mars","Avril","Mai","Juin","Juillet","Août","Septembre","Octobre","Novembre","Décembre"]
+    let month_list : Vec<String> = ["Janvier","Février","Mars","Avril","Mai","Juin","Juillet","Août","Septembre","Octobre","Novembre","Décembre"]
         .iter()
         .map(|&s|String::from(s))
         .collect();
@@ -61,7 +62,7 @@ pub fn date_selector(rofi_config : &RofiParams, default_date : NaiveDate) -> Opt
         if month_list.contains(&selected_month) {
             month = month_list.iter().position(|r| r.eq(&selected_month)).unwrap() as u32 + 1;
             break;
-        } else if selected_month.eq("") {
+        } else if selected_month.is_empty() {
             return None;
         }
     }
@@ -72,14 +73,39 @@ pub fn date_selector(rofi_config : &RofiParams, default_date : NaiveDate) -> Opt
         if day_list.contains(&selected_day) {
             day = day_list.iter().position(|r| r.eq(&selected_day)).unwrap() as u32 + 1;
             break;
-        } else if selected_day.eq("") {
+        } else if selected_day.is_empty() {
             return None;
         }
     }
-    let dt = NaiveDate::from_ymd(year, month, day);
+    let dt = NaiveDate::from_ymd_opt(year, month, day).unwrap();
     Some(dt)
 }
 
+/// Open a Rofi prompt to type a date, like `fri`, `+3d`, `eom` or `2024-03-01` (see `parse_due`)
+///
+/// The date can also be selected from menus with the calendar entry
+///
+/// Returns `Some(NaiveDate)` if a date is given, `None` if the user quitted
+///
+/// Arguments:
+///
+/// * `rofi_config` - the Rofi parameters
+/// * `today` - the current date
+pub fn typed_date_selector(rofi_config : &RofiParams, today : NaiveDate) -> Option<NaiveDate> {
+    let mut msg = String::from("Type a date: tomorrow, fri, +3d, +1m, eow, eom, 15, 2024-03-01");
+    loop {
+        let input = Rofi::from(rofi_config).prompt("Date").msg(msg.clone()).run(vec![String::from("~ calendar")]).unwrap();
+        match input.as_str() {
+            "" => return None,
+            "~ calendar" => return date_selector(rofi_config, today),
+            _ => match parse_due(&input, today) {
+                Some(date) => return Some(date),
+                None => msg = format!("\"{}\" is not a date, type tomorrow, fri, +3d, +1m, eow, eom, 15, 2024-03-01", input)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod day_in_month_tests {
     use super::*;
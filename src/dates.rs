@@ -0,0 +1,417 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveTime, Weekday};
+use std::fmt::Write;
+
+/// Parse a due date typed by the user
+///
+/// Accept ISO dates (`2024-03-01`), `today`, `tomorrow`, weekday names (`fri` or `friday`, the next one after today),
+/// offsets from today (`+3d`, `+2w`, `+1m`, `+1y`), the end of the week or month (`eow`, `eom`)
+/// and day numbers (`15`, the next 15th of a month, today included)
+///
+/// Month and year offsets are clamped to the last day of the month (Jan 31 + 1m is Feb 28/29)
+///
+/// Returns `None` if the date is not understood
+///
+/// Arguments:
+///
+/// * `input` - the typed date
+/// * `today` - the current date
+pub fn parse_due(input : &str, today : NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    match input.as_str() {
+        "today" => return Some(today),
+        "tomorrow" => return today.succ_opt(),
+        "eow" => return Some(today + Duration::days(6 - today.weekday().num_days_from_monday() as i64)),
+        "eom" => return (today.with_day(1)? + Months::new(1)).pred_opt(),
+        _ => ()
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Some(date);
+    }
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        return Some(next_weekday(today, weekday));
+    }
+    if !input.is_empty() && input.chars().all(|c| c.is_ascii_digit()) {
+        return next_day_of_month(today, input.parse().ok()?);
+    }
+    let offset = input.strip_prefix('+')?;
+    let (count, unit) = offset.split_at(offset.len().checked_sub(1)?);
+    if count.is_empty() || !count.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let count = count.parse::<u32>().ok()?;
+    match unit {
+        "d" => today.checked_add_signed(Duration::days(count as i64)),
+        "w" => today.checked_add_signed(Duration::weeks(count as i64)),
+        "m" => today.checked_add_months(Months::new(count)),
+        "y" => today.checked_add_months(Months::new(count.checked_mul(12)?)),
+        _ => None
+    }
+}
+
+/// Parse a due date typed by the user with an optional time, like `friT14:00` or `2024-05-06T14:00`
+///
+/// The date is read by `parse_due`, the time is written `HH:MM` after a `T`
+///
+/// Arguments:
+///
+/// * `input` - the typed date
+/// * `today` - the current date
+pub fn parse_due_time(input : &str, today : NaiveDate) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    // The time follows the last `T`, the `t` of `today` and `tomorrow` is never followed by a time
+    match input.rsplit_once(['T', 't']).and_then(|(date, time)| Some((date, NaiveTime::parse_from_str(time, "%H:%M").ok()?))) {
+        Some((date, time)) => Some((parse_due(date, today)?, Some(time))),
+        None => Some((parse_due(input, today)?, None))
+    }
+}
+
+/// Read the value of a `due:` tag, `2024-05-06` or `2024-05-06T14:00`
+///
+/// Arguments:
+///
+/// * `value` - the value of the tag
+pub fn parse_due_tag(value : &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    match value.split_once('T') {
+        Some((date, time)) => Some((NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?, Some(NaiveTime::parse_from_str(time, "%H:%M").ok()?))),
+        None => Some((NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?, None))
+    }
+}
+
+/// Write the value of a `due:` tag, the time is only written when there is one
+///
+/// Arguments:
+///
+/// * `date` - the due date
+/// * `time` - the time of the due date
+pub fn due_tag(date : NaiveDate, time : Option<NaiveTime>) -> String {
+    match time {
+        Some(time) => format!("{}T{}", date.format("%Y-%m-%d"), time.format("%H:%M")),
+        None => date.format("%Y-%m-%d").to_string()
+    }
+}
+
+/// Read a day of the week, like `fri` or `friday`
+///
+/// Arguments:
+///
+/// * `text` - the name of the day, in English
+pub fn parse_weekday(text : &str) -> Result<Weekday, String> {
+    text.trim().parse::<Weekday>().map_err(|_| format!("`{}` is not a day of the week", text))
+}
+
+/// Return the next date after today falling on a weekday, never today itself
+fn next_weekday(today : NaiveDate, weekday : Weekday) -> NaiveDate {
+    let days = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today + Duration::days(if days == 0 { 7 } else { days as i64 })
+}
+
+/// Return the next date on a day of the month, today included, skipping the months too short
+fn next_day_of_month(today : NaiveDate, day : u32) -> Option<NaiveDate> {
+    if !(1..=31).contains(&day) {
+        return None;
+    }
+    let first = today.with_day(1)?;
+    // A 31st is always found within a year
+    (0..12).filter_map(|months| first.checked_add_months(Months::new(months))?.with_day(day))
+           .find(|date| *date >= today)
+}
+
+/// The days of the weekend, and the tasks whose postponed and recurring due dates move from them to the next working
+/// day, see `Task::postpone_with`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workdays {
+    /// The days which are not working days, Saturday and Sunday by default
+    pub weekend : Vec<Weekday>,
+    /// Move the dates of all the tasks, else only the dates of the tasks tagged `wd:1`
+    pub skip_weekends : bool
+}
+
+impl Default for Workdays {
+    fn default() -> Self {
+        Workdays { weekend : vec![Weekday::Sat, Weekday::Sun], skip_weekends : false }
+    }
+}
+
+impl Workdays {
+    /// Return the first working day from a date, the date itself if it is a working day
+    ///
+    /// The date is kept when every day of the week is in the weekend
+    ///
+    /// Arguments:
+    ///
+    /// * `date` - the date to move
+    pub fn next_workday(&self, date : NaiveDate) -> NaiveDate {
+        (0..7).map(|days| date + Duration::days(days))
+              .find(|day| !self.weekend.contains(&day.weekday()))
+              .unwrap_or(date)
+    }
+}
+
+/// How the due dates are shown in the lists
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateStyle {
+    /// As `2024-05-03`
+    Absolute,
+    /// Relative to today (`in 3d`, `5d overdue`), the dates further than `max_days` days are absolute
+    Relative {
+        /// The number of days from today shown relative
+        max_days : i64
+    }
+}
+
+impl DateStyle {
+    /// Format a due date in this style
+    ///
+    /// Arguments:
+    ///
+    /// * `date` - the due date
+    /// * `today` - the current date
+    pub fn format(&self, date : NaiveDate, today : NaiveDate) -> String {
+        self.format_with(date, today, ISO_FORMAT)
+    }
+
+    /// Format a due date in this style, the absolute dates with a chrono format
+    ///
+    /// Arguments:
+    ///
+    /// * `date` - the due date
+    /// * `today` - the current date
+    /// * `date_format` - the chrono format of the absolute dates, like `%d.%m.%Y`
+    pub fn format_with(&self, date : NaiveDate, today : NaiveDate, date_format : &str) -> String {
+        match self {
+            DateStyle::Relative { max_days } if (date - today).num_days().abs() <= *max_days => relative_due(date, today),
+            _ => format_date(date, date_format)
+        }
+    }
+}
+
+/// The format of the dates of the todo.txt lines, which are always read and written in this format
+pub const ISO_FORMAT : &str = "%Y-%m-%d";
+
+/// Write a date with a chrono format, in the ISO format if the format can't write it
+///
+/// Arguments:
+///
+/// * `date` - the date
+/// * `date_format` - the chrono format, like `%d.%m.%Y`
+pub fn format_date(date : NaiveDate, date_format : &str) -> String {
+    let mut s = String::new();
+    match write!(s, "{}", date.format(date_format)) {
+        Ok(()) => s,
+        Err(_) => date.format(ISO_FORMAT).to_string()
+    }
+}
+
+/// Describe a due date relative to today: `today`, `tomorrow`, `in 3d`, `in 2w`, `5d overdue`
+///
+/// Arguments:
+///
+/// * `date` - the due date
+/// * `today` - the current date
+pub fn relative_due(date : NaiveDate, today : NaiveDate) -> String {
+    let days = (date - today).num_days();
+    // Two weeks and more are rounded down to weeks
+    let amount = |days : i64| if days < 14 { format!("{}d", days) } else { format!("{}w", days / 7) };
+    match days {
+        0 => String::from("today"),
+        1 => String::from("tomorrow"),
+        d if d > 0 => format!("in {}", amount(d)),
+        d => format!("{} overdue", amount(-d))
+    }
+}
+
+/// Parse the value of an `est:` tag, like `30m`, `2h`, `1d` or `1h30m`, a number without unit is a number of minutes
+///
+/// Returns `None` if the value is not understood
+///
+/// Arguments:
+///
+/// * `value` - the value of the tag
+pub fn parse_estimate(value : &str) -> Option<Duration> {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        return Some(Duration::minutes(value.parse().ok()?));
+    }
+    if value.is_empty() {
+        return None;
+    }
+    let mut total = Duration::zero();
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest.find(|c : char| !c.is_ascii_digit())?;
+        if digits == 0 {
+            return None;
+        }
+        let count = rest[..digits].parse::<i64>().ok()?;
+        let unit = rest[digits..].chars().next()?;
+        let amount = match unit {
+            'm' => Duration::try_minutes(count)?,
+            'h' => Duration::try_hours(count)?,
+            'd' => Duration::try_days(count)?,
+            _ => return None
+        };
+        total = total.checked_add(&amount)?;
+        rest = &rest[digits + unit.len_utf8()..];
+    }
+    Some(total)
+}
+
+/// Describe an estimate in days, hours and minutes, like `1d 2h` or `5h 30m`
+///
+/// Arguments:
+///
+/// * `estimate` - the estimate
+pub fn format_estimate(estimate : Duration) -> String {
+    let minutes = estimate.num_minutes();
+    let parts = [(minutes / (24 * 60), "d"), (minutes / 60 % 24, "h"), (minutes % 60, "m")];
+    let text = parts.iter().filter(|(count, _)| *count > 0).map(|(count, unit)| format!("{}{}", count, unit)).collect::<Vec<_>>();
+    if text.is_empty() { String::from("0m") } else { text.join(" ") }
+}
+
+#[cfg(test)]
+mod dates_tests {
+    use super::*;
+
+    fn date(y : i32, m : u32, d : u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn keywords() {
+        // A wednesday
+        let today = date(2024, 1, 10);
+        assert_eq!(parse_due("today", today), Some(today));
+        assert_eq!(parse_due("Tomorrow", today), Some(date(2024, 1, 11)));
+        assert_eq!(parse_due("2024-02-29", today), Some(date(2024, 2, 29)));
+        assert_eq!(parse_due("2024-02-30", today), None);
+        assert_eq!(parse_due("someday", today), None);
+    }
+
+    #[test]
+    fn estimates() {
+        assert_eq!(parse_estimate("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_estimate("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_estimate("1d"), Some(Duration::days(1)));
+        assert_eq!(parse_estimate("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_estimate("45"), Some(Duration::minutes(45)));
+        for garbage in ["", "h", "2x", "1.5h", "-2h", "2h30", "2é", "soon", "99999999999999999999d"] {
+            assert_eq!(parse_estimate(garbage), None, "{}", garbage);
+        }
+        assert_eq!(format_estimate(Duration::minutes(330)), "5h 30m");
+        assert_eq!(format_estimate(Duration::minutes(45)), "45m");
+        assert_eq!(format_estimate(Duration::hours(26)), "1d 2h");
+        assert_eq!(format_estimate(Duration::zero()), "0m");
+    }
+
+    #[test]
+    fn due_times() {
+        let today = date(2024, 1, 10);
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0);
+        assert_eq!(parse_due_time("2024-05-06T14:00", today), Some((date(2024, 5, 6), time(14, 0))));
+        assert_eq!(parse_due_time("friT09:30", today), Some((date(2024, 1, 12), time(9, 30))));
+        assert_eq!(parse_due_time("tomorrow", today), Some((date(2024, 1, 11), None)));
+        assert_eq!(parse_due_time("tomorrowt18:00", today), Some((date(2024, 1, 11), time(18, 0))));
+        assert_eq!(parse_due_time("2024-05-06T25:00", today), None);
+        assert_eq!(parse_due_tag("2024-05-06T14:00"), Some((date(2024, 5, 6), time(14, 0))));
+        assert_eq!(parse_due_tag("2024-05-06"), Some((date(2024, 5, 6), None)));
+        assert_eq!(parse_due_tag("2024-05-06T14"), None);
+        assert_eq!(parse_due_tag("fri"), None);
+        assert_eq!(due_tag(date(2024, 5, 6), time(8, 5)), "2024-05-06T08:05");
+        assert_eq!(due_tag(date(2024, 5, 6), None), "2024-05-06");
+    }
+
+    #[test]
+    fn next_workday() {
+        let workdays = Workdays::default();
+        // Friday, Saturday, Sunday
+        assert_eq!(workdays.next_workday(date(2024, 5, 10)), date(2024, 5, 10));
+        assert_eq!(workdays.next_workday(date(2024, 5, 11)), date(2024, 5, 13));
+        assert_eq!(workdays.next_workday(date(2024, 5, 12)), date(2024, 5, 13));
+        let friday_saturday = Workdays { weekend : vec![Weekday::Fri, Weekday::Sat], skip_weekends : true };
+        assert_eq!(friday_saturday.next_workday(date(2024, 5, 10)), date(2024, 5, 12));
+        let every_day = Workdays { weekend : vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun], skip_weekends : true };
+        assert_eq!(every_day.next_workday(date(2024, 5, 11)), date(2024, 5, 11));
+        assert_eq!((parse_weekday("fri"), parse_weekday("Saturday")), (Ok(Weekday::Fri), Ok(Weekday::Sat)));
+        assert_eq!(parse_weekday("holiday"), Err(String::from("`holiday` is not a day of the week")));
+    }
+
+    #[test]
+    fn weekdays() {
+        let today = date(2024, 1, 10);
+        assert_eq!(parse_due("fri", today), Some(date(2024, 1, 12)));
+        assert_eq!(parse_due("monday", today), Some(date(2024, 1, 15)));
+        // Never today itself
+        assert_eq!(parse_due("wed", today), Some(date(2024, 1, 17)));
+        assert_eq!(parse_due("Sunday", today), Some(date(2024, 1, 14)));
+        assert_eq!(parse_due("tue", today), Some(date(2024, 1, 16)));
+    }
+
+    #[test]
+    fn offsets() {
+        let today = date(2024, 12, 30);
+        assert_eq!(parse_due("+3d", today), Some(date(2025, 1, 2)));
+        assert_eq!(parse_due("+2w", today), Some(date(2025, 1, 13)));
+        assert_eq!(parse_due("+0d", today), Some(today));
+        assert_eq!(parse_due("+d", today), None);
+        assert_eq!(parse_due("+3x", today), None);
+        assert_eq!(parse_due("+", today), None);
+        assert_eq!(parse_due("+-1d", today), None);
+        assert_eq!(parse_due("+1y", today), Some(date(2025, 12, 30)));
+    }
+
+    #[test]
+    fn months_are_clamped() {
+        assert_eq!(parse_due("+1m", date(2024, 1, 31)), Some(date(2024, 2, 29)));
+        assert_eq!(parse_due("+1m", date(2023, 1, 31)), Some(date(2023, 2, 28)));
+        assert_eq!(parse_due("+3m", date(2024, 11, 30)), Some(date(2025, 2, 28)));
+        assert_eq!(parse_due("+1y", date(2024, 2, 29)), Some(date(2025, 2, 28)));
+    }
+
+    #[test]
+    fn end_of_period() {
+        // A wednesday
+        let today = date(2024, 2, 14);
+        assert_eq!(parse_due("eow", today), Some(date(2024, 2, 18)));
+        assert_eq!(parse_due("eow", date(2024, 2, 18)), Some(date(2024, 2, 18)));
+        assert_eq!(parse_due("eom", today), Some(date(2024, 2, 29)));
+        assert_eq!(parse_due("EOM", date(2024, 12, 31)), Some(date(2024, 12, 31)));
+    }
+
+    #[test]
+    fn day_of_month() {
+        let today = date(2024, 1, 15);
+        assert_eq!(parse_due("20", today), Some(date(2024, 1, 20)));
+        assert_eq!(parse_due("15", today), Some(today));
+        assert_eq!(parse_due("3", today), Some(date(2024, 2, 3)));
+        assert_eq!(parse_due("30", date(2024, 1, 31)), Some(date(2024, 3, 30)));
+        assert_eq!(parse_due("31", date(2024, 4, 1)), Some(date(2024, 5, 31)));
+        assert_eq!(parse_due("3", date(2024, 12, 20)), Some(date(2025, 1, 3)));
+        assert_eq!(parse_due("0", today), None);
+        assert_eq!(parse_due("32", today), None);
+    }
+
+    #[test]
+    fn relative_dates() {
+        let today = date(2024, 5, 1);
+        let cases = [(0, "today"), (1, "tomorrow"), (3, "in 3d"), (13, "in 13d"), (14, "in 2w"), (20, "in 2w"), (60, "in 8w"),
+                     (-1, "1d overdue"), (-5, "5d overdue"), (-21, "3w overdue")];
+        for (days, expected) in cases {
+            assert_eq!(relative_due(today + Duration::days(days), today), expected);
+        }
+    }
+
+    #[test]
+    fn date_style() {
+        let today = date(2024, 5, 1);
+        let relative = DateStyle::Relative { max_days : 60 };
+        assert_eq!(DateStyle::Absolute.format(date(2024, 5, 4), today), "2024-05-04");
+        assert_eq!(relative.format(date(2024, 5, 4), today), "in 3d");
+        assert_eq!(relative.format(date(2024, 6, 30), today), "in 8w");
+        assert_eq!(relative.format(date(2024, 7, 1), today), "2024-07-01");
+        assert_eq!(relative.format(date(2024, 3, 1), today), "2024-03-01");
+        assert_eq!(DateStyle::Relative { max_days : 0 }.format(today, today), "today");
+        assert_eq!(relative.format_with(date(2024, 7, 1), today, "%d.%m.%Y"), "01.07.2024");
+        assert_eq!(relative.format_with(date(2024, 5, 4), today, "%d.%m.%Y"), "in 3d");
+        assert_eq!(format_date(date(2024, 5, 6), "%a %d %b"), "Mon 06 May");
+        // A format chrono can't write falls back to the todo.txt format
+        assert_eq!(format_date(date(2024, 5, 6), "%Q"), "2024-05-06");
+    }
+}
@@ -0,0 +1,227 @@
+/// An entry of the menus, shown with its label
+#[derive(Clone, Debug, PartialEq)]
+pub enum Entry {
+    /// Add a task
+    Add,
+    /// Add the tasks copied to the clipboard
+    AddFromClipboard,
+    /// Open the tasks of the day
+    Today,
+    /// Show the tasks one at a time
+    Focus,
+    /// Undo the last change
+    Undo,
+    /// Redo the last undone change
+    Redo,
+    /// Search the tasks
+    Search,
+    /// Open the tasks of the week
+    Week,
+    /// Change several tasks at once
+    Bulk,
+    /// Open the completed tasks
+    Done,
+    /// Open the tasks completed today
+    Recent,
+    /// Review the stale tasks
+    Stale,
+    /// Open the deleted tasks
+    Trash,
+    /// Open the project tags
+    ProjectTags,
+    /// Open the context tags
+    ContextTags,
+    /// Filter the tasks by tag
+    FilterByTag,
+    /// Filter the tasks
+    Filter,
+    /// Sort the tasks
+    Sort,
+    /// Open the saved views
+    Views,
+    /// Open the statistics
+    Statistics,
+    /// Open another task list
+    SwitchList,
+    /// Restore a backup of the task file
+    Backups,
+    /// Write the task file in the order of the sort
+    SortFile,
+    /// Show or hide the future tasks, true if they are shown
+    FutureTasks(bool),
+    /// Show or hide the hidden tasks, true if they are shown
+    HiddenTasks(bool),
+    /// Show or hide the completed tasks, true if they are shown
+    CompletedTasks(bool),
+    /// Group the tasks by project or not, true if they are grouped
+    GroupByProject(bool),
+    /// Close the menus
+    Exit,
+    /// Go back to the previous menu
+    Back,
+    /// Mark the task as done
+    MarkAsDone,
+    /// Mark the completed task as to do
+    MarkAsToDo,
+    /// Pin or unpin the task, true if it is pinned
+    Pin(bool),
+    /// Edit the todo.txt line of the task
+    Edit,
+    /// Postpone the task
+    Postpone,
+    /// Change the due date of the task
+    ChangeDate,
+    /// Change the threshold date of the task
+    ChangeThreshold,
+    /// Change the priority of the task
+    ChangePriority,
+    /// Raise the priority of the task
+    IncreasePriority,
+    /// Lower the priority of the task
+    DecreasePriority,
+    /// Add or remove the project and context tags of the task
+    Tags,
+    /// Edit the custom tags of the task
+    EditTags,
+    /// Remove the due date of the task
+    RemoveDate,
+    /// Remove the threshold date of the task
+    RemoveThreshold,
+    /// Move the task to another task list
+    MoveToOtherList,
+    /// Open the notes of the task
+    Notes,
+    /// Make the task depend on another task
+    DependsOn,
+    /// Copy the task to the clipboard
+    CopyToClipboard,
+    /// Copy the todo.txt line of the task to the clipboard
+    CopyLine,
+    /// Open a link of the task
+    OpenLink,
+    /// Move the task to the trash
+    Remove,
+    /// Run the custom action of the configuration with this name on the task
+    Action(String),
+    /// Restore the archived or deleted task
+    Restore,
+    /// Close the menu without a change
+    Cancel,
+    /// Rename a tag in all the tasks
+    RenameTag,
+    /// Show the next task to focus on
+    Skip,
+    /// Close the focus menu
+    ExitFocus,
+    /// Keep the stale task as it is
+    Keep,
+    /// Close the review of the stale tasks
+    ExitReview
+}
+
+impl Entry {
+    /// Return the label of the entry in the menus
+    pub fn label(&self) -> String {
+        let label = match self {
+            Entry::Add => "+ add",
+            Entry::AddFromClipboard => "+ add from clipboard",
+            Entry::Today => "~ today",
+            Entry::Focus => "~ focus",
+            Entry::Undo => "↶ undo",
+            Entry::Redo => "↷ redo",
+            Entry::Search => "~ search…",
+            Entry::Week => "~ this week",
+            Entry::Bulk => "~ bulk actions",
+            Entry::Done => "~ done",
+            Entry::Recent => "~ recently completed",
+            Entry::Stale => "~ review stale tasks",
+            Entry::Trash => "~ trash",
+            Entry::ProjectTags => "@ project tags",
+            Entry::ContextTags => "@ context tags",
+            Entry::FilterByTag => "@ filter by tag",
+            Entry::Filter => "~ filter",
+            Entry::Sort => "~ sort by…",
+            Entry::Views => "~ views",
+            Entry::Statistics => "~ statistics",
+            Entry::SwitchList => "~ switch list",
+            Entry::Backups => "~ restore from backup",
+            Entry::SortFile => "~ sort file permanently",
+            Entry::FutureTasks(shown) => if *shown { "~ hide future tasks" } else { "~ show future tasks" },
+            Entry::HiddenTasks(shown) => if *shown { "~ hide hidden tasks" } else { "~ show hidden tasks" },
+            Entry::CompletedTasks(shown) => if *shown { "~ hide completed tasks" } else { "~ show completed tasks" },
+            Entry::GroupByProject(grouped) => if *grouped { "~ ungroup tasks" } else { "~ group by project" },
+            Entry::Exit => "* exit",
+            Entry::Back => "← back",
+            Entry::MarkAsDone => "✔ mark as done",
+            Entry::MarkAsToDo => "✔ mark as to do",
+            Entry::Pin(pinned) => if *pinned { "★ unpin" } else { "★ pin" },
+            Entry::Edit => "+ edit",
+            Entry::Postpone => "+ postpone",
+            Entry::ChangeDate => "+ change date",
+            Entry::ChangeThreshold => "+ change threshold",
+            Entry::ChangePriority => "+ change priority",
+            Entry::IncreasePriority => "↑ increase priority",
+            Entry::DecreasePriority => "↓ decrease priority",
+            Entry::Tags => "+ tags…",
+            Entry::EditTags => "+ edit tags",
+            Entry::RemoveDate => "! remove date",
+            Entry::RemoveThreshold => "! remove threshold",
+            Entry::MoveToOtherList => "→ move to other list",
+            Entry::Notes => "+ notes",
+            Entry::DependsOn => "+ depends on…",
+            Entry::CopyToClipboard => "→ copy to clipboard",
+            Entry::CopyLine => "→ copy todo.txt line",
+            Entry::OpenLink => "→ open link",
+            Entry::Remove => "! remove",
+            Entry::Action(name) => return format!("» {}", name),
+            Entry::Restore => "✔ restore",
+            Entry::Cancel => "* cancel",
+            Entry::RenameTag => "+ rename tag",
+            Entry::Skip => "→ skip",
+            Entry::ExitFocus => "* exit focus",
+            Entry::Keep => "→ keep",
+            Entry::ExitReview => "* exit review"
+        };
+        label.to_string()
+    }
+
+    /// Return true if the entry changes the task list, it is hidden when the list is read-only
+    pub fn mutates(&self) -> bool {
+        matches!(self, Entry::Add | Entry::AddFromClipboard | Entry::Undo | Entry::Redo | Entry::Bulk | Entry::Backups | Entry::SortFile
+                     | Entry::MarkAsDone | Entry::MarkAsToDo | Entry::Pin(_) | Entry::Edit | Entry::Postpone | Entry::ChangeDate
+                     | Entry::ChangeThreshold | Entry::ChangePriority | Entry::IncreasePriority | Entry::DecreasePriority | Entry::Tags
+                     | Entry::EditTags | Entry::RemoveDate | Entry::RemoveThreshold | Entry::MoveToOtherList | Entry::Notes
+                     | Entry::DependsOn | Entry::Remove | Entry::Restore | Entry::RenameTag)
+    }
+}
+
+/// Return the entries of a menu shown for a task list, without the entries changing it if it is read-only
+///
+/// Arguments:
+///
+/// * `entries` - the entries of the menu
+/// * `read_only` - true if the task list is read-only
+pub fn shown(mut entries : Vec<Entry>, read_only : bool) -> Vec<Entry> {
+    if read_only {
+        entries.retain(|entry| !entry.mutates());
+    }
+    entries
+}
+
+/// Return the labels of entries, in the order of the entries
+pub fn labels(entries : &[Entry]) -> Vec<String> {
+    entries.iter().map(Entry::label).collect()
+}
+
+#[cfg(test)]
+mod entry_tests {
+    use super::*;
+
+    #[test]
+    fn read_only_entries() {
+        let entries = vec![Entry::MarkAsDone, Entry::Back, Entry::Pin(true), Entry::CopyLine, Entry::Action(String::from("mail")), Entry::Remove];
+        assert_eq!(labels(&shown(entries.clone(), false)), ["✔ mark as done", "← back", "★ unpin", "→ copy todo.txt line", "» mail", "! remove"]);
+        assert_eq!(shown(entries, true), [Entry::Back, Entry::CopyLine, Entry::Action(String::from("mail"))]);
+        assert_eq!(labels(&[Entry::FutureTasks(false), Entry::GroupByProject(true)]), ["~ show future tasks", "~ ungroup tasks"]);
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::NaiveDate;
+use std::fmt;
+use std::str::FromStr;
+use crate::task::{Task, TaskId};
+use crate::tasklist::TaskList;
+use crate::undo::Op;
+
+/// The tag of the tasks whose priority was raised, `autopri:1`, or which opted out of the escalation, `autopri:0`
+pub const TAG : &str = "autopri";
+
+/// A priority given to the tasks due in a number of days or less, written `B=2`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rule {
+    /// The priority given to the tasks, from A to Z
+    pub priority : char,
+    /// The number of days before the due date from which the priority is given, 0 for the tasks due today or overdue
+    pub days : i64
+}
+
+/// A priority raised by `escalate`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Escalation {
+    /// The task
+    pub id : TaskId,
+    /// The priority of the task before the escalation
+    pub old : Option<char>,
+    /// The priority given to the task
+    pub new : char
+}
+
+/// Read a rule like `B=2`, fails when the priority is not an uppercase letter or the days are not a number
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(text : &str) -> Result<Self, Self::Err> {
+        let error = || format!("`{}` is not a rule like `B=2`", text);
+        let (priority, days) = text.split_once('=').ok_or_else(error)?;
+        let priority = match priority.trim().chars().collect::<Vec<_>>().as_slice() {
+            [p] if p.is_ascii_uppercase() => *p,
+            _ => return Err(error())
+        };
+        let days = days.trim().parse::<i64>().map_err(|_| error())?;
+        Ok(Rule { priority, days })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.priority, self.days)
+    }
+}
+
+/// Return the priorities to raise: a task to do due in the days of a rule gets its priority when it has a lower one or
+/// none, the highest priority of the rules it matches
+///
+/// The tasks without due date and the tasks tagged `autopri:0` are left out. Nothing is changed, see `apply`
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks and their ids
+/// * `today` - the current date
+/// * `rules` - the priorities and their number of days
+pub fn escalate<'a>(tasks : impl IntoIterator<Item=(TaskId, &'a Task)>, today : NaiveDate, rules : &[Rule]) -> Vec<Escalation> {
+    tasks.into_iter().filter_map(|(id, task)| {
+        if task.completion || task.get_custom_tag(TAG) == Some("0") {
+            return None;
+        }
+        let days = ((*task.get_due())? - today).num_days();
+        let new = rules.iter().filter(|rule| days <= rule.days).map(|rule| rule.priority).min()?;
+        let old = task.get_priority();
+        // A is the highest priority, the lowest letter
+        match old {
+            Some(p) if p <= new => None,
+            _ => Some(Escalation { id, old, new })
+        }
+    }).collect()
+}
+
+/// Give its raised priority to a task and tag it `autopri:1`
+///
+/// Arguments:
+///
+/// * `task` - the task
+/// * `priority` - the new priority, from A to Z
+pub fn apply(task : &mut Task, priority : char) {
+    task.set_priority(Some(priority)).expect("the priority of a rule is a letter");
+    task.set_custom_tag(TAG, "1").expect("autopri:1 is a valid tag");
+}
+
+/// Keep the priority chosen for a task whose priority was raised, it is tagged `autopri:0` and never raised again
+///
+/// Arguments:
+///
+/// * `task` - the task whose priority is changed by hand
+pub fn opt_out(task : &mut Task) {
+    if task.get_custom_tag(TAG) == Some("1") {
+        task.set_custom_tag(TAG, "0").expect("autopri:0 is a valid tag");
+    }
+}
+
+/// Raise the priorities of the tasks of a list as their due date approaches, recorded as a single operation
+///
+/// Return the escalated tasks with their old and new priority, like `call mom (B → A)`
+///
+/// Arguments:
+///
+/// * `list` - the task list
+/// * `today` - the current date
+/// * `rules` - the priorities and their number of days
+pub fn escalate_list(list : &mut TaskList, today : NaiveDate, rules : &[Rule]) -> Vec<String> {
+    let mut ops = Vec::new();
+    let mut changes = Vec::new();
+    for change in escalate(list.iter(), today, rules) {
+        let old = list.get(change.id).cloned().expect("the task is in the list");
+        apply(&mut list.get_mut(change.id).expect("the task is in the list"), change.new);
+        let old_priority = change.old.map_or(String::from("none"), |p| p.to_string());
+        changes.push(format!("{} ({} → {})", old.get_content(), old_priority, change.new));
+        ops.push(Op::Replace(old, list.get(change.id).cloned().expect("the task is in the list")));
+    }
+    if !ops.is_empty() {
+        list.history.push(Op::Group(format!("escalate {} task{}", ops.len(), if ops.len() == 1 { "" } else { "s" }), ops));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod escalation_tests {
+    use super::*;
+
+    fn rules() -> Vec<Rule> {
+        vec!["B=2".parse().unwrap(), "A=0".parse().unwrap()]
+    }
+
+    #[test]
+    fn escalated_tasks() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let tasks = ["(C) pay rent due:2024-05-12", "call mom due:2024-05-10", "(B) renew passport due:2024-05-01",
+                     "(A) fix the sink due:2024-05-11", "buy milk due:2024-05-13", "water plants", "x 2024-05-09 2024-05-01 send report due:2024-05-10",
+                     "book flights due:2024-05-10 autopri:0", "(B) call the bank due:2024-05-09 autopri:1"]
+            .iter().map(|line| (TaskId::next(), line.parse::<Task>().unwrap())).collect::<Vec<_>>();
+        let escalated = escalate(tasks.iter().map(|(id, t)| (*id, t)), today, &rules());
+        let changes = escalated.iter().map(|e| (tasks.iter().position(|(id, _)| *id == e.id).unwrap(), e.old, e.new)).collect::<Vec<_>>();
+        // An overdue task gets the priority of the tasks due today, a task escalated before can be escalated again
+        assert_eq!(changes, vec![(0, Some('C'), 'B'), (1, None, 'A'), (2, Some('B'), 'A'), (8, Some('B'), 'A')]);
+
+        // The escalated tasks are not escalated again
+        let mut escalated_tasks = tasks.clone();
+        for e in &escalated {
+            let (_, task) = escalated_tasks.iter_mut().find(|(id, _)| *id == e.id).unwrap();
+            apply(task, e.new);
+        }
+        assert_eq!(escalated_tasks[1].1.to_string(), "(A) call mom due:2024-05-10 autopri:1");
+        assert!(escalate(escalated_tasks.iter().map(|(id, t)| (*id, t)), today, &rules()).is_empty());
+        assert!(escalate(tasks.iter().map(|(id, t)| (*id, t)), today, &[]).is_empty());
+    }
+
+    #[test]
+    fn rules_and_opt_out() {
+        assert_eq!("B=2".parse::<Rule>(), Ok(Rule { priority : 'B', days : 2 }));
+        assert_eq!(" A = -1 ".parse::<Rule>().map(|rule| rule.to_string()), Ok(String::from("A=-1")));
+        for rule in ["B", "b=2", "BB=2", "B=two", "=2"] {
+            assert_eq!(rule.parse::<Rule>(), Err(format!("`{}` is not a rule like `B=2`", rule)));
+        }
+        let mut task = "(A) call mom due:2024-05-10 autopri:1".parse::<Task>().unwrap();
+        opt_out(&mut task);
+        assert_eq!(task.get_custom_tag(TAG), Some("0"));
+        let mut task = "(A) call mom".parse::<Task>().unwrap();
+        opt_out(&mut task);
+        assert_eq!(task.get_custom_tag(TAG), None);
+    }
+}
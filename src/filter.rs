@@ -0,0 +1,331 @@
+use chrono::NaiveDate;
+use crate::task::Task;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// A set of criteria to select tasks
+///
+/// Every criterion is optional and a task matches the filter if it matches all of them:
+/// the default filter matches every task
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct TaskFilter {
+    /// The project tags the task must all carry
+    pub projects : Vec<String>,
+    /// The context tags the task must all carry
+    pub contexts : Vec<String>,
+    /// The project tags the task must not carry
+    pub exclude_projects : Vec<String>,
+    /// The context tags the task must not carry
+    pub exclude_contexts : Vec<String>,
+    /// The words the content must all contain (case insensitive)
+    pub words : Vec<String>,
+    /// The lowest priority of the task (`B` keeps the `A` and `B` tasks)
+    pub priority_at_least : Option<char>,
+    /// The task must be due on or before this date
+    pub due_before : Option<NaiveDate>,
+    /// The task must be due on or after this date
+    pub due_after : Option<NaiveDate>,
+    /// The completion state of the task
+    pub completed : Option<bool>
+}
+
+impl TaskFilter {
+    /// Create a filter matching the tasks to do
+    pub fn todo() -> Self {
+        TaskFilter { completed : Some(false), ..Default::default() }
+    }
+
+    /// Create a filter from a query like `@home -@work +errands`
+    /// 
+    /// The query is a list of tokens separated by whitespaces:
+    /// `+project` and `@context` must be carried by the task, `-+project` and `-@context` must not,
+    /// any other token must be found in the content of the task
+    /// 
+    /// Arguments:
+    /// 
+    /// * `query` - the query
+    pub fn from_query(query : &str) -> Self {
+        let mut filter = Self::default();
+        for token in query.split_whitespace() {
+            let (excluded, tag) = match token.strip_prefix('-') {
+                Some(tag) => (true, tag),
+                None => (false, token)
+            };
+            let name = tag.get(1..).unwrap_or("");
+            let list = match (excluded, tag.chars().next()) {
+                _ if name.is_empty() => None,
+                (false, Some('+')) => Some(&mut filter.projects),
+                (false, Some('@')) => Some(&mut filter.contexts),
+                (true, Some('+')) => Some(&mut filter.exclude_projects),
+                (true, Some('@')) => Some(&mut filter.exclude_contexts),
+                _ => None
+            };
+            match list {
+                Some(list) => {
+                    if !list.iter().any(|t| t == name) {
+                        list.push(name.to_string());
+                    }
+                },
+                None => filter.words.push(token.to_string())
+            }
+        }
+        filter
+    }
+
+    /// Replace the tags and words of the filter with the ones of a query, see `TaskFilter::from_query`
+    /// 
+    /// Arguments:
+    /// 
+    /// * `query` - the query
+    pub fn set_query(&mut self, query : &str) {
+        let query = Self::from_query(query);
+        self.projects = query.projects;
+        self.contexts = query.contexts;
+        self.exclude_projects = query.exclude_projects;
+        self.exclude_contexts = query.exclude_contexts;
+        self.words = query.words;
+    }
+
+    /// Return the tags and words of the filter as a query, see `TaskFilter::set_query`
+    pub fn query(&self) -> String {
+        let mut parts = Vec::new();
+        parts.extend(self.projects.iter().map(|p| format!("+{}", p)));
+        parts.extend(self.contexts.iter().map(|c| format!("@{}", c)));
+        parts.extend(self.exclude_projects.iter().map(|p| format!("-+{}", p)));
+        parts.extend(self.exclude_contexts.iter().map(|c| format!("-@{}", c)));
+        parts.extend(self.words.iter().cloned());
+        parts.join(" ")
+    }
+
+    /// Return true if the task matches all the criteria of the filter
+    ///
+    /// Arguments:
+    ///
+    /// * `task` - the task to check
+    pub fn matches(&self, task : &Task) -> bool {
+        if let Some(completed) = self.completed {
+            if task.completion != completed {
+                return false;
+            }
+        }
+        if !self.projects.iter().all(|p| task.get_project_tags().contains(p)) {
+            return false;
+        }
+        if !self.contexts.iter().all(|c| task.get_context_tags().contains(c)) {
+            return false;
+        }
+        if self.exclude_projects.iter().any(|p| task.get_project_tags().contains(p)) {
+            return false;
+        }
+        if self.exclude_contexts.iter().any(|c| task.get_context_tags().contains(c)) {
+            return false;
+        }
+        if !self.words.is_empty() {
+            let content = task.get_content().to_lowercase();
+            if !self.words.iter().all(|w| content.contains(&w.to_lowercase())) {
+                return false;
+            }
+        }
+        if let Some(lowest) = self.priority_at_least {
+            match task.get_priority() {
+                Some(priority) if priority <= lowest => (),
+                _ => return false
+            }
+        }
+        if self.due_before.is_some() || self.due_after.is_some() {
+            let due = match *task.get_due() {
+                Some(due) => due,
+                None => return false
+            };
+            if self.due_before.is_some_and(|date| due > date) || self.due_after.is_some_and(|date| due < date) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Add the tag to the filter if it is not in it, remove it otherwise
+    ///
+    /// Arguments:
+    ///
+    /// * `tags` - the project or context tags of the filter
+    /// * `tag` - the tag to toggle
+    pub fn toggle(tags : &mut Vec<String>, tag : &str) {
+        match tags.iter().position(|t| t == tag) {
+            Some(i) => {tags.remove(i);},
+            None => tags.push(tag.to_string())
+        }
+    }
+
+    /// Return a short description of the criteria of the filter (e.g. `+garage @shop (A-B)`)
+    pub fn describe(&self) -> String {
+        let query = self.query();
+        let mut parts = if query.is_empty() { vec![] } else { vec![query] };
+        if let Some(priority) = self.priority_at_least {
+            parts.push(format!("(A-{})", priority));
+        }
+        if let Some(date) = self.due_after {
+            parts.push(format!("due≥{}", date.format("%Y-%m-%d")));
+        }
+        if let Some(date) = self.due_before {
+            parts.push(format!("due≤{}", date.format("%Y-%m-%d")));
+        }
+        match self.completed {
+            Some(true) => parts.push(String::from("done")),
+            Some(false) => (),
+            None => parts.push(String::from("all"))
+        }
+        parts.join(" ")
+    }
+}
+
+/// Count the tasks to do of each project
+///
+/// Return the projects sorted by name with their number of tasks
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks to count
+pub fn counts_by_project<'a>(tasks : impl IntoIterator<Item = &'a Task>) -> Vec<(String, usize)> {
+    counts_by(tasks, Task::get_project_tags)
+}
+
+/// Count the tasks to do of each context
+///
+/// Return the contexts sorted by name with their number of tasks
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks to count
+pub fn counts_by_context<'a>(tasks : impl IntoIterator<Item = &'a Task>) -> Vec<(String, usize)> {
+    counts_by(tasks, Task::get_context_tags)
+}
+
+/// Count the tasks to do carrying each tag returned by `tags`
+fn counts_by<'a>(tasks : impl IntoIterator<Item = &'a Task>, tags : fn(&Task) -> &Vec<String>) -> Vec<(String, usize)> {
+    let mut counts = std::collections::BTreeMap::<String, usize>::new();
+    for task in tasks.into_iter().filter(|t| !t.completion) {
+        for tag in tags(task) {
+            *counts.entry(tag.to_string()).or_insert(0) += 1;
+        }
+    }
+    counts.into_iter().collect()
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+
+    fn tasks() -> Vec<Task> {
+        vec![
+            "(A) call mom +family @phone due:2024-03-01",
+            "(C) fix the bike +garage due:2024-05-01",
+            "buy paint +garage @shop",
+            "x 2024-01-02 2024-01-01 sell the car +garage pri:B",
+        ].into_iter().map(|l| l.parse::<Task>().unwrap()).collect()
+    }
+
+    fn matching(filter : &TaskFilter) -> Vec<String> {
+        tasks().iter().filter(|t| filter.matches(t)).map(|t| t.get_content().to_string()).collect()
+    }
+
+    #[test]
+    fn empty_filter() {
+        assert_eq!(matching(&TaskFilter::default()).len(), 4);
+        assert_eq!(matching(&TaskFilter::todo()).len(), 3);
+    }
+
+    #[test]
+    fn single_criteria() {
+        let filter = TaskFilter { projects : vec![String::from("garage")], ..Default::default() };
+        assert_eq!(matching(&filter), vec!["fix the bike +garage", "buy paint +garage @shop", "sell the car +garage"]);
+        let filter = TaskFilter { contexts : vec![String::from("phone")], ..Default::default() };
+        assert_eq!(matching(&filter), vec!["call mom +family @phone"]);
+        let filter = TaskFilter { priority_at_least : Some('B'), ..Default::default() };
+        assert_eq!(matching(&filter), vec!["call mom +family @phone", "sell the car +garage"]);
+        let filter = TaskFilter { due_before : NaiveDate::from_ymd_opt(2024, 3, 1), ..Default::default() };
+        assert_eq!(matching(&filter), vec!["call mom +family @phone"]);
+        let filter = TaskFilter { due_after : NaiveDate::from_ymd_opt(2024, 3, 2), ..Default::default() };
+        assert_eq!(matching(&filter), vec!["fix the bike +garage"]);
+        let filter = TaskFilter { completed : Some(true), ..Default::default() };
+        assert_eq!(matching(&filter), vec!["sell the car +garage"]);
+    }
+
+    #[test]
+    fn combined_criteria() {
+        let filter = TaskFilter { projects : vec![String::from("garage")], completed : Some(false), ..Default::default() };
+        assert_eq!(matching(&filter), vec!["fix the bike +garage", "buy paint +garage @shop"]);
+        let filter = TaskFilter { projects : vec![String::from("garage")], contexts : vec![String::from("shop")], ..Default::default() };
+        assert_eq!(matching(&filter), vec!["buy paint +garage @shop"]);
+        let filter = TaskFilter { projects : vec![String::from("garage"), String::from("family")], ..Default::default() };
+        assert!(matching(&filter).is_empty());
+        let filter = TaskFilter {
+            due_after : NaiveDate::from_ymd_opt(2024, 1, 1),
+            due_before : NaiveDate::from_ymd_opt(2024, 12, 31),
+            priority_at_least : Some('C'),
+            ..Default::default()
+        };
+        assert_eq!(matching(&filter), vec!["call mom +family @phone", "fix the bike +garage"]);
+    }
+
+    #[test]
+    fn toggle_tags() {
+        let mut tags = vec![];
+        TaskFilter::toggle(&mut tags, "garage");
+        TaskFilter::toggle(&mut tags, "family");
+        assert_eq!(tags, vec!["garage", "family"]);
+        TaskFilter::toggle(&mut tags, "garage");
+        assert_eq!(tags, vec!["family"]);
+    }
+
+    #[test]
+    fn describe() {
+        assert_eq!(TaskFilter::todo().describe(), "");
+        assert_eq!(TaskFilter::default().describe(), "all");
+        let filter = TaskFilter {
+            projects : vec![String::from("garage")],
+            contexts : vec![String::from("shop")],
+            priority_at_least : Some('B'),
+            due_before : NaiveDate::from_ymd_opt(2024, 3, 1),
+            completed : Some(true),
+            ..Default::default()
+        };
+        assert_eq!(filter.describe(), "+garage @shop (A-B) due≤2024-03-01 done");
+    }
+
+    #[test]
+    fn tag_counts() {
+        let tasks = tasks();
+        assert_eq!(counts_by_project(&tasks), vec![(String::from("family"), 1), (String::from("garage"), 2)]);
+        assert_eq!(counts_by_context(&tasks), vec![(String::from("phone"), 1), (String::from("shop"), 1)]);
+        assert!(counts_by_project(&[]).is_empty());
+    }
+
+    #[test]
+    fn exclusions() {
+        let filter = TaskFilter { exclude_projects : vec![String::from("garage")], ..Default::default() };
+        assert_eq!(matching(&filter), vec!["call mom +family @phone"]);
+        let filter = TaskFilter { projects : vec![String::from("garage")], exclude_contexts : vec![String::from("shop")], ..Default::default() };
+        assert_eq!(matching(&filter), vec!["fix the bike +garage", "sell the car +garage"]);
+    }
+
+    #[test]
+    fn queries() {
+        let filter = TaskFilter::from_query("  @home  -@work +errands\tcall -+garage ");
+        assert_eq!(filter.contexts, vec!["home"]);
+        assert_eq!(filter.exclude_contexts, vec!["work"]);
+        assert_eq!(filter.projects, vec!["errands"]);
+        assert_eq!(filter.exclude_projects, vec!["garage"]);
+        assert_eq!(filter.words, vec!["call"]);
+        assert_eq!(filter.query(), "+errands @home -+garage -@work call");
+        assert_eq!(TaskFilter::from_query(""), TaskFilter::default());
+        // Lone prefixes and dashes are searched in the content
+        assert_eq!(TaskFilter::from_query("+ @ - -x").words, vec!["+", "@", "-", "-x"]);
+
+        assert_eq!(matching(&TaskFilter::from_query("+garage -@shop")), vec!["fix the bike +garage", "sell the car +garage"]);
+        assert_eq!(matching(&TaskFilter::from_query("-+garage -+family")), Vec::<String>::new());
+        assert_eq!(matching(&TaskFilter::from_query("BIKE +garage")), vec!["fix the bike +garage"]);
+        assert_eq!(matching(&TaskFilter::from_query("the -@phone")), vec!["fix the bike +garage", "sell the car +garage"]);
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use rofitodo::storage;
+
+/// Run git in a directory and return its output
+fn output(dir : &Path, args : &[&str]) -> Result<Output, String> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Can't run git: {}", e))
+}
+
+/// Run git in a directory, return its error output if it fails
+fn git(dir : &Path, args : &[&str]) -> Result<(), String> {
+    let output = output(dir, args)?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Return the directory and the name of a file for git
+fn split(file : &Path) -> Result<(&Path, &str), String> {
+    let name = file.file_name().and_then(|n| n.to_str()).ok_or_else(|| format!("{} is not a file", file.display()))?;
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new(".")
+    };
+    Ok((dir, name))
+}
+
+/// Commit the changes of a file in the git repository of its directory, `rofitodo: ` followed by the summary
+///
+/// Nothing is committed with `--dry-run` or if the file didn't change
+///
+/// Arguments:
+///
+/// * `file` - the saved file
+/// * `summary` - the description of the changes
+pub fn commit(file : &Path, summary : &str) -> Result<(), String> {
+    if storage::dry_run() {
+        return Ok(());
+    }
+    let (dir, name) = split(file)?;
+    git(dir, &["add", "--", name])?;
+    // `git diff --quiet` succeeds when nothing is staged
+    if output(dir, &["diff", "--cached", "--quiet", "--", name])?.status.success() {
+        return Ok(());
+    }
+    git(dir, &["commit", "--quiet", "-m", &format!("rofitodo: {}", summary), "--", name])
+}
+
+/// Update the git repository of the directory of a file with `git pull --ff-only`, nothing is done with `--dry-run`
+///
+/// Arguments:
+///
+/// * `file` - the task file
+pub fn pull(file : &Path) -> Result<(), String> {
+    if storage::dry_run() {
+        return Ok(());
+    }
+    let (dir, _) = split(file)?;
+    git(dir, &["pull", "--ff-only", "--quiet"])
+}
+
+#[cfg(test)]
+mod git_tests {
+    use super::*;
+    use std::fs;
+
+    /// Create a git repository with a committed task file, `None` if git isn't installed
+    fn repository(name : &str) -> Option<std::path::PathBuf> {
+        let dir = std::env::temp_dir().join(format!("rofitodo_git_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        git(&dir, &["init", "--quiet"]).ok()?;
+        git(&dir, &["config", "user.name", "RofiTodo"]).unwrap();
+        git(&dir, &["config", "user.email", "rofitodo@example.com"]).unwrap();
+        fs::write(dir.join("todo.txt"), "pay rent\n").unwrap();
+        commit(&dir.join("todo.txt"), "add 'pay rent'").unwrap();
+        Some(dir)
+    }
+
+    fn log(dir : &Path) -> Vec<String> {
+        String::from_utf8(output(dir, &["log", "--format=%s"]).unwrap().stdout).unwrap().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn commit_file() {
+        let dir = match repository("commit") {
+            Some(dir) => dir,
+            None => return
+        };
+        let todo = dir.join("todo.txt");
+        fs::write(&todo, "x 2024-01-02 2024-01-01 pay rent\n").unwrap();
+        // The other files are not committed
+        fs::write(dir.join("notes.txt"), "draft\n").unwrap();
+        commit(&todo, "complete 'pay rent'").unwrap();
+        assert_eq!(log(&dir), vec!["rofitodo: complete 'pay rent'", "rofitodo: add 'pay rent'"]);
+        // A file which didn't change is not committed
+        commit(&todo, "nothing").unwrap();
+        assert_eq!(log(&dir).len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn not_a_repository() {
+        let dir = std::env::temp_dir().join(format!("rofitodo_git_none_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let todo = dir.join("todo.txt");
+        fs::write(&todo, "pay rent\n").unwrap();
+        if git(&dir, &["--version"]).is_ok() {
+            assert!(commit(&todo, "add 'pay rent'").is_err());
+            assert!(pull(&todo).is_err());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use rofitodo::storage::Cipher;
+
+/// The encryption of the files with gpg, the passphrase is asked by the gpg agent
+pub struct Gpg {
+    /// The gpg program, like `gpg2` or a fake gpg in the tests
+    program : String,
+    /// The key the files are encrypted for, by default the keys the file was encrypted for
+    recipient : Option<String>,
+    /// The task file whose keys a new file is encrypted for, like the archive created by the first completed task
+    task_file : PathBuf
+}
+
+impl Gpg {
+    /// Create the encryption with gpg
+    ///
+    /// Arguments:
+    ///
+    /// * `program` - the command run instead of `gpg`
+    /// * `recipient` - a key id, a fingerprint or an email address known to gpg
+    /// * `task_file` - the task file, its archive and trash are encrypted for its keys when they are created
+    pub fn new(program : Option<String>, recipient : Option<String>, task_file : &Path) -> Self {
+        Gpg { program : program.unwrap_or_else(|| String::from("gpg")), recipient, task_file : task_file.to_path_buf() }
+    }
+
+    /// Run gpg, writing `input` to its standard input, and return its output
+    fn run(&self, args : &[&str], input : Option<&[u8]>) -> Result<Output, String> {
+        run_program(&self.program, args, input)
+    }
+
+    /// Return the ids of the keys a file is encrypted for, without decrypting it
+    fn recipients_of(&self, path : &Path) -> Vec<String> {
+        let path_arg = path.to_string_lossy();
+        let output = match self.run(&["--batch", "--list-only", "--list-packets", "--", &path_arg], None) {
+            Ok(output) => output,
+            Err(_) => return vec![]
+        };
+        // `:pubkey enc packet: version 3, algo 1, keyid 0123456789ABCDEF`
+        let mut keys = Vec::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines().filter(|l| l.starts_with(":pubkey enc packet:")) {
+            if let Some(key) = line.split("keyid ").nth(1).and_then(|k| k.split_whitespace().next()) {
+                keys.push(key.to_string());
+            }
+        }
+        keys
+    }
+}
+
+impl Cipher for Gpg {
+    fn decrypt(&self, path : &Path) -> Result<String, String> {
+        let path_arg = path.to_string_lossy();
+        let output = self.run(&["--quiet", "--decrypt", "--", &path_arg], None)?;
+        if !output.status.success() {
+            return Err(format!("Could not decrypt {} : {}", path.display(), error_of(&output)));
+        }
+        String::from_utf8(output.stdout).map_err(|_| format!("{} is not a text file once decrypted", path.display()))
+    }
+
+    /// Encrypt the new content for the recipient of the configuration, otherwise for the keys the file is encrypted for,
+    /// or the keys of the task file if the file doesn't exist yet
+    fn encrypt(&self, path : &Path, content : &str) -> Result<Vec<u8>, String> {
+        let recipients = match &self.recipient {
+            Some(recipient) => vec![recipient.clone()],
+            None if !path.exists() => self.recipients_of(&self.task_file),
+            None => self.recipients_of(path)
+        };
+        if recipients.is_empty() {
+            return Err(format!("Could not encrypt {} : no recipient, set `gpg_recipient` in the configuration", path.display()));
+        }
+        let mut args = vec!["--quiet", "--batch", "--yes", "--output", "-", "--encrypt"];
+        for recipient in &recipients {
+            args.push("--recipient");
+            args.push(recipient);
+        }
+        let output = self.run(&args, Some(content.as_bytes()))?;
+        if !output.status.success() || output.stdout.is_empty() {
+            return Err(format!("Could not encrypt {} : {}", path.display(), error_of(&output)));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Run a gpg program, see `run`
+fn run_program(program : &str, args : &[&str], input : Option<&[u8]>) -> Result<Output, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("{} is not installed, it is needed for the encrypted files", program),
+            _ => format!("Can't run {}: {}", program, e)
+        })?;
+    // The input is written while the output is read: gpg stops reading once its output fills the pipe
+    std::thread::scope(|scope| {
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            // gpg may stop reading on an error, which is in its error output
+            scope.spawn(move || { let _ = stdin.write_all(input); });
+        }
+        child.wait_with_output().map_err(|e| format!("Can't run {}: {}", program, e))
+    })
+}
+
+/// Return the error output of gpg, or its exit status if there is none
+fn error_of(output : &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.trim() {
+        "" => output.status.to_string(),
+        stderr => stderr.to_string()
+    }
+}
+
+/// A fake gpg for the tests, a shell script "encrypting" a file with a `FAKEGPG <recipient>` first line
+#[cfg(test)]
+fn fake_program() -> &'static Path {
+    static FAKE : std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+    FAKE.get_or_init(|| {
+        let path = std::env::temp_dir().join(format!("rofitodo_fake_gpg_{}", std::process::id()));
+        let script = r#"#!/bin/sh
+last() { for arg; do :; done; printf '%s' "$arg"; }
+case " $* " in
+    *" --list-packets "*) sed -n '1s/^FAKEGPG \(.*\)$/:pubkey enc packet: version 3, algo 1, keyid \1/p' "$(last "$@")" ;;
+    *" --decrypt "*)
+        file=$(last "$@")
+        head -n 1 "$file" | grep -q '^FAKEGPG ' || { echo "gpg: decryption failed: No secret key" >&2; exit 2; }
+        tail -n +2 "$file" ;;
+    *" --encrypt "*)
+        echo "FAKEGPG $(printf '%s\n' "$@" | sed -n '/^--recipient$/{n;p;q}')"; cat ;;
+esac
+"#;
+        std::fs::write(&path, script).unwrap();
+        Command::new("chmod").arg("+x").arg(&path).status().unwrap();
+        // A process started by another test may still hold the script open for writing for a moment
+        while let Err(e) = Command::new(&path).output() {
+            assert_eq!(e.kind(), ErrorKind::ExecutableFileBusy);
+        }
+        path
+    })
+}
+
+#[cfg(test)]
+mod gpg_tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("rofitodo_gpg_round_{}.txt.gpg", std::process::id()));
+        let gpg = Gpg::new(Some(fake_program().to_string_lossy().to_string()), None, &path);
+        fs::write(&path, "FAKEGPG 0123456789ABCDEF\ncall mom\n").unwrap();
+        assert_eq!(gpg.decrypt(&path).unwrap(), "call mom\n");
+        // The file keeps the key it was encrypted for
+        assert_eq!(gpg.recipients_of(&path), vec!["0123456789ABCDEF"]);
+        assert_eq!(gpg.encrypt(&path, "pay rent\n").unwrap(), b"FAKEGPG 0123456789ABCDEF\npay rent\n");
+        fs::write(&path, "not encrypted\n").unwrap();
+        assert_eq!(gpg.decrypt(&path).unwrap_err(), format!("Could not decrypt {} : gpg: decryption failed: No secret key", path.display()));
+        assert!(gpg.encrypt(&path, "pay rent\n").unwrap_err().contains("no recipient"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn new_file() {
+        let todo = std::env::temp_dir().join(format!("rofitodo_gpg_todo_{}.txt.gpg", std::process::id()));
+        let done = std::env::temp_dir().join(format!("rofitodo_gpg_done_{}.txt.gpg", std::process::id()));
+        fs::write(&todo, "FAKEGPG 0123456789ABCDEF\ncall mom\n").unwrap();
+        // The archive which doesn't exist yet is encrypted for the keys of the task file
+        let gpg = Gpg::new(Some(fake_program().to_string_lossy().to_string()), None, &todo);
+        assert_eq!(gpg.encrypt(&done, "x pay rent\n").unwrap(), b"FAKEGPG 0123456789ABCDEF\nx pay rent\n");
+        // The recipient of the configuration comes first
+        let gpg = Gpg::new(Some(fake_program().to_string_lossy().to_string()), Some(String::from("me@example.com")), &todo);
+        assert_eq!(gpg.encrypt(&done, "x pay rent\n").unwrap(), b"FAKEGPG me@example.com\nx pay rent\n");
+        fs::remove_file(&todo).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn large_input() {
+        // Far more than the buffer of a pipe
+        let input = "call mom\n".repeat(100_000);
+        let output = run_program("cat", &[], Some(input.as_bytes())).unwrap();
+        assert_eq!(output.stdout, input.as_bytes());
+    }
+
+    #[test]
+    fn missing_program() {
+        assert_eq!(run_program("rofitodo_no_gpg", &["--version"], None).unwrap_err(),
+                   "rofitodo_no_gpg is not installed, it is needed for the encrypted files");
+    }
+}
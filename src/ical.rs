@@ -0,0 +1,183 @@
+use chrono::NaiveDate;
+use crate::task::Task;
+
+/// The longest line of an iCalendar file, in octets without the line break
+const MAX_LINE : usize = 75;
+
+/// Return the tasks as an iCalendar file, one VTODO per task
+///
+/// The tasks without creation or completion date are stamped with `today`
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks
+/// * `today` - the current date
+pub fn to_ics<'a>(tasks : impl IntoIterator<Item=&'a Task>, today : NaiveDate) -> String {
+    let mut lines = vec![String::from("BEGIN:VCALENDAR"), String::from("VERSION:2.0"), String::from("PRODID:-//RofiTodo//RofiTodo//EN")];
+    for task in tasks {
+        lines.extend(vtodo(task, today));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+    lines.iter().map(|line| fold(line)).collect::<Vec<_>>().concat()
+}
+
+/// Return the properties of the VTODO of a task, before folding
+fn vtodo(task : &Task, today : NaiveDate) -> Vec<String> {
+    let mut lines = vec![String::from("BEGIN:VTODO"), format!("UID:{}", uid(task))];
+    let stamp = task.completion_date.or(task.creation_date).unwrap_or(today);
+    lines.push(format!("DTSTAMP:{}", date_time(stamp)));
+    if let Some(date) = task.creation_date {
+        lines.push(format!("CREATED:{}", date_time(date)));
+    }
+    lines.push(format!("SUMMARY:{}", escape(task.get_content())));
+    match (task.get_due(), task.get_due_time()) {
+        // A floating time, in the time zone of the calendar
+        (Some(date), Some(time)) => lines.push(format!("DUE:{}", date.and_time(time).format("%Y%m%dT%H%M%S"))),
+        (Some(date), None) => lines.push(format!("DUE;VALUE=DATE:{}", date.format("%Y%m%d"))),
+        (None, _) => ()
+    }
+    if let Some(priority) = task.get_priority() {
+        lines.push(format!("PRIORITY:{}", ics_priority(priority)));
+    }
+    if task.completion {
+        lines.push(String::from("STATUS:COMPLETED"));
+        if let Some(date) = task.completion_date {
+            lines.push(format!("COMPLETED:{}", date_time(date)));
+        }
+    } else {
+        lines.push(String::from("STATUS:NEEDS-ACTION"));
+    }
+    let mut categories : Vec<String> = Vec::new();
+    for tag in task.get_project_tags().iter().chain(task.get_context_tags().iter()).map(|t| escape(t)) {
+        if !categories.contains(&tag) {
+            categories.push(tag);
+        }
+    }
+    if !categories.is_empty() {
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    lines.push(String::from("END:VTODO"));
+    lines
+}
+
+/// Return the UID of a task, the same for each export of the task
+///
+/// The UID is a hash of the creation date and the content, so the task keeps it when it is completed, postponed or
+/// given another priority
+fn uid(task : &Task) -> String {
+    // FNV-1a, whose value doesn't change with the version of Rust
+    let key = format!("{} {}", task.creation_date.map(|d| d.to_string()).unwrap_or_default(), task.get_content());
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}@rofitodo", hash)
+}
+
+/// Return a date as an iCalendar UTC date-time, at midnight
+fn date_time(date : NaiveDate) -> String {
+    format!("{}T000000Z", date.format("%Y%m%d"))
+}
+
+/// Map a todo.txt priority to an iCalendar priority: A is 1, the highest, and the priorities after H are 9, the lowest
+fn ics_priority(priority : char) -> u8 {
+    (priority as u8).saturating_sub(b'A').saturating_add(1).min(9)
+}
+
+/// Escape a text value: backslashes, semicolons, commas and line breaks
+fn escape(text : &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+/// Fold a line in lines of at most 75 octets ended by CRLF, the next lines starting with a space
+///
+/// A UTF-8 character is never split between two lines
+fn fold(line : &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod ical_tests {
+    use super::*;
+
+    fn date(day : u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    #[test]
+    fn vtodos() {
+        let tasks = ["(B) 2024-01-02 call mom, then dad; @phone +family due:2024-01-10",
+                     "x 2024-01-05 2024-01-03 water plants +home pri:A"].iter().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let ics = to_ics(&tasks, date(20));
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VTODO\r\nEND:VCALENDAR\r\n"));
+        let lines = ics.split("\r\n").collect::<Vec<_>>();
+        assert_eq!(&lines[4..12], &[
+            lines[4], "DTSTAMP:20240102T000000Z", "CREATED:20240102T000000Z", "SUMMARY:call mom\\, then dad\\; @phone +family",
+            "DUE;VALUE=DATE:20240110", "PRIORITY:2", "STATUS:NEEDS-ACTION", "CATEGORIES:family,phone"
+        ]);
+        assert!(lines[4].starts_with("UID:"));
+        assert!(ics.contains("\r\nDTSTAMP:20240105T000000Z\r\nCREATED:20240103T000000Z\r\nSUMMARY:water plants +home\r\nPRIORITY:1\r\nSTATUS:COMPLETED\r\nCOMPLETED:20240105T000000Z\r\nCATEGORIES:home\r\n"));
+        assert!(to_ics(&["call mom due:2024-01-10T18:30".parse::<Task>().unwrap()], date(20)).contains("\r\nDUE:20240110T183000\r\n"));
+        // A task without date is stamped today
+        assert!(to_ics(&["call mom".parse::<Task>().unwrap()], date(20)).contains("DTSTAMP:20240120T000000Z"));
+    }
+
+    #[test]
+    fn stable_uid() {
+        let mut task = "2024-01-02 call mom due:2024-01-10".parse::<Task>().unwrap();
+        let first = uid(&task);
+        task.set_priority(Some('A')).unwrap();
+        task.set_due(Some(date(12)));
+        task.set_completed();
+        assert_eq!(uid(&task), first);
+        assert_ne!(uid(&"2024-01-02 call dad".parse::<Task>().unwrap()), first);
+        assert_ne!(uid(&"2024-01-03 call mom".parse::<Task>().unwrap()), first);
+    }
+
+    #[test]
+    fn priorities() {
+        assert_eq!(['A', 'B', 'H', 'I', 'Z'].iter().map(|p| ics_priority(*p)).collect::<Vec<_>>(), vec![1, 2, 8, 9, 9]);
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape("a,b;c\\d\ne\r\n"), "a\\,b\\;c\\\\d\\ne\\n");
+    }
+
+    #[test]
+    fn folding() {
+        assert_eq!(fold("SUMMARY:short"), "SUMMARY:short\r\n");
+        let long = format!("SUMMARY:{}", "a".repeat(100));
+        let folded = fold(&long);
+        let lines = folded.trim_end_matches("\r\n").split("\r\n").collect::<Vec<_>>();
+        assert_eq!(lines.iter().map(|l| l.len()).collect::<Vec<_>>(), vec![75, 34]);
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines.concat().replace(" a", "a").len(), long.len());
+        // The characters of several octets stay whole
+        let accents = format!("SUMMARY:{}", "é".repeat(40));
+        let folded = fold(&accents);
+        assert!(folded.split("\r\n").all(|l| l.len() <= 75));
+        assert_eq!(folded.split("\r\n").next().unwrap().len(), 74);
+        assert_eq!(folded.replace("\r\n ", "").trim_end(), accents);
+    }
+}
@@ -3,20 +3,39 @@ use std::collections::HashMap;
 use std::rc::Rc;
 use std::cmp::Ordering;
 
-type CompareFunction<T> = fn(&T,&T) -> Ordering;
+type CompareFunction<T> = Rc<dyn Fn(&T,&T) -> Ordering>;
 
 /// Store data data with mutiple indexes and filters
 pub struct Indexer<T> {
     /// An index which stores all the values without filtering
-    main_index : BTreeSet<Rc<T>>,
+    main_index : BTreeSet<ElementWrapper<T>>,
+    /// A function to compare the elements of the main index, two elements are only stored once if it finds them equal
+    order : CompareFunction<T>,
     /// An hashmap of indexes for storing all the filtered and ordered data
     indexes : HashMap<String, Index<T>>
 }
 
-impl<T : std::cmp::Ord> Indexer<T> {
+impl<T : std::cmp::Ord + 'static> Default for Indexer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T : std::cmp::Ord + 'static> Indexer<T> {
     /// Create new Indexer
     pub fn new() -> Self {
-        Indexer { main_index : BTreeSet::<Rc<T>>::new() , indexes : HashMap::<String,Index<T>>::new() }
+        Self::with_order(T::cmp)
+    }
+}
+
+impl<T> Indexer<T> {
+    /// Create new Indexer whose main index is sorted by a function
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare_fn` - a function to compare the elements, the elements it finds equal are only stored once
+    pub fn with_order(compare_fn : impl Fn(&T,&T) -> Ordering + 'static) -> Self {
+        Indexer { main_index : BTreeSet::<ElementWrapper<T>>::new(), order : Rc::new(compare_fn), indexes : HashMap::<String,Index<T>>::new() }
     }
 
     /// Index a new element into the structure
@@ -26,8 +45,8 @@ impl<T : std::cmp::Ord> Indexer<T> {
     /// * `element` - the element to index
     pub fn add(&mut self, element : T) -> Rc<T> {
         let e = Rc::new(element);
-        self.main_index.insert(Rc::clone(&e));
-        for (_, index) in &mut self.indexes {
+        self.main_index.insert(ElementWrapper::new(Rc::clone(&e), Rc::clone(&self.order)));
+        for index in self.indexes.values_mut() {
             index.register(Rc::clone(&e));
         }
         e
@@ -39,7 +58,7 @@ impl<T : std::cmp::Ord> Indexer<T> {
     /// 
     /// * `element` - Rc reference to the element to remove
     pub fn remove(&mut self, element : Rc<T>) -> Option<T> {
-        self.main_index.remove(&element);
+        self.main_index.remove(&ElementWrapper::new(Rc::clone(&element), Rc::clone(&self.order)));
         let mut empty_indexes = Vec::<String>::new();
         for (name, index) in &mut self.indexes {
             index.remove(&element);
@@ -50,14 +69,11 @@ impl<T : std::cmp::Ord> Indexer<T> {
         for name in empty_indexes {
             self.remove_index(&name);
         }
-        match Rc::try_unwrap(element) {
-            Ok(task) => Some(task),
-            _ => None
-        }
+        Rc::try_unwrap(element).ok()
     }
 
     /// Get the main index to iterate over all the stored elements
-    pub fn get_main_index(&self) -> &BTreeSet<Rc<T>> {
+    pub fn get_main_index(&self) -> &BTreeSet<ElementWrapper<T>> {
         &self.main_index
     }
 
@@ -69,16 +85,13 @@ impl<T : std::cmp::Ord> Indexer<T> {
     /// * `name` - the name of the new index
     /// * `filter` - a closure to filter the elements (returns `true` if the value should be in the index)
     /// * `compare_fn` - a function to compare and sort elements
-    pub fn new_index(&mut self, name : String, filter : impl Fn(&T) -> bool + 'static, compare_fn : CompareFunction<T>) {
-        match self.get_index(&name) {
-            None => {
-                let mut new_idx = Index::new(filter, compare_fn);
-                for x in &self.main_index {
-                    new_idx.register(Rc::clone(&x));
-                }
-                self.indexes.insert(name.clone(), new_idx);
-            },
-            _ => ()
+    pub fn new_index(&mut self, name : String, filter : impl Fn(&T) -> bool + 'static, compare_fn : impl Fn(&T,&T) -> Ordering + 'static) {
+        if self.get_index(&name).is_none() {
+            let mut new_idx = Index::new(filter, compare_fn);
+            for x in &self.main_index {
+                new_idx.register(Rc::clone(&x.content));
+            }
+            self.indexes.insert(name.clone(), new_idx);
         }
     }
 
@@ -89,16 +102,13 @@ impl<T : std::cmp::Ord> Indexer<T> {
     /// 
     /// * `name` - the name of the new index
     /// * `filter` - a closure to filter the elements (returns `true` if the value should be in the index)
-    pub fn new_autoremove_index(&mut self, name : String, filter : impl Fn(&T) -> bool + 'static, compare_fn : CompareFunction<T>) {
-        match self.get_index(&name) {
-            None => {
-                let mut new_idx = Index::new_autoremove(filter, compare_fn);
-                for x in &self.main_index {
-                    new_idx.register(Rc::clone(&x));
-                }
-                self.indexes.insert(name.clone(), new_idx);
-            },
-            _ => ()
+    pub fn new_autoremove_index(&mut self, name : String, filter : impl Fn(&T) -> bool + 'static, compare_fn : impl Fn(&T,&T) -> Ordering + 'static) {
+        if self.get_index(&name).is_none() {
+            let mut new_idx = Index::new_autoremove(filter, compare_fn);
+            for x in &self.main_index {
+                new_idx.register(Rc::clone(&x.content));
+            }
+            self.indexes.insert(name.clone(), new_idx);
         }
     }
 
@@ -107,7 +117,7 @@ impl<T : std::cmp::Ord> Indexer<T> {
     /// Arguments:
     /// 
     /// * `name` - the name of the index
-    pub fn remove_index(&mut self, name: &String) -> () {
+    pub fn remove_index(&mut self, name: &String) {
         self.indexes.remove(name);
     }
 
@@ -132,6 +142,7 @@ impl<T : std::cmp::Ord> Indexer<T> {
         self.indexes.get(name)
     }
 
+    /// Return the names of the indexes
     pub fn get_index_list(&self) -> Vec<&String> {
         self.indexes.iter().map(|t|t.0).collect::<Vec<_>>()
     }
@@ -158,8 +169,8 @@ impl<T> Index<T> {
     /// 
     /// * `is_indexable` - a closure to filter the elements (returns `true` if the value should be in the index)
     /// * `compare_fn` - a function to compare and sort elements
-    pub fn new(is_indexable : impl Fn(&T) -> bool + 'static, compare : CompareFunction<T>) -> Self {
-        Index { content : BTreeSet::<ElementWrapper<T>>::new(), is_indexable : Box::new(is_indexable), compare : compare , remove_if_empty : false }
+    pub fn new(is_indexable : impl Fn(&T) -> bool + 'static, compare : impl Fn(&T,&T) -> Ordering + 'static) -> Self {
+        Index { content : BTreeSet::<ElementWrapper<T>>::new(), is_indexable : Box::new(is_indexable), compare : Rc::new(compare), remove_if_empty : false }
     }
 
     /// Create a new Index which is removed when empty
@@ -170,8 +181,8 @@ impl<T> Index<T> {
     /// 
     /// * `is_indexable` - a closure to filter the elements (returns `true` if the value should be in the index)
     /// * `compare_fn` - a function to compare and sort elements
-    pub fn new_autoremove(is_indexable : impl Fn(&T) -> bool + 'static, compare : CompareFunction<T>) -> Self {
-        Index { content : BTreeSet::<ElementWrapper<T>>::new(), is_indexable : Box::new(is_indexable), compare : compare , remove_if_empty : true }
+    pub fn new_autoremove(is_indexable : impl Fn(&T) -> bool + 'static, compare : impl Fn(&T,&T) -> Ordering + 'static) -> Self {
+        Index { content : BTreeSet::<ElementWrapper<T>>::new(), is_indexable : Box::new(is_indexable), compare : Rc::new(compare), remove_if_empty : true }
     }
 
     /// Register a new element in the Index
@@ -179,9 +190,9 @@ impl<T> Index<T> {
     /// Arguments:
     /// 
     /// * `element` - a boxed element
-    pub fn register(&mut self, element : Rc<T>) -> () {
+    pub fn register(&mut self, element : Rc<T>) {
         if (self.is_indexable)(element.as_ref()) {
-            let ew = ElementWrapper::new(element, self.compare);
+            let ew = ElementWrapper::new(element, Rc::clone(&self.compare));
             self.content.insert(ew);
         }
     }
@@ -192,8 +203,8 @@ impl<T> Index<T> {
     /// 
     /// * `element` - a reference to boxed element
     pub fn remove(&mut self, element : &Rc<T>) {
-        if (self.is_indexable)(&element) {
-            self.content.remove(&ElementWrapper::new(Rc::clone(element), self.compare));
+        if (self.is_indexable)(element) {
+            self.content.remove(&ElementWrapper::new(Rc::clone(element), Rc::clone(&self.compare)));
         }
     }
 
@@ -261,6 +272,25 @@ impl<T> ElementWrapper<T> {
     pub fn _content(&self) -> &T {
         self.content.as_ref()
     }
+
+    /// Return a new smart pointer to the content
+    pub fn shared(&self) -> Rc<T> {
+        Rc::clone(&self.content)
+    }
+}
+
+impl<T> std::ops::Deref for ElementWrapper<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.content.as_ref()
+    }
+}
+
+impl<T> AsRef<T> for ElementWrapper<T> {
+    fn as_ref(&self) -> &T {
+        self.content.as_ref()
+    }
 }
 
 /// Implementing ordering for `ElementWrapper` to allow BTreeSet to sort it
@@ -273,7 +303,7 @@ impl<T> Ord for ElementWrapper<T> {
 /// Implementing `PartialOrd` to implement `Ord` for `ElementWrapper`
 impl<T> PartialOrd for ElementWrapper<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some((self.compare)(self.content.as_ref(), other.content.as_ref()))
+        Some(self.cmp(other))
     }
 }
 
@@ -289,6 +319,7 @@ impl<T> PartialEq for ElementWrapper<T> {
 impl<T> Eq for ElementWrapper<T> { }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod index_tests {
     use super::*;
 
@@ -361,6 +392,7 @@ mod index_tests {
 }
 
 #[cfg(test)]
+#[allow(clippy::nonminimal_bool, clippy::into_iter_on_ref)]
 mod indexer_tests {
     use super::*;
 
@@ -0,0 +1,187 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use serde::de::Error;
+use std::collections::BTreeMap;
+use crate::task::Task;
+use crate::builder::TaskBuilder;
+use crate::dates::due_tag;
+
+/// A task as a JSON object
+///
+/// The due and threshold dates and the priority of a completed task have their own field instead of their custom tag.
+/// The line and the id of the task are not kept, they only have a meaning in the task list
+#[derive(Debug, Serialize, Deserialize)]
+struct TaskJson {
+    content : String,
+    #[serde(default)]
+    completed : bool,
+    #[serde(default)]
+    priority : Option<char>,
+    #[serde(default)]
+    creation_date : Option<NaiveDate>,
+    #[serde(default)]
+    completion_date : Option<NaiveDate>,
+    #[serde(default)]
+    due : Option<NaiveDate>,
+    /// Only written for a task due at a time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due_time : Option<NaiveTime>,
+    #[serde(default)]
+    threshold : Option<NaiveDate>,
+    #[serde(default)]
+    projects : Vec<String>,
+    #[serde(default)]
+    contexts : Vec<String>,
+    #[serde(default)]
+    tags : BTreeMap<String, String>
+}
+
+impl From<&Task> for TaskJson {
+    fn from(task : &Task) -> Self {
+        let tags = task.custom_tags()
+            .filter(|(key, _)| match *key {
+                "due" => task.get_due().is_none(),
+                "t" => task.get_threshold().is_none(),
+                "pri" => task.priority.is_some() || task.get_priority().is_none(),
+                _ => true
+            })
+            .map(|(key, value)| (String::from(key), String::from(value)))
+            .collect();
+        TaskJson {
+            content : task.get_content().clone(),
+            completed : task.completion,
+            priority : task.get_priority(),
+            creation_date : task.creation_date,
+            completion_date : task.completion_date,
+            due : *task.get_due(),
+            due_time : task.get_due_time(),
+            threshold : *task.get_threshold(),
+            projects : task.get_project_tags().clone(),
+            contexts : task.get_context_tags().clone(),
+            tags
+        }
+    }
+}
+
+impl TaskJson {
+    /// Build the task with the checks of `TaskBuilder`
+    ///
+    /// A completed task without creation date is given its completion date as creation date, like `Task::set_completed`
+    fn into_task(self) -> Result<Task, String> {
+        let mut builder = TaskBuilder::new().content(&self.content);
+        for tag in self.projects.iter() {
+            builder = builder.project(tag);
+        }
+        for tag in self.contexts.iter() {
+            builder = builder.context(tag);
+        }
+        for (key, value) in self.tags.iter() {
+            builder = builder.tag(key, value);
+        }
+        if let Some(date) = self.threshold {
+            builder = builder.tag("t", &date.format("%Y-%m-%d").to_string());
+        }
+        if let Some(date) = self.due {
+            builder = match self.due_time {
+                Some(time) => builder.tag("due", &due_tag(date, Some(time))),
+                None => builder.due(date)
+            };
+        }
+        let creation_date = if self.completed { self.creation_date.or(self.completion_date) } else { self.creation_date };
+        if let Some(date) = creation_date {
+            builder = builder.created(date);
+        }
+        match (self.completed, self.completion_date) {
+            (true, Some(date)) => builder = builder.completed(date),
+            // A completed todo.txt line can have no dates, the priority goes to the `pri` tag once completed
+            (true, None) => {
+                let mut task = builder.build()?;
+                task.completion = true;
+                task.set_priority(self.priority).map_err(|e| e.to_string())?;
+                return Ok(task);
+            },
+            (false, _) => ()
+        }
+        if let Some(priority) = self.priority {
+            builder = builder.priority(priority);
+        }
+        // The completion date of a task to do is dropped, like `Task::set_not_completed` does
+        Ok(builder.build()?)
+    }
+}
+
+impl Serialize for Task {
+    fn serialize<S : Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+        TaskJson::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Task {
+    fn deserialize<D : Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+        TaskJson::deserialize(deserializer)?.into_task().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+
+    #[test]
+    fn task_to_json() {
+        let task = "(A) 2024-01-15 call plumber +house @phone est:2h due:2024-03-01 t:2024-02-20".parse::<Task>().unwrap();
+        let json = serde_json::to_value(&task).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "content" : "call plumber +house @phone",
+            "completed" : false,
+            "priority" : "A",
+            "creation_date" : "2024-01-15",
+            "completion_date" : null,
+            "due" : "2024-03-01",
+            "threshold" : "2024-02-20",
+            "projects" : ["house"],
+            "contexts" : ["phone"],
+            "tags" : { "est" : "2h" }
+        }));
+        // The time of a due date is only written when there is one
+        let timed = "call plumber due:2024-03-01T09:30".parse::<Task>().unwrap();
+        let json = serde_json::to_value(&timed).unwrap();
+        assert_eq!((&json["due"], &json["due_time"]), (&serde_json::json!("2024-03-01"), &serde_json::json!("09:30:00")));
+        assert_eq!(serde_json::from_value::<Task>(json).unwrap().to_string(), "call plumber due:2024-03-01T09:30");
+        // The priority of a completed task is not repeated in the tags
+        let done = "x 2024-01-16 2024-01-15 call plumber pri:B".parse::<Task>().unwrap();
+        let json = serde_json::to_value(&done).unwrap();
+        assert_eq!(json["priority"], "B");
+        assert_eq!(json["tags"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn round_trip() {
+        let lines = ["(A) 2024-01-15 call plumber +house @phone est:2h due:2024-03-01",
+                     "x 2024-01-16 2024-01-15 water plants rec:1w pri:C",
+                     "x sell the bike +garage",
+                     "answer mail @work t:2024-02-01 url:example.com"];
+        for line in lines.iter() {
+            let json = serde_json::to_string(&line.parse::<Task>().unwrap()).unwrap();
+            let task : Task = serde_json::from_str(&json).unwrap();
+            let reparsed = task.to_string().parse::<Task>().unwrap();
+            assert_eq!(serde_json::to_string(&reparsed).unwrap(), json);
+            assert_eq!(reparsed.get_priority(), line.parse::<Task>().unwrap().get_priority());
+        }
+    }
+
+    #[test]
+    fn from_json() {
+        // The tags missing from the content are added to it, the completed task gets a creation date
+        let task : Task = serde_json::from_str(r#"{ "content" : "pay rent", "completed" : true, "completion_date" : "2024-02-01",
+                                                    "priority" : "A", "projects" : ["home"], "tags" : { "est" : "5m" } }"#).unwrap();
+        assert_eq!(task.to_string(), "x 2024-02-01 2024-02-01 pay rent +home pri:A est:5m");
+        let task : Task = serde_json::from_str(r#"{ "content" : "pay rent", "completion_date" : "2024-02-01" }"#).unwrap();
+        assert_eq!(task.to_string(), "pay rent");
+
+        let invalid = |json : &str| serde_json::from_str::<Task>(json).unwrap_err().to_string();
+        assert!(invalid(r#"{ "content" : " " }"#).starts_with("the task is empty"));
+        assert!(invalid(r#"{ "content" : "pay rent", "priority" : "a" }"#).starts_with("invalid priority a"));
+        assert!(invalid(r#"{ "content" : "pay rent", "tags" : { "est" : "5 m" } }"#).starts_with("invalid value 5 m for tag est"));
+        assert!(invalid(r#"{ "content" : "pay rent", "due" : "friday" }"#).contains("input contains invalid characters"));
+    }
+}
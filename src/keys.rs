@@ -0,0 +1,87 @@
+/// An action run at once on the highlighted task of a list by a custom key of rofi
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyAction {
+    /// Mark the task as done
+    Complete,
+    /// Delete the task after a confirmation
+    Delete,
+    /// Open the postpone menu of the task
+    Postpone,
+    /// Edit the todo.txt line of the task
+    Edit
+}
+
+impl KeyAction {
+    /// All the actions, in the order of their custom keys
+    pub const ALL : [KeyAction; 4] = [KeyAction::Complete, KeyAction::Delete, KeyAction::Postpone, KeyAction::Edit];
+
+    /// Return the name of the action in the `[keys]` table of the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyAction::Complete => "complete",
+            KeyAction::Delete => "delete",
+            KeyAction::Postpone => "postpone",
+            KeyAction::Edit => "edit"
+        }
+    }
+
+    /// Return the key of the action when the configuration doesn't change it
+    pub fn default_key(&self) -> &'static str {
+        match self {
+            KeyAction::Complete => "Control+Return",
+            KeyAction::Delete => "Control+d",
+            KeyAction::Postpone => "Control+p",
+            KeyAction::Edit => "Control+e"
+        }
+    }
+}
+
+/// Return true if a text is a key for rofi, like `Control+Return` or `Alt+x`
+///
+/// Arguments:
+///
+/// * `key` - the key, the modifiers and the key name separated by `+`
+pub fn is_key(key : &str) -> bool {
+    key.split('+').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// Return the keys of the actions, the actions whose key is empty have no key
+///
+/// Arguments:
+///
+/// * `key_of` - the key of an action in the configuration, `None` for the default key
+pub fn bindings(key_of : impl Fn(KeyAction) -> Option<String>) -> Vec<(KeyAction, String)> {
+    KeyAction::ALL.iter()
+        .map(|action| (*action, key_of(*action).unwrap_or_else(|| action.default_key().to_string())))
+        .filter(|(_, key)| !key.is_empty())
+        .collect()
+}
+
+/// Describe the keys of the actions for the message of the lists, like `Control+Return: complete · Control+d: delete`
+///
+/// Arguments:
+///
+/// * `bindings` - the actions and their keys
+pub fn help(bindings : &[(KeyAction, String)]) -> String {
+    bindings.iter().map(|(action, key)| format!("{}: {}", key, action.name())).collect::<Vec<_>>().join(" · ")
+}
+
+#[cfg(test)]
+mod keys_tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        assert!(is_key("Control+Return") && is_key("Alt+Shift+x") && is_key("F5"));
+        assert!(!is_key("") && !is_key("Control+") && !is_key("Control Return") && !is_key("Control+d,Delete"));
+        let configured = |action : KeyAction| match action {
+            KeyAction::Delete => Some(String::new()),
+            KeyAction::Edit => Some(String::from("Alt+e")),
+            _ => None
+        };
+        let bindings = bindings(configured);
+        assert_eq!(bindings, vec![(KeyAction::Complete, String::from("Control+Return")), (KeyAction::Postpone, String::from("Control+p")),
+                                  (KeyAction::Edit, String::from("Alt+e"))]);
+        assert_eq!(help(&bindings), "Control+Return: complete · Control+p: postpone · Alt+e: edit");
+    }
+}
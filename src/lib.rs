@@ -0,0 +1,116 @@
+//! Read, modify and write task lists in the [todo.txt format](https://github.com/todotxt/todo.txt)
+//!
+//! This is the library under the `rofitodo` menus: the tasks and their todo.txt lines, the task files with their
+//! sort orders and filters, the archive, the trash, the backups and the undo history. It never runs a program,
+//! the menus and the commands they launch are in the `rofitodo` executable.
+//!
+//! A task is read from its todo.txt line, modified and written back:
+//!
+//! ```
+//! use rofitodo::{Task, TaskParseError};
+//!
+//! # fn main() -> Result<(), TaskParseError> {
+//! let mut task = "2024-01-15 call mom @phone due:2024-03-01".parse::<Task>()?;
+//! task.set_priority(Some('A')).unwrap();
+//! task.add_project_tag("family");
+//! assert_eq!(task.to_string(), "(A) 2024-01-15 call mom @phone +family due:2024-03-01");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A `TaskList` keeps the lines of its task file, only the lines of the modified tasks change when it is saved:
+//!
+//! ```no_run
+//! use rofitodo::{TaskList, TaskFilter, SortTaskBy, SortDirection};
+//!
+//! # fn main() -> Result<(), String> {
+//! let mut list = TaskList::load("todo.txt".as_ref(), 20)?;
+//! list.sort(vec![SortTaskBy::DueDate, SortTaskBy::Priority], SortDirection::Ascending);
+//! for (_, task) in list.filter(&TaskFilter::from_query("@phone")) {
+//!     println!("{}", task.to_string());
+//! }
+//! list.save()
+//! # }
+//! ```
+
+#![warn(missing_docs)]
+
+/// Tasks and their todo.txt lines
+pub mod task;
+/// Building tasks from their parts
+pub mod builder;
+/// Task files and their tasks
+pub mod tasklist;
+/// Sets of elements sorted and filtered by several indexes
+pub mod indexer;
+/// The `rec:` tag of the recurring tasks
+pub mod recurrence;
+/// The Pango markup of the menus
+pub mod markup;
+/// Templates of the lines of the tasks in the lists
+pub mod template;
+/// The templates of the new tasks, with a placeholder for the typed text
+pub mod task_templates;
+/// The language of the menus and the format of their dates
+pub mod locale;
+/// The urgency score of the tasks
+pub mod urgency;
+/// Filters of the tasks by tag, priority, due date and text
+pub mod filter;
+/// Fuzzy search of the tasks
+pub mod search;
+/// Saved filters and sort orders
+#[cfg(feature = "serde")]
+pub mod views;
+/// The archive of the completed tasks, `done.txt`
+pub mod archive;
+/// Atomic saves and backups of the files
+pub mod storage;
+/// The files kept next to a task list, its archive and its trash
+mod sibling;
+/// The lock of a task file against a second RofiTodo
+pub mod lock;
+/// Merging the task file modified by another program
+pub mod merge;
+/// Reading and writing the lines of a task file
+pub mod todofile;
+/// The trash of the deleted tasks, `trash.txt`
+pub mod trash;
+/// The history of the changes which can be undone
+pub mod undo;
+/// The task lists open together and the changes of their tasks
+pub mod session;
+/// The log of the changes of the task lists
+pub mod audit;
+/// Typed dates and dates relative to today
+pub mod dates;
+/// The tasks grouped by due date and by project
+pub mod agenda;
+/// The priorities raised as the due dates approach
+pub mod escalation;
+/// The links of the tasks
+pub mod links;
+/// The number of tasks to do, overdue and due today
+pub mod status;
+/// The statistics of the tasks
+pub mod stats;
+/// The export of the tasks to iCalendar
+pub mod ical;
+/// The Markdown checklists of the tasks
+pub mod markdown;
+/// The CSV export of the tasks
+pub mod csv;
+/// The import of the tasks of Taskwarrior
+#[cfg(feature = "serde")]
+pub mod taskwarrior;
+/// The JSON form of the tasks
+#[cfg(feature = "serde")]
+mod json;
+/// The temporary files of the tests
+#[cfg(test)]
+mod test_files;
+
+pub use task::{Task, TaskId, SortTaskBy, SortDirection, TaskParseError, InvalidPriority};
+pub use builder::{TaskBuilder, BuildError};
+pub use tasklist::{TaskList, TaskMut, ParseErrors};
+pub use filter::TaskFilter;
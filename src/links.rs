@@ -0,0 +1,40 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Find the http and https URLs in a text
+///
+/// The punctuation ending a sentence is not part of the URLs, a closing parenthesis is kept only when it closes
+/// a parenthesis of the URL (`https://en.wikipedia.org/wiki/Rust_(language)`)
+///
+/// Arguments:
+///
+/// * `text` - the text to search
+pub fn find_urls(text : &str) -> Vec<String> {
+    lazy_static! {
+        static ref RE_URL : Regex = Regex::new(r#"https?://[^\s<>"]*[^\s<>".,;:!?)]"#).unwrap();
+    }
+    RE_URL.find_iter(text).map(|m| {
+        let mut url = m.as_str().to_string();
+        let balanced = |url : &str| url.matches('(').count() <= url.matches(')').count();
+        let mut rest = text[m.end()..].chars();
+        while !balanced(&url) && rest.next() == Some(')') {
+            url.push(')');
+        }
+        url
+    }).collect()
+}
+
+#[cfg(test)]
+mod links_tests {
+    use super::*;
+
+    #[test]
+    fn find_urls_in_text() {
+        assert_eq!(find_urls("read https://blog.example.com/post @reading"), vec!["https://blog.example.com/post"]);
+        assert_eq!(find_urls("see http://example.com/a?b=1&c=2#d."), vec!["http://example.com/a?b=1&c=2#d"]);
+        assert_eq!(find_urls("(see https://example.com/page)"), vec!["https://example.com/page"]);
+        assert_eq!(find_urls("https://en.wikipedia.org/wiki/Rust_(language)."), vec!["https://en.wikipedia.org/wiki/Rust_(language)"]);
+        assert_eq!(find_urls("a:https://a.org, b:https://b.org/x/!"), vec!["https://a.org", "https://b.org/x/"]);
+        assert!(find_urls("ftp://example.com https:// example.com").is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+use crate::dates::{self, format_date};
+use crate::template::{self, Labels};
+
+/// The languages of the built-in translations
+pub const LANGUAGES : [&str; 3] = ["en", "fr", "de"];
+
+/// The texts of the menus: their key in a translation file, in English, in French and in German
+///
+/// The labels of the recap are keyed `label.`, the words of its status `status.`, the prompts `prompt.` and the
+/// entries of the menus `menu.`
+const TEXTS : [(&str, &str, &str, &str); 123] = [
+    ("label.task", "Task", "Tâche", "Aufgabe"),
+    ("label.status", "Status", "Statut", "Status"),
+    ("label.priority", "Priority", "Priorité", "Priorität"),
+    ("label.created_on", "Created on", "Créée le", "Erstellt am"),
+    ("label.due_date", "Due date", "Échéance", "Fällig am"),
+    ("label.threshold", "Threshold", "Début", "Ab"),
+    ("label.recurrence", "Recurrence", "Récurrence", "Wiederholung"),
+    ("label.context_tags", "Context tags", "Contextes", "Kontexte"),
+    ("label.project_tags", "Project tags", "Projets", "Projekte"),
+    ("label.estimate", "Estimate", "Estimation", "Schätzung"),
+    ("label.tags", "Tags", "Étiquettes", "Tags"),
+    ("label.note", "Note", "Note", "Notiz"),
+    ("label.blocked_by", "Blocked by", "Bloquée par", "Blockiert durch"),
+    ("status.done", "Done", "Faite", "Erledigt"),
+    ("status.to_do", "To do", "À faire", "Offen"),
+    ("status.hidden", "hidden", "cachée", "versteckt"),
+    ("status.pinned", "pinned", "épinglée", "angeheftet"),
+    ("status.waiting", "not shown until then", "cachée jusque-là", "bis dahin ausgeblendet"),
+    ("prompt.todo", "Todo", "Tâches", "Aufgaben"),
+    ("prompt.task", "Task", "Tâche", "Aufgabe"),
+    ("prompt.edit", "Edit", "Modifier", "Bearbeiten"),
+    ("prompt.tag", "Tag", "Étiquette", "Tag"),
+    ("prompt.tags", "Tags", "Étiquettes", "Tags"),
+    ("prompt.bulk", "Bulk", "Groupe", "Sammelaktion"),
+    ("prompt.done", "Done", "Faites", "Erledigt"),
+    ("prompt.archive", "Archive", "Archive", "Archiv"),
+    ("prompt.archived", "Archived", "Archivée", "Archiviert"),
+    ("prompt.trash", "Trash", "Corbeille", "Papierkorb"),
+    ("prompt.deleted", "Deleted", "Supprimée", "Gelöscht"),
+    ("prompt.remove", "Remove", "Supprimer", "Löschen"),
+    ("prompt.restore", "Restore", "Restaurer", "Wiederherstellen"),
+    ("prompt.notes", "Notes", "Notes", "Notizen"),
+    ("prompt.search", "Search", "Rechercher", "Suchen"),
+    ("prompt.filter", "Filter", "Filtrer", "Filter"),
+    ("prompt.priority", "Priority", "Priorité", "Priorität"),
+    ("prompt.postpone", "Postpone", "Reporter", "Verschieben"),
+    ("prompt.views", "Views", "Vues", "Ansichten"),
+    ("prompt.statistics", "Statistics", "Statistiques", "Statistik"),
+    ("prompt.confirm", "Confirm", "Confirmer", "Bestätigen"),
+    ("prompt.day", "Day", "Jour", "Tag"),
+    ("prompt.this_week", "This week", "Cette semaine", "Diese Woche"),
+    ("prompt.copy", "Copy", "Copier", "Kopieren"),
+    ("prompt.open", "Open", "Ouvrir", "Öffnen"),
+    ("prompt.switch_list", "Switch list", "Changer de liste", "Liste wechseln"),
+    ("prompt.depends_on", "Depends on", "Dépend de", "Hängt ab von"),
+    ("prompt.new_task", "New task", "Nouvelle tâche", "Neue Aufgabe"),
+    ("prompt.focus", "Focus", "Concentration", "Fokus"),
+    ("prompt.recently_completed", "Recently completed", "Faites récemment", "Kürzlich erledigt"),
+    ("prompt.review", "Review", "Revue", "Durchsicht"),
+    ("menu.add", "+ add", "+ ajouter", "+ hinzufügen"),
+    ("menu.add_from_clipboard", "+ add from clipboard", "+ ajouter depuis le presse-papiers", "+ aus der Zwischenablage hinzufügen"),
+    ("menu.back", "← back", "← retour", "← zurück"),
+    ("menu.exit", "* exit", "* quitter", "* beenden"),
+    ("menu.cancel", "* cancel", "* annuler", "* abbrechen"),
+    ("menu.undo", "↶ undo", "↶ annuler la modification", "↶ rückgängig"),
+    ("menu.redo", "↷ redo", "↷ rétablir", "↷ wiederholen"),
+    ("menu.mark_as_done", "✔ mark as done", "✔ marquer comme faite", "✔ als erledigt markieren"),
+    ("menu.mark_as_to_do", "✔ mark as to do", "✔ marquer à faire", "✔ als offen markieren"),
+    ("menu.edit", "+ edit", "+ modifier", "+ bearbeiten"),
+    ("menu.postpone", "+ postpone", "+ reporter", "+ verschieben"),
+    ("menu.change_date", "+ change date", "+ changer l'échéance", "+ Fälligkeit ändern"),
+    ("menu.change_threshold", "+ change threshold", "+ changer le début", "+ Beginn ändern"),
+    ("menu.change_priority", "+ change priority", "+ changer la priorité", "+ Priorität ändern"),
+    ("menu.increase_priority", "↑ increase priority", "↑ augmenter la priorité", "↑ Priorität erhöhen"),
+    ("menu.decrease_priority", "↓ decrease priority", "↓ baisser la priorité", "↓ Priorität senken"),
+    ("menu.tags", "+ tags…", "+ étiquettes…", "+ Tags…"),
+    ("menu.edit_tags", "+ edit tags", "+ modifier les étiquettes", "+ Tags bearbeiten"),
+    ("menu.remove_date", "! remove date", "! retirer l'échéance", "! Fälligkeit entfernen"),
+    ("menu.remove_threshold", "! remove threshold", "! retirer le début", "! Beginn entfernen"),
+    ("menu.move_to_other_list", "→ move to other list", "→ déplacer dans une autre liste", "→ in andere Liste verschieben"),
+    ("menu.notes", "+ notes", "+ notes", "+ Notizen"),
+    ("menu.remove", "! remove", "! supprimer", "! löschen"),
+    ("menu.copy_to_clipboard", "→ copy to clipboard", "→ copier", "→ in die Zwischenablage kopieren"),
+    ("menu.copy_todo_txt_line", "→ copy todo.txt line", "→ copier la ligne todo.txt", "→ todo.txt-Zeile kopieren"),
+    ("menu.open_link", "→ open link", "→ ouvrir le lien", "→ Link öffnen"),
+    ("menu.depends_on", "+ depends on…", "+ dépend de…", "+ hängt ab von…"),
+    ("menu.pin", "★ pin", "★ épingler", "★ anheften"),
+    ("menu.unpin", "★ unpin", "★ désépingler", "★ lösen"),
+    ("menu.restore", "✔ restore", "✔ restaurer", "✔ wiederherstellen"),
+    ("menu.validate", "✔ validate", "✔ valider", "✔ bestätigen"),
+    ("menu.add_date", "+ add date", "+ ajouter une échéance", "+ Fälligkeit hinzufügen"),
+    ("menu.blank", "+ blank", "+ vide", "+ leer"),
+    ("menu.done", "~ done", "~ faites", "~ erledigt"),
+    ("menu.recently_completed", "~ recently completed", "~ faites récemment", "~ kürzlich erledigt"),
+    ("menu.today", "~ today", "~ aujourd'hui", "~ heute"),
+    ("menu.focus", "~ focus", "~ concentration", "~ Fokus"),
+    ("menu.skip", "→ skip", "→ passer", "→ überspringen"),
+    ("menu.exit_focus", "* exit focus", "* quitter la concentration", "* Fokus beenden"),
+    ("menu.review_stale_tasks", "~ review stale tasks", "~ revoir les tâches anciennes", "~ alte Aufgaben durchsehen"),
+    ("menu.keep", "→ keep", "→ garder", "→ behalten"),
+    ("menu.exit_review", "* exit review", "* quitter la revue", "* Durchsicht beenden"),
+    ("menu.all_tasks", "~ all tasks", "~ toutes les tâches", "~ alle Aufgaben"),
+    ("menu.this_week", "~ this week", "~ cette semaine", "~ diese Woche"),
+    ("menu.search", "~ search…", "~ rechercher…", "~ suchen…"),
+    ("menu.sort_by", "~ sort by…", "~ trier par…", "~ sortieren nach…"),
+    ("menu.filter", "~ filter", "~ filtrer", "~ filtern"),
+    ("menu.clear_filter", "* clear filter", "* retirer le filtre", "* Filter entfernen"),
+    ("menu.bulk_actions", "~ bulk actions", "~ actions groupées", "~ Sammelaktionen"),
+    ("menu.trash", "~ trash", "~ corbeille", "~ Papierkorb"),
+    ("menu.completed_archive", "~ completed archive", "~ archive des tâches faites", "~ Archiv"),
+    ("menu.project_tags", "@ project tags", "@ projets", "@ Projekte"),
+    ("menu.context_tags", "@ context tags", "@ contextes", "@ Kontexte"),
+    ("menu.filter_by_tag", "@ filter by tag", "@ filtrer par étiquette", "@ nach Tag filtern"),
+    ("menu.views", "~ views", "~ vues", "~ Ansichten"),
+    ("menu.statistics", "~ statistics", "~ statistiques", "~ Statistik"),
+    ("menu.switch_list", "~ switch list", "~ changer de liste", "~ Liste wechseln"),
+    ("menu.restore_from_backup", "~ restore from backup", "~ restaurer une sauvegarde", "~ Sicherung wiederherstellen"),
+    ("menu.sort_file_permanently", "~ sort file permanently", "~ trier le fichier", "~ Datei dauerhaft sortieren"),
+    ("menu.show_future_tasks", "~ show future tasks", "~ montrer les tâches futures", "~ künftige Aufgaben zeigen"),
+    ("menu.hide_future_tasks", "~ hide future tasks", "~ cacher les tâches futures", "~ künftige Aufgaben ausblenden"),
+    ("menu.show_hidden_tasks", "~ show hidden tasks", "~ montrer les tâches cachées", "~ versteckte Aufgaben zeigen"),
+    ("menu.hide_hidden_tasks", "~ hide hidden tasks", "~ cacher les tâches cachées", "~ versteckte Aufgaben ausblenden"),
+    ("menu.show_completed_tasks", "~ show completed tasks", "~ montrer les tâches faites", "~ erledigte Aufgaben zeigen"),
+    ("menu.hide_completed_tasks", "~ hide completed tasks", "~ cacher les tâches faites", "~ erledigte Aufgaben ausblenden"),
+    ("menu.group_by_project", "~ group by project", "~ grouper par projet", "~ nach Projekt gruppieren"),
+    ("menu.ungroup_tasks", "~ ungroup tasks", "~ dégrouper les tâches", "~ Gruppierung aufheben"),
+    ("menu.save_current_view", "+ save current view", "+ enregistrer la vue", "+ Ansicht speichern"),
+    ("menu.default_view", "* default view", "* vue par défaut", "* Standardansicht"),
+    ("menu.rename", "+ rename", "+ renommer", "+ umbenennen"),
+    ("menu.rename_tag", "+ rename tag", "+ renommer l'étiquette", "+ Tag umbenennen"),
+    ("menu.apply", "✔ apply", "✔ appliquer", "✔ anwenden"),
+    ("menu.complete", "✔ complete", "✔ terminer", "✔ erledigen"),
+    ("menu.delete", "! delete", "! supprimer", "! löschen")
+];
+
+/// The language of the menus and the format of the dates they show
+///
+/// The texts are looked up by their English text, a text without translation stays in English.
+/// The dates of the todo.txt lines are always read and written in the ISO format
+#[derive(Clone, Debug, PartialEq)]
+pub struct Locale {
+    /// The chrono format of the dates shown in the menus
+    date_format : String,
+    /// How the labels of the recap of a task are written
+    pub labels : Labels,
+    /// The translated texts by key, from the language and the translation file
+    texts : BTreeMap<&'static str, String>
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale { date_format : dates::ISO_FORMAT.to_string(), labels : Labels::Bold, texts : BTreeMap::new() }
+    }
+}
+
+impl Locale {
+    /// Create the locale of a built-in language, with ISO dates
+    ///
+    /// Arguments:
+    ///
+    /// * `lang` - one of `LANGUAGES`
+    pub fn new(lang : &str) -> Result<Self, String> {
+        let column = LANGUAGES.iter().position(|l| *l == lang)
+            .ok_or_else(|| format!("`{}` is not a language, the languages are {}", lang, LANGUAGES.join(", ")))?;
+        let mut locale = Locale::default();
+        for (key, english, french, german) in TEXTS {
+            let text = [english, french, german][column];
+            if column > 0 && !text.is_empty() {
+                locale.texts.insert(key, text.to_string());
+            }
+        }
+        Ok(locale)
+    }
+
+    /// Translate a text, fails if there is no text with this key
+    ///
+    /// Arguments:
+    ///
+    /// * `key` - the key of the text, like `menu.mark_as_done`
+    /// * `text` - the translation
+    pub fn set_text(&mut self, key : &str, text : &str) -> Result<(), String> {
+        let (key, _, _, _) = TEXTS.iter().find(|(k, _, _, _)| *k == key).ok_or_else(|| format!("unknown text `{}`", key))?;
+        self.texts.insert(key, text.to_string());
+        Ok(())
+    }
+
+    /// Change the format of the dates shown in the menus, fails if chrono can't write a date with it
+    ///
+    /// Arguments:
+    ///
+    /// * `date_format` - the chrono format, like `%d.%m.%Y`
+    pub fn set_date_format(&mut self, date_format : &str) -> Result<(), String> {
+        if !template::is_date_format(date_format) {
+            return Err(format!("`{}` is not a date format", date_format));
+        }
+        self.date_format = date_format.to_string();
+        Ok(())
+    }
+
+    /// Return the chrono format of the dates shown in the menus
+    pub fn date_format(&self) -> &str {
+        &self.date_format
+    }
+
+    /// Write a date in the format of the menus
+    ///
+    /// Arguments:
+    ///
+    /// * `date` - the date
+    pub fn date(&self, date : NaiveDate) -> String {
+        format_date(date, &self.date_format)
+    }
+
+    /// Return the translation of a text of a kind of `TEXTS`, or the English text
+    fn translate<'a>(&'a self, kind : &str, english : &'a str) -> &'a str {
+        TEXTS.iter()
+            .find(|(key, en, _, _)| *en == english && key.split_once('.').is_some_and(|(k, _)| k == kind))
+            .and_then(|(key, _, _, _)| self.texts.get(key))
+            .map_or(english, String::as_str)
+    }
+
+    /// Write a line of the recap of a task with a translated label
+    ///
+    /// Arguments:
+    ///
+    /// * `label` - the English label
+    /// * `value` - the value
+    pub fn label(&self, label : &str, value : &str) -> String {
+        self.labels.line(self.translate("label", label), value)
+    }
+
+    /// Return the translation of a word of the status of a task, like `To do`
+    ///
+    /// Arguments:
+    ///
+    /// * `status` - the English word
+    pub fn status<'a>(&'a self, status : &'a str) -> &'a str {
+        self.translate("status", status)
+    }
+
+    /// Return the translation of the prompt of a menu
+    ///
+    /// Arguments:
+    ///
+    /// * `prompt` - the English prompt
+    pub fn prompt<'a>(&'a self, prompt : &'a str) -> &'a str {
+        self.translate("prompt", prompt)
+    }
+
+    /// Return the translation of an entry of a menu, the entries which are not in `TEXTS` like the tasks are unchanged
+    ///
+    /// Arguments:
+    ///
+    /// * `entry` - the English entry
+    pub fn entry<'a>(&'a self, entry : &'a str) -> &'a str {
+        self.translate("menu", entry)
+    }
+}
+
+#[cfg(test)]
+mod locale_tests {
+    use super::*;
+
+    #[test]
+    fn languages() {
+        let german = Locale::new("de").unwrap();
+        assert_eq!(german.entry("✔ mark as done"), "✔ als erledigt markieren");
+        assert_eq!(german.prompt("Task"), "Aufgabe");
+        assert_eq!(german.status("To do"), "Offen");
+        assert_eq!(german.label("Due date", "06.05.2024"), "𝐅ä𝐥𝐥𝐢𝐠 𝐚𝐦 : 06.05.2024");
+        assert_eq!(Locale::new("fr").unwrap().entry("← back"), "← retour");
+        let english = Locale::new("en").unwrap();
+        assert_eq!(english, Locale::default());
+        assert_eq!(english.entry("✔ mark as done"), "✔ mark as done");
+        // The tasks and the unknown texts are unchanged
+        assert_eq!(german.entry("(A) call mom"), "(A) call mom");
+        assert_eq!(german.prompt("✔ mark as done"), "✔ mark as done");
+        assert_eq!(Locale::new("es"), Err(String::from("`es` is not a language, the languages are en, fr, de")));
+        // Each key is written once, and each text has its English version
+        for (i, (key, english, _, _)) in TEXTS.iter().enumerate() {
+            assert!(!english.is_empty() && TEXTS[..i].iter().all(|(k, _, _, _)| k != key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn overrides() {
+        let mut locale = Locale::new("de").unwrap();
+        locale.set_text("menu.mark_as_done", "✔ erledigt").unwrap();
+        assert_eq!(locale.entry("✔ mark as done"), "✔ erledigt");
+        assert_eq!(locale.set_text("menu.fly", "fliegen"), Err(String::from("unknown text `menu.fly`")));
+        // An English locale can be translated text by text, the other texts stay in English
+        let mut partial = Locale::default();
+        partial.set_text("label.task", "Aufgabe").unwrap();
+        partial.labels = Labels::Ascii;
+        assert_eq!(partial.label("Task", "call mom"), "Aufgabe: call mom");
+        assert_eq!(partial.label("Status", "To do"), "Status: To do");
+    }
+
+    #[test]
+    fn date_format() {
+        let mut locale = Locale::default();
+        let date = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        assert_eq!(locale.date(date), "2024-05-06");
+        locale.set_date_format("%d.%m.%Y").unwrap();
+        assert_eq!((locale.date(date), locale.date_format()), (String::from("06.05.2024"), "%d.%m.%Y"));
+        assert_eq!(locale.set_date_format("%Q"), Err(String::from("`%Q` is not a date format")));
+        assert_eq!(locale.date_format(), "%d.%m.%Y");
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Why a task file could not be locked
+#[derive(Debug, PartialEq)]
+pub enum LockError {
+    /// The file is locked by a running process, with its PID if it was written already
+    Held(Option<u32>),
+    /// The lock file could not be read or written
+    Io(String)
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LockError::Held(Some(pid)) => write!(f, "it is open in another RofiTodo (process {})", pid),
+            LockError::Held(None) => write!(f, "it is open in another RofiTodo"),
+            LockError::Io(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+/// An advisory lock of a task file: a `.lock` file next to it locked by the process and holding its PID
+///
+/// The lock of the system is released when the process exits, even when it crashes, so a lock file left behind is
+/// taken over without having to tell whether its process is running. The lock file is emptied when the lock is
+/// dropped but never removed: a process could otherwise lock the removed file while another one creates a new one
+#[derive(Debug)]
+pub struct FileLock {
+    /// The locked file
+    file : File
+}
+
+impl FileLock {
+    /// Return the path of the lock file of a task file, `todo.txt.lock` for `todo.txt`
+    ///
+    /// Arguments:
+    ///
+    /// * `file` - the path of the task file
+    pub fn path_for(file : &Path) -> PathBuf {
+        let mut name = file.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        name.push(".lock");
+        file.with_file_name(name)
+    }
+
+    /// Lock a task file for the current process
+    ///
+    /// Arguments:
+    ///
+    /// * `file` - the path of the task file
+    pub fn acquire(file : &Path) -> Result<Self, LockError> {
+        let path = Self::path_for(file);
+        let io_error = |e : std::io::Error| LockError::Io(format!("Could not lock {} : {}", path.display(), e));
+        let mut lock = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path).map_err(io_error)?;
+        match lock.try_lock() {
+            Ok(()) => (),
+            Err(TryLockError::WouldBlock) => return Err(LockError::Held(holder(&path))),
+            Err(TryLockError::Error(e)) => return Err(io_error(e))
+        }
+        // The PID of a process which crashed is replaced
+        lock.set_len(0).and_then(|_| writeln!(lock, "{}", std::process::id())).and_then(|_| lock.sync_all()).map_err(io_error)?;
+        Ok(FileLock { file : lock })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // The lock of the system is released when the file is closed
+        let _ = self.file.set_len(0).and_then(|_| self.file.seek(SeekFrom::Start(0)));
+    }
+}
+
+/// Return the PID written in a lock file, `None` if it can't be read
+fn holder(path : &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod lock_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    #[test]
+    fn path_for() {
+        assert_eq!(FileLock::path_for(Path::new("/home/me/todo.txt")), PathBuf::from("/home/me/todo.txt.lock"));
+    }
+
+    #[test]
+    fn second_lock() {
+        let file = temp_path("lock_second.txt");
+        let first = FileLock::acquire(&file).unwrap();
+        assert_eq!(fs::read_to_string(FileLock::path_for(&file)).unwrap(), format!("{}\n", std::process::id()));
+        assert_eq!(FileLock::acquire(&file).unwrap_err(), LockError::Held(Some(std::process::id())));
+        drop(first);
+        assert_eq!(holder(&FileLock::path_for(&file)), None);
+        let second = FileLock::acquire(&file).unwrap();
+        assert_eq!(holder(&FileLock::path_for(&file)), Some(std::process::id()));
+        drop(second);
+        assert_eq!(fs::read_to_string(FileLock::path_for(&file)).unwrap(), "");
+        fs::remove_file(FileLock::path_for(&file)).unwrap();
+    }
+
+    #[test]
+    fn stale_lock() {
+        let file = temp_path("lock_stale.txt");
+        // The lock file of a process which crashed, and a lock file written only in part
+        for content in ["4194305\n", "", "41"] {
+            fs::write(FileLock::path_for(&file), content).unwrap();
+            let lock = FileLock::acquire(&file).unwrap();
+            assert_eq!(holder(&FileLock::path_for(&file)), Some(std::process::id()));
+            drop(lock);
+        }
+        fs::remove_file(FileLock::path_for(&file)).unwrap();
+    }
+
+    #[test]
+    fn held_whatever_the_content() {
+        let file = temp_path("lock_content.txt");
+        let lock = FileLock::acquire(&file).unwrap();
+        // The lock of the system is held even if the PID of the lock file is changed or removed
+        fs::write(FileLock::path_for(&file), "1\n").unwrap();
+        assert_eq!(FileLock::acquire(&file).unwrap_err(), LockError::Held(Some(1)));
+        fs::write(FileLock::path_for(&file), "").unwrap();
+        assert_eq!(FileLock::acquire(&file).unwrap_err(), LockError::Held(None));
+        assert_eq!(LockError::Held(None).to_string(), "it is open in another RofiTodo");
+        drop(lock);
+        drop(FileLock::acquire(&file).unwrap());
+        fs::remove_file(FileLock::path_for(&file)).unwrap();
+    }
+}
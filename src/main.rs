@@ -1,189 +1,1113 @@
 mod rofi;
-use rofi::{Rofi, RofiParams};
-mod task;
-use task::{Task, SortTaskBy};
+use rofi::{Rofi, RofiParams, MenuBackend, StdioBackend, Choice};
+use rofitodo::{task, storage, markup, template, task_templates, locale, dates, filter, search, agenda, merge, status, stats, ical, markdown, csv, taskwarrior};
+use task::{Task, SortTaskBy, SortDirection, TaskParseError, PostponeBy};
+use task_templates::{TaskTemplate, Expansion};
 mod date_selector;
-use date_selector::date_selector;
+use date_selector::{date_selector, typed_date_selector};
 use std::fs;
-use std::io::{self, BufRead};
+use std::io::{self, Read};
 use structopt::StructOpt;
-use chrono::{Local, NaiveDate, Datelike};
-mod indexer;
-use indexer::Indexer;
+use chrono::{Local, NaiveDate};
+mod browser;
+mod notes;
+mod clipboard;
+use clipboard::ClipboardError;
+mod actions;
+mod notify;
+mod script;
+mod config;
+mod paths;
+mod git;
+mod gpg;
+use gpg::Gpg;
+mod navigation;
+mod keys;
+mod entry;
+use config::Config;
+use script::{ScriptEvent, ScriptState};
+use navigation::{Screen, Transition, navigate};
+use keys::KeyAction;
+use entry::Entry;
+use actions::Action;
+use rofitodo::{TaskList, TaskId};
+use rofitodo::undo::{self, Op};
+use rofitodo::audit::AuditLog;
+use rofitodo::session::Session;
+use rofitodo::escalation::{self, Rule};
+use rofitodo::storage::Cipher;
+use rofitodo::lock::{FileLock, LockError};
+use rofitodo::views::{View, ViewStore};
+use merge::{Conflict, Resolution};
+use filter::{TaskFilter, counts_by_project, counts_by_context};
+use markup::MarkupColors;
+use dates::{DateStyle, Workdays};
+use template::{Template, Labels};
+use locale::Locale;
 use std::rc::Rc;
+use std::ops::{Deref, DerefMut};
+use std::collections::{HashMap, HashSet};
 
 #[derive(StructOpt)]
 struct Cli {
-    /// The path to the RofiTodo config/task list file
-    #[structopt(short, long, parse(from_os_str), default_value = "./todo.txt")]
-    config: std::path::PathBuf,
+    /// The path to the RofiTodo config/task list file, repeat it to load several task lists (e.g. `-c work.txt -c personal.txt`).
+    /// By default `$ROFITODO_FILE`, else `todo` of the configuration file, else `$XDG_DATA_HOME/rofitodo/todo.txt`
+    /// unless only `~/todo.txt` exists
+    #[structopt(short, long, visible_alias = "file", parse(from_os_str), number_of_values = 1)]
+    config: Vec<std::path::PathBuf>,
+    /// Print the task files and the configuration file used
+    #[structopt(long = "verbose")]
+    verbose: bool,
+    /// Never write the files: the changes are lost when RofiTodo exits
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+    /// Open the main menu with a saved view instead of the view used last
+    #[structopt(long = "view")]
+    view: Option<String>,
+    /// Show the completed tasks after the tasks to do in the main menu
+    #[structopt(long = "show-completed")]
+    show_completed: bool,
     /// Do not load Rofi configuration, use default values.
     #[structopt(long = "no-config")]
     no_config: bool,
     /// Set filter to be case insensitive
     #[structopt(short = "i", long = "case-insensitive")]
     case_insensitive: bool,
-    /// How to sort the tasks
-    #[structopt(short = "s", long="sort", possible_values = &["creation","content","priority","due"], case_insensitive = true, default_value="content")]
-    sort : String
+    /// The program showing the menus, `auto` uses rofi if it is installed, else the first launcher found (`auto` by default).
+    /// `stdio` is the same as `--stdio`
+    #[structopt(long = "backend", possible_values = &["auto","rofi","wofi","fuzzel","bemenu","dmenu","stdio"])]
+    backend: Option<String>,
+    /// Write the menus to the standard output and read the choices from the standard input, one per line:
+    /// the number or the text of an entry, or a typed text. An empty line closes the menu
+    #[structopt(long = "stdio")]
+    stdio: bool,
+    /// Do not ask to confirm the deletions and the other destructive actions
+    #[structopt(long = "no-confirm")]
+    no_confirm: bool,
+    /// How to sort the tasks, several keys can be separated by commas (e.g. `due,priority,creation`)
+    /// (`content` by default, or the sort order chosen last in the main menu)
+    #[structopt(short = "s", long="sort", possible_values = &["creation","content","priority","due","completion","urgency","estimate"], case_insensitive = true, use_delimiter = true)]
+    sort : Vec<String>,
+    /// Reverse the sort order
+    #[structopt(short = "r", long = "reverse")]
+    reverse : bool,
+    /// The number of backups of the task file to keep, 0 to disable them (3 by default)
+    #[structopt(long = "backups")]
+    backups : Option<usize>,
+    /// Overwrite the task file even if another program modified it
+    #[structopt(long = "force-save")]
+    force_save : bool,
+    /// The number of operations which can be undone
+    #[structopt(long = "undo-depth", default_value = "20")]
+    undo_depth : usize,
+    /// The number of days the deleted tasks are kept in the trash, 0 to keep them forever
+    #[structopt(long = "trash-days", default_value = "30")]
+    trash_days : u32,
+    /// Move the tasks completed more than this number of days ago to the archive at launch
+    #[structopt(long = "auto-archive-days")]
+    auto_archive_days : Option<u32>,
+    /// Mark the tasks to do created more than this number of days ago as stale, 0 to never mark them (30 by default)
+    #[structopt(long = "stale-days")]
+    stale_days : Option<u32>,
+    /// Raise the priority of the tasks to do as their due date approaches at launch, with rules `priority=days` separated
+    /// by commas: `B=2,A=0` gives B to the tasks due in 2 days or less and A to the tasks due today or overdue
+    /// (disabled by default)
+    #[structopt(long = "escalate", require_delimiter = true)]
+    escalate : Vec<Rule>,
+    /// Move the postponed and recurring due dates falling on the weekend to the next working day, the tasks tagged
+    /// `wd:0` keep them and the tasks tagged `wd:1` always move them
+    #[structopt(long = "skip-weekends")]
+    skip_weekends : bool,
+    /// The days of the weekend, separated by commas (`sat,sun` by default)
+    #[structopt(long = "weekend", require_delimiter = true, parse(try_from_str = dates::parse_weekday))]
+    weekend : Vec<chrono::Weekday>,
+    /// Commit the task file in the git repository of its directory after each save
+    #[structopt(long = "git-autocommit")]
+    git_autocommit : bool,
+    /// Update the git repository of the task file with `git pull --ff-only` at launch
+    #[structopt(long = "git-pull")]
+    git_pull : bool,
+    /// Open the task files read-only: the tasks can be browsed, searched and copied but not changed.
+    /// A task file which can't be written is opened read-only too
+    #[structopt(long = "read-only")]
+    read_only : bool,
+    /// The key the task files whose name ends in `.gpg` are encrypted for, by default the keys they are encrypted for
+    #[structopt(long = "gpg-recipient")]
+    gpg_recipient : Option<String>,
+    /// The gpg program decrypting and encrypting the task files whose name ends in `.gpg` (`gpg` by default)
+    #[structopt(long = "gpg-program")]
+    gpg_program : Option<String>,
+    /// Write a line to this log for each change of the tasks (e.g. `2024-05-06T10:03:12 complete "pay rent"`)
+    #[structopt(long = "audit-log", parse(from_os_str))]
+    audit_log : Option<std::path::PathBuf>,
+    /// The size in kilobytes after which the log is moved to `rofitodo.log.1` and a new log is started (1024 by default)
+    #[structopt(long = "audit-log-size")]
+    audit_log_size : Option<u64>,
+    /// The color of the due date of overdue tasks (`red` by default)
+    #[structopt(long = "overdue-color")]
+    overdue_color : Option<String>,
+    /// The color of the due date of tasks due today (`orange` by default)
+    #[structopt(long = "today-color")]
+    today_color : Option<String>,
+    /// The color of the `(A)` priority, only bold by default
+    #[structopt(long = "priority-color")]
+    priority_color : Option<String>,
+    /// Show the tasks waiting for a task they depend on with a `dep:` tag in the today menu and the status
+    #[structopt(long = "show-blocked")]
+    show_blocked : bool,
+    /// Color the whole line of the tasks with the color of their first project or context, or of their priority,
+    /// instead of their tags
+    #[structopt(long = "color-rows")]
+    color_rows : bool,
+    /// The number of characters after which the lines of the tasks are cut with `…`, the recap of a task shows its whole
+    /// text (0 by default, the lines are never cut)
+    #[structopt(long = "max-line-width")]
+    max_line_width : Option<usize>,
+    /// The template of the lines of the tasks: `{priority}`, `{content}`, `{due}`, `{threshold}`, `{created}`, `{completed}`,
+    /// `{projects}`, `{contexts}`, `{estimate}`, `{recurrence}` and `{tag.<key>}`, the dates with a chrono format like
+    /// `{due:%d %b}`, a section between brackets is left out when one of its values is empty (`[{priority} ][{due} : ]{content}`
+    /// by default)
+    #[structopt(long = "row-format")]
+    row_format : Option<Template>,
+    /// Prefix the lines of the tasks with their number in the menu (`27 │ (B) renew passport`), typing a number which
+    /// matches no line opens the task with this number
+    #[structopt(long = "numbered-rows")]
+    numbered_rows : bool,
+    /// Do not show the numbers of open and overdue tasks, the filter and the sort order under the prompt of the main menu
+    #[structopt(long = "no-summary")]
+    no_summary : bool,
+    /// Write the labels of the recap of a task in plain ASCII (`Due date:`) instead of bold letters, for the terminals
+    /// without these letters and the screen readers
+    #[structopt(long = "ascii")]
+    ascii : bool,
+    /// The language of the menus and of the recap of the tasks (en by default)
+    #[structopt(long = "lang", possible_values = &locale::LANGUAGES)]
+    lang : Option<String>,
+    /// The chrono format of the dates shown in the menus, like `%d.%m.%Y` (`%Y-%m-%d` by default), the todo.txt lines
+    /// always keep the ISO dates
+    #[structopt(long = "date-format")]
+    date_format : Option<String>,
+    /// Show the menus without colors nor markup, like for a backend which can't show them
+    #[structopt(long = "no-color")]
+    no_color : bool,
+    /// The menu shown at launch: the tasks needing attention today, or all the tasks (`today` by default)
+    #[structopt(long = "start-view", possible_values = &["today","all"])]
+    start_view : Option<String>,
+    /// Show the due dates relative to today in the lists (`in 3d`, `5d overdue`)
+    #[structopt(long = "relative-dates")]
+    relative_dates : bool,
+    /// The number of days from today after which the relative due dates are shown as dates (60 by default)
+    #[structopt(long = "relative-days")]
+    relative_days : Option<i64>,
+    /// A shell command shown in the task menu, written `name=command`, repeat it to add several commands.
+    /// `{content}`, `{todotxt}`, `{due}` and `{priority}` are replaced by the values of the task
+    /// (e.g. `--action 'calendar=gcalcli add --title {content} --when {due}'`)
+    #[structopt(long = "action", number_of_values = 1)]
+    actions : Vec<Action>,
+    /// Send a desktop notification of the tasks overdue and due today, and exit without opening the menu
+    /// (e.g. from a cron job or a systemd timer)
+    #[structopt(long = "notify")]
+    notify : bool,
+    /// Send the notification of the due tasks when the menu is opened
+    #[structopt(long = "notify-at-launch")]
+    notify_at_launch : bool,
+    /// The number of days after today the tasks due are notified too (1 for the tasks due tomorrow)
+    #[structopt(long = "notify-days", default_value = "0")]
+    notify_days : u32,
+    /// Show the first task list as a rofi mode (e.g. `rofi -modi "todo:rofitodo --rofi-script" -show todo`),
+    /// rofi gives the selected text as value
+    #[structopt(long = "rofi-script", allow_hyphen_values = true)]
+    rofi_script : Option<Option<String>>,
+    /// Run a command on the first task list instead of opening the menu
+    #[structopt(subcommand)]
+    command : Option<Subcommand>
+}
+
+/// The commands run without opening the menu, the tasks are numbered by their line in the task file
+#[derive(StructOpt)]
+enum Subcommand {
+    /// Add a task, read like a task typed in the menu (e.g. `add "(A) buy milk @store due:fri"`)
+    Add {
+        /// The task, `-` reads it from the standard input
+        #[structopt(required = true)]
+        task : Vec<String>
+    },
+    /// Print the tasks to do with their line number
+    List {
+        /// Only print the tasks matching a query like `@home -@work +errands`
+        #[structopt(short, long)]
+        filter : Option<String>,
+        /// How to sort the tasks, in the order of the file by default
+        #[structopt(short, long, possible_values = &["creation","content","priority","due","completion","urgency","estimate"], case_insensitive = true, use_delimiter = true)]
+        sort : Vec<String>,
+        /// Print the completed tasks too
+        #[structopt(short, long)]
+        all : bool
+    },
+    /// Print the number of tasks to do, overdue and due today, and the most urgent task, for a status bar
+    Status {
+        /// Print a line of text or a JSON object
+        #[structopt(long, possible_values = &["plain","json"], default_value = "plain")]
+        format : String
+    },
+    /// Print the statistics of the tasks and of their archive: the completions of the last weeks, the open tasks of
+    /// each project and context, the overdue tasks and the average age of the open tasks
+    Report,
+    /// Export all the tasks, one VTODO per task for the ics format, a checklist grouped by project for the md format
+    /// or a row per task for the csv format
+    Export {
+        /// The format of the export
+        #[structopt(long, possible_values = &["ics","md","csv"], default_value = "ics")]
+        format : String,
+        /// The columns of the csv format, separated by commas: content, priority, due, created, completed, projects
+        /// and contexts. All of them by default
+        #[structopt(long, use_delimiter = true)]
+        columns : Vec<String>,
+        /// Write the export to a file instead of printing it
+        #[structopt(short, long, parse(from_os_str))]
+        output : Option<std::path::PathBuf>
+    },
+    /// Add the tasks of the `- [ ]` and `- [x]` items of a Markdown file or of a `task export` of Taskwarrior, after a
+    /// confirmation
+    Import {
+        /// The format of the file
+        #[structopt(long, possible_values = &["md","taskwarrior"], default_value = "md")]
+        format : String,
+        /// The file to import
+        #[structopt(parse(from_os_str))]
+        file : std::path::PathBuf
+    },
+    /// Complete the task on a line
+    Done {
+        /// The line number of the task, as printed by `list`
+        line : usize
+    },
+    /// Move the task on a line to the trash
+    Rm {
+        /// The line number of the task, as printed by `list`
+        line : usize
+    },
+    /// Print the changes of the tasks written to the log of `--audit-log`
+    Log {
+        /// Only print the changes of this day and after (e.g. `--since 2024-05-01`)
+        #[structopt(long)]
+        since : Option<NaiveDate>
+    }
 }
 
 #[derive(PartialEq)]
 enum MenuStatus {
-    EXIT,
-    MAINMENU,
-    BACK
+    Exit,
+    MainMenu,
+    Back
 }
 
-fn show_task_menu(rofi_config : &RofiParams, params : &mut Params, task: Rc<Task>) -> MenuStatus {
-    let mut updated_task = task;
+fn show_task_menu(rofi_config : &RofiParams, params : &mut Params, id: TaskId) -> MenuStatus {
     loop {
-        let mut menu =  vec![String::from("✔ mark as done"), String::from("* cancel"), String::from("+ edit"), String::from("+ change date"), String::from("+ change priority")];
-        match updated_task.get_due() {
-            Some(_) => menu.push(String::from("! remove date")),
-            None => ()
-        }
-        menu.push(String::from("! remove"));
-        match Rofi::from(rofi_config).msg(updated_task.recap_str()).select_range(0,menu.len()-1).prompt("Edit").run(menu).unwrap().as_ref() {
-            "✔ mark as done" => {
-                let mut t = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                t.set_completed();
-                add_task(&mut params.todos,t);
-                return MenuStatus::BACK;
-            },
-            "* cancel" => return MenuStatus::BACK,
-            "+ edit" => {
-                let task = Rofi::from(rofi_config)
-                            .prompt("Task")
-                            .placeholder("")
-                            .pretext(updated_task.get_content().to_string())
-                            .text_only()
-                            .run(vec![])
-                            .unwrap();
-                if task.eq("") {
-                    continue;
+        // The task is read again after each change
+        let updated_task = match params.list().get(id) {
+            Some(task) => task.clone(),
+            None => return MenuStatus::Back
+        };
+        let mut menu = vec![Entry::MarkAsDone, Entry::Back, Entry::Pin(updated_task.is_pinned()), Entry::Edit, Entry::Postpone, Entry::ChangeDate,
+                            Entry::ChangeThreshold, Entry::ChangePriority, Entry::IncreasePriority, Entry::DecreasePriority, Entry::Tags, Entry::EditTags];
+        if updated_task.get_due().is_some() {
+            menu.push(Entry::RemoveDate);
+        }
+        if updated_task.get_threshold().is_some() {
+            menu.push(Entry::RemoveThreshold);
+        }
+        if params.lists.len() > 1 {
+            menu.push(Entry::MoveToOtherList);
+        }
+        menu.push(Entry::Notes);
+        if !updated_task.completion {
+            menu.push(Entry::DependsOn);
+        }
+        menu.push(Entry::CopyToClipboard);
+        menu.push(Entry::CopyLine);
+        if !updated_task.urls().is_empty() {
+            menu.push(Entry::OpenLink);
+        }
+        menu.push(Entry::Remove);
+        menu.extend(params.actions.iter().map(|a| Entry::Action(a.name.clone())));
+        let menu = entry::shown(menu, params.list().read_only);
+        let rofi = Rofi::from(rofi_config).msg(params.recap(&updated_task)).select_range(0,menu.len()-1).prompt("Edit");
+        let selected = match select_entry(rofi, &menu) {
+            Ok(selected) => selected,
+            Err(e) => return menu_error(e)
+        };
+        match selected {
+            Some(Entry::MarkAsDone) => {
+                let op = params.complete_task(id);
+                params.record(op);
+                return MenuStatus::Back;
+            },
+            Some(Entry::Back) => return MenuStatus::Back,
+            Some(Entry::Pin(_)) => {
+                params.update_task(id, |t| t.set_pinned(!t.is_pinned()));
+                continue;
+            },
+            Some(Entry::Edit) => {
+                edit_task_line(rofi_config, params, id);
+                continue;
+            },
+            Some(Entry::ChangeDate) => {
+                if let Some(date) = typed_date_selector(rofi_config, Local::now().date_naive()) {
+                    params.update_task(id, |t| t.set_due(Some(date)));
                 }
-                let mut old_task = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                old_task.set_content(task);
-                updated_task = add_task(&mut params.todos,old_task);
                 continue;
             },
-            "+ change date" => {
-                let now = Local::now();
-                match date_selector(rofi_config, NaiveDate::from_ymd(now.year(), now.month(), now.day())) {
-                    Some(date) => {
-                        let mut old_task = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                        old_task.set_due(Some(date));
-                        updated_task = add_task(&mut params.todos,old_task);
-                    },
-                    None => ()
+            Some(Entry::Postpone) => {
+                if let Some(offset) = postpone_selector(rofi_config) {
+                    let op = params.postpone_task(id, offset, Local::now().date_naive());
+                    params.record(op);
+                }
+                continue;
+            },
+            Some(Entry::ChangeThreshold) => {
+                if let Some(date) = date_selector(rofi_config, Local::now().date_naive()) {
+                    params.update_task(id, |t| t.set_threshold(Some(date)));
                 }
                 continue;
             },
-            "+ change priority" => {
-                match priority_selector(rofi_config) {
-                    Some(priority) => {
-                        let mut old_task = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                        old_task.priority = priority.chars().nth(0);
-                        updated_task = add_task(&mut params.todos,old_task);
+            Some(Entry::ChangePriority) => {
+                if let Some(priority) = priority_selector(rofi_config, updated_task.get_priority()) {
+                    params.update_task(id, |t| {
+                        t.set_priority(priority).expect("the priorities of the menu are valid");
+                        escalation::opt_out(t);
+                    });
+                }
+                continue;
+            },
+            Some(Entry::IncreasePriority) => {
+                params.update_task(id, |t| {
+                    t.bump_priority();
+                    escalation::opt_out(t);
+                });
+                continue;
+            },
+            Some(Entry::DecreasePriority) => {
+                params.update_task(id, |t| {
+                    t.lower_priority();
+                    escalation::opt_out(t);
+                });
+                continue;
+            },
+            Some(Entry::CopyToClipboard) => {
+                copy_to_clipboard(rofi_config, &updated_task.display_line());
+                continue;
+            },
+            Some(Entry::CopyLine) => {
+                copy_to_clipboard(rofi_config, &updated_task.to_string());
+                continue;
+            },
+            Some(Entry::Notes) => {
+                match notes::prepare(&params.list().path, &updated_task) {
+                    Ok((name, path)) => {
+                        if updated_task.get_custom_tag(notes::NOTE_TAG) != Some(name.as_str()) {
+                            params.update_task(id, |t| t.set_custom_tag(notes::NOTE_TAG, &name).expect("the name of a note is a valid tag"));
+                        }
+                        match notes::open(&path) {
+                            Ok(()) => return MenuStatus::Exit,
+                            Err(e) => show_message(rofi_config, "Notes", e)
+                        }
                     },
-                    None => ()
+                    Err(e) => show_message(rofi_config, "Notes", e)
+                }
+                continue;
+            },
+            Some(Entry::OpenLink) => {
+                let urls = updated_task.urls();
+                let url = if urls.len() == 1 {
+                    urls.into_iter().next()
+                } else {
+                    match Rofi::from(rofi_config).prompt("Open").run_index(urls.clone()) {
+                        Ok(index) => index.and_then(|i| urls.into_iter().nth(i)),
+                        Err(e) => return menu_error(e)
+                    }
+                };
+                if let Some(url) = url {
+                    match browser::open(&url) {
+                        Ok(()) => return MenuStatus::Exit,
+                        Err(e) => show_message(rofi_config, "Open", e)
+                    }
+                }
+                continue;
+            },
+            Some(Entry::Tags) => {
+                show_task_tags_menu(rofi_config, params, id);
+                continue;
+            },
+            Some(Entry::EditTags) => {
+                show_custom_tags_menu(rofi_config, params, id);
+                continue;
+            },
+            Some(Entry::DependsOn) => {
+                let others = params.list().iter()
+                                .filter(|(other, t)| *other != id && !t.completion)
+                                .map(|(other, _)| other)
+                                .collect::<Vec<_>>();
+                let choices = others.iter().map(|other| params.task_line(params.list().get(*other).expect("the task is in the list"))).collect::<Vec<_>>();
+                match Rofi::from(rofi_config).prompt("Depends on").markup_rows().run_index(choices) {
+                    Ok(Some(i)) => params.add_dependency(id, others[i]),
+                    Ok(None) => (),
+                    Err(e) => return menu_error(e)
                 }
                 continue;
             },
-            "! remove date" => {
-                let mut old_task = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                old_task.set_due(None);
-                updated_task = add_task(&mut params.todos,old_task);
+            Some(Entry::RemoveDate) => {
+                params.update_task(id, |t| t.set_due(None));
+                continue;
+            },
+            Some(Entry::RemoveThreshold) => {
+                params.update_task(id, |t| t.set_threshold(None));
+                continue;
+            },
+            Some(Entry::MoveToOtherList) => {
+                let others = (0..params.lists.len()).filter(|i| *i != params.current).collect::<Vec<_>>();
+                if let Some(target) = list_selector(rofi_config, params, "Move to", &others) {
+                    params.move_task(id, target);
+                    return MenuStatus::Back;
+                }
                 continue;
             },
-            "! remove" => {
-                params.todos.remove(updated_task);
-                return MenuStatus::BACK;
+            Some(Entry::Remove) => {
+                if !confirm(rofi_config, &format!("Delete '{}' ?", updated_task.get_content())) {
+                    continue;
+                }
+                if let Err(e) = params.delete_task(id) {
+                    show_message(rofi_config, "Remove", e);
+                }
+                return MenuStatus::Back;
+            },
+            Some(Entry::Action(name)) => {
+                if let Some(action) = params.actions.iter().find(|a| a.name == name) {
+                    if let Err(e) = action.run(&updated_task) {
+                        show_message(rofi_config, "Action", e);
+                    }
+                }
+                continue;
             },
-            _ => return MenuStatus::BACK
+            _ => return MenuStatus::Back
         }
     }
 }
 
-fn priority_selector(rofi_config : &RofiParams) -> Option<String> {
-    let alpha = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars();
-    let priority_list : Vec<String> = alpha.map(|x| x.to_string()).collect();
+/// Edit the custom tags of a task
+/// 
+/// Typing `key:value` sets a tag, selecting an existing tag removes it
+fn show_custom_tags_menu(rofi_config : &RofiParams, params : &mut Params, id: TaskId) {
     loop {
-        let selected_priority = Rofi::from(rofi_config).prompt("Year").run(priority_list.clone()).unwrap();
-        if priority_list.contains(&selected_priority) {
-            return Some(selected_priority);
-        } else if selected_priority.eq("") {
-            return None;
+        let updated_task = match params.list().get(id) {
+            Some(task) => task.clone(),
+            None => return
+        };
+        let mut choices = vec![String::from("← back")];
+        for (key, value) in updated_task.custom_tags() {
+            choices.push(format!("{}:{}", key, value));
+        }
+        let selected = Rofi::from(rofi_config)
+                        .prompt("Tag")
+                        .placeholder("key:value")
+                        .msg(String::from("Type key:value to set a tag, select a tag to remove it"))
+                        .select_range(0,0)
+                        .run(choices.clone())
+                        .unwrap();
+        if selected.is_empty() || selected == "← back" {
+            return;
         }
+        // The tag must be read back unchanged when the task file is loaded again
+        let (key, value) = match selected.split_once(':') {
+            Some((key, value)) if Task::check_custom_tag(key, value).is_ok() => (key, value),
+            _ => continue
+        };
+        let remove = choices.contains(&selected);
+        params.update_task(id, |t| if remove {
+            t.remove_custom_tag(key);
+        } else {
+            t.set_custom_tag(key, value).expect("the tag is checked");
+        });
     }
 }
 
+/// Copy a text to the clipboard, showing the error if it can't be copied
+fn copy_to_clipboard(rofi_config : &RofiParams, text : &str) {
+    if let Err(e) = clipboard::copy(text) {
+        show_message(rofi_config, "Copy", e.to_string());
+    }
+}
 
-fn show_done_task_menu(rofi_config : &RofiParams, params : &mut Params, task: Rc<Task>) -> MenuStatus {
+/// Add or remove the project and context tags of a task
+/// 
+/// The tags of the task are marked and removed when selected, the other tags of the list are proposed,
+/// a new tag can be typed as `+project` or `@context`
+fn show_task_tags_menu(rofi_config : &RofiParams, params : &mut Params, id: TaskId) {
     loop {
-        let menu =  vec![String::from("✔ mark as to do"),String::from("! remove"),String::from("* cancel")];
-        match Rofi::from(rofi_config).msg(task.recap_str()).select_range(0,menu.len()-1).prompt("Edit").run(menu).unwrap().as_ref() {
-            "✔ mark as to do" => {
-                let mut t = params.todos.remove(task).expect("Some references to task were not deleted");
-                t.set_not_completed();
-                add_task(&mut params.todos,t);
-                return MenuStatus::BACK;
-            },
-            "* cancel" => return MenuStatus::BACK,
-            "! remove" => {
-                params.todos.remove(task);
-                return MenuStatus::BACK;
+        let updated_task = match params.list().get(id) {
+            Some(task) => task.clone(),
+            None => return
+        };
+        let mut tags = updated_task.get_project_tags().iter().map(|t| (true, format!("+{}", t))).collect::<Vec<_>>();
+        tags.extend(updated_task.get_context_tags().iter().map(|t| (true, format!("@{}", t))));
+        for tag in params.known_tags() {
+            if !tags.iter().any(|(_, t)| *t == tag) {
+                tags.push((false, tag));
+            }
+        }
+        let mut choices = vec![String::from("← back")];
+        choices.extend(tags.iter().map(|(set, tag)| if *set { format!("✔ {}", tag) } else { tag.clone() }));
+        let selected = Rofi::from(rofi_config)
+                        .prompt("Tags")
+                        .placeholder("+project or @context")
+                        .msg(String::from("Select a tag to add or remove it, or type a new one"))
+                        .select_range(0,0)
+                        .run(choices)
+                        .unwrap();
+        if selected.is_empty() || selected == "← back" {
+            return;
+        }
+        let (remove, tag) = match selected.strip_prefix("✔ ") {
+            Some(tag) => (true, tag.to_string()),
+            None => (false, selected)
+        };
+        let name = tag[1..].to_string();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            continue;
+        }
+        params.update_task(id, |t| match (tag.chars().next(), remove) {
+            (Some('+'), false) => t.add_project_tag(&name),
+            (Some('+'), true) => t.remove_project_tag(&name),
+            (Some('@'), false) => t.add_context_tag(&name),
+            (Some('@'), true) => t.remove_context_tag(&name),
+            _ => ()
+        });
+    }
+}
+
+/// Show a message in a menu with a single `← back` entry, the message is printed if the menu can't be shown
+///
+/// Arguments:
+///
+/// * `prompt` - the prompt of the menu
+/// * `message` - the message
+fn show_message(rofi_config : &RofiParams, prompt : &str, message : String) {
+    if let Err(e) = Rofi::from(rofi_config).prompt(prompt).msg(message.clone()).run(vec![String::from("← back")]) {
+        eprintln!("{}\n{}", message, e);
+    }
+}
+
+/// Show a menu of entries and return the selected entry, `None` if the menu was closed
+///
+/// Arguments:
+///
+/// * `rofi` - the menu
+/// * `entries` - the entries of the menu, shown with their labels
+fn select_entry(rofi : Rofi, entries : &[Entry]) -> Result<Option<Entry>, String> {
+    Ok(rofi.run_index(entry::labels(entries))?.map(|i| entries[i].clone()))
+}
+
+/// Print the error of a menu which could not be shown and close the menus
+fn menu_error(error : String) -> MenuStatus {
+    eprintln!("{}", error);
+    MenuStatus::Exit
+}
+
+/// Ask to confirm an action, return true if it was confirmed
+/// 
+/// "no" is selected by default and closing Rofi answers "no".
+/// Always return true when the confirmations are disabled
+/// 
+/// Arguments:
+/// 
+/// * `prompt` - the question to ask
+fn confirm(rofi_config : &RofiParams, prompt : &str) -> bool {
+    if rofi_config.no_confirm {
+        return true;
+    }
+    let menu = vec![String::from("no"), String::from("yes")];
+    Rofi::from(rofi_config).prompt("Confirm").msg(prompt.to_string()).selected(0).run_index(menu).unwrap() == Some(1)
+}
+
+/// Open a Rofi menu to select a priority, the current one is selected and marked
+///
+/// Returns `Some(None)` if "None" is selected, `None` if the user quitted
+///
+/// Arguments:
+///
+/// * `rofi_config` - the Rofi parameters
+/// * `current` - the current priority
+fn priority_selector(rofi_config : &RofiParams, current : Option<char>) -> Option<Option<char>> {
+    let priorities = std::iter::once(None).chain(('A'..='Z').map(Some)).collect::<Vec<_>>();
+    let choices = priorities.iter().map(|p| {
+        let name = p.map_or(String::from("None"), |p| p.to_string());
+        if *p == current { format!("✔ {}", name) } else { name }
+    }).collect::<Vec<_>>();
+    let selected = priorities.iter().position(|p| *p == current).unwrap_or(0);
+    Rofi::from(rofi_config).prompt("Priority").selected(selected as u32).run_index(choices).unwrap().map(|i| priorities[i])
+}
+
+/// Open a Rofi menu to select how far tasks are postponed
+///
+/// Returns `None` if the user quitted
+///
+/// Arguments:
+///
+/// * `rofi_config` - the Rofi parameters
+fn postpone_selector(rofi_config : &RofiParams) -> Option<PostponeBy> {
+    let mut choices = PostponeBy::PRESETS.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+    choices.push(String::from("~ custom"));
+    match Rofi::from(rofi_config).prompt("Postpone").run_index(choices).unwrap() {
+        Some(i) if i < PostponeBy::PRESETS.len() => Some(PostponeBy::PRESETS[i]),
+        Some(_) => typed_date_selector(rofi_config, Local::now().date_naive()).map(PostponeBy::Until),
+        None => None
+    }
+}
+
+fn show_done_task_menu(rofi_config : &RofiParams, params : &mut Params, id: TaskId) -> MenuStatus {
+    let task = match params.list().get(id) {
+        Some(task) => task.clone(),
+        None => return MenuStatus::Back
+    };
+    let menu = entry::shown(vec![Entry::MarkAsToDo, Entry::Remove, Entry::Back], params.list().read_only);
+    let rofi = Rofi::from(rofi_config).msg(params.recap(&task)).select_range(0,menu.len()-1).prompt("Edit");
+    match select_entry(rofi, &menu) {
+        Ok(Some(Entry::MarkAsToDo)) => {
+            params.update_task(id, |t| t.set_not_completed());
+            MenuStatus::Back
+        },
+        Ok(Some(Entry::Remove)) => {
+            if !confirm(rofi_config, &format!("Delete '{}' ?", task.get_content())) {
+                return MenuStatus::Back;
+            }
+            if let Err(e) = params.delete_task(id) {
+                show_message(rofi_config, "Remove", e);
+            }
+            MenuStatus::Back
+        },
+        Ok(_) => MenuStatus::Back,
+        Err(e) => menu_error(e)
+    }
+}
+
+/// Return the known tags completing the tag typed at the end of a new task
+/// 
+/// A tag is completed when the text ends with a bare `+` or `@`, or with the start of the name of a known tag.
+/// Return `None` if there is nothing to complete
+/// 
+/// Arguments:
+/// 
+/// * `text` - the typed task
+/// * `known` - the known tags, with their prefix
+fn tag_completions(text : &str, known : &[String]) -> Option<Vec<String>> {
+    let word = text.rsplit(' ').next()?;
+    if !word.starts_with('+') && !word.starts_with('@') {
+        return None;
+    }
+    if known.iter().any(|t| t == word) {
+        return None;
+    }
+    let tags = known.iter().filter(|t| t.starts_with(word)).cloned().collect::<Vec<_>>();
+    if tags.is_empty() { None } else { Some(tags) }
+}
+
+/// Replace the tag typed at the end of a new task by a completed tag, followed by a space to keep typing
+/// 
+/// Arguments:
+/// 
+/// * `text` - the typed task, ending with the start of a tag
+/// * `tag` - the completed tag, `None` to remove the started tag
+fn complete_tag(text : &str, tag : Option<&str>) -> String {
+    let start = text.rfind(' ').map(|i| i + 1).unwrap_or(0);
+    match tag {
+        Some(tag) => format!("{}{} ", &text[..start], tag),
+        None => text[..start].to_string()
+    }
+}
+
+/// Ask for a new task, completing the project and context tags
+/// 
+/// Return the typed task, empty if cancelled
+/// 
+/// Arguments:
+/// 
+/// * `text` - the text the input starts with
+/// * `message` - a message shown under the input
+fn task_input(rofi_config : &RofiParams, params : &Params, mut text : String, message : Option<String>) -> String {
+    let known = params.known_tags();
+    loop {
+        let mut rofi = Rofi::from(rofi_config).prompt("Task").placeholder("").pretext(text).text_only();
+        if let Some(message) = &message {
+            rofi = rofi.msg(message.clone());
+        }
+        text = rofi.run(vec![]).unwrap();
+        let tags = match tag_completions(&text, &known) {
+            Some(tags) => tags,
+            None => return text.trim().to_string()
+        };
+        let tag = Rofi::from(rofi_config).prompt("Tag").msg(String::from("Select a tag or type a new one")).run(tags).unwrap();
+        let tag = tag.trim();
+        // Cancelling removes the started tag and goes back to the task
+        let tag = (tag.len() > 1 && !tag.contains(char::is_whitespace)).then_some(tag);
+        text = complete_tag(&text, tag);
+    }
+}
+
+/// Edit the todo.txt line of a task of the current list until it is valid, Escape leaves the task unchanged
+fn edit_task_line(rofi_config : &RofiParams, params : &mut Params, id : TaskId) {
+    let task = params.copy(id);
+    let mut line = task.to_string();
+    let mut msg = String::from("Edit the todo.txt line of the task");
+    loop {
+        line = Rofi::from(rofi_config)
+                    .prompt("Task")
+                    .msg(msg)
+                    .placeholder("")
+                    .pretext(line)
+                    .text_only()
+                    .run(vec![])
+                    .unwrap();
+        if line.is_empty() {
+            return;
+        }
+        let mut edited = task.clone();
+        match edited.edit(&line, Local::now().date_naive()) {
+            Ok(()) => {
+                if edited.get_priority() != task.get_priority() {
+                    escalation::opt_out(&mut edited);
+                }
+                params.update_task(id, |t| *t = edited);
+                return;
             },
-            _ => return MenuStatus::EXIT
+            Err(e) => msg = format!("The task can't be saved: {}", e)
         }
     }
 }
 
-fn show_add_task(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
-    let task = Rofi::from(rofi_config).prompt("Task").placeholder("").text_only().run(vec![]).unwrap();
-    if task.eq("") {
-        return MenuStatus::MAINMENU;
+/// Run the action of a custom key on a task of the current list without opening its menu
+/// 
+/// Arguments:
+/// 
+/// * `action` - the action of the key
+/// * `id` - the highlighted task
+fn run_key_action(rofi_config : &RofiParams, params : &mut Params, action : KeyAction, id : TaskId) {
+    let task = params.copy(id);
+    match action {
+        // A completed task of the list stays completed
+        KeyAction::Complete => if !task.completion {
+            let op = params.complete_task(id);
+            params.record(op);
+        },
+        KeyAction::Delete => if confirm(rofi_config, &format!("Delete '{}' ?", task.get_content())) {
+            if let Err(e) = params.delete_task(id) {
+                show_message(rofi_config, "Remove", e);
+            }
+        },
+        KeyAction::Postpone => if let Some(offset) = postpone_selector(rofi_config) {
+            let op = params.postpone_task(id, offset, Local::now().date_naive());
+            params.record(op);
+        },
+        KeyAction::Edit => edit_task_line(rofi_config, params, id)
+    }
+}
+
+/// Show the menu of a task, or the menu of a completed task
+fn open_task_menu(rofi_config : &RofiParams, params : &mut Params, id : TaskId) -> MenuStatus {
+    if params.list().get(id).is_some_and(|t| t.completion) {
+        show_done_task_menu(rofi_config, params, id)
+    } else {
+        show_task_menu(rofi_config, params, id)
+    }
+}
+
+/// The number of characters of a pasted task kept, the rest of a long text is left out
+const MAX_PASTED_LENGTH : usize = 500;
+
+/// Ask which template a new task starts from, `Some(None)` for a blank task and `None` if the menu was closed
+/// 
+/// The menu is only shown when the configuration has templates
+fn template_selector(rofi_config : &RofiParams, params : &Params) -> Option<Option<Expansion>> {
+    if params.templates.is_empty() {
+        return Some(None);
+    }
+    let menu = std::iter::once(String::from("+ blank")).chain(params.templates.iter().map(|(name, _)| name.clone())).collect::<Vec<_>>();
+    match Rofi::from(rofi_config).prompt("New task").select_range(0, 0).run_index(menu).unwrap()? {
+        0 => Some(None),
+        i => Some(Some(params.templates[i - 1].1.expand(Local::now().date_naive())))
     }
+}
+
+fn show_add_task(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    let task = match template_selector(rofi_config, params) {
+        // Escape leaves the list unchanged
+        None => return MenuStatus::Back,
+        Some(None) => {
+            let task = task_input(rofi_config, params, String::new(), None);
+            if task.is_empty() {
+                return MenuStatus::MainMenu;
+            }
+            // The number of a row of the last menu opens its task instead of adding a task
+            if params.numbered_rows {
+                if let Some(id) = params.numbered_task(&task) {
+                    return open_task_menu(rofi_config, params, id);
+                }
+                strip_row_number(&task).to_string()
+            } else {
+                task
+            }
+        },
+        Some(Some(expansion)) => match &expansion.after {
+            // A template without placeholder only has to be validated
+            None => expansion.before.clone(),
+            Some(after) => {
+                let typed = task_input(rofi_config, params, expansion.before.clone(), Some(format!("Followed by : {}", after.trim())));
+                if typed.is_empty() {
+                    return MenuStatus::MainMenu;
+                }
+                expansion.complete(&typed)
+            }
+        }
+    };
+    validate_new_task(rofi_config, params, task)
+}
+
+/// Add a task read from the clipboard, after showing its recap
+/// 
+/// Arguments:
+/// 
+/// * `paste` - read the text of the clipboard
+fn show_paste_task(rofi_config : &RofiParams, params : &mut Params, paste : impl FnOnce() -> Result<String, ClipboardError>) -> MenuStatus {
+    let message = match paste() {
+        Ok(text) => match pasted_task(&text) {
+            Some(task) => return validate_new_task(rofi_config, params, task),
+            None => String::from("The clipboard is empty, no task to add")
+        },
+        Err(e) => e.to_string()
+    };
+    show_message(rofi_config, "Task", message);
+    MenuStatus::Back
+}
+
+/// Return the line of a task from a pasted text, on one line with the spaces collapsed and cut after
+/// `MAX_PASTED_LENGTH` characters, `None` if there is no text
+fn pasted_task(text : &str) -> Option<String> {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let line = line.chars().take(MAX_PASTED_LENGTH).collect::<String>().trim_end().to_string();
+    (!line.is_empty()).then_some(line)
+}
+
+/// Show the recap of a new task to validate it, with its due date to add
+fn validate_new_task(rofi_config : &RofiParams, params : &mut Params, task : String) -> MenuStatus {
+    // A line which can't be read is added as plain content rather than rejected
+    let (mut new_task, msg) = match Task::from_quick_add(&task, Local::now().date_naive()) {
+        Ok(new_task) => { let recap = params.recap(&new_task); (new_task, recap) },
+        Err(e) => {
+            let new_task = Task::new(task);
+            let recap = format!("{}\n⚠ {}, the line is added as plain text", params.recap(&new_task), e);
+            (new_task, recap)
+        }
+    };
     let menu =  vec![String::from("✔ validate"), String::from("+ add date"), String::from("* cancel")];
-    match Rofi::from(rofi_config).prompt("Edit").select_range(0,menu.len()-1).run(menu).unwrap().as_ref() {
+    match Rofi::from(rofi_config).prompt("Edit").msg(msg).select_range(0,menu.len()-1).run(menu).unwrap().as_ref() {
         "✔ validate" => {
-            add_task(&mut params.todos,Task::new(task));
-            MenuStatus::MAINMENU
+            add_to_list(rofi_config, params, new_task);
+            MenuStatus::MainMenu
         },
-        "* cancel" => MenuStatus::MAINMENU,
+        "* cancel" => MenuStatus::MainMenu,
         "+ add date" => {
-            let now = Local::now();
-            match date_selector(rofi_config, NaiveDate::from_ymd(now.year(), now.month(), now.day())) {
-                Some(date) => {add_task(&mut params.todos,Task::new_with_date(task, date));},
-                None => ()
+            if let Some(date) = typed_date_selector(rofi_config, Local::now().date_naive()) {
+                new_task.set_due(Some(date));
+                add_to_list(rofi_config, params, new_task);
             }
-            MenuStatus::MAINMENU
+            MenuStatus::MainMenu
         },
-        _ => MenuStatus::EXIT
+        // Escape leaves the list unchanged
+        _ => MenuStatus::Back
+    }
+}
+
+/// Add a new task, asking which list to add it to when several lists are loaded
+fn add_to_list(rofi_config : &RofiParams, params : &mut Params, task : Task) {
+    if params.lists.len() == 1 {
+        let id = params.add_task(task);
+        params.session.record(Op::Add(params.session.copy(id)));
+        return;
+    }
+    // The current list is proposed first
+    let mut lists = vec![params.current];
+    lists.extend((0..params.lists.len()).filter(|i| *i != params.current));
+    if let Some(index) = list_selector(rofi_config, params, "Add to list", &lists) {
+        let list = &mut params.lists[index];
+        let id = list.add(task);
+        let added = list.get(id).cloned().expect("the task was just added");
+        list.history.push(Op::Add(added));
     }
 }
 
 fn show_old_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
     loop {
-        let mut choices =  vec![String::from("← back"), String::from("* exit")];
-        for todo in params.todos.index(&String::from("done")).unwrap() {
-            choices.push(todo.to_string());
+        let menu = vec![String::from("← back"), String::from("~ completed archive"), String::from("* exit")];
+        let mut choices = menu.clone();
+        let mut ids = Vec::new();
+        for (id, todo) in params.list().done() {
+            choices.push(params.plain_line(todo));
+            ids.push(id);
         }
-        match Rofi::from(rofi_config).prompt("Done").select_range(0,1).run(choices).unwrap().as_ref() {
-            "← back" => return MenuStatus::BACK,
-            "* exit" => return MenuStatus::EXIT,
-            "" => return MenuStatus::EXIT,
-            s => {
-                let result = params.todos.index(&String::from("done")).unwrap().into_iter().find(|x| x.to_string().eq(s));
-                if let None = result {
-                    continue
-                }
-                match show_done_task_menu(rofi_config, params, result.unwrap()) {
-                    MenuStatus::BACK => continue,
-                    MenuStatus::EXIT => return MenuStatus::EXIT,
-                    MenuStatus::MAINMENU => return MenuStatus::MAINMENU
-                }
+        let status = match Rofi::from(rofi_config).prompt("Done").select_range(0,2).run_index(choices).unwrap() {
+            None | Some(0) => return MenuStatus::Back,
+            Some(2) => return MenuStatus::Exit,
+            Some(1) => show_archive_menu(rofi_config, params),
+            Some(i) => show_done_task_menu(rofi_config, params, ids[i - menu.len()])
+        };
+        match status {
+            MenuStatus::Back => continue,
+            MenuStatus::Exit => return MenuStatus::Exit,
+            MenuStatus::MainMenu => return MenuStatus::MainMenu
+        }
+    }
+}
+
+/// A task of the recently completed menu
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Completed {
+    /// A completed task of the current list
+    Listed(TaskId),
+    /// A task of the archive, with the index of its line
+    Archived(usize)
+}
+
+/// Return the tasks completed today, the most recent first: the tasks of the list by the order they were completed in,
+/// then the tasks of the archive by the order they were archived in
+/// 
+/// A task of the archive with the same line as a task of the list is left out, like a task completed, archived,
+/// restored then completed again
+/// 
+/// Arguments:
+/// 
+/// * `listed` - the completed tasks of the list
+/// * `archived` - the tasks of the archive with the index of their line
+/// * `today` - the current date
+fn recently_completed(listed : &[(TaskId, &Task)], archived : &[(usize, Task)], today : NaiveDate) -> Vec<(Completed, Task)> {
+    let mut listed = listed.iter().filter(|(_, t)| t.completion_date == Some(today)).collect::<Vec<_>>();
+    listed.sort_by(|(a, _), (b, _)| b.cmp(a));
+    let mut archived = archived.iter().filter(|(_, t)| t.completion && t.completion_date == Some(today)).collect::<Vec<_>>();
+    archived.sort_by(|(a, _), (b, _)| b.cmp(a));
+    let mut lines = HashSet::new();
+    let mut tasks = listed.into_iter().map(|(id, t)| (Completed::Listed(*id), (*t).clone()))
+                          .chain(archived.into_iter().map(|(index, t)| (Completed::Archived(*index), t.clone())))
+                          .filter(|(_, t)| lines.insert(t.to_string()))
+                          .collect::<Vec<_>>();
+    // The dates are the same, the sort only keeps the tasks without date last
+    tasks.sort_by(|(_, a), (_, b)| a.comp_sorted(b, &SortTaskBy::CompletionDate, SortDirection::Descending));
+    tasks
+}
+
+/// List the tasks completed today, in the list and in its archive, to mark them as to do again
+fn show_recently_completed_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    loop {
+        let mut archive = match params.list().archive() {
+            Ok(archive) => archive,
+            Err(e) => {
+                show_message(rofi_config, "Recently completed", e);
+                return MenuStatus::Back;
+            }
+        };
+        let tasks = recently_completed(&params.list().done(), &archive.tasks().0, Local::now().date_naive());
+        let mut choices = vec![String::from("← back")];
+        choices.extend(tasks.iter().map(|(_, task)| params.plain_line(task)));
+        let mut rofi = Rofi::from(rofi_config).prompt("Recently completed").select_range(0,0);
+        if tasks.is_empty() {
+            rofi = rofi.msg(String::from("No task completed today"));
+        }
+        let (completed, task) = match rofi.run_index(choices).unwrap() {
+            None | Some(0) => return MenuStatus::Back,
+            Some(i) => &tasks[i - 1]
+        };
+        let menu = entry::shown(vec![Entry::MarkAsToDo, Entry::Back], params.list().read_only);
+        match select_entry(Rofi::from(rofi_config).msg(params.recap(task)).select_range(0,menu.len()-1).prompt("Done"), &menu) {
+            Ok(Some(Entry::MarkAsToDo)) => (),
+            Ok(_) => continue,
+            Err(e) => return menu_error(e)
+        }
+        match *completed {
+            Completed::Listed(id) => params.update_task(id, |t| t.set_not_completed()),
+            Completed::Archived(index) => if let Err(e) = params.unarchive(&mut archive, index) {
+                show_message(rofi_config, "Archive", e);
+            }
+        }
+    }
+}
+
+/// Browse the tasks archived in `done.txt` and restore them in the task list
+fn show_archive_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    loop {
+        let mut archive = match params.list().archive() {
+            Ok(archive) => archive,
+            Err(e) => {
+                show_message(rofi_config, "Archive", e);
+                return MenuStatus::Back;
+            }
+        };
+        let (tasks, skipped) = archive.tasks();
+        let mut choices = vec![String::from("← back")];
+        for (_, task) in &tasks {
+            choices.push(params.plain_line(task));
+        }
+        let mut rofi = Rofi::from(rofi_config).prompt("Archive").select_range(0,0);
+        if skipped > 0 {
+            rofi = rofi.msg(format!("{} lines could not be parsed", skipped));
+        }
+        let (index, task) = match rofi.run_index(choices).unwrap() {
+            None | Some(0) => return MenuStatus::Back,
+            Some(i) => &tasks[i - 1]
+        };
+        let menu = entry::shown(vec![Entry::Restore, Entry::Cancel], params.list().read_only);
+        match select_entry(Rofi::from(rofi_config).msg(params.recap(task)).select_range(0,menu.len()-1).prompt("Archived"), &menu) {
+            Ok(Some(Entry::Restore)) => if let Err(e) = params.unarchive(&mut archive, *index) {
+                eprintln!("{}", e);
+            },
+            Ok(_) => (),
+            Err(e) => return menu_error(e)
+        }
+    }
+}
+
+/// Browse the deleted tasks and restore them in the task list
+fn show_trash_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    loop {
+        let mut trash = match params.list().trash() {
+            Ok(trash) => trash,
+            Err(e) => {
+                show_message(rofi_config, "Trash", e);
+                return MenuStatus::Back;
+            }
+        };
+        let tasks = trash.tasks();
+        let mut choices = vec![String::from("← back")];
+        for (_, task) in &tasks {
+            choices.push(params.plain_line(task));
+        }
+        let mut rofi = Rofi::from(rofi_config).prompt("Trash").select_range(0,0);
+        if tasks.is_empty() {
+            rofi = rofi.msg(String::from("The trash is empty"));
+        }
+        let (index, task) = match rofi.run_index(choices).unwrap() {
+            None | Some(0) => return MenuStatus::MainMenu,
+            Some(i) => &tasks[i - 1]
+        };
+        let menu = entry::shown(vec![Entry::Restore, Entry::Cancel], params.list().read_only);
+        let restore = match select_entry(Rofi::from(rofi_config).msg(params.recap(task)).select_range(0,menu.len()-1).prompt("Deleted"), &menu) {
+            Ok(selected) => selected == Some(Entry::Restore),
+            Err(e) => return menu_error(e)
+        };
+        if restore {
+            let line = trash.line(*index).unwrap_or_default().to_string();
+            match trash.take(*index) {
+                Ok(task) => {
+                    let id = params.add_task(task);
+                    params.session.record(Op::Batch(vec![Op::TakeLine(params.session.list().trash_path(), line), Op::Add(params.session.copy(id))]));
+                },
+                Err(e) => eprintln!("{}", e)
             }
         }
     }
@@ -193,211 +1117,2730 @@ fn show_tags_menu(rofi_config : &RofiParams, params : &mut Params, index_name: S
     loop {
         let mut choices = vec![String::from("← back")];
         // Exiting if the index was removed
-        let idx = match params.todos.index(&index_name) {
-            Some(index) => index,
-            None => return MenuStatus::BACK
-        };
-        for todo in idx {
-            choices.push(todo.to_string());
-        }
-        let status : MenuStatus = match Rofi::from(rofi_config).prompt("Todo").select_range(0,0).run(choices).unwrap().as_ref() {
-            "← back" => MenuStatus::MAINMENU,
-            "" => MenuStatus::EXIT,
-            s => {
-                let result = params.todos.index(&index_name).unwrap().into_iter().find(|x| x.to_string().eq(s));
-                match result {
-                    Some(t) => show_task_menu(rofi_config, params, t),
-                    None => MenuStatus::MAINMENU
-                }
-            }
+        let ids = match params.list().index(&index_name) {
+            Some(tasks) => tasks.into_iter().filter(|(_, x)| params.is_shown(x)).map(|(id, x)| {
+                choices.push(params.task_line(x));
+                id
+            }).collect::<Vec<_>>(),
+            None => return MenuStatus::Back
+        };
+        let status : MenuStatus = match Rofi::from(rofi_config).prompt("Todo").select_range(0,0).markup_rows().run_index(choices).unwrap() {
+            None => return MenuStatus::Back,
+            Some(0) => MenuStatus::MainMenu,
+            Some(i) => show_task_menu(rofi_config, params, ids[i - 1])
         };
         match status {
-            MenuStatus::BACK => continue,
-            MenuStatus::EXIT => return MenuStatus::EXIT,
-            MenuStatus::MAINMENU => return MenuStatus::MAINMENU
+            MenuStatus::Back => continue,
+            MenuStatus::Exit => return MenuStatus::Exit,
+            MenuStatus::MainMenu => return MenuStatus::MainMenu
         }
     }
 }
 
 fn show_tag_list(rofi_config : &RofiParams, params : &mut Params, tag_type: String) -> MenuStatus {
     loop {
-        let mut choices = vec![String::from("← back")];
-        let tags = params.todos.get_index_list()
+        let menu = entry::shown(vec![Entry::Back, Entry::RenameTag], params.list().read_only);
+        let mut choices = entry::labels(&menu);
+        let tags = params.list().todos.get_index_list()
                                 .iter()
                                 .filter(|x|x.starts_with(&tag_type))
                                 .map(|x|{let mut s = String::from(*x); s.replace_range(0..tag_type.len(), ""); s})
                                 .collect::<Vec<String>>();
-        for tag in tags {
-            choices.push(tag.to_string());
-        }
-        let status : MenuStatus = match Rofi::from(rofi_config).prompt("Tag").select_range(0,0).run(choices).unwrap().as_ref() {
-            "← back" => MenuStatus::MAINMENU,
-            "" => MenuStatus::EXIT,
-            s => {
+        choices.extend(tags.iter().cloned());
+        let selected = match Rofi::from(rofi_config).prompt("Tag").select_range(0,menu.len()-1).run_index(choices) {
+            Ok(selected) => selected,
+            Err(e) => return menu_error(e)
+        };
+        let status : MenuStatus = match selected {
+            Some(i) if i >= menu.len() => {
                 let mut idx_name = tag_type.to_string();
-                idx_name.push_str(s);
-                let result = params.todos.index(&idx_name);
+                idx_name.push_str(&tags[i - menu.len()]);
+                let result = params.list().todos.index(&idx_name);
                 match result {
                     Some(_) => show_tags_menu(rofi_config, params, idx_name),
-                    None => MenuStatus::BACK
+                    None => MenuStatus::Back
                 }
-            }
+            },
+            Some(i) if menu[i] == Entry::RenameTag => show_rename_tag_menu(rofi_config, params, &tag_type),
+            _ => MenuStatus::MainMenu
         };
         match status {
-            MenuStatus::BACK => continue,
-            MenuStatus::EXIT => return MenuStatus::EXIT,
-            MenuStatus::MAINMENU => return MenuStatus::MAINMENU
+            MenuStatus::Back => continue,
+            MenuStatus::Exit => return MenuStatus::Exit,
+            MenuStatus::MainMenu => return MenuStatus::MainMenu
         }
     }
 }
 
-fn show_main_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
-    loop {
-        let mut choices = vec![String::from("+ add"), String::from("~ done"), String::from("@ project tags"), String::from("@ context tags") , String::from("* exit")];
-        for todo in params.todos.index(&params.get_sort_string()).unwrap() {
-            choices.push(todo.to_string());
+/// Rename a project or context tag in all the tasks and show the number of modified tasks
+/// 
+/// Arguments:
+/// 
+/// * `tag_type` - the prefix of the indexes of the tags (`project_` or `context_`)
+fn show_rename_tag_menu(rofi_config : &RofiParams, params : &mut Params, tag_type : &str) -> MenuStatus {
+    let old = match tag_selector(rofi_config, params, tag_type, &[], "Select the tag to rename") {
+        Some(tag) => tag,
+        None => return MenuStatus::Back
+    };
+    let prefix = if tag_type == "project_" { '+' } else { '@' };
+    let new = Rofi::from(rofi_config).prompt("Rename to").placeholder("").pretext(old.clone()).text_only().run(vec![]).unwrap();
+    let new = new.trim().trim_start_matches(prefix);
+    if new.is_empty() || new == old {
+        return MenuStatus::Back;
+    }
+    let msg = if new.contains(char::is_whitespace) {
+        String::from("A tag can't contain spaces")
+    } else {
+        match params.rename_tag(prefix, &old, new) {
+            Ok(count) => format!("{}{} renamed to {}{} in {} task{}", prefix, old, prefix, new, count, if count == 1 { "" } else { "s" }),
+            Err(e) => e
         }
-        let status : MenuStatus = match Rofi::from(rofi_config).prompt("Todo").select_range(0,4).run(choices).unwrap().as_ref() {
-            "+ add" => {
-                show_add_task(rofi_config, params)
-            },
-            "~ done" => {
-                show_old_menu(rofi_config, params)
-            },
-            "@ project tags" => {
-                show_tag_list(rofi_config, params, String::from("project_"))
-            },
-            "@ context tags" => {
-                show_tag_list(rofi_config, params, String::from("context_"))
-            },
-            "* exit" => MenuStatus::EXIT,
-            "" => MenuStatus::EXIT,
-            s => {
-                let result = params.todos.index(&params.get_sort_string()).unwrap().into_iter().find(|x| x.to_string().eq(s));
-                match result {
-                    Some(t) => show_task_menu(rofi_config, params, t),
-                    None => MenuStatus::MAINMENU
-                }
+    };
+    show_message(rofi_config, "Rename", msg);
+    MenuStatus::Back
+}
+
+/// The message of the tag selector of the filter menu
+const FILTER_TAG_MSG : &str = "Select a tag to add or remove it from the filter";
+
+/// Choose a project or context tag among the tags of the tasks to do
+/// 
+/// Arguments:
+/// 
+/// * `tag_type` - the prefix of the indexes of the tags (`project_` or `context_`)
+/// * `selected` - the tags already selected, shown first
+/// * `msg` - the message shown above the tags
+fn tag_selector(rofi_config : &RofiParams, params : &Params, tag_type : &str, selected : &[String], msg : &str) -> Option<String> {
+    let mut choices = selected.to_vec();
+    for name in params.list().todos.get_index_list() {
+        if let Some(tag) = name.strip_prefix(tag_type) {
+            if !selected.iter().any(|s| s == tag) {
+                choices.push(tag.to_string());
             }
-        };
-        match status {
-            MenuStatus::BACK => continue,
-            MenuStatus::EXIT => return MenuStatus::EXIT,
-            MenuStatus::MAINMENU => continue
         }
     }
+    choices[selected.len()..].sort();
+    let tag = Rofi::from(rofi_config).prompt("Tag").msg(msg.to_string()).run(choices).unwrap();
+    if tag.is_empty() || tag.contains(char::is_whitespace) {
+        None
+    } else {
+        Some(tag)
+    }
 }
 
-fn load_config(config_file: &std::path::PathBuf, todos: &mut Indexer<Task>) -> Result<bool, String> {
-    if !std::path::Path::new(config_file).exists() {
-        save_config(config_file, todos).unwrap();
-    }
-    if let Ok(lines) = read_lines(config_file) {
-        for line in lines {
-            if let Ok(linestr) = line {
-                let task_result = Task::from_todotxt(String::from(linestr));
-                match task_result {
-                    Ok(task) => {add_task(todos, task);}
-                    Err(_) => ()
+/// Build the filter of the main menu
+/// 
+/// Selecting a criterion which is set removes it
+fn show_filter_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    loop {
+        let filter = &params.filter;
+        let optional = |value : Option<String>| value.unwrap_or_else(|| String::from("any"));
+        let completed = match filter.completed {
+            Some(false) => "no",
+            Some(true) => "yes",
+            None => "any"
+        };
+        let menu = vec![
+            String::from("← back"),
+            String::from("* clear filter"),
+            format!("+ query : {}", optional(Some(filter.query()).filter(|q| !q.is_empty()))),
+            format!("+ projects : {}", if filter.projects.is_empty() { String::from("any") } else { filter.projects.join(", ") }),
+            format!("+ contexts : {}", if filter.contexts.is_empty() { String::from("any") } else { filter.contexts.join(", ") }),
+            format!("+ priority at least : {}", optional(filter.priority_at_least.map(|p| p.to_string()))),
+            format!("+ due after : {}", optional(filter.due_after.map(|d| d.format("%Y-%m-%d").to_string()))),
+            format!("+ due before : {}", optional(filter.due_before.map(|d| d.format("%Y-%m-%d").to_string()))),
+            format!("~ completed : {}", completed),
+            String::from("+ project actions")
+        ];
+        let selected = match Rofi::from(rofi_config).prompt("Filter").select_range(0,menu.len()-1).run_index(menu).unwrap() {
+            Some(i) => i,
+            None => return MenuStatus::MainMenu
+        };
+        let today = Local::now().date_naive();
+        match selected {
+            0 => return MenuStatus::MainMenu,
+            1 => params.filter = TaskFilter::todo(),
+            2 => {
+                let query = Rofi::from(rofi_config)
+                            .prompt("Query")
+                            .placeholder("+project @context -@excluded words")
+                            .pretext(params.filter.query())
+                            .text_only()
+                            .run(vec![])
+                            .unwrap();
+                if !query.is_empty() {
+                    params.filter.set_query(&query);
+                }
+            },
+            3 => {
+                if let Some(tag) = tag_selector(rofi_config, params, "project_", &params.filter.projects, FILTER_TAG_MSG) {
+                    TaskFilter::toggle(&mut params.filter.projects, &tag);
+                }
+            },
+            4 => {
+                if let Some(tag) = tag_selector(rofi_config, params, "context_", &params.filter.contexts, FILTER_TAG_MSG) {
+                    TaskFilter::toggle(&mut params.filter.contexts, &tag);
+                }
+            },
+            5 => {
+                params.filter.priority_at_least = match params.filter.priority_at_least {
+                    Some(_) => None,
+                    None => priority_selector(rofi_config, None).flatten()
+                };
+            },
+            6 => {
+                params.filter.due_after = match params.filter.due_after {
+                    Some(_) => None,
+                    None => date_selector(rofi_config, today)
+                };
+            },
+            7 => {
+                params.filter.due_before = match params.filter.due_before {
+                    Some(_) => None,
+                    None => date_selector(rofi_config, today)
+                };
+            },
+            8 => {
+                params.filter.completed = match params.filter.completed {
+                    Some(false) => Some(true),
+                    Some(true) => None,
+                    None => Some(false)
+                };
+            },
+            _ => {
+                if let Some(project) = tag_selector(rofi_config, params, "project_", &[], "Select the project to complete, archive or reschedule") {
+                    return show_project_menu(rofi_config, params, Some(project));
                 }
             }
         }
     }
-    Ok(true)
-}
-
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<fs::File>>> where P: AsRef<std::path::Path> {
-    let file = fs::File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
 }
 
-fn save_config(config_file: &std::path::PathBuf, todos: &mut Indexer<Task>) -> Result<bool,String> {
-    let mut save = String::new();
-    for todo in todos.get_main_index() {
-        save.push_str(&todo.to_todotxt());
-        save.push_str("\n");
+/// Restrict the main menu to the tasks of a project or context
+/// 
+/// Selecting a tag of the filter removes it from the filter
+fn show_filter_by_tag_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    let tasks = params.list().todos.get_main_index().iter().map(|t| t.as_ref()).collect::<Vec<_>>();
+    let projects = counts_by_project(tasks.iter().copied());
+    let contexts = counts_by_context(tasks.iter().copied());
+    let mut choices = vec![String::from("← back"), String::from("* clear filter")];
+    let active = |tags : &[String], tag : &str| if tags.iter().any(|t| t == tag) { "✔ " } else { "" };
+    for (tag, count) in &projects {
+        choices.push(format!("{}+{} ({})", active(&params.filter.projects, tag), tag, count));
     }
-
-    match fs::write(config_file, save) {
-        Ok(_) => Ok(true),
-        Err(e) => Err(e.to_string())
+    for (tag, count) in &contexts {
+        choices.push(format!("{}@{} ({})", active(&params.filter.contexts, tag), tag, count));
+    }
+    match Rofi::from(rofi_config).prompt("Filter by tag").select_range(0,1).run_index(choices).unwrap() {
+        None | Some(0) => MenuStatus::MainMenu,
+        Some(1) => {
+            params.filter = TaskFilter::todo();
+            MenuStatus::MainMenu
+        },
+        Some(i) if i - 2 < projects.len() => {
+            TaskFilter::toggle(&mut params.filter.projects, &projects[i - 2].0);
+            MenuStatus::MainMenu
+        },
+        Some(i) => {
+            TaskFilter::toggle(&mut params.filter.contexts, &contexts[i - 2 - projects.len()].0);
+            MenuStatus::MainMenu
+        }
     }
 }
 
-fn add_task(idx: &mut Indexer<Task>, tsk: Task) -> Rc<Task> {
-    if !tsk.completion {
-        for tag in tsk.get_context_tags().clone() {
-            let mut idx_name = String::from("context_");
-            idx_name.push_str(&tag);
-            idx.new_autoremove_index(idx_name, move |x|!x.completion && x.get_context_tags().contains(&tag), Task::comp_content);
+/// Search the tasks of the main menu and show them from the best match to the worst
+fn show_search_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    let query = Rofi::from(rofi_config).prompt("Search").placeholder("").text_only().run(vec![]).unwrap();
+    loop {
+        let shown = params.list().tasks().into_iter().filter(|(_, x)| params.is_shown(x) && params.filter.matches(x)).map(|(_, x)| x);
+        let tasks = search::search(shown, &query).into_iter().filter_map(|(_, task)| Some((task.id()?, task.completion))).collect::<Vec<_>>();
+        let mut choices = vec![String::from("← back")];
+        for (id, _) in &tasks {
+            choices.extend(params.list().get(*id).map(|todo| params.task_line(todo)));
         }
-        for tag in tsk.get_project_tags().clone() {
-            let mut idx_name = String::from("project_");
-            idx_name.push_str(&tag);
-            idx.new_autoremove_index(idx_name, move |x|!x.completion && x.get_project_tags().contains(&tag), Task::comp_content);
+        let (id, completed) = match Rofi::from(rofi_config).prompt(&format!("Search [{}]", query)).select_range(0,0).markup_rows().run_index(choices).unwrap() {
+            None | Some(0) => return MenuStatus::MainMenu,
+            Some(i) => tasks[i - 1]
+        };
+        let status = if completed {
+            show_done_task_menu(rofi_config, params, id)
+        } else {
+            show_task_menu(rofi_config, params, id)
+        };
+        match status {
+            MenuStatus::Back => continue,
+            MenuStatus::Exit => return MenuStatus::Exit,
+            MenuStatus::MainMenu => return MenuStatus::MainMenu
         }
     }
-    idx.add(tsk)
-}
-
-
-struct Params {
-    sort : SortTaskBy,
-    todos : Indexer<Task>,
 }
 
-impl Params {
-    fn new(sort : SortTaskBy, idx : Indexer<Task>) -> Self {
-        Params { sort : sort, todos : idx }
+/// Complete, postpone or delete several tasks of the main menu at once
+/// 
+/// The tasks are selected with Shift+Enter, the action is only applied if all the selected entries are understood
+fn show_bulk_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    let actions = vec![String::from("← back"), String::from("✔ complete"), String::from("+ postpone"), String::from("+ change priority"), String::from("! delete")];
+    let action = match Rofi::from(rofi_config).prompt("Bulk").select_range(0,0).run_index(actions.clone()).unwrap() {
+        None | Some(0) => return MenuStatus::MainMenu,
+        Some(i) => actions[i].clone()
+    };
+    let tasks = params.list().tasks().into_iter()
+                    .filter(|(_, x)| !x.completion && params.is_shown(x) && params.filter.matches(x))
+                    .collect::<Vec<_>>();
+    let choices = tasks.iter().map(|(_, t)| params.task_line(t)).collect::<Vec<_>>();
+    let ids = tasks.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+    let prompt = format!("{} (Shift+Enter to select)", action.split_once(' ').map_or(action.as_str(), |(_, name)| name));
+    let selected = match Rofi::from(rofi_config).prompt(&prompt).multi_select().markup_rows().run_indexes(choices) {
+        Ok(selected) if selected.is_empty() => return MenuStatus::MainMenu,
+        Ok(selected) => selected,
+        Err(e) => {
+            show_message(rofi_config, "Bulk", e);
+            return MenuStatus::MainMenu;
+        }
+    };
+    let selected = selected.into_iter().map(|i| ids[i]).collect::<Vec<_>>();
+    let done = match action.as_ref() {
+        "✔ complete" => params.bulk(selected, |params, task| Ok(params.complete_task(task))),
+        "+ postpone" => match postpone_selector(rofi_config) {
+            Some(offset) => {
+                let today = Local::now().date_naive();
+                params.bulk(selected, |params, task| Ok(params.postpone_task(task, offset, today)))
+            },
+            None => Ok(())
+        },
+        "+ change priority" => match priority_selector(rofi_config, None) {
+            Some(priority) => params.bulk(selected, |params, task| {
+                Ok(params.change_task(task, |t| {
+                    t.set_priority(priority).expect("the priorities of the menu are valid");
+                    escalation::opt_out(t);
+                }))
+            }),
+            None => Ok(())
+        },
+        _ => {
+            if !confirm(rofi_config, &format!("Delete {} tasks ?", selected.len())) {
+                return MenuStatus::MainMenu;
+            }
+            params.bulk(selected, |params, task| params.trash_task(task))
+        }
+    };
+    if let Err(e) = done {
+        show_message(rofi_config, "Bulk", e);
+    }
+    MenuStatus::MainMenu
+}
+
+/// Select, save, rename and delete the saved views
+fn show_views_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    loop {
+        let mut choices = vec![String::from("← back"), String::from("+ save current view"), String::from("* default view")];
+        for view in &params.views.views {
+            let active = if params.views.last.as_ref() == Some(&view.name) { "✔ " } else { "" };
+            choices.push(format!("{}{}", active, view.name));
+        }
+        let selected = match Rofi::from(rofi_config).prompt("Views").select_range(0,2).run_index(choices).unwrap() {
+            Some(i) => i,
+            None => return MenuStatus::MainMenu
+        };
+        match selected {
+            0 => return MenuStatus::MainMenu,
+            1 => {
+                if let Some(name) = view_name_prompt(rofi_config, params.views.last.clone().unwrap_or_default()) {
+                    let view = params.current_view(name.clone());
+                    params.views.set(view);
+                    params.views.last = Some(name);
+                    params.save_views();
+                }
+            },
+            2 => {
+                let view = params.default_view.clone();
+                params.apply_view(&view);
+                params.views.set_last_sort(&view.sort, view.direction);
+                params.views.last = None;
+                params.save_views();
+                return MenuStatus::MainMenu;
+            },
+            i => {
+                let view = params.views.views[i - 3].clone();
+                if show_view_menu(rofi_config, params, view) == MenuStatus::MainMenu {
+                    return MenuStatus::MainMenu;
+                }
+            }
+        }
+    }
+}
+
+/// Apply, rename or delete a saved view
+fn show_view_menu(rofi_config : &RofiParams, params : &mut Params, view : View) -> MenuStatus {
+    let menu = vec![String::from("✔ apply"), String::from("+ rename"), String::from("! delete"), String::from("← back")];
+    let message = format!("Filter : {}\nSort : {}", view.filter.describe(), sort_description(&view.sort, view.direction));
+    match Rofi::from(rofi_config).prompt(&view.name).msg(message).select_range(0,menu.len()-1).run(menu).unwrap().as_ref() {
+        "✔ apply" => {
+            params.apply_view(&view);
+            params.views.set_last_sort(&view.sort, view.direction);
+            params.views.last = Some(view.name);
+            params.save_views();
+            MenuStatus::MainMenu
+        },
+        "+ rename" => {
+            if let Some(name) = view_name_prompt(rofi_config, view.name.clone()) {
+                params.views.rename(&view.name, &name);
+                params.save_views();
+            }
+            MenuStatus::Back
+        },
+        "! delete" => {
+            params.views.remove(&view.name);
+            params.save_views();
+            MenuStatus::Back
+        },
+        _ => MenuStatus::Back
+    }
+}
+
+/// Describe a sort order with the names of the `--sort` option (e.g. `due,priority (reversed)`)
+fn sort_description(sort : &[SortTaskBy], direction : SortDirection) -> String {
+    let keys = sort.iter().map(|key| key.name()).collect::<Vec<_>>().join(",");
+    match direction {
+        SortDirection::Ascending    => keys,
+        SortDirection::Descending   => format!("{} (reversed)", keys)
     }
+}
+
+/// The separator between the number of a row and the line of its task, like `27 │ (B) renew passport`
+const ROW_NUMBER_SEPARATOR : &str = " │ ";
+
+/// The line of a task without the number put before it by `--numbered-rows`
+fn strip_row_number(line : &str) -> &str {
+    match line.split_once(ROW_NUMBER_SEPARATOR) {
+        Some((number, rest)) if row_number(number, usize::MAX).is_some() => rest,
+        _ => line
+    }
+}
+
+/// The number of a row typed in a menu, `None` if the text is not a number from 1 to `count`
+fn row_number(text : &str, count : usize) -> Option<usize> {
+    let text = text.trim();
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    text.parse::<usize>().ok().filter(|n| *n >= 1 && *n <= count)
+}
 
-    fn get_sort_string(&self) -> String {
-        String::from(match self.sort {
-            SortTaskBy::Content         => "content",
-            SortTaskBy::CreationDate    => "creation",
-            SortTaskBy::Priority        => "priority",
-            SortTaskBy::DueDate         => "due"
+/// Describe the listed tasks in the message of the main menu, like `12 open · 2 overdue · ~5h 30m · filter: @work · sort: due ↑`
+///
+/// The sum of the estimates of the tasks to do is only shown when one of them has an estimate
+///
+/// Arguments:
+///
+/// * `tasks` - the listed tasks
+/// * `today` - the current date
+/// * `filter` - the description of the filter of the list, empty without filter
+/// * `sort` - the sort keys of the list
+/// * `direction` - the direction of the sort
+fn view_summary(tasks : &[&Task], today : NaiveDate, filter : &str, sort : &[SortTaskBy], direction : SortDirection) -> String {
+    let todo = tasks.iter().filter(|t| !t.completion).collect::<Vec<_>>();
+    let mut parts = vec![format!("{} open", todo.len()), format!("{} overdue", todo.iter().filter(|t| t.is_overdue(today)).count())];
+    let estimates = todo.iter().filter_map(|t| t.get_estimate()).collect::<Vec<_>>();
+    if !estimates.is_empty() {
+        let total = estimates.into_iter().fold(chrono::Duration::zero(), |total, estimate| total + estimate);
+        parts.push(format!("~{}", dates::format_estimate(total)));
+    }
+    if !filter.is_empty() {
+        parts.push(format!("filter: {}", filter));
+    }
+    let keys = sort.iter().map(|key| key.name()).collect::<Vec<_>>().join(",");
+    parts.push(format!("sort: {} {}", keys, if direction == SortDirection::Ascending { "↑" } else { "↓" }));
+    parts.join(" · ")
+}
+
+/// Add the due date after a sort by priority only, sorting only by priority has always broken ties with the due date
+fn with_tie_break(mut sort : Vec<SortTaskBy>) -> Vec<SortTaskBy> {
+    if let [SortTaskBy::Priority] = sort.as_slice() {
+        sort.push(SortTaskBy::DueDate);
+    }
+    sort
+}
+
+/// Change the sort order of the main menu, the current sort key is marked
+fn show_sort_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    let mut choices = vec![String::from("← back")];
+    for key in SortTaskBy::ALL.iter() {
+        let active = if params.sort.first() == Some(key) { "✔ " } else { "" };
+        choices.push(format!("{}{}", active, key.name()));
+    }
+    choices.push(String::from(match params.direction {
+        SortDirection::Ascending    => "~ reverse order",
+        SortDirection::Descending   => "✔ reverse order"
+    }));
+    let prompt = format!("Sort by ({})", sort_description(&params.sort, params.direction));
+    let selected = match Rofi::from(rofi_config).prompt(&prompt).select_range(0,0).run_index(choices).unwrap() {
+        None | Some(0) => return MenuStatus::MainMenu,
+        Some(i) => i
+    };
+    let (sort, direction) = match SortTaskBy::ALL.get(selected - 1) {
+        Some(key) => (with_tie_break(vec![key.clone()]), params.direction),
+        None => (params.sort.clone(), match params.direction {
+            SortDirection::Ascending    => SortDirection::Descending,
+            SortDirection::Descending   => SortDirection::Ascending
         })
+    };
+    params.set_sort(sort, direction);
+    params.views.set_last_sort(&params.session.sort, params.session.direction);
+    params.save_views();
+    MenuStatus::MainMenu
+}
+
+/// Ask the name of a view, return `None` if it is empty
+fn view_name_prompt(rofi_config : &RofiParams, name : String) -> Option<String> {
+    let name = Rofi::from(rofi_config).prompt("Name").placeholder("").pretext(name).text_only().run(vec![]).unwrap();
+    let name = name.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
     }
 }
 
-fn main() {
-    let mut todos = Indexer::<Task>::new();
+/// Replace the task file with one of its backups
+fn show_backups_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    let backups = storage::list_backups(&params.list().path);
+    let mut choices = vec![String::from("← back")];
+    for (number, _, modified) in &backups {
+        let date : chrono::DateTime<Local> = (*modified).into();
+        choices.push(format!("backup {} : {}", number, date.format("%Y-%m-%d %H:%M:%S")));
+    }
+    let mut rofi = Rofi::from(rofi_config).prompt("Backups").select_range(0,0);
+    if backups.is_empty() {
+        rofi = rofi.msg(String::from("No backup yet"));
+    }
+    let number = match rofi.run_index(choices.clone()).unwrap() {
+        None | Some(0) => return MenuStatus::MainMenu,
+        Some(i) => backups[i - 1].0
+    };
+    if storage::dry_run() {
+        show_message(rofi_config, "Restore", String::from("The backups can't be restored with --dry-run"));
+    } else if confirm(rofi_config, &format!("Replace the tasks with {} ? The current tasks are backed up first", choices[number])) {
+        let restored = save_merging(rofi_config, params.list())
+                        .and_then(|_| storage::restore_backup(&params.list().path, number, params.backups, params.list().cipher.as_deref()).map_err(|e| e.to_string()))
+                        .and_then(|_| params.reload());
+        if let Err(e) = restored {
+            show_message(rofi_config, "Restore", e);
+        }
+    }
+    MenuStatus::MainMenu
+}
+
+/// Select a task list, return its index
+/// 
+/// Arguments:
+/// 
+/// * `prompt` - the prompt of the menu
+/// * `lists` - the indexes of the lists to choose from
+fn list_selector(rofi_config : &RofiParams, params : &Params, prompt : &str, lists : &[usize]) -> Option<usize> {
+    let choices = lists.iter().map(|i| params.lists[*i].name()).collect::<Vec<_>>();
+    Rofi::from(rofi_config).prompt(prompt).run_index(choices).unwrap().map(|i| lists[i])
+}
+
+/// Show another task list in the main menu
+fn show_switch_list_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    let others = (0..params.lists.len()).filter(|i| *i != params.current).collect::<Vec<_>>();
+    if let Some(index) = list_selector(rofi_config, params, "Switch list", &others) {
+        if let Err(e) = params.switch_list(rofi_config, index) {
+            show_message(rofi_config, "Switch list", e);
+        }
+    }
+    MenuStatus::MainMenu
+}
+
+/// Rewrite the task file in the sort order of the main menu after a confirmation
+fn show_sort_file_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    if storage::dry_run() {
+        show_message(rofi_config, "Sort file", String::from("The task file can't be sorted with --dry-run"));
+        return MenuStatus::MainMenu;
+    }
+    if confirm(rofi_config, &format!("Rewrite the task file sorted by {} ? The current file is backed up first", sort_description(&params.sort, params.direction))) {
+        // Saving first so the changes made by other programs are merged before rewriting the file
+        let sorted = save_merging(rofi_config, params.list())
+                        .and_then(|_| params.reload())
+                        .and_then(|_| params.sort_file());
+        if let Err(e) = sorted {
+            show_message(rofi_config, "Sort file", e);
+        }
+    }
+    MenuStatus::MainMenu
+}
+
+/// The row selected in a menu of tasks
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SelectedRow {
+    /// An entry of the menu or a header, with its index
+    Entry(usize),
+    /// A task, with the number of tasks before it in the menu
+    Task(TaskId, usize)
+}
+
+/// The choice made in a menu of tasks, see `Params::select_row`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RowChoice {
+    /// A row chosen with Return, with its index
+    Row(usize),
+    /// A row chosen with the custom key of an action
+    Key(KeyAction, usize),
+    /// A typed text which is neither a row nor the number of a task
+    Unknown,
+    /// The menu was closed
+    Closed,
+    /// The menu could not be shown, the error is printed
+    Failed
+}
 
-    let args = Cli::from_args();
+/// A task row or a project header of the main menu
+#[derive(PartialEq)]
+enum ListRow {
+    /// A task of the current list
+    Task(TaskId),
+    /// The header of a project, `None` for the tasks without project
+    Project(Option<String>)
+}
 
-    let sort  = match args.sort.as_ref() {
-        "content"   => SortTaskBy::Content,
-        "creation"  => SortTaskBy::CreationDate,
-        "priority"  => SortTaskBy::Priority,
-        "due"       => SortTaskBy::DueDate,
-        _           => SortTaskBy::Content
+/// Complete, archive, reschedule or rename the tasks of a project
+/// 
+/// Each action is confirmed with the number of tasks it changes, done for all the tasks or none of them and undone at once
+/// 
+/// Arguments:
+/// 
+/// * `project` - the project tag, `None` for the tasks without project
+fn show_project_menu(rofi_config : &RofiParams, params : &mut Params, project : Option<String>) -> MenuStatus {
+    let (done, todo) : (Vec<_>, Vec<_>) = params.list().iter()
+                    .filter(|(_, t)| match &project {
+                        Some(p) => t.get_project_tags().contains(p),
+                        None => t.get_project_tags().is_empty()
+                    })
+                    .partition(|(_, t)| t.completion);
+    let todo = todo.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+    let all = done.into_iter().map(|(id, _)| id).chain(todo.iter().copied()).collect::<Vec<_>>();
+    let name = project.as_ref().map_or(String::from("(no project)"), |p| format!("+{}", p));
+    let logged = project.as_ref().map_or(String::from("the tasks without project"), |p| format!("project +{}", p));
+    let mut menu = vec![String::from("← back"), format!("✔ complete all ({})", todo.len()), format!("~ archive project ({})", all.len()),
+                        format!("+ reschedule all ({})", todo.len())];
+    if params.list().read_only {
+        menu.truncate(1);
+    } else if project.is_some() {
+        menu.push(String::from("+ rename project"));
+    }
+    let selected = match Rofi::from(rofi_config).prompt(&name).select_range(0,0).run_index(menu).unwrap() {
+        Some(i) => i,
+        None => return MenuStatus::MainMenu
     };
+    let count = |tasks : &[TaskId]| format!("{} task{}", tasks.len(), if tasks.len() == 1 { "" } else { "s" });
+    let result = match (selected, project) {
+        (1, _) if !todo.is_empty() && confirm(rofi_config, &format!("Complete the {} to do in {} ?", count(&todo), name)) => {
+            let description = format!("complete {} ({})", logged, count(&todo));
+            params.group(description, todo, |params, task| Ok(params.complete_task(task)))
+        },
+        (2, _) if !all.is_empty() && confirm(rofi_config, &format!("Complete and move the {} of {} to the archive ?", count(&all), name)) => {
+            let description = format!("archive {} ({})", logged, count(&all));
+            params.group(description, all, |params, task| {
+                // The tasks to do are completed without their next occurrence, the project is over
+                let completed = if params.copy(task).completion { None } else { Some(params.change_task(task, |t| t.set_completed())) };
+                match params.archive_task(task) {
+                    Ok(archived) => Ok(Op::Batch(completed.into_iter().chain(Some(archived)).collect())),
+                    Err(e) => {
+                        if let Some(completed) = completed {
+                            completed.revert(params.list_mut())?;
+                        }
+                        Err(e)
+                    }
+                }
+            })
+        },
+        (3, _) if !todo.is_empty() => match postpone_selector(rofi_config) {
+            Some(offset) if confirm(rofi_config, &format!("Reschedule the {} to do in {} by {} ?", count(&todo), name, offset)) => {
+                let today = Local::now().date_naive();
+                let description = format!("reschedule {} by {} ({})", logged, offset, count(&todo));
+                params.group(description, todo, |params, task| Ok(params.postpone_task(task, offset, today)))
+            },
+            _ => Ok(())
+        },
+        (4, Some(project)) => {
+            let new_name = Rofi::from(rofi_config).prompt("Rename to").placeholder("").pretext(project.clone()).text_only().run(vec![]).unwrap();
+            let new_name = new_name.trim().trim_start_matches('+');
+            if new_name.is_empty() || new_name == project || new_name.contains(char::is_whitespace) {
+                return MenuStatus::MainMenu;
+            }
+            params.rename_tag('+', &project, new_name).map(|_| ())
+        },
+        _ => return MenuStatus::MainMenu
+    };
+    if let Err(e) = result {
+        show_message(rofi_config, &name, e);
+    }
+    MenuStatus::MainMenu
+}
 
-    todos.new_index(String::from("content"),    |x|!x.completion, Task::comp_content);
-    todos.new_index(String::from("creation"),   |x|!x.completion, Task::comp_creation_date);
-    todos.new_index(String::from("priority"),   |x|!x.completion, Task::comp_priority);
-    todos.new_index(String::from("due"),        |x|!x.completion, Task::comp_due_date);
-    todos.new_index(String::from("done"),       |x|x.completion, Task::comp_content);
-
-    let rofi_config = RofiParams { no_config : args.no_config, case_insensitive : args.case_insensitive };
-    let config = args.config;
-    match load_config(&config, &mut todos) {
-        Ok(_) => (),
-        Err(s) => {
-            println!("{}", s);
-            return;
+/// Show all the tasks with the entries of the other menus, the toggles and the undo are applied at once
+fn show_main_menu(rofi_config : &RofiParams, params : &mut Params) -> Transition {
+    loop {
+        params.refresh();
+        let notice = params.notice.take();
+        let read_only = params.list().read_only;
+        let mut menu = vec![Entry::Add, Entry::AddFromClipboard, Entry::Today, Entry::Focus];
+        if params.list().history.can_undo() {
+            menu.push(Entry::Undo);
+        }
+        if params.list().history.can_redo() {
+            menu.push(Entry::Redo);
+        }
+        menu.extend(vec![Entry::Search, Entry::Week, Entry::Bulk, Entry::Done, Entry::Recent, Entry::Stale, Entry::Trash, Entry::ProjectTags,
+                         Entry::ContextTags, Entry::FilterByTag, Entry::Filter, Entry::Sort, Entry::Views, Entry::Statistics]);
+        if params.lists.len() > 1 {
+            menu.push(Entry::SwitchList);
+        }
+        menu.extend(vec![Entry::Backups, Entry::SortFile, Entry::FutureTasks(params.show_future), Entry::HiddenTasks(params.show_hidden),
+                         Entry::CompletedTasks(params.views.show_completed), Entry::GroupByProject(params.group_by_project), Entry::Exit]);
+        let menu = entry::shown(menu, read_only);
+        let (ids, tasks) : (Vec<_>, Vec<_>) = params.list().tasks().into_iter().filter(|(_, x)| params.is_shown(x) && params.filter.matches(x)).unzip();
+        let mut choices = entry::labels(&menu);
+        let mut rows = Vec::new();
+        // The numbers follow the order of the rows, they are kept until the menu is shown again
+        let mut numbered = Vec::new();
+        let width = ids.len().to_string().len();
+        if params.group_by_project {
+            for group in agenda::group_by_project(&tasks) {
+                let name = group.project.as_ref().map_or(String::from("(no project)"), |p| format!("+{}", p));
+                choices.push(format!("<b>── {} ({}) ──</b>", markup::escape(&name), group.indexes.len()));
+                rows.push(ListRow::Project(group.project));
+                for index in group.indexes {
+                    numbered.push(ids[index]);
+                    choices.push(format!("    {}", params.numbered_line(numbered.len(), width, params.task_line(tasks[index]))));
+                    rows.push(ListRow::Task(ids[index]));
+                }
+            }
+        } else {
+            for (id, todo) in ids.iter().zip(&tasks) {
+                numbered.push(*id);
+                choices.push(params.numbered_line(numbered.len(), width, params.task_line(todo)));
+                rows.push(ListRow::Task(*id));
+            }
+        }
+        let mut prompt = params.locale.prompt("Todo").to_string();
+        if params.lists.len() > 1 {
+            prompt = format!("{} ({})", prompt, params.list().name());
+        }
+        if read_only {
+            prompt.push_str(" (read-only)");
+        }
+        let description = params.filter.describe();
+        if !description.is_empty() {
+            prompt = format!("{} [{}]", prompt, description);
+        }
+        prompt = format!("{} ⇅ {}", prompt, sort_description(&params.sort, params.direction));
+        let mut rofi = Rofi::from(rofi_config).prompt(&prompt).select_range(0,menu.len()-1).markup_rows();
+        let row_tasks = menu.iter().map(|_| None).chain(rows.iter().map(|row| match row {
+            ListRow::Task(id) => Some(*id),
+            ListRow::Project(_) => None
+        })).collect::<Vec<_>>();
+        if let Some(row) = params.selected_row("main", &row_tasks) {
+            rofi = rofi.selected(row as u32);
+        }
+        let mut message = Vec::new();
+        // A backend without message would show the summary in the prompt, which already has the filter and the sort
+        if params.summary && rofi_config.backend.capabilities().message {
+            message.push(view_summary(&tasks, Local::now().date_naive(), &description, &params.sort, params.direction));
+        }
+        message.extend(notice);
+        if !params.list().parse_errors().is_empty() {
+            message.push(params.list().parse_errors_str());
+        }
+        if params.key_help && !read_only && !params.keys.is_empty() {
+            message.push(keys::help(&params.keys));
+        }
+        if !message.is_empty() {
+            rofi = rofi.msg(message.join("\n"));
+        }
+        let selected = params.select_row(rofi, choices, &numbered, |id| rows.iter().position(|row| *row == ListRow::Task(id)).map(|row| menu.len() + row));
+        params.row_numbers = numbered;
+        let selected = match selected {
+            RowChoice::Row(i) => i,
+            RowChoice::Key(action, i) => {
+                params.remember_row("main", i, &row_tasks);
+                if let Some(id) = row_tasks[i] {
+                    run_key_action(rofi_config, params, action, id);
+                }
+                continue;
+            },
+            RowChoice::Unknown => continue,
+            RowChoice::Closed => return Transition::Back,
+            RowChoice::Failed => return Transition::Quit
+        };
+        params.remember_row("main", selected, &row_tasks);
+        if selected >= menu.len() {
+            return match rows.swap_remove(selected - menu.len()) {
+                ListRow::Task(id) => Transition::Open(Screen::Task(id)),
+                ListRow::Project(project) => Transition::Open(Screen::Project(project))
+            };
+        }
+        let screen = match &menu[selected] {
+            Entry::Add => Screen::Add,
+            Entry::AddFromClipboard => Screen::Paste,
+            Entry::Today => Screen::Today,
+            Entry::Focus => Screen::Focus,
+            Entry::Undo | Entry::Redo => {
+                let done = if menu[selected] == Entry::Undo { params.undo() } else { params.redo() };
+                if let Err(e) = done {
+                    show_message(rofi_config, "Undo", e);
+                }
+                continue;
+            },
+            Entry::Search => Screen::Search,
+            Entry::Week => Screen::Week,
+            Entry::Bulk => Screen::Bulk,
+            Entry::Done => Screen::Done,
+            Entry::Recent => Screen::Recent,
+            Entry::Stale => Screen::Stale,
+            Entry::Trash => Screen::Trash,
+            Entry::ProjectTags => Screen::Tags(String::from("project_")),
+            Entry::ContextTags => Screen::Tags(String::from("context_")),
+            Entry::FilterByTag => Screen::FilterByTag,
+            Entry::Filter => Screen::Filter,
+            Entry::Sort => Screen::Sort,
+            Entry::Views => Screen::Views,
+            Entry::Statistics => Screen::Statistics,
+            Entry::SwitchList => Screen::SwitchList,
+            Entry::Backups => Screen::Backups,
+            Entry::SortFile => Screen::SortFile,
+            Entry::FutureTasks(_) => {
+                params.show_future = !params.show_future;
+                continue;
+            },
+            Entry::HiddenTasks(_) => {
+                params.show_hidden = !params.show_hidden;
+                continue;
+            },
+            Entry::GroupByProject(_) => {
+                params.group_by_project = !params.group_by_project;
+                continue;
+            },
+            Entry::CompletedTasks(_) => {
+                params.show_completed(!params.views.show_completed);
+                params.save_views();
+                continue;
+            },
+            _ => return Transition::Quit
+        };
+        return Transition::Open(screen);
+    }
+}
+
+/// Show the tasks needing attention today, the most urgent first, see `Task::is_for_today`
+/// 
+/// `~ all tasks` opens the main menu
+fn show_today_menu(rofi_config : &RofiParams, params : &mut Params) -> Transition {
+    loop {
+        params.refresh();
+        let notice = params.notice.take();
+        let today = Local::now().date_naive();
+        let mut tasks = params.list().iter()
+                            .filter(|(id, t)| params.is_shown(t) && t.is_for_today(today) && (params.show_blocked || !params.list().is_blocked(*id)))
+                            .collect::<Vec<_>>();
+        tasks.sort_by(|(_, a), (_, b)| a.comp_pinned_first(b, |a, b| a.comp_multi(b, &[SortTaskBy::Urgency])));
+        let width = tasks.len().to_string().len();
+        let mut choices = tasks.iter().enumerate().map(|(i, (_, t))| params.numbered_line(i + 1, width, params.task_line(t))).collect::<Vec<_>>();
+        let numbered = tasks.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        if tasks.is_empty() {
+            choices.push(String::from("Nothing due today"));
         }
+        choices.extend(vec![String::from("~ all tasks"), String::from("* exit")]);
+        let count = tasks.len();
+        let mut prompt = format!("Today — {} task{}", count, if count == 1 { "" } else { "s" });
+        if params.list().read_only {
+            prompt.push_str(" (read-only)");
+        }
+        let first_entry = choices.len() - 2;
+        let mut rofi = Rofi::from(rofi_config).prompt(&prompt).select_range(first_entry, first_entry + 1).markup_rows();
+        let row_tasks = choices.iter().enumerate().map(|(i, _)| numbered.get(i).copied()).collect::<Vec<_>>();
+        if let Some(row) = params.selected_row("today", &row_tasks) {
+            rofi = rofi.selected(row as u32);
+        }
+        let message = notice.into_iter()
+                            .chain((params.key_help && !params.list().read_only && !params.keys.is_empty()).then(|| keys::help(&params.keys)))
+                            .collect::<Vec<_>>();
+        if !message.is_empty() {
+            rofi = rofi.msg(message.join("\n"));
+        }
+        let selected = match params.select_row(rofi, choices, &numbered, |id| tasks.iter().position(|(i, _)| *i == id)) {
+            RowChoice::Row(i) => i,
+            RowChoice::Key(action, i) => {
+                params.remember_row("today", i, &row_tasks);
+                if let Some(id) = row_tasks[i] {
+                    run_key_action(rofi_config, params, action, id);
+                }
+                continue;
+            },
+            RowChoice::Unknown => continue,
+            RowChoice::Closed => return Transition::Back,
+            RowChoice::Failed => return Transition::Quit
+        };
+        params.remember_row("today", selected, &row_tasks);
+        if selected < count {
+            return Transition::Open(Screen::Task(numbered[selected]));
+        } else if selected == first_entry {
+            return Transition::Open(Screen::Main);
+        } else if selected == first_entry + 1 {
+            return Transition::Quit;
+        }
+    }
+}
+
+/// Show only the most urgent task, see `Params::focus_task`, until the focus menu is closed
+/// 
+/// `✔ mark as done` completes the task and `→ skip` leaves it for the session, both show the next task
+fn show_focus_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    loop {
+        params.refresh();
+        let id = match params.focus_task() {
+            Some(id) => id,
+            None => {
+                let mut message = String::from("No task left to focus on");
+                if !params.focus_skipped.is_empty() {
+                    message.push_str(&format!(", {} skipped", params.focus_skipped.len()));
+                }
+                Rofi::from(rofi_config).prompt("Focus").msg(message).run(vec![String::from("* exit focus")]).unwrap();
+                return MenuStatus::Back;
+            }
+        };
+        let task = params.copy(id);
+        let menu = entry::shown(vec![Entry::MarkAsDone, Entry::Skip, Entry::ExitFocus], params.list().read_only);
+        match select_entry(Rofi::from(rofi_config).prompt("Focus").msg(params.recap(&task)).select_range(0, menu.len() - 1), &menu) {
+            Ok(Some(Entry::MarkAsDone)) => {
+                let op = params.complete_task(id);
+                params.record(op);
+            },
+            Ok(Some(Entry::Skip)) => {
+                params.focus_skipped.insert(id);
+            },
+            Ok(_) => return MenuStatus::Back,
+            Err(e) => return menu_error(e)
+        }
+    }
+}
+
+/// Go through the stale tasks, see `Params::stale_tasks`, until none is left or the review is closed
+/// 
+/// A task can be completed, postponed, deleted or kept, the postponed and kept tasks are not shown again in the session
+fn show_stale_review_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+    loop {
+        params.refresh();
+        let stale = params.stale_tasks();
+        let id = match stale.first() {
+            Some(id) => *id,
+            None => {
+                let mut message = String::from("No stale task left to review");
+                if !params.stale_reviewed.is_empty() {
+                    message.push_str(&format!(", {} kept", params.stale_reviewed.len()));
+                }
+                Rofi::from(rofi_config).prompt("Review").msg(message).run(vec![String::from("* exit review")]).unwrap();
+                return MenuStatus::Back;
+            }
+        };
+        let task = params.copy(id);
+        let age = task.age_days(Local::now().date_naive()).unwrap_or_default();
+        let message = format!("{} days old, {} stale task{} left\n{}", age, stale.len(), if stale.len() == 1 { "" } else { "s" }, params.recap(&task));
+        let menu = entry::shown(vec![Entry::MarkAsDone, Entry::Postpone, Entry::Remove, Entry::Keep, Entry::ExitReview], params.list().read_only);
+        match select_entry(Rofi::from(rofi_config).prompt("Review").msg(message).select_range(0, menu.len() - 1), &menu) {
+            Ok(Some(Entry::MarkAsDone)) => {
+                let op = params.complete_task(id);
+                params.record(op);
+            },
+            Ok(Some(Entry::Postpone)) => {
+                if let Some(offset) = postpone_selector(rofi_config) {
+                    let op = params.postpone_task(id, offset, Local::now().date_naive());
+                    params.record(op);
+                    params.stale_reviewed.insert(id);
+                }
+            },
+            Ok(Some(Entry::Remove)) => {
+                if confirm(rofi_config, &format!("Delete '{}' ?", task.get_content())) {
+                    if let Err(e) = params.delete_task(id) {
+                        show_message(rofi_config, "Remove", e);
+                    }
+                }
+            },
+            Ok(Some(Entry::Keep)) => {
+                params.stale_reviewed.insert(id);
+            },
+            Ok(_) => return MenuStatus::Back,
+            Err(e) => return menu_error(e)
+        }
+    }
+}
+
+/// Show the menus from the last one of a stack until they are all closed
+/// 
+/// Arguments:
+/// 
+/// * `stack` - the open menus, the first shown menu last
+fn run_menus(rofi_config : &RofiParams, params : &mut Params, mut stack : Vec<Screen>) {
+    while let Some(screen) = stack.last() {
+        let transition = show_screen(rofi_config, params, screen);
+        stack = navigate(stack, transition);
+    }
+}
+
+/// Show a menu of the stack and return where to go next
+fn show_screen(rofi_config : &RofiParams, params : &mut Params, screen : &Screen) -> Transition {
+    let status = match screen {
+        Screen::Today => return show_today_menu(rofi_config, params),
+        Screen::Main => return show_main_menu(rofi_config, params),
+        Screen::Task(id) => open_task_menu(rofi_config, params, *id),
+        Screen::Add => show_add_task(rofi_config, params),
+        Screen::Paste => show_paste_task(rofi_config, params, clipboard::paste),
+        Screen::Focus => show_focus_menu(rofi_config, params),
+        Screen::Search => show_search_menu(rofi_config, params),
+        Screen::Week => show_week_menu(rofi_config, params),
+        Screen::Bulk => show_bulk_menu(rofi_config, params),
+        Screen::Done => show_old_menu(rofi_config, params),
+        Screen::Recent => show_recently_completed_menu(rofi_config, params),
+        Screen::Stale => show_stale_review_menu(rofi_config, params),
+        Screen::Trash => show_trash_menu(rofi_config, params),
+        Screen::Tags(tag_type) => show_tag_list(rofi_config, params, tag_type.clone()),
+        Screen::FilterByTag => show_filter_by_tag_menu(rofi_config, params),
+        Screen::Filter => show_filter_menu(rofi_config, params),
+        Screen::Sort => show_sort_menu(rofi_config, params),
+        Screen::Views => show_views_menu(rofi_config, params),
+        Screen::Statistics => show_statistics(rofi_config, params),
+        Screen::SwitchList => show_switch_list_menu(rofi_config, params),
+        Screen::Backups => show_backups_menu(rofi_config, params),
+        Screen::SortFile => show_sort_file_menu(rofi_config, params),
+        Screen::Project(project) => show_project_menu(rofi_config, params, project.clone())
     };
+    // The menus opened from a list go back to it, after an action as after Escape
+    match status {
+        MenuStatus::Exit => Transition::Quit,
+        MenuStatus::MainMenu | MenuStatus::Back => Transition::Back
+    }
+}
 
-    let mut parameters = Params::new(sort, todos);
+/// A row of the week menu
+enum WeekRow {
+    /// The header of a day, or of the overdue tasks and tasks without date with `None`
+    Header(Option<NaiveDate>),
+    /// A task of the current list
+    Task(TaskId)
+}
 
+/// Show the tasks due in the next seven days under a header for each day,
+/// with the overdue tasks on top and the tasks without due date at the bottom
+/// 
+/// Selecting a day adds a task due this day
+fn show_week_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
     loop {
-        if show_main_menu(&rofi_config, &mut parameters) == MenuStatus::EXIT { break }
+        let today = Local::now().date_naive();
+        let tasks = params.list().tasks().into_iter()
+                        .map(|(_, t)| t)
+                        .filter(|t| !t.completion && params.is_shown(t))
+                        .collect::<Vec<_>>();
+        let mut rows = vec![];
+        let mut choices = vec![String::from("← back")];
+        let mut section = |header : Option<NaiveDate>, title : String, tasks : Vec<&&Task>| {
+            choices.push(format!("<b>{}</b>", title));
+            rows.push(WeekRow::Header(header));
+            for task in tasks {
+                choices.push(format!("    {}", params.task_line(task)));
+                rows.push(WeekRow::Task(task.id().expect("the tasks of a list have an id")));
+            }
+        };
+        section(None, String::from("── Overdue ──"), tasks.iter().filter(|t| t.is_overdue(today)).collect());
+        for (date, due) in agenda::group_by_due(&tasks, today, 7) {
+            section(Some(date), date.format("%a %Y-%m-%d").to_string(), due);
+        }
+        section(None, String::from("── No date ──"), tasks.iter().filter(|t| t.get_due().is_none()).collect());
+        let row_tasks = std::iter::once(None).chain(rows.iter().map(|row| match row {
+            WeekRow::Task(id) => Some(*id),
+            WeekRow::Header(_) => None
+        })).collect::<Vec<_>>();
+        let mut rofi = Rofi::from(rofi_config).prompt("This week").select_range(0,0).markup_rows();
+        if let Some(row) = params.selected_row("week", &row_tasks) {
+            rofi = rofi.selected(row as u32);
+        }
+        let selected = match rofi.run_index(choices).unwrap() {
+            None | Some(0) => return MenuStatus::MainMenu,
+            Some(i) => i - 1
+        };
+        params.remember_row("week", selected + 1, &row_tasks);
+        match rows.into_iter().nth(selected).unwrap() {
+            WeekRow::Task(id) => {
+                if show_task_menu(rofi_config, params, id) == MenuStatus::Exit {
+                    return MenuStatus::Exit;
+                }
+            },
+            WeekRow::Header(Some(date)) => {
+                let menu = vec![format!("+ add task due {}", date.format("%a %Y-%m-%d")), String::from("← back")];
+                if params.list().read_only || Rofi::from(rofi_config).prompt("Day").run_index(menu).unwrap() != Some(0) {
+                    continue;
+                }
+                let input = Rofi::from(rofi_config).prompt("Task").placeholder("").text_only().run(vec![]).unwrap();
+                if input.is_empty() {
+                    continue;
+                }
+                let mut task = Task::from_quick_add(&input, today).unwrap_or_else(|_| Task::new(input));
+                task.set_due(Some(date));
+                add_to_list(rofi_config, params, task);
+            },
+            // The overdue and no date headers can't be selected
+            WeekRow::Header(None) => ()
+        }
     }
+}
+
+/// Save the tasks, merging them with the task file if another program modified it since it was loaded
+/// 
+/// The lines changed on both sides are resolved by asking which version to keep
+fn save_merging(rofi_config : &RofiParams, list : &TaskList) -> Result<bool,String> {
+    list.save_merging(|conflict| conflict_prompt(rofi_config, conflict)).map(|_| true)
+}
 
-    match save_config(&config, &mut parameters.todos) {
-        Ok(_) => (),
-        Err(s) => println!("{}", s)
+/// Back up and save a task list, then load it again so the next save is merged with the saved file
+/// 
+/// Arguments:
+/// 
+/// * `backups` - the number of backups of the task file to keep
+/// * `force` - overwrite the task file even if another program modified it
+fn save_list(rofi_config : &RofiParams, list : &mut TaskList, backups : usize, force : bool, autocommit : bool) -> Result<(),String> {
+    // Loading the file again would lose the changes which were not written
+    if storage::dry_run() {
+        return Ok(());
+    }
+    // Before the backup, which would write next to the task file
+    list.check_writable()?;
+    if let Err(e) = storage::rotate_backups(&list.path, backups) {
+        eprintln!("Could not back up {} : {}", list.path.display(), e);
+    }
+    if force {
+        list.save()?;
+    } else {
+        save_merging(rofi_config, list)?;
+    }
+    if autocommit {
+        commit_saved(&list.path, undo::summary(list.history.done()));
+    }
+    list.reload()
+}
+
+/// Commit a saved task file in the git repository of its directory, a failure is only a warning
+///
+/// Arguments:
+///
+/// * `path` - the task file
+/// * `summary` - the description of the changes, from the operations which can be undone
+fn commit_saved(path : &std::path::Path, summary : Option<String>) {
+    let summary = summary.unwrap_or_else(|| String::from("update the tasks"));
+    if let Err(e) = git::commit(path, &summary) {
+        eprintln!("Could not commit {} : {}", path.display(), e);
+    }
+}
+
+/// Return the text printed for a task by the commands: its line number and its todo.txt line
+fn numbered_line(task : &Task) -> String {
+    format!("{} {}", task.get_source_line().map(|l| l + 1).unwrap_or(0), task)
+}
+
+/// Find the task on a line of the current task list
+/// 
+/// Arguments:
+/// 
+/// * `line` - the line number of the task, from 1
+fn task_on_line(params : &Params, line : usize) -> Result<TaskId, String> {
+    params.list().iter()
+          .find(|(_, t)| line > 0 && t.get_source_line() == Some(line - 1))
+          .map(|(id, _)| id)
+          .ok_or_else(|| format!("There is no task on line {}", line))
+}
+
+/// Run a command on the current task list and return what to print
+/// 
+/// The list is saved if the command changed it.
+/// The tasks keep their line number whatever the filter and the sort order, as long as the file is not modified
+/// 
+/// Arguments:
+/// 
+/// * `command` - the command to run
+/// * `confirm` - ask a question, true if it is answered yes
+fn run_command(params : &mut Params, command : &Subcommand, confirm : impl FnOnce(&str) -> bool) -> Result<String, String> {
+    let today = Local::now().date_naive();
+    if !matches!(command, Subcommand::List { .. } | Subcommand::Status { .. } | Subcommand::Report | Subcommand::Export { .. } | Subcommand::Log { .. }) {
+        params.list().check_writable()?;
+    }
+    let output = match command {
+        Subcommand::Add { task } => {
+            let task = if *task == ["-"] {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text).map_err(|e| format!("Could not read the standard input : {}", e))?;
+                pasted_task(&text).ok_or_else(|| String::from("The standard input is empty, no task added"))?
+            } else {
+                task.join(" ")
+            };
+            let task = Task::from_quick_add(&task, today).map_err(|e| e.to_string())?;
+            if task.get_content().trim().is_empty() {
+                return Err(TaskParseError::EmptyContent.to_string());
+            }
+            let id = params.add_task(task);
+            // The operations describe the changes committed with `--git-autocommit`
+            params.session.record(Op::Add(params.session.copy(id)));
+            numbered_line(&params.copy(id))
+        },
+        Subcommand::List { filter, sort, all } => {
+            let mut filter = filter.as_deref().map(TaskFilter::from_query).unwrap_or_default();
+            if !all && filter.completed.is_none() {
+                filter.completed = Some(false);
+            }
+            let mut tasks = params.list().iter().map(|(_, t)| t).filter(|t| filter.matches(t)).collect::<Vec<_>>();
+            if !sort.is_empty() {
+                let sort = with_tie_break(sort.iter().filter_map(|key| SortTaskBy::from_name(key)).collect());
+                let today = Local::now().date_naive();
+                tasks.sort_by(|a, b| a.comp_completed_last(b, |a, b| a.comp_pinned_first(b, |a, b| a.comp_multi_directed(b, &sort, SortDirection::Ascending, today))));
+            }
+            return Ok(tasks.iter().map(|t| numbered_line(t)).collect::<Vec<_>>().join("\n"));
+        },
+        Subcommand::Status { format } => {
+            let status = if params.show_blocked {
+                status::Status::of(params.list().todos.get_main_index().iter().map(|t| t.as_ref()), today)
+            } else {
+                status::Status::of_list(params.list(), today)
+            };
+            return Ok(if format == "json" { status.to_json() } else { status.to_plain() });
+        },
+        Subcommand::Report => {
+            return Ok(statistics(params, today)?.to_report());
+        },
+        Subcommand::Export { format, columns, output } => {
+            if !columns.is_empty() && format != "csv" {
+                return Err(format!("The columns are only used by the csv format, not {}", format));
+            }
+            let export = match format.as_str() {
+                "ics" => ical::to_ics(params.list().iter().map(|(_, t)| t), today),
+                "md" => markdown::to_markdown(&params.list().iter().map(|(_, t)| t).collect::<Vec<_>>()),
+                "csv" => csv::to_csv(params.list().iter().map(|(_, t)| t), &csv::Column::parse_list(columns)?),
+                format => return Err(format!("Unknown export format {}", format))
+            };
+            return match output {
+                Some(path) => storage::save_atomic(path, &export, None).map(|_| String::new())
+                                      .map_err(|e| format!("Could not write {} : {}", path.display(), e)),
+                // The last line break is replaced by the one of `println`
+                None => Ok(export.trim_end_matches(&['\r', '\n'][..]).to_string())
+            };
+        },
+        Subcommand::Import { format, file } => {
+            let text = fs::read_to_string(file).map_err(|e| format!("Could not read {} : {}", file.display(), e))?;
+            // The lines printed after the added tasks
+            let mut report = Vec::new();
+            let tasks = match format.as_str() {
+                "md" => markdown::from_markdown(&text, today),
+                "taskwarrior" => {
+                    let import = taskwarrior::from_taskwarrior(&text, &Local)?;
+                    let (tasks, duplicates) = taskwarrior::skip_duplicates(import.tasks, params.list().iter().map(|(_, t)| t));
+                    if duplicates > 0 {
+                        report.push(format!("Skipped {} tasks already in the list", duplicates));
+                    }
+                    if import.ignored > 0 {
+                        report.push(format!("Ignored {} deleted or recurring tasks", import.ignored));
+                    }
+                    if !import.errors.is_empty() {
+                        report.push(format!("Could not read {} entries :", import.errors.len()));
+                        report.extend(import.errors.into_iter().map(|e| format!("  {}", e)));
+                    }
+                    tasks
+                },
+                format => return Err(format!("Unknown import format {}", format))
+            };
+            if tasks.is_empty() {
+                if report.is_empty() {
+                    return Err(format!("There is no task in {}", file.display()));
+                }
+                return Ok(report.join("\n"));
+            }
+            if !confirm(&format!("Add the {} tasks found in {} ?", tasks.len(), file.display())) {
+                return Ok(report.join("\n"));
+            }
+            let ids = tasks.into_iter().map(|task| params.add_task(task)).collect::<Vec<_>>();
+            params.session.record(Op::Batch(ids.iter().map(|id| Op::Add(params.session.copy(*id))).collect()));
+            let mut lines = ids.into_iter().map(|id| numbered_line(&params.copy(id))).collect::<Vec<_>>();
+            lines.extend(report);
+            lines.join("\n")
+        },
+        Subcommand::Done { line } => {
+            let id = task_on_line(params, *line)?;
+            if params.copy(id).completion {
+                return Err(format!("The task on line {} is already done", line));
+            }
+            let unblocked = params.list().unblocked_by(id);
+            let op = params.complete_task(id);
+            params.record(op);
+            let mut lines = vec![numbered_line(&params.copy(id))];
+            lines.extend(unblocked.into_iter().map(|id| format!("Unblocked : {}", numbered_line(&params.copy(id)))));
+            lines.join("\n")
+        },
+        Subcommand::Rm { line } => {
+            let id = task_on_line(params, *line)?;
+            let removed = numbered_line(&params.copy(id));
+            params.delete_task(id)?;
+            removed
+        },
+        Subcommand::Log { since } => {
+            let log = params.list().history.log().ok_or_else(|| String::from("There is no log, set `--audit-log` or `audit_log` in the configuration"))?;
+            return Ok(log.read(*since)?.join("\n"));
+        }
     };
+    save_unattended(params)?;
+    Ok(output)
+}
+
+/// Compute the statistics of the tasks of the current list and of its archive
+fn statistics(params : &Params, today : NaiveDate) -> Result<stats::Stats, String> {
+    let archive = params.list().archive()?;
+    let mut tasks = params.list().iter().map(|(_, t)| t.clone()).collect::<Vec<_>>();
+    tasks.extend(archive.tasks().0.into_iter().map(|(_, t)| t));
+    Ok(stats::Stats::of(&tasks, today))
+}
+
+/// Show a summary of the statistics of the tasks, see `Subcommand::Report` for the full report
+fn show_statistics(rofi_config : &RofiParams, params : &Params) -> MenuStatus {
+    let message = match statistics(params, Local::now().date_naive()) {
+        Ok(stats) => stats.to_summary(),
+        Err(e) => format!("Could not read the archive : {}", e)
+    };
+    Rofi::from(rofi_config).prompt("Statistics").msg(message).run_index(vec![String::from("← back")]).unwrap();
+    MenuStatus::Back
+}
+
+/// Ask a question in the terminal, true if it is answered `y` or `yes`
+fn confirm_in_terminal(question : &str) -> bool {
+    eprint!("{} [y/N] ", question);
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Back up and save the current task list without opening rofi, the conflicting tasks keep their version of RofiTodo
+fn save_unattended(params : &Params) -> Result<(), String> {
+    let list = params.list();
+    list.check_writable()?;
+    if let Err(e) = storage::rotate_backups(&list.path, params.backups) {
+        eprintln!("Could not back up {} : {}", list.path.display(), e);
+    }
+    // Nobody can be asked which version of a conflicting task to keep
+    list.save_merging(|_| Resolution::KeepMine)?;
+    if params.git_autocommit {
+        commit_saved(&list.path, undo::summary(list.history.done()));
+    }
+    Ok(())
+}
+
+/// Return the menu of a state of the rofi script mode
+/// 
+/// Arguments:
+/// 
+/// * `state` - the menu to show
+/// * `message` - a message to show under the prompt
+fn script_screen(params : &Params, state : &ScriptState, message : Option<String>) -> Result<script::Screen, String> {
+    let back = (String::from("← back"), String::from("back"));
+    let screen = match state {
+        ScriptState::List => {
+            let mut rows = vec![(String::from("+ add"), String::from("add"))];
+            // Each step of the script mode is a new process, the rows name the tasks by line number rather than by id
+            for (_, task) in params.list().tasks() {
+                if params.filter.matches(task) && params.is_shown(task) {
+                    let line = task.get_source_line().map(|l| l + 1).unwrap_or(0);
+                    rows.push((params.task_line(task), format!("task:{}", line)));
+                }
+            }
+            script::Screen { prompt : String::from("Todo"), message, rows }
+        },
+        ScriptState::Task(line) => {
+            let task = params.copy(task_on_line(params, *line)?);
+            let rows = vec![(String::from("✔ mark as done"), format!("done:{}", line)), (String::from("! remove"), format!("remove:{}", line)), back];
+            let recap = params.recap(&task);
+            script::Screen { prompt : String::from("Edit"), message : Some(message.map(|m| format!("{}\n{}", m, recap)).unwrap_or(recap)), rows }
+        },
+        ScriptState::ConfirmDelete(line) => {
+            let task = params.copy(task_on_line(params, *line)?);
+            let rows = vec![(String::from("no"), format!("task:{}", line)), (String::from("yes"), format!("delete:{}", line))];
+            script::Screen { prompt : String::from("Confirm"), message : Some(format!("Delete '{}' ?", task.get_content())), rows }
+        },
+        ScriptState::Add => {
+            let message = message.unwrap_or_else(|| String::from("Type the task in the todo.txt format"));
+            script::Screen { prompt : String::from("Task"), message : Some(message), rows : vec![back] }
+        }
+    };
+    Ok(screen)
+}
+
+/// Handle an event of the rofi script mode on the current task list, the list is saved if it changed
+/// 
+/// Return the next state and its menu, a task which is not in the list anymore goes back to the list of the tasks
+/// 
+/// Arguments:
+/// 
+/// * `state` - the menu shown when the event happened
+/// * `event` - what was selected or typed
+fn script_step(params : &mut Params, state : ScriptState, event : ScriptEvent) -> (ScriptState, script::Screen) {
+    let line = |info : &str, action : &str| info.strip_prefix(action).and_then(|l| l.parse::<usize>().ok());
+    let result = match &event {
+        ScriptEvent::Start => Ok((ScriptState::List, None)),
+        ScriptEvent::Select(info) if info == "add" => Ok((ScriptState::Add, None)),
+        ScriptEvent::Select(info) => {
+            if let Some(l) = line(info, "task:") {
+                Ok((ScriptState::Task(l), None))
+            } else if let Some(l) = line(info, "remove:") {
+                Ok((ScriptState::ConfirmDelete(l), None))
+            } else if let Some(l) = line(info, "done:") {
+                task_on_line(params, l).and_then(|id| {
+                    let content = params.copy(id).get_content().clone();
+                    let op = params.complete_task(id);
+                    params.record(op);
+                    save_unattended(params).map(|_| (ScriptState::List, Some(format!("✔ {}", content))))
+                })
+            } else if let Some(l) = line(info, "delete:") {
+                task_on_line(params, l).and_then(|id| {
+                    let content = params.copy(id).get_content().clone();
+                    params.delete_task(id)?;
+                    save_unattended(params).map(|_| (ScriptState::List, Some(format!("Deleted '{}'", content))))
+                })
+            } else {
+                Ok((ScriptState::List, None))
+            }
+        },
+        ScriptEvent::Input(text) if state == ScriptState::List || state == ScriptState::Add => {
+            match Task::from_quick_add(text, Local::now().date_naive()) {
+                Ok(task) if !task.get_content().trim().is_empty() => {
+                    let added = task.get_content().clone();
+                    params.add_task(task);
+                    save_unattended(params).map(|_| (ScriptState::List, Some(format!("Added '{}'", added))))
+                },
+                Ok(_) => Ok((state.clone(), Some(TaskParseError::EmptyContent.to_string()))),
+                Err(e) => Ok((ScriptState::Add, Some(format!("The task can't be added: {}", e))))
+            }
+        },
+        // A typed text is not an action of the other menus
+        ScriptEvent::Input(_) => Ok((state.clone(), None))
+    };
+    let (next, message) = result.unwrap_or_else(|e| (ScriptState::List, Some(e)));
+    match script_screen(params, &next, message) {
+        Ok(screen) => (next, screen),
+        Err(e) => (ScriptState::List, script_screen(params, &ScriptState::List, Some(e)).expect("the list of the tasks can always be shown"))
+    }
+}
+
+/// Ask which version of a task changed both in RofiTodo and in the task file to keep
+fn conflict_prompt(rofi_config : &RofiParams, conflict : &Conflict) -> Resolution {
+    let removed = String::from("(removed)");
+    let message = format!("This task was changed in the file since it was loaded\nloaded : {}\nmine : {}\ntheirs : {}",
+                            conflict.base, conflict.mine.as_ref().unwrap_or(&removed), conflict.theirs.as_ref().unwrap_or(&removed));
+    let menu = vec![String::from("keep mine"), String::from("keep theirs")];
+    match Rofi::from(rofi_config).prompt("Conflict").msg(message).select_range(0,menu.len()-1).run(menu).unwrap().as_ref() {
+        "keep theirs" => Resolution::KeepTheirs,
+        _ => Resolution::KeepMine
+    }
+}
+
+struct Params {
+    /// The task lists, the current one is shown in the menus
+    session : Session,
+    /// Show the tasks whose threshold date is not reached yet
+    show_future : bool,
+    /// Show the tasks hidden with a `h:1` tag
+    show_hidden : bool,
+    /// Show the tasks blocked by a dependency in the today menu and the status
+    show_blocked : bool,
+    /// The number of columns after which the lines of the tasks are cut, 0 to never cut them
+    max_line_width : usize,
+    /// The template of the lines of the tasks
+    row_format : Template,
+    /// Prefix the lines of the tasks with their number in the menu
+    numbered_rows : bool,
+    /// The tasks of the last numbered menu, in the order of their numbers
+    row_numbers : Vec<TaskId>,
+    /// The last selected row of each menu of tasks, selected again when the menu is shown after an action
+    selected_rows : HashMap<&'static str, SelectedRow>,
+    /// The tasks skipped in the focus menu, not shown again until RofiTodo exits
+    focus_skipped : HashSet<TaskId>,
+    /// The number of days after which a task to do is stale, 0 to never mark the tasks
+    stale_days : u32,
+    /// The stale tasks kept or postponed in the review, not shown again until RofiTodo exits
+    stale_reviewed : HashSet<TaskId>,
+    /// The custom keys running an action on the highlighted task of the main and today menus
+    keys : Vec<(KeyAction, String)>,
+    /// Show the custom keys in the message of the main and today menus
+    key_help : bool,
+    /// Show the numbers of tasks, the filter and the sort order of the main menu in its message
+    summary : bool,
+    /// The language of the recap of the tasks and the format of its dates
+    locale : Rc<Locale>,
+    /// Show the tasks of the main menu under a header for each project
+    group_by_project : bool,
+    /// The colors used to show the tasks
+    colors : MarkupColors,
+    /// How the due dates are shown
+    dates : DateStyle,
+    /// The filter of the tasks of the main menu
+    filter : TaskFilter,
+    /// The saved views
+    views : ViewStore,
+    /// The path of the file of the saved views
+    views_path : std::path::PathBuf,
+    /// The view used when no saved view is selected
+    default_view : View,
+    /// The number of backups of the task files to keep
+    backups : usize,
+    /// Overwrite the task files even if another program modified them
+    force_save : bool,
+    /// The shell commands of the task menu
+    actions : Vec<Action>,
+    /// The templates of the new tasks, with their name
+    templates : Vec<(String, TaskTemplate)>,
+    /// Commit the task files in their git repository after each save
+    git_autocommit : bool
+}
+
+impl Deref for Params {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl DerefMut for Params {
+    fn deref_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+}
+
+impl Params {
+    fn new(sort : Vec<SortTaskBy>, direction : SortDirection, lists : Vec<TaskList>) -> Self {
+        let default_view = View { name : String::from("default"), filter : TaskFilter::todo(), sort : sort.clone(), direction };
+        Params {
+            session : Session::new(sort, direction, lists),
+            show_future : false,
+            show_hidden : false,
+            show_blocked : false,
+            max_line_width : 0,
+            row_format : Template::default(),
+            numbered_rows : false,
+            row_numbers : vec![],
+            selected_rows : HashMap::new(),
+            focus_skipped : HashSet::new(),
+            stale_days : 30,
+            stale_reviewed : HashSet::new(),
+            keys : vec![],
+            key_help : false,
+            summary : true,
+            locale : Rc::new(Locale::default()),
+            group_by_project : false,
+            colors : MarkupColors::default(),
+            dates : DateStyle::Absolute,
+            filter : TaskFilter::todo(),
+            views : ViewStore::default(),
+            views_path : std::path::PathBuf::new(),
+            default_view,
+            backups : 0,
+            force_save : false,
+            actions : vec![],
+            templates : vec![],
+            git_autocommit : false
+        }
+    }
+
+    /// Use the filter and sort order of a view in the main menu
+    fn apply_view(&mut self, view : &View) {
+        self.filter = view.filter.clone();
+        self.set_sort(view.sort.clone(), view.direction);
+        self.show_completed(self.views.show_completed);
+    }
+
+    /// Show or hide the completed tasks after the tasks to do in the main menu, a filter of the completed tasks only is kept
+    /// 
+    /// Arguments:
+    /// 
+    /// * `show` - true to show the completed tasks
+    fn show_completed(&mut self, show : bool) {
+        self.views.show_completed = show;
+        if self.filter.completed != Some(true) {
+            self.filter.completed = if show { None } else { Some(false) };
+        }
+    }
+
+    /// Return a view with the current filter and sort order of the main menu
+    fn current_view(&self, name : String) -> View {
+        View { name, filter : self.filter.clone(), sort : self.sort.clone(), direction : self.direction }
+    }
+
+    /// Show another task list, saving the current one first if it was modified
+    /// 
+    /// Arguments:
+    /// 
+    /// * `index` - the index of the list to show
+    fn switch_list(&mut self, rofi_config : &RofiParams, index : usize) -> Result<(), String> {
+        if self.list().modified {
+            let (backups, force, autocommit) = (self.backups, self.force_save, self.git_autocommit);
+            save_list(rofi_config, self.list_mut(), backups, force, autocommit)?;
+        }
+        self.current = index;
+        self.session.set_sort(self.session.sort.clone(), self.session.direction);
+        Ok(())
+    }
+
+    /// Rewrite the task file of the current list in the sort order of the main menu and load it again
+    fn sort_file(&mut self) -> Result<(), String> {
+        let backups = self.backups;
+        self.list_mut().sort_file(backups)?;
+        if self.git_autocommit {
+            commit_saved(&self.list().path, Some(String::from("sort the task file")));
+        }
+        self.session.set_sort(self.session.sort.clone(), self.session.direction);
+        Ok(())
+    }
+
+    /// Save the views to their file
+    fn save_views(&self) {
+        if let Err(e) = self.views.save(&self.views_path) {
+            eprintln!("{}", e);
+        }
+    }
+
+    /// The line without markup showing a task in the menus of the completed, archived and deleted tasks
+    fn plain_line(&self, task : &Task) -> String {
+        task.display_line_with(&self.row_format, Local::now().date_naive(), DateStyle::Absolute)
+    }
+
+    /// The Pango markup line showing a task in the lists, with a marker for pinned and overdue tasks and tasks due today,
+    /// the tasks blocked by a dependency are dimmed and the end of the long lines is cut
+    fn task_line(&self, task : &Task) -> String {
+        let now = Local::now().naive_local();
+        let today = now.date();
+        let mut line = task.to_markup_with(&self.row_format, today, &self.colors, self.dates);
+        if task.is_pinned() {
+            line = format!("★ {}", line);
+        }
+        let line = if !task.completion && task.id().is_some_and(|id| self.list().is_blocked(id)) {
+            markup::dimmed(&format!("🔒 {}", line))
+        } else if task.completion {
+            format!("✔ {}", line)
+        } else if task.is_overdue_at(now) {
+            format!("⚠ {}", line)
+        } else if task.is_due_today(today) {
+            format!("⏰ {}", line)
+        } else if task.is_stale(today, self.stale_days) {
+            format!("⌛ {}", line)
+        } else {
+            line
+        };
+        markup::truncate(&line, self.max_line_width)
+    }
+
+    /// The line of a task with its number before it when the rows are numbered
+    /// 
+    /// Arguments:
+    /// 
+    /// * `number` - the number of the row, from 1
+    /// * `width` - the number of digits of the greatest number, the numbers are aligned on the right
+    /// * `line` - the line of the task
+    fn numbered_line(&self, number : usize, width : usize, line : String) -> String {
+        if self.numbered_rows {
+            format!("{:>width$}{}{}", number, ROW_NUMBER_SEPARATOR, line, width = width)
+        } else {
+            line
+        }
+    }
+
+    /// The task of the last numbered menu whose number is typed, `None` if the text isn't the number of a task
+    fn numbered_task(&self, text : &str) -> Option<TaskId> {
+        row_number(text, self.row_numbers.len()).map(|n| self.row_numbers[n - 1]).filter(|id| self.list().get(*id).is_some())
+    }
+
+    /// The row to select when a menu is shown again: the row of the task selected last time, the next task when it is
+    /// no longer in the menu, or the same entry of the menu
+    /// 
+    /// Arguments:
+    /// 
+    /// * `menu` - the name of the menu
+    /// * `rows` - the task of each row of the menu, `None` for the entries and the headers
+    fn selected_row(&self, menu : &str, rows : &[Option<TaskId>]) -> Option<usize> {
+        match *self.selected_rows.get(menu)? {
+            SelectedRow::Entry(index) => Some(index).filter(|i| *i < rows.len()),
+            SelectedRow::Task(id, position) => {
+                let tasks = (0..rows.len()).filter(|i| rows[*i].is_some()).collect::<Vec<_>>();
+                rows.iter().position(|row| *row == Some(id)).or_else(|| tasks.get(position).or_else(|| tasks.last()).copied())
+            }
+        }
+    }
+
+    /// Remember the selected row of a menu, see `Params::selected_row`
+    /// 
+    /// Arguments:
+    /// 
+    /// * `menu` - the name of the menu
+    /// * `index` - the index of the selected row
+    /// * `rows` - the task of each row of the menu, `None` for the entries and the headers
+    fn remember_row(&mut self, menu : &'static str, index : usize, rows : &[Option<TaskId>]) {
+        let row = match rows.get(index).copied().flatten() {
+            Some(id) => SelectedRow::Task(id, rows[..index].iter().filter(|row| row.is_some()).count()),
+            None => SelectedRow::Entry(index)
+        };
+        self.selected_rows.insert(menu, row);
+    }
+
+    /// The task of the focus menu: the most urgent task to do which is shown and not blocked, unless it was skipped
+    /// 
+    /// The pinned tasks come first like in the today menu, the tasks as urgent are taken in the order of their contents
+    /// then of their lines
+    fn focus_task(&self) -> Option<TaskId> {
+        self.list().iter()
+            .filter(|(id, t)| !t.completion && self.is_shown(t) && !self.list().is_blocked(*id) && !self.focus_skipped.contains(id))
+            .min_by(|(_, a), (_, b)| a.comp_pinned_first(b, |a, b| a.comp_multi(b, &[SortTaskBy::Urgency])))
+            .map(|(id, _)| id)
+    }
+
+    /// Return the stale tasks of the current list which were not reviewed in the session, the oldest first
+    /// 
+    /// The tasks created the same day are taken in the order of their contents
+    fn stale_tasks(&self) -> Vec<TaskId> {
+        let today = Local::now().date_naive();
+        let mut tasks = self.list().iter()
+                            .filter(|(id, t)| t.is_stale(today, self.stale_days) && self.is_shown(t) && !self.stale_reviewed.contains(id))
+                            .collect::<Vec<_>>();
+        tasks.sort_by(|(_, a), (_, b)| a.creation_date.cmp(&b.creation_date).then_with(|| a.get_content().cmp(b.get_content())));
+        tasks.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Show a menu of numbered tasks, a typed text which is not a row opens the task with this number
+    /// 
+    /// The custom keys of the actions choose the highlighted row, except in a read-only list
+    /// 
+    /// Arguments:
+    /// 
+    /// * `rofi` - the menu
+    /// * `choices` - the rows of the menu
+    /// * `numbers` - the tasks of the numbered rows, in the order of their numbers
+    /// * `row_of` - the index of the row of a task
+    fn select_row(&self, mut rofi : Rofi, choices : Vec<String>, numbers : &[TaskId], row_of : impl Fn(TaskId) -> Option<usize>) -> RowChoice {
+        let actions = if self.list().read_only { vec![] } else { self.keys.clone() };
+        rofi = rofi.custom_keys(&actions.iter().map(|(_, key)| key.clone()).collect::<Vec<_>>());
+        if !self.numbered_rows {
+            rofi = rofi.no_custom();
+        }
+        // The lines are compared without their numbers
+        let lines = choices.iter().map(|c| strip_row_number(c.trim_start()).to_string()).collect::<Vec<_>>();
+        let (key, choice) = match rofi.run_key(choices) {
+            Ok(choice) => choice,
+            Err(e) => {
+                eprintln!("{}", e);
+                return RowChoice::Failed;
+            }
+        };
+        let row = match choice {
+            None => return RowChoice::Closed,
+            Some(Choice::Index(i)) => Some(i),
+            Some(Choice::Text(text)) => {
+                let text = strip_row_number(text.trim());
+                lines.iter().position(|l| l == text).or_else(|| row_number(text, numbers.len()).and_then(|n| row_of(numbers[n - 1])))
+            }
+        };
+        match (key.and_then(|key| actions.get(key)), row) {
+            (Some((action, _)), Some(row)) => RowChoice::Key(*action, row),
+            (None, Some(row)) => RowChoice::Row(row),
+            (_, None) => RowChoice::Unknown
+        }
+    }
+
+    /// The complete description of a task, with the tasks it depends on
+    fn recap(&self, task : &Task) -> String {
+        let mut recap = task.recap_str(Local::now().date_naive(), self.dates, &self.locale);
+        let id = match task.id() {
+            Some(id) if !task.completion => id,
+            _ => return recap
+        };
+        let blocking = self.list().blocking(id).into_iter().map(|id| self.copy(id).get_content().clone()).collect::<Vec<_>>();
+        if !blocking.is_empty() {
+            recap.push_str(&format!("\n{}", self.locale.label("Blocked by", &blocking.join(", "))));
+        }
+        let unknown = self.list().unknown_dependencies(id);
+        if !unknown.is_empty() {
+            recap.push_str(&format!("\n⚠ No task has the id {}, the dependency is ignored", unknown.join(", ")));
+        }
+        if let Some(dependency_id) = task.get_dependency_id() {
+            for cycle in self.list().dependency_cycles().into_iter().filter(|c| c.iter().any(|d| d == dependency_id)) {
+                recap.push_str(&format!("\n⚠ Dependency cycle : {}", cycle.join(" → ")));
+            }
+        }
+        recap
+    }
+
+    /// Return true if the task should be listed in the menus
+    fn is_shown(&self, task : &Task) -> bool {
+        (self.show_future || task.is_visible_on(Local::now().date_naive()))
+            && (self.show_hidden || !task.is_hidden())
+    }
+}
+
+/// The language and the date format of the menus, from the options and the translation file of the configuration
+///
+/// Fails on a date format chrono can't write or a translation file which can't be read, the unknown texts of the file
+/// are ignored with a warning
+fn load_locale(args : &Cli, settings : &Config) -> Result<Locale, String> {
+    let mut locale = Locale::new(args.lang.as_deref().unwrap_or("en"))?;
+    if let Some(path) = &settings.translations {
+        for (key, text) in config::load_translations(path)? {
+            if let Err(e) = locale.set_text(&key, &text) {
+                eprintln!("{} : {}, it is ignored", path.display(), e);
+            }
+        }
+    }
+    if let Some(date_format) = &args.date_format {
+        locale.set_date_format(date_format)?;
+    }
+    if args.ascii {
+        locale.labels = Labels::Ascii;
+    }
+    Ok(locale)
+}
+
+fn main() {
+    let settings = match Config::path().map(|path| Config::load(&path).map(|loaded| (path, loaded))) {
+        Some(Ok((path, (settings, warnings)))) => {
+            for warning in warnings {
+                eprintln!("{} : {}", path.display(), warning);
+            }
+            settings
+        },
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+        None => Config::default()
+    };
+    let mut args = Cli::from_args().with_config(&settings);
+    storage::set_dry_run(args.dry_run);
+    match paths::todo_files(&args.config, settings.todo.as_deref(), &paths::Environment::current(), |p| p.exists()) {
+        Some((files, source)) => {
+            if source == paths::FileSource::DataDir && !args.dry_run {
+                if let Some(Err(e)) = files[0].parent().map(fs::create_dir_all) {
+                    eprintln!("Could not create {} : {}", files[0].display(), e);
+                    std::process::exit(1);
+                }
+            }
+            if args.verbose {
+                for file in &files {
+                    eprintln!("Task file : {} ({})", file.display(), source);
+                }
+                if let Some(path) = Config::path() {
+                    eprintln!("Configuration file : {}{}", path.display(), if path.exists() { "" } else { " (missing)" });
+                }
+            }
+            args.config = files;
+        },
+        None => {
+            eprintln!("No task file: there is no home directory, use --file or ${}", paths::FILE_VARIABLE);
+            std::process::exit(1);
+        }
+    }
+
+    let mut sort = args.sort.iter().map(|key| SortTaskBy::from_name(key).unwrap_or(SortTaskBy::Content)).collect::<Vec<_>>();
+    if sort.is_empty() {
+        sort = settings.sort.clone().unwrap_or_else(|| vec![SortTaskBy::Content]);
+    }
+    let sort = with_tie_break(sort);
+    let direction = if args.reverse { SortDirection::Descending } else { SortDirection::Ascending };
+    let backups = args.backups.unwrap_or(3);
+
+    // Only the menus stay open long enough for a second instance to overwrite their changes, the commands merge them
+    let mut locks = Vec::new();
+    let mut read_only = None;
+    if args.command.is_none() && args.rofi_script.is_none() && !args.notify && !args.dry_run {
+        // A read-only list can't be changed by a second RofiTodo, nor can its directory hold the lock
+        for file in args.config.iter().filter(|f| !args.read_only && !storage::is_read_only(f)) {
+            match FileLock::acquire(file) {
+                Ok(lock) => locks.push(lock),
+                Err(LockError::Held(pid)) => {
+                    let process = pid.map(|pid| format!(" (process {})", pid)).unwrap_or_default();
+                    read_only = Some(format!("{} is open in another RofiTodo{}, the changes won't be saved", file.display(), process));
+                    storage::set_dry_run(true);
+                    locks.clear();
+                    break;
+                },
+                Err(e) => eprintln!("Could not lock {} : {}", file.display(), e)
+            }
+        }
+    }
+    if let Some(message) = &read_only {
+        eprintln!("{}", message);
+    }
+
+    if args.git_pull {
+        let mut dirs = Vec::new();
+        for file in &args.config {
+            // The lists of a directory are pulled once
+            if dirs.contains(&file.parent()) {
+                continue;
+            }
+            dirs.push(file.parent());
+            if let Err(e) = git::pull(file) {
+                eprintln!("Could not pull the changes of {} : {}", file.display(), e);
+            }
+        }
+    }
+
+    let mut lists = Vec::new();
+    for config in &args.config {
+        let cipher : Rc<dyn Cipher> = Rc::new(Gpg::new(args.gpg_program.clone(), args.gpg_recipient.clone(), config));
+        match TaskList::load_with(config, args.undo_depth, Some(cipher)) {
+            Ok(list) => lists.push(list),
+            Err(s) => {
+                // A task file which can't be read or decrypted makes the commands fail
+                eprintln!("{}", s);
+                drop(locks);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &args.audit_log {
+        let log = AuditLog::new(path, args.audit_log_size.unwrap_or(1024) * 1024);
+        for list in lists.iter_mut() {
+            list.history.set_log(Some(log.clone()));
+        }
+    }
+    // The archive and the trash of the configuration file are the ones of the first list
+    lists[0].done_file = settings.done.clone();
+    lists[0].trash_file = settings.trash.clone();
+    let mut file_warnings = Vec::new();
+    for list in lists.iter_mut() {
+        list.read_only = args.read_only || storage::is_read_only(&list.path);
+        if list.read_only && !args.read_only {
+            file_warnings.push(format!("{} can't be written, it is read-only", list.name()));
+        }
+        for cycle in list.dependency_cycles() {
+            file_warnings.push(format!("{} has a dependency cycle : {}", list.name(), cycle.join(" → ")));
+        }
+        let format = list.format();
+        if format.mixed_endings {
+            let warning = format!("{} has mixed line endings, they are saved as {}", list.name(), if format.crlf { "CRLF" } else { "LF" });
+            eprintln!("{}", warning);
+            file_warnings.push(warning);
+        }
+    }
+
+    if args.trash_days > 0 {
+        let today = Local::now().date_naive();
+        for list in lists.iter().filter(|l| !l.read_only) {
+            if let Err(e) = list.trash().and_then(|mut trash| trash.purge(today, args.trash_days)) {
+                eprintln!("Could not purge the trash of {} : {}", list.name(), e);
+            }
+        }
+    }
+
+    let mut archived = Vec::new();
+    if let Some(days) = args.auto_archive_days {
+        let today = Local::now().date_naive();
+        for list in lists.iter_mut().filter(|l| !l.read_only) {
+            match list.archive_completed(today, days, backups) {
+                Ok(0) => (),
+                Ok(count) => {
+                    if args.git_autocommit {
+                        commit_saved(&list.path, Some(format!("archive {} tasks", count)));
+                    }
+                    archived.push(format!("{} : archived {} tasks", list.name(), count));
+                },
+                Err(e) => eprintln!("Could not archive the completed tasks of {} : {}", list.name(), e)
+            }
+        }
+        for line in &archived {
+            eprintln!("{}", line);
+        }
+    }
+
+    let mut workdays = Workdays { skip_weekends : args.skip_weekends, ..Default::default() };
+    if !args.weekend.is_empty() {
+        workdays.weekend = args.weekend.clone();
+    }
+
+    let mut escalated = Vec::new();
+    if !args.escalate.is_empty() {
+        let today = Local::now().date_naive();
+        for list in lists.iter_mut().filter(|l| !l.read_only) {
+            let changes = escalation::escalate_list(list, today, &args.escalate);
+            if !changes.is_empty() {
+                escalated.push(format!("{} : escalated {}", list.name(), changes.join(", ")));
+            }
+        }
+        for line in &escalated {
+            eprintln!("{}", line);
+        }
+    }
+
+    if let Some(command) = &args.command {
+        let mut parameters = Params::new(sort, direction, lists);
+        parameters.backups = backups;
+        parameters.git_autocommit = args.git_autocommit;
+        parameters.show_blocked = args.show_blocked;
+        parameters.workdays = workdays.clone();
+        let no_confirm = args.no_confirm;
+        let result = run_command(&mut parameters, command, |question| no_confirm || confirm_in_terminal(question));
+        for error in parameters.lists.iter_mut().filter_map(|l| l.history.take_log_error()) {
+            eprintln!("{}", error);
+        }
+        match result {
+            Ok(output) => {
+                if !output.is_empty() {
+                    println!("{}", output);
+                }
+                return;
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.notify || args.notify_at_launch {
+        let tasks = lists.iter().flat_map(|l| l.todos.get_main_index().iter().map(|t| t.as_ref()));
+        // A notification which can't be sent doesn't prevent the menu from opening
+        if let Err(e) = notify::notify_due(&notify::NotifySend, tasks, Local::now().date_naive(), args.notify_days) {
+            eprintln!("Could not send the notification : {}", e);
+        }
+        if args.notify {
+            return;
+        }
+    }
+
+    let locale = match load_locale(&args, &settings) {
+        Ok(locale) => Rc::new(locale),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut parameters = Params::new(sort, direction, lists);
+    parameters.colors = MarkupColors {
+        overdue : args.overdue_color.unwrap_or_else(|| String::from("red")),
+        today : args.today_color.unwrap_or_else(|| String::from("orange")),
+        priority : args.priority_color,
+        priorities : settings.colors.priorities.clone(),
+        projects : settings.colors.project.clone(),
+        contexts : settings.colors.context.clone(),
+        whole_row : args.color_rows
+    };
+    if args.relative_dates {
+        parameters.dates = DateStyle::Relative { max_days : args.relative_days.unwrap_or(60) };
+    }
+    parameters.views_path = ViewStore::path_for(&args.config[0]);
+    parameters.backups = backups;
+    parameters.force_save = args.force_save;
+    parameters.show_blocked = args.show_blocked;
+    parameters.workdays = workdays;
+    parameters.max_line_width = args.max_line_width.unwrap_or(0);
+    parameters.stale_days = args.stale_days.unwrap_or(30);
+    parameters.row_format = args.row_format.unwrap_or_default().with_date_format(locale.date_format());
+    parameters.locale = locale.clone();
+    parameters.numbered_rows = args.numbered_rows;
+    parameters.keys = keys::bindings(|action| settings.keys.get(action));
+    parameters.key_help = settings.keys.help == Some(true);
+    parameters.summary = !args.no_summary;
+    parameters.git_autocommit = args.git_autocommit;
+    parameters.actions = args.actions;
+    parameters.templates = settings.templates.clone().into_iter().collect();
+    let notice = read_only.into_iter().chain(file_warnings).chain(archived).chain(escalated).collect::<Vec<_>>();
+    if !notice.is_empty() {
+        parameters.notice = Some(notice.join("\n"));
+    }
+    if let Some(arg) = &args.rofi_script {
+        let state_path = ScriptState::path_for(&parameters.list().path);
+        let event = ScriptEvent::from_rofi(std::env::var("ROFI_RETV").ok().as_deref(), std::env::var("ROFI_INFO").ok().as_deref(), arg.as_deref());
+        let (state, screen) = script_step(&mut parameters, ScriptState::load(&state_path), event);
+        if let Err(e) = state.save(&state_path) {
+            eprintln!("Could not save the state of the rofi mode : {}", e);
+        }
+        print!("{}", screen.render());
+        return;
+    }
+    let backend : Rc<dyn MenuBackend> = if args.stdio || args.backend.as_deref() == Some("stdio") {
+        Rc::new(StdioBackend::new(io::BufReader::new(io::stdin()), io::stdout()))
+    } else {
+        match rofi::choose_launcher(args.backend.as_deref().unwrap_or("auto"), std::env::var_os("WAYLAND_DISPLAY").is_some(), rofi::in_path) {
+            Ok(launcher) => Rc::new(launcher),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+    let rofi_config = RofiParams { no_config : args.no_config, case_insensitive : args.case_insensitive, no_confirm : args.no_confirm,
+                                   no_color : args.no_color, backend, locale, failure : Default::default() };
+    parameters.views = ViewStore::load(&parameters.views_path);
+    // The setting of the configuration file is used until it is changed in the menu
+    if let (false, Some(show)) = (parameters.views_path.exists(), settings.show_completed) {
+        parameters.views.show_completed = show;
+    }
+    let view = match &args.view {
+        Some(name) => match parameters.views.get(name) {
+            Some(view) => Some(view.clone()),
+            None => {
+                let names = parameters.views.views.iter().map(|v| v.name.as_str()).collect::<Vec<_>>();
+                eprintln!("There is no view '{}', the saved views are : {}", name, if names.is_empty() { String::from("none") } else { names.join(", ") });
+                std::process::exit(1);
+            }
+        },
+        None => parameters.views.last_view().cloned()
+    };
+    match view {
+        Some(view) => parameters.apply_view(&view),
+        None => parameters.show_completed(parameters.views.show_completed)
+    }
+    if args.show_completed {
+        parameters.show_completed(true);
+    }
+    // The sort order given on the command line or the view given win over the sort order chosen last
+    if let (true, Some((sort, direction))) = (args.sort.is_empty() && args.view.is_none(), parameters.views.last_sort()) {
+        parameters.set_sort(sort, direction);
+    }
+
+    // Going back from the main menu shows the today view it was opened from
+    let start = if args.start_view.as_deref() == Some("all") { Screen::Main } else { Screen::Today };
+    run_menus(&rofi_config, &mut parameters, vec![start]);
+
+    for list in parameters.lists.iter_mut().filter(|l| l.modified) {
+        if let Err(s) = save_list(&rofi_config, list, backups, args.force_save, args.git_autocommit) {
+            eprintln!("{}", s);
+        }
+    }
+    // The changes are saved before exiting when the menus could not be shown anymore
+    drop(locks);
+    let failure = rofi_config.failure.borrow().clone();
+    if let Some(e) = failure {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod main_tests {
+    use super::*;
+
+    #[test]
+    fn move_task_between_lists() {
+        let dir = std::env::temp_dir();
+        let work = dir.join(format!("rofitodo_work_{}.txt", std::process::id()));
+        let personal = dir.join(format!("rofitodo_personal_{}.txt", std::process::id()));
+        fs::write(&work, "send report\ncall mom\n").unwrap();
+        fs::write(&personal, "water plants\n").unwrap();
+        let lists = vec![TaskList::load(&work, 20).unwrap(), TaskList::load(&personal, 20).unwrap()];
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, lists);
+        assert!(!params.list().modified);
+
+        let id = params.list().iter().find(|(_, t)| t.get_content() == "call mom").map(|(id, _)| id).unwrap();
+        params.move_task(id, 1);
+        assert!(params.lists[0].modified && params.lists[1].modified);
+        params.lists[1].save().unwrap();
+        assert_eq!(fs::read_to_string(&personal).unwrap(), "water plants\ncall mom\n");
+
+        // Switching saves the modified list
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : true, no_color : false, backend : Rc::new(rofi::Launcher::Rofi), locale : Default::default(), failure : Default::default() };
+        params.switch_list(&rofi_config, 1).unwrap();
+        assert_eq!(params.current, 1);
+        assert!(!params.lists[0].modified);
+        assert_eq!(fs::read_to_string(&work).unwrap(), "send report\n");
+        fs::remove_file(&work).unwrap();
+        fs::remove_file(&personal).unwrap();
+    }
+
+    #[test]
+    fn summary() {
+        let tasks = ["answer the mail est:15m due:2024-05-01", "write the report est:5h", "x 2024-05-02 old est:3h", "call mom est:soon", "buy milk est:15"]
+                        .iter().map(|line| line.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let today = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let summary = |tasks : &[Task], filter : &str, direction| view_summary(&tasks.iter().collect::<Vec<_>>(), today, filter, &[SortTaskBy::DueDate], direction);
+        // The completed task is not counted, the tasks without estimate are
+        assert_eq!(summary(&tasks, "", SortDirection::Ascending), "4 open · 1 overdue · ~5h 30m · sort: due ↑");
+        assert_eq!(summary(&tasks[2..4], "@work <b>", SortDirection::Descending), "1 open · 0 overdue · filter: @work <b> · sort: due ↓");
+    }
+
+    #[test]
+    fn dependencies() {
+        let path = std::env::temp_dir().join(format!("rofitodo_dependencies_{}.txt", std::process::id()));
+        fs::write(&path, "paint walls\nbuy paint\nsand walls id:1\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let find = |params : &Params, content : &str| params.list().iter().find(|(_, t)| t.get_content() == content).map(|(id, _)| id).unwrap();
+        let (paint, buy, sand) = (find(&params, "paint walls"), find(&params, "buy paint"), find(&params, "sand walls"));
+
+        // The prerequisite without id gets the first free one
+        params.add_dependency(paint, buy);
+        params.add_dependency(paint, sand);
+        assert_eq!(params.copy(buy).get_dependency_id(), Some("2"));
+        assert_eq!(params.copy(paint).get_dependencies(), vec!["2", "1"]);
+        assert!(params.task_line(&params.copy(paint)).starts_with("<span alpha=\"50%\">🔒 "));
+        assert!(params.recap(&params.copy(paint)).contains("𝐁𝐥𝐨𝐜𝐤𝐞𝐝 𝐛𝐲 : buy paint, sand walls"));
+        let mut ascii = Locale::default();
+        ascii.labels = Labels::Ascii;
+        params.locale = Rc::new(ascii);
+        assert!(params.recap(&params.copy(paint)).contains("\nBlocked by: buy paint, sand walls"));
+        params.locale = Rc::new(Locale::default());
+
+        // Both changes are undone at once
+        params.undo().unwrap();
+        let (paint, buy) = (find(&params, "paint walls"), find(&params, "buy paint"));
+        assert_eq!(params.copy(paint).get_dependencies(), vec!["2"]);
+
+        let op = params.complete_task(buy);
+        params.record(op);
+        assert_eq!(params.notice.take(), Some(String::from("Unblocked : paint walls")));
+        assert!(!params.task_line(&params.copy(paint)).contains("🔒"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn confirm_disabled() {
+        // Rofi is not launched when the confirmations are disabled
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : true, no_color : false, backend : Rc::new(rofi::Launcher::Rofi), locale : Default::default(), failure : Default::default() };
+        assert!(confirm(&rofi_config, "Delete 'call mom' ?"));
+    }
+
+    #[test]
+    fn show_completed_last() {
+        let path = std::env::temp_dir().join(format!("rofitodo_completed_{}.txt", std::process::id()));
+        fs::write(&path, "x 2024-01-02 a done
+(A) b todo
+c todo
+").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let shown = |params : &Params| params.list().tasks().into_iter()
+                                             .filter(|(_, t)| params.filter.matches(t)).map(|(_, t)| t.get_content().clone()).collect::<Vec<_>>();
+        assert_eq!(shown(&params), vec!["b todo", "c todo"]);
+        params.show_completed(true);
+        assert_eq!(shown(&params), vec!["b todo", "c todo", "a done"]);
+        params.set_sort(vec![SortTaskBy::Content], SortDirection::Descending);
+        assert_eq!(shown(&params), vec!["c todo", "b todo", "a done"]);
+
+        // A view of the completed tasks only is kept
+        params.apply_view(&View { name : String::from("done"), filter : TaskFilter { completed : Some(true), ..Default::default() }, sort : vec![], direction : SortDirection::Ascending });
+        assert_eq!(shown(&params), vec!["a done"]);
+        params.show_completed(false);
+        assert_eq!(shown(&params), vec!["a done"]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn complete_tags() {
+        let known = vec![String::from("+garden"), String::from("+home"), String::from("@errands"), String::from("@phone")];
+        assert_eq!(tag_completions("call mom @", &known), Some(vec![String::from("@errands"), String::from("@phone")]));
+        assert_eq!(tag_completions("+", &known), Some(vec![String::from("+garden"), String::from("+home")]));
+        assert_eq!(tag_completions("buy soil +g", &known), Some(vec![String::from("+garden")]));
+        assert_eq!(tag_completions("buy soil +garden", &known), None);
+        assert_eq!(tag_completions("buy soil +shop", &known), None);
+        assert_eq!(tag_completions("mail me@", &known), None);
+        assert_eq!(tag_completions("call mom @ ", &known), None);
+        assert_eq!(tag_completions("", &known), None);
+
+        // Several tags in a row
+        let text = complete_tag("buy soil +g", Some("+garden"));
+        assert_eq!(text, "buy soil +garden ");
+        let text = complete_tag(&format!("{}@", text), Some("@errands"));
+        assert_eq!(text, "buy soil +garden @errands ");
+        assert_eq!(tag_completions(&text, &known), None);
+
+        // Cancelled
+        assert_eq!(complete_tag("buy soil +garden @", None), "buy soil +garden ");
+        assert_eq!(complete_tag("+", None), "");
+        assert_eq!(complete_tag("@", Some("@phone")), "@phone ");
+    }
+
+    #[test]
+    fn failed_menus() {
+        let path = std::env::temp_dir().join(format!("rofitodo_failed_menus_{}.txt", std::process::id()));
+        fs::write(&path, "call mom\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        // The backend fails when the main menu is shown again
+        let capabilities = rofi::Capabilities { markup_rows : true, multi_select : true, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["+ add", "buy milk", "✔ validate"], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        run_menus(&rofi_config, &mut params, vec![Screen::Main]);
+        assert_eq!(rofi_config.failure.borrow().as_deref(), Some("no answer left"));
+        assert!(params.list().modified);
+        assert!(params.list().todos.get_main_index().iter().any(|t| t.get_content() == "buy milk"));
+        // The next menus are closed at once
+        run_menus(&rofi_config, &mut params, vec![Screen::Today]);
+        assert_eq!(backend.shown.borrow().len(), 4);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_only_menus() {
+        let path = std::env::temp_dir().join(format!("rofitodo_read_only_menus_{}.txt", std::process::id()));
+        fs::write(&path, "call mom\n").unwrap();
+        let mut list = TaskList::load(&path, 20).unwrap();
+        list.read_only = true;
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![list]);
+        let id = params.list().iter().next().unwrap().0;
+        let capabilities = rofi::Capabilities { markup_rows : true, multi_select : true, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["← back", ""], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        show_task_menu(&rofi_config, &mut params, id);
+        run_menus(&rofi_config, &mut params, vec![Screen::Main]);
+        let shown = backend.shown.borrow();
+        assert_eq!(shown[0].0, vec!["← back", "→ copy to clipboard", "→ copy todo.txt line"]);
+        assert!(!shown[1].0.iter().any(|e| ["+ add", "~ bulk actions", "~ sort file permanently"].contains(&e.as_str())));
+        assert!(shown[1].0.contains(&String::from("~ search…")));
+        assert_eq!(shown[1].1.prompt.as_deref(), Some("Todo (read-only) ⇅ content"));
+        // A change which slipped through the menus is not saved
+        params.add_task(Task::new(String::from("water plants")));
+        assert!(save_list(&rofi_config, params.list_mut(), 3, false, false).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "call mom\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn task_templates() {
+        let path = std::env::temp_dir().join(format!("rofitodo_task_templates_{}.txt", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        params.templates = vec![(String::from("review"), "review PR #{input} @work due:+2d".parse().unwrap()),
+                                (String::from("standup"), "standup notes +team".parse().unwrap())];
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        // A template with a placeholder, a template without placeholder, a blank task, then a closed template menu
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["+ add", "review", "review PR #42", "✔ validate", "+ add", "standup", "✔ validate",
+                                                           "+ add", "+ blank", "buy milk", "✔ validate", "+ add", "", ""], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        run_menus(&rofi_config, &mut params, vec![Screen::Main]);
+        let shown = backend.shown.borrow();
+        assert_eq!(shown.len(), 14);
+        assert_eq!(shown[1].0, vec!["+ blank", "review", "standup"]);
+        assert_eq!(shown[1].1.prompt.as_deref(), Some("New task"));
+        let due = Local::now().date_naive() + chrono::Duration::days(2);
+        assert_eq!(shown[2].1.pretext.as_deref(), Some("review PR #"));
+        assert_eq!(shown[2].1.message, Some(format!("Followed by : @work due:{}", due.format("%Y-%m-%d"))));
+        // The template without placeholder goes straight to the validation
+        assert_eq!(shown[6].1.prompt.as_deref(), Some("Edit"));
+        assert_eq!(shown[9].1.pretext.as_deref(), Some(""));
+        let mut tasks = params.list().iter().map(|(_, t)| t.get_content().clone()).collect::<Vec<_>>();
+        tasks.sort();
+        assert_eq!(tasks, vec!["buy milk", "review PR #42 @work", "standup notes +team"]);
+        assert!(params.list().iter().any(|(_, t)| *t.get_due() == Some(due)));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn summary_menus() {
+        let path = std::env::temp_dir().join(format!("rofitodo_summary_menus_{}.txt", std::process::id()));
+        fs::write(&path, "pay rent due:2000-01-01\ncall mom\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::DueDate], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["⚠ 2000-01-01 : pay rent", "✔ mark as done", ""], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        run_menus(&rofi_config, &mut params, vec![Screen::Main]);
+        let shown = backend.shown.borrow();
+        assert_eq!(shown[0].1.message.as_deref(), Some("2 open · 1 overdue · sort: due ↑"));
+        // The summary is rebuilt after the task is completed
+        assert_eq!(shown[2].1.message.as_deref(), Some("1 open · 0 overdue · sort: due ↑"));
+
+        // A backend without message doesn't show it in the prompt
+        let plain = Rc::new(rofi::ScriptedBackend::new(&[""], rofi::Capabilities { message : false, ..capabilities }));
+        let rofi_config = RofiParams { backend : plain.clone(), ..rofi_config };
+        run_menus(&rofi_config, &mut params, vec![Screen::Main]);
+        assert_eq!(plain.shown.borrow()[0].1.prompt.as_deref(), Some("Todo ⇅ due"));
+        // Turned off by `--no-summary`
+        params.summary = false;
+        let quiet = Rc::new(rofi::ScriptedBackend::new(&[""], capabilities));
+        run_menus(&RofiParams { backend : quiet.clone(), ..rofi_config }, &mut params, vec![Screen::Main]);
+        assert_eq!(quiet.shown.borrow()[0].1.message, None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn row_numbers() {
+        assert_eq!(strip_row_number(" 7 │ (B) renew passport"), "(B) renew passport");
+        assert_eq!(strip_row_number("(B) renew passport │ soon"), "(B) renew passport │ soon");
+        assert_eq!(strip_row_number("renew passport"), "renew passport");
+        assert_eq!(row_number(" 2 ", 3), Some(2));
+        assert_eq!((row_number("0", 3), row_number("4", 3), row_number("+2", 3), row_number("2 cats", 3)), (None, None, None, None));
+    }
+
+    #[test]
+    fn numbered_menus() {
+        let path = std::env::temp_dir().join(format!("rofitodo_numbered_menus_{}.txt", std::process::id()));
+        fs::write(&path, "wash car\ncall mom\npay rent\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        params.numbered_rows = true;
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        // A number, a number without task, a copied row, then a number typed to add a task
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["2", "", "9", "3 │ wash car", "", "+ add", "1", "", ""], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        run_menus(&rofi_config, &mut params, vec![Screen::Main]);
+        let shown = backend.shown.borrow();
+        let menu = shown[0].0.iter().filter(|e| e.contains('│')).collect::<Vec<_>>();
+        assert_eq!(menu, ["1 │ call mom", "2 │ pay rent", "3 │ wash car"]);
+        let recap = |i : usize| shown[i].1.message.clone().unwrap_or_default();
+        assert!(recap(1).contains("pay rent"));
+        assert!(recap(4).contains("wash car"));
+        assert!(recap(7).contains("call mom"));
+        assert_eq!(params.list().iter().count(), 3);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn recently_completed_tasks() {
+        let dir = std::env::temp_dir().join(format!("rofitodo_recently_completed_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let today = Local::now().date_naive().format("%Y-%m-%d").to_string();
+        fs::write(dir.join("todo.txt"), format!("x {0} 2024-01-01 call mom\nx 2000-01-01 2000-01-01 water plants\nx {0} 2024-01-01 buy milk\nfix the sink\n", today)).unwrap();
+        // The line of `call mom` is also in the archive, it is listed once
+        fs::write(dir.join("done.txt"), format!("x {0} 2024-01-01 pay rent\nx {0} 2024-01-01 call mom\nx 2000-01-02 2000-01-01 renew passport\n", today)).unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&dir.join("todo.txt"), 20).unwrap()]);
+        let line = |content : &str| params.plain_line(&format!("x {} 2024-01-01 {}", today, content).parse().unwrap());
+        let (pay_rent, buy_milk, call_mom) = (line("pay rent"), line("buy milk"), line("call mom"));
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&[&pay_rent, "✔ mark as to do", &buy_milk, "✔ mark as to do", ""], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        show_recently_completed_menu(&rofi_config, &mut params);
+        let shown = backend.shown.borrow();
+        // The most recent first: the completed tasks of the list from the last line, then the archive from its last line
+        assert_eq!(shown[0].0, vec![String::from("← back"), buy_milk.clone(), call_mom.clone(), pay_rent]);
+        assert_eq!(shown[2].0, vec![String::from("← back"), buy_milk, call_mom.clone()]);
+        assert_eq!(shown[4].0, vec![String::from("← back"), call_mom]);
+        assert_eq!(fs::read_to_string(dir.join("done.txt")).unwrap(), format!("x {} 2024-01-01 call mom\nx 2000-01-02 2000-01-01 renew passport\n", today));
+        let open = params.list().iter().filter(|(_, t)| !t.completion).map(|(_, t)| t.get_content().clone()).collect::<Vec<_>>();
+        assert_eq!(open, vec!["buy milk", "fix the sink", "pay rent"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn escalate_at_launch() {
+        let path = std::env::temp_dir().join(format!("rofitodo_escalate_{}.txt", std::process::id()));
+        fs::write(&path, "(C) pay rent due:2024-05-12\ncall mom due:2024-05-10\n(A) fix the sink due:2024-05-11\nwater plants\n").unwrap();
+        let mut list = TaskList::load(&path, 20).unwrap();
+        let log = std::env::temp_dir().join(format!("rofitodo_escalate_{}.log", std::process::id()));
+        list.history.set_log(Some(AuditLog::new(&log, 1024 * 1024)));
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let rules = vec!["B=2".parse().unwrap(), "A=0".parse().unwrap()];
+        assert_eq!(escalation::escalate_list(&mut list, today, &rules), vec!["pay rent (C → B)", "call mom (none → A)"]);
+        // The escalation is applied once and undone at once
+        assert!(escalation::escalate_list(&mut list, today, &rules).is_empty());
+        let lines = |params : &Params| {
+            let mut lines = params.list().iter().map(|(_, t)| t.to_string()).collect::<Vec<_>>();
+            lines.sort();
+            lines
+        };
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![list]);
+        assert_eq!(lines(&params), vec!["(A) call mom due:2024-05-10 autopri:1", "(A) fix the sink due:2024-05-11", "(B) pay rent due:2024-05-12 autopri:1", "water plants"]);
+        let logged = params.list().history.log().unwrap().read(None).unwrap();
+        assert_eq!(logged.iter().map(|line| line.split_once(' ').unwrap().1).collect::<Vec<_>>(), vec!["escalate 2 tasks"]);
+        params.undo().unwrap();
+        assert_eq!(lines(&params), vec!["(A) fix the sink due:2024-05-11", "(C) pay rent due:2024-05-12", "call mom due:2024-05-10", "water plants"]);
+        params.redo().unwrap();
+
+        // A priority changed by hand is never raised again
+        let id = params.list().iter().find(|(_, t)| t.get_content() == "call mom").unwrap().0;
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["↓ decrease priority", "← back"], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend, locale : Default::default(), failure : Default::default() };
+        show_task_menu(&rofi_config, &mut params, id);
+        assert!(lines(&params).contains(&String::from("(B) call mom due:2024-05-10 autopri:0")));
+        assert!(escalation::escalate_list(params.list_mut(), today, &rules).is_empty());
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&log).unwrap();
+    }
+
+    #[test]
+    fn stale_review() {
+        let path = std::env::temp_dir().join(format!("rofitodo_stale_review_{}.txt", std::process::id()));
+        let today = Local::now().date_naive();
+        fs::write(&path, format!("2000-01-01 call the bank\n2000-03-01 renew passport\n2000-02-01 fix the sink\nsort the garage\n{} buy milk\nx 2000-02-02 2000-01-01 pay rent\n", today)).unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["→ keep", "✔ mark as done", "+ postpone", "+1 week", "* exit review", "* exit review"], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+        let line = |params : &Params, content : &str| params.list().iter().find(|(_, t)| t.get_content() == content).map(|(_, t)| params.task_line(t)).unwrap();
+        // The tasks without creation date are never stale
+        assert!(line(&params, "call the bank").starts_with("⌛ "));
+        assert_eq!((line(&params, "sort the garage"), line(&params, "buy milk")), (String::from("sort the garage"), String::from("buy milk")));
+
+        // The oldest task first, the kept and postponed tasks are not shown again in the session
+        show_stale_review_menu(&rofi_config, &mut params);
+        show_stale_review_menu(&rofi_config, &mut params);
+        let shown = backend.shown.borrow();
+        let message = |i : usize| shown[i].1.message.clone().unwrap_or_default();
+        assert!(message(0).contains("3 stale tasks left") && message(0).contains("call the bank"));
+        assert!(message(1).contains("2 stale tasks left") && message(1).contains("fix the sink"));
+        assert!(message(2).contains("1 stale task left") && message(2).contains("renew passport"));
+        assert_eq!(shown[0].0, vec!["✔ mark as done", "+ postpone", "! remove", "→ keep", "* exit review"]);
+        assert_eq!((message(4), message(5)), (String::from("No stale task left to review, 2 kept"), String::from("No stale task left to review, 2 kept")));
+        let fixed = params.list().iter().find(|(_, t)| t.get_content() == "fix the sink").map(|(_, t)| t.completion);
+        assert_eq!(fixed, Some(true));
+        assert!(params.list().iter().any(|(_, t)| t.get_content() == "renew passport" && t.get_due().is_some()));
+
+        params.stale_days = 0;
+        assert_eq!(line(&params, "call the bank"), "call the bank");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn project_actions() {
+        let dir = std::env::temp_dir().join(format!("rofitodo_project_actions_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("todo.txt"), "x 2024-05-01 2024-01-01 pay rent +home\nfix the sink +home due:2024-06-01\npaint the fence +home +garden\nwater the lawn +garden\ncall mom\n").unwrap();
+        fs::write(dir.join("done.txt"), "").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&dir.join("todo.txt"), 20).unwrap()]);
+        params.list_mut().history.set_log(Some(AuditLog::new(&dir.join("audit.log"), 1024 * 1024)));
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["~ archive project (3)", "yes", "✔ complete all (2)", "yes", "+ reschedule all (1)", "+1 week", "yes"], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+        let contents = |params : &Params, completed : bool| {
+            let mut contents = params.list().iter().filter(|(_, t)| t.completion == completed).map(|(_, t)| t.get_content().clone()).collect::<Vec<_>>();
+            contents.sort();
+            contents
+        };
+
+        // The completed task of the project is archived with the tasks to do, and restored with them by a single undo
+        show_project_menu(&rofi_config, &mut params, Some(String::from("home")));
+        assert_eq!(backend.shown.borrow()[1].1.message.as_deref(), Some("Complete and move the 3 tasks of +home to the archive ?"));
+        assert_eq!(contents(&params, false), vec!["call mom", "water the lawn +garden"]);
+        assert_eq!(fs::read_to_string(dir.join("done.txt")).unwrap().lines().count(), 3);
+        params.undo().unwrap();
+        assert_eq!(params.list().iter().count(), 5);
+        assert_eq!(fs::read_to_string(dir.join("done.txt")).unwrap(), "");
+        // The task of two projects is completed once
+        show_project_menu(&rofi_config, &mut params, Some(String::from("garden")));
+        assert_eq!(contents(&params, true), vec!["paint the fence +home +garden", "pay rent +home", "water the lawn +garden"]);
+        assert_eq!(params.list().iter().count(), 5);
+        // Only the task to do of the project is rescheduled
+        show_project_menu(&rofi_config, &mut params, Some(String::from("home")));
+        let due = params.list().iter().filter_map(|(_, t)| t.get_due().map(|d| (t.get_content().clone(), d))).collect::<Vec<_>>();
+        assert_eq!(due, vec![(String::from("fix the sink +home"), NaiveDate::from_ymd_opt(2024, 6, 8).unwrap())]);
+        let log = params.list().history.log().unwrap().read(None).unwrap();
+        let log = log.iter().map(|line| line.split_once(' ').unwrap().1).collect::<Vec<_>>();
+        assert_eq!(log, vec!["archive project +home (3 tasks)", "undo archive project +home (3 tasks)", "complete project +garden (2 tasks)",
+                             "reschedule project +home by +1 week (1 task)"]);
+
+        // Nothing is changed when the action fails on a task
+        let ids = params.list().iter().map(|(id, _)| id).collect::<Vec<_>>();
+        let before = params.list().iter().map(|(_, t)| t.to_string()).collect::<Vec<_>>();
+        let last = ids[ids.len() - 1];
+        let failed = params.group(String::from("edit"), ids, |params, task| match task == last {
+            true => Err(String::from("failed")),
+            false => Ok(params.change_task(task, |t| t.set_priority(Some('A')).unwrap()))
+        });
+        assert_eq!(failed, Err(String::from("failed")));
+        assert_eq!(params.list().iter().map(|(_, t)| t.to_string()).collect::<Vec<_>>(), before);
+        assert_eq!(params.list().history.log().unwrap().read(None).unwrap().len(), 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn focus_mode() {
+        let path = std::env::temp_dir().join(format!("rofitodo_focus_mode_{}.txt", std::process::id()));
+        fs::write(&path, "(A) pay rent\ncall mom\n(A) buy milk\nx 2024-01-01 (A) fix the sink\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        // Skip the first task, complete the next ones until the list is empty
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["→ skip", "✔ mark as done", "✔ mark as done", "* exit focus", "* exit focus"], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+        // The tasks as urgent are taken in the order of their contents
+        let content = |params : &Params| params.focus_task().map(|id| params.copy(id).get_content().clone());
+        assert_eq!(content(&params).as_deref(), Some("buy milk"));
+
+        show_focus_menu(&rofi_config, &mut params);
+        // The skipped task is not shown again in the session
+        show_focus_menu(&rofi_config, &mut params);
+        let shown = backend.shown.borrow();
+        let recap = |i : usize| shown[i].1.message.clone().unwrap_or_default();
+        assert!(recap(0).contains("buy milk") && recap(1).contains("pay rent") && recap(2).contains("call mom"));
+        assert_eq!(shown[1].0, vec!["✔ mark as done", "→ skip", "* exit focus"]);
+        assert_eq!((recap(3), recap(4)), (String::from("No task left to focus on, 1 skipped"), String::from("No task left to focus on, 1 skipped")));
+        assert_eq!(params.list().iter().filter(|(_, t)| !t.completion).map(|(_, t)| t.get_content().clone()).collect::<Vec<_>>(), vec!["buy milk"]);
+        assert_eq!(content(&params), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn pasted_tasks() {
+        assert_eq!(pasted_task("  call mom\nabout   the party\r\n"), Some(String::from("call mom about the party")));
+        assert_eq!(pasted_task(" \n\t"), None);
+        assert_eq!(pasted_task(&"a".repeat(600)).map(|t| t.len()), Some(MAX_PASTED_LENGTH));
+
+        let path = std::env::temp_dir().join(format!("rofitodo_pasted_tasks_{}.txt", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["✔ validate", "← back", "← back"], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        show_paste_task(&rofi_config, &mut params, || Ok(String::from("read https://example.com/post\n+reading")));
+        show_paste_task(&rofi_config, &mut params, || Ok(String::from("\n")));
+        show_paste_task(&rofi_config, &mut params, || Err(ClipboardError::NoTool));
+        let shown = backend.shown.borrow();
+        assert!(shown[0].1.message.as_deref().unwrap().contains("read https://example.com/post +reading"));
+        assert_eq!(shown[1].1.message.as_deref(), Some("The clipboard is empty, no task to add"));
+        assert!(shown[2].1.message.as_deref().unwrap().starts_with("No clipboard tool found"));
+        assert_eq!(params.list().iter().map(|(_, t)| t.get_content().clone()).collect::<Vec<_>>(), vec!["read https://example.com/post +reading"]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn custom_keys() {
+        let path = std::env::temp_dir().join(format!("rofitodo_custom_keys_{}.txt", std::process::id()));
+        fs::write(&path, "wash car\ncall mom\npay rent\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        params.keys = keys::bindings(|_| None);
+        params.key_help = true;
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        // Complete, cancel a deletion, edit, then a key without action
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["[kb-custom-1] call mom", "[kb-custom-2] pay rent", "no", "[kb-custom-4] wash car",
+                                                           "wash the car", "[kb-custom-9] pay rent", ""], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        run_menus(&rofi_config, &mut params, vec![Screen::Main]);
+        let shown = backend.shown.borrow();
+        assert_eq!(shown.len(), 7);
+        assert_eq!(shown[0].1.custom_keys, ["Control+Return", "Control+d", "Control+p", "Control+e"]);
+        assert!(shown[0].1.message.as_deref().unwrap_or("").contains("Control+Return: complete · Control+d: delete"));
+        assert_eq!(shown[2].1.prompt.as_deref(), Some("Confirm"));
+        let tasks = params.list().iter().map(|(_, t)| (t.completion, t.get_content().clone())).collect::<Vec<_>>();
+        assert!(tasks.contains(&(true, String::from("call mom"))));
+        assert!(tasks.contains(&(false, String::from("pay rent"))));
+        assert!(tasks.contains(&(false, String::from("wash the car"))));
+        // The actions are not run on a read-only list
+        params.list_mut().read_only = true;
+        let backend = Rc::new(rofi::ScriptedBackend::new(&[""], capabilities));
+        let rofi_config = RofiParams { backend : backend.clone(), ..rofi_config };
+        run_menus(&rofi_config, &mut params, vec![Screen::Today]);
+        assert!(backend.shown.borrow()[0].1.custom_keys.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn selected_rows() {
+        let path = std::env::temp_dir().join(format!("rofitodo_selected_rows_{}.txt", std::process::id()));
+        fs::write(&path, "wash car\ncall mom\npay rent\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        // Complete a task, then open the next one and go back
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["pay rent", "✔ mark as done", "wash car", "", "~ group by project", ""], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        run_menus(&rofi_config, &mut params, vec![Screen::Main]);
+        let shown = backend.shown.borrow();
+        let row = |i : usize, text : &str| shown[i].0.iter().position(|e| e == text).map(|row| row as u32);
+        assert_eq!(shown[0].1.selected, None);
+        // The completed task is no longer listed, the next task takes its place
+        assert_eq!(shown[2].1.selected, row(2, "wash car"));
+        assert_eq!(shown[4].1.selected, row(4, "wash car"));
+        // The entries of the menu are selected again too
+        assert_eq!(shown[5].1.selected, row(5, "~ ungroup tasks"));
+
+        // The last task of the list is followed by the one before it
+        let ids = params.list().tasks().into_iter().map(|(id, _)| Some(id)).collect::<Vec<_>>();
+        params.selected_rows.insert("today", SelectedRow::Task(ids[0].unwrap(), 2));
+        assert_eq!(params.selected_row("today", &[None, ids[1], ids[2], None]), Some(2));
+        params.selected_rows.insert("today", SelectedRow::Task(ids[0].unwrap(), 0));
+        assert_eq!(params.selected_row("today", &[None, ids[1], ids[2], None]), Some(1));
+        assert_eq!(params.selected_row("week", &[None, ids[1]]), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn back_navigation() {
+        let path = std::env::temp_dir().join(format!("rofitodo_back_navigation_{}.txt", std::process::id()));
+        fs::write(&path, "call mom\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let capabilities = rofi::Capabilities { markup_rows : false, multi_select : false, message : true };
+        // Escape closes one menu at a time, also in the middle of adding a task
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["~ all tasks", "call mom", "", "~ filter", "", "+ add", "buy milk", "",
+                                                           "+ add", "buy milk", "+ add date", "", "", ""], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        run_menus(&rofi_config, &mut params, vec![Screen::Today]);
+        assert_eq!(rofi_config.failure.borrow().as_deref(), None);
+        let shown = backend.shown.borrow();
+        let prompts = shown.iter().map(|(_, options)| options.prompt.clone().unwrap_or_default()).collect::<Vec<_>>();
+        assert_eq!(prompts.len(), 14);
+        assert!(prompts[0].starts_with("Today") && prompts[13].starts_with("Today"), "{:?}", prompts);
+        for (i, prompt) in [(1, "Todo ⇅ content"), (2, "Edit"), (3, "Todo ⇅ content"), (4, "Filter"), (5, "Todo ⇅ content"), (6, "Task"),
+                            (7, "Edit"), (8, "Todo ⇅ content"), (9, "Task"), (10, "Edit"), (12, "Todo ⇅ content")] {
+            assert_eq!(prompts[i], prompt);
+        }
+        // The cancelled task is not added
+        assert!(!params.list().modified);
+        assert_eq!(params.list().iter().count(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tags_menu() {
+        let path = std::env::temp_dir().join(format!("rofitodo_tags_menu_{}.txt", std::process::id()));
+        fs::write(&path, "call mom +home\nbuy milk @errands\n").unwrap();
+        let mut params = Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let id = params.list().iter().find(|(_, t)| t.get_content() == "call mom +home").map(|(id, _)| id).unwrap();
+        // A typed tag, a removed tag, a suggested tag, then back
+        let capabilities = rofi::Capabilities { markup_rows : true, multi_select : true, message : true };
+        let backend = Rc::new(rofi::ScriptedBackend::new(&["@phone", "✔ +home", "@errands", "← back"], capabilities));
+        let rofi_config = RofiParams { no_config : true, case_insensitive : false, no_confirm : false, no_color : false, backend : backend.clone(), locale : Default::default(), failure : Default::default() };
+
+        show_task_tags_menu(&rofi_config, &mut params, id);
+        assert_eq!(params.list().get(id).unwrap().get_content(), "call mom @phone @errands");
+        let shown = backend.shown.borrow();
+        assert_eq!(shown.len(), 4);
+        assert_eq!(shown[0].0, vec!["← back", "✔ +home", "@errands"]);
+        assert_eq!(shown[3].0, vec!["← back", "✔ @errands", "✔ @phone"]);
+        assert_eq!(shown[0].1.prompt.as_deref(), Some("Tags"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rofi_script() {
+        let path = std::env::temp_dir().join(format!("rofitodo_script_mode_{}.txt", std::process::id()));
+        fs::write(&path, "(B) call mom\nx 2024-01-03 done task\nwater plants\n").unwrap();
+        let load = || Params::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let infos = |screen : &script::Screen| screen.rows.iter().map(|(_, info)| info.clone()).collect::<Vec<_>>();
+
+        // Every call of rofi loads the list again
+        let (state, screen) = script_step(&mut load(), ScriptState::List, ScriptEvent::Start);
+        assert_eq!(state, ScriptState::List);
+        assert_eq!(infos(&screen), vec!["add", "task:1", "task:3"]);
+        let (state, screen) = script_step(&mut load(), state, ScriptEvent::Select(String::from("task:3")));
+        assert_eq!(state, ScriptState::Task(3));
+        assert!(screen.message.unwrap().contains("water plants"));
+        let (state, screen) = script_step(&mut load(), state, ScriptEvent::Select(String::from("done:3")));
+        assert_eq!(state, ScriptState::List);
+        assert_eq!(screen.message.as_deref(), Some("✔ water plants"));
+        assert_eq!(infos(&screen), vec!["add", "task:1"]);
+        assert!(fs::read_to_string(&path).unwrap().lines().nth(2).unwrap().starts_with("x "));
+
+        // Adding from the add menu and from the list
+        let (state, _) = script_step(&mut load(), state, ScriptEvent::Select(String::from("add")));
+        assert_eq!(state, ScriptState::Add);
+        let (state, screen) = script_step(&mut load(), state, ScriptEvent::Input(String::from("buy milk +home")));
+        assert_eq!((state.clone(), screen.message.as_deref()), (ScriptState::List, Some("Added 'buy milk +home'")));
+        let (state, _) = script_step(&mut load(), state, ScriptEvent::Input(String::from("(A)")));
+        assert_eq!(infos(&script_screen(&load(), &state, None).unwrap()), vec!["add", "task:5", "task:4", "task:1"]);
+        let (state, screen) = script_step(&mut load(), state, ScriptEvent::Input(String::from("x 2024-13-45 broken")));
+        assert_eq!(state, ScriptState::Add);
+        assert!(screen.message.unwrap().starts_with("The task can't be added"));
+
+        // Deleting after a confirmation
+        let (state, _) = script_step(&mut load(), ScriptState::Task(1), ScriptEvent::Select(String::from("remove:1")));
+        assert_eq!(state, ScriptState::ConfirmDelete(1));
+        let (state, screen) = script_step(&mut load(), state, ScriptEvent::Select(String::from("delete:1")));
+        assert_eq!((state, screen.message.as_deref()), (ScriptState::List, Some("Deleted 'call mom'")));
+        assert!(!fs::read_to_string(&path).unwrap().contains("call mom"));
+
+        // A task which is not in the list anymore
+        let (state, screen) = script_step(&mut load(), ScriptState::List, ScriptEvent::Select(String::from("task:9")));
+        assert_eq!((state, screen.message.as_deref()), (ScriptState::List, Some("There is no task on line 9")));
+        fs::remove_file(load().list().trash_path()).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,207 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use lazy_static::lazy_static;
+use crate::task::Task;
+use crate::agenda::group_by_project;
+
+/// Return the tasks as a Markdown checklist, under a heading for each project
+///
+/// A task is written under its first project, the tasks without project last. The project and context tags
+/// are written at the end of the item, the project tags as `#project`:
+/// `- [ ] (A) call the plumber (due 2024-05-01) #house @phone`
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks, in the order of the items of each project
+pub fn to_markdown(tasks : &[&Task]) -> String {
+    let mut written = vec![false; tasks.len()];
+    let mut sections = Vec::new();
+    for group in group_by_project(tasks) {
+        let indexes = group.indexes.into_iter().filter(|i| !std::mem::replace(&mut written[*i], true)).collect::<Vec<_>>();
+        if indexes.is_empty() {
+            continue;
+        }
+        let mut section = vec![format!("## {}", group.project.as_deref().unwrap_or("No project"))];
+        section.extend(indexes.into_iter().map(|i| item(tasks[i])));
+        sections.push(section.join("\n"));
+    }
+    sections.into_iter().map(|s| s + "\n").collect::<Vec<_>>().join("\n")
+}
+
+/// Return the checklist item of a task
+fn item(task : &Task) -> String {
+    let mut words = vec![String::from(if task.completion { "- [x]" } else { "- [ ]" })];
+    if let Some(priority) = task.get_priority() {
+        words.push(format!("({})", priority));
+    }
+    let content = plain_content(task);
+    if !content.is_empty() {
+        words.push(content);
+    }
+    if let Some(due) = task.get_due() {
+        words.push(format!("(due {})", due.format("%Y-%m-%d")));
+    }
+    words.extend(task.get_project_tags().iter().map(|p| format!("#{}", p)));
+    words.extend(task.get_context_tags().iter().map(|c| format!("@{}", c)));
+    words.join(" ")
+}
+
+/// Return the content of a task without its project and context tags
+fn plain_content(task : &Task) -> String {
+    task.get_content().split(' ')
+        .filter(|word| !((word.starts_with('+') || word.starts_with('@')) && word.len() > 1))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A list item containing other items, see `from_markdown`
+struct Parent {
+    /// The indentation of the item
+    indent : usize,
+    /// The content of the item without its tags, prefixed by its own parents
+    prefix : String,
+    /// The project tags of the item
+    projects : Vec<String>,
+    /// The context tags of the item
+    contexts : Vec<String>
+}
+
+/// Read the tasks of the `- [ ]` and `- [x]` items of a Markdown text
+///
+/// An item is read like a task typed in the menu, with `#project` for a project tag and `(due 2024-05-01)` for a due
+/// date, except in the code spans. A checked item is completed `today`.
+/// A nested item is prefixed by the text of its parent items (`trip: book the hotel`) and gets their tags,
+/// the items without a checkbox are only parents
+///
+/// Arguments:
+///
+/// * `text` - the Markdown text
+/// * `today` - the current date
+pub fn from_markdown(text : &str, today : NaiveDate) -> Vec<Task> {
+    lazy_static! {
+        static ref RE_ITEM : Regex = Regex::new(r"^(?P<indent>\s*)(?:[-*+]|\d+[.)]) +(?:\[(?P<check>[ xX])\](?: +|$))?(?P<text>.*)$").unwrap();
+    }
+    let mut parents : Vec<Parent> = Vec::new();
+    let mut tasks = Vec::new();
+    for line in text.lines() {
+        let cap = match RE_ITEM.captures(line) {
+            Some(cap) => cap,
+            None => {
+                // A heading or a paragraph ends the list
+                if !line.trim().is_empty() {
+                    parents.clear();
+                }
+                continue;
+            }
+        };
+        let indent = cap["indent"].chars().map(|c| if c == '\t' { 4 } else { 1 }).sum();
+        parents.retain(|p| p.indent < indent);
+        let mut task = match item_task(&cap["text"], parents.last(), today) {
+            Some(task) => task,
+            None => continue
+        };
+        parents.push(Parent {
+            indent,
+            prefix : plain_content(&task),
+            projects : task.get_project_tags().clone(),
+            contexts : task.get_context_tags().clone()
+        });
+        match cap.name("check").map(|c| c.as_str()) {
+            Some(" ") => tasks.push(task),
+            Some(_) => {
+                task.set_completed_on(today);
+                tasks.push(task);
+            },
+            None => ()
+        }
+    }
+    tasks
+}
+
+/// Read the task of an item, `None` if the item has no text
+fn item_task(text : &str, parent : Option<&Parent>, today : NaiveDate) -> Option<Task> {
+    lazy_static! {
+        static ref RE_PRIORITY : Regex = Regex::new(r"^(?P<priority>\([A-Z]\) )?(?P<rest>.*)$").unwrap();
+    }
+    let line = todo_line(text);
+    let line = match parent {
+        Some(parent) if !parent.prefix.is_empty() => {
+            // The priority stays first
+            let cap = RE_PRIORITY.captures(&line).expect("the regex matches any line");
+            format!("{}{}: {}", cap.name("priority").map(|p| p.as_str()).unwrap_or(""), parent.prefix, &cap["rest"])
+        },
+        _ => line
+    };
+    if line.trim().is_empty() {
+        return None;
+    }
+    // The completion is given by the checkbox, not by an item starting with `x `
+    let mut task = match Task::from_quick_add(&line, today) {
+        Ok(task) if !task.completion => task,
+        _ => Task::new_with_date(line, today)
+    };
+    if let Some(parent) = parent {
+        for project in parent.projects.iter() {
+            task.add_project_tag(project);
+        }
+        for context in parent.contexts.iter() {
+            task.add_context_tag(context);
+        }
+    }
+    Some(task)
+}
+
+/// Turn the text of an item into a todo.txt line: `#project` into `+project` and `(due 2024-05-01)` into a due tag
+///
+/// The code spans are kept unchanged
+fn todo_line(text : &str) -> String {
+    lazy_static! {
+        static ref RE_DUE : Regex = Regex::new(r"\(due (?P<date>\d{4}-\d{2}-\d{2})\)").unwrap();
+        static ref RE_HASHTAG : Regex = Regex::new(r"(?P<start>^| )#(?P<tag>\p{L}\S*)").unwrap();
+    }
+    text.trim().split('`').enumerate().map(|(index, part)| {
+        if index % 2 == 1 {
+            return part.to_string();
+        }
+        let part = RE_DUE.replace_all(part, "due:$date");
+        RE_HASHTAG.replace_all(&part, "$start+$tag").into_owned()
+    }).collect::<Vec<_>>().join("`")
+}
+
+#[cfg(test)]
+mod markdown_tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, 20).unwrap()
+    }
+
+    #[test]
+    fn export() {
+        let tasks = include_str!("../tests/fixtures/tasks.txt").lines().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let tasks = tasks.iter().collect::<Vec<_>>();
+        assert_eq!(to_markdown(&tasks), include_str!("../tests/fixtures/export.md"));
+        assert_eq!(to_markdown(&[]), "");
+    }
+
+    #[test]
+    fn import() {
+        let tasks = from_markdown(include_str!("../tests/fixtures/meeting.md"), today());
+        let expected = include_str!("../tests/fixtures/meeting.txt").lines().collect::<Vec<_>>();
+        assert_eq!(tasks.iter().map(|t| t.to_string()).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        let tasks = include_str!("../tests/fixtures/tasks.txt").lines().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let markdown = to_markdown(&tasks.iter().collect::<Vec<_>>());
+        let imported = from_markdown(&markdown, today());
+        assert_eq!(to_markdown(&imported.iter().collect::<Vec<_>>()), markdown);
+    }
+
+    #[test]
+    fn code_spans() {
+        assert_eq!(todo_line("run `make #all (due 2024-05-01)` #build (due 2024-05-01)"), "run `make #all (due 2024-05-01)` +build due:2024-05-01");
+        assert_eq!(todo_line("fix issue #42 in [the docs](https://example.com/#install)"), "fix issue #42 in [the docs](https://example.com/#install)");
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+
+/// The colors given to the project and context tags without a color of their own, chosen by the name of the tag
+pub const PALETTE : [&str; 8] = ["#5294e2", "#73d216", "#c061cb", "#e5a50a", "#33c7de", "#f66151", "#9a9996", "#2ec27e"];
+
+/// The colors used to render tasks with Pango markup
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkupColors {
+    /// The color of the due date of overdue tasks
+    pub overdue : String,
+    /// The color of the due date of tasks due today
+    pub today : String,
+    /// The color of the `(A)` priority, only bold if `None`
+    pub priority : Option<String>,
+    /// The colors of the priorities by letter, `priority` is used for `A` when it has none
+    pub priorities : BTreeMap<char, String>,
+    /// The colors of the project tags by name, the other projects get a color of `PALETTE`
+    pub projects : BTreeMap<String, String>,
+    /// The colors of the context tags by name, the other contexts get a color of `PALETTE`
+    pub contexts : BTreeMap<String, String>,
+    /// Color the whole line of a task with the color of its first project or context, or of its priority,
+    /// instead of its tags
+    pub whole_row : bool
+}
+
+impl Default for MarkupColors {
+    fn default() -> Self {
+        MarkupColors { overdue : String::from("red"), today : String::from("orange"), priority : None, priorities : BTreeMap::new(),
+                       projects : BTreeMap::new(), contexts : BTreeMap::new(), whole_row : false }
+    }
+}
+
+impl MarkupColors {
+    /// Return the color of a priority, `None` if it has no color
+    ///
+    /// Arguments:
+    ///
+    /// * `priority` - the letter of the priority
+    pub fn priority_color(&self, priority : char) -> Option<&str> {
+        match self.priorities.get(&priority) {
+            Some(color) => Some(color),
+            None if priority == 'A' => self.priority.as_deref(),
+            None => None
+        }
+    }
+
+    /// Return the color of a project tag, from the configured colors or else from `PALETTE`
+    ///
+    /// Arguments:
+    ///
+    /// * `project` - the name of the project, without `+`
+    pub fn project_color(&self, project : &str) -> &str {
+        self.projects.get(project).map_or_else(|| palette_color(project), |c| c.as_str())
+    }
+
+    /// Return the color of a context tag, from the configured colors or else from `PALETTE`
+    ///
+    /// Arguments:
+    ///
+    /// * `context` - the name of the context, without `@`
+    pub fn context_color(&self, context : &str) -> &str {
+        self.contexts.get(context).map_or_else(|| palette_color(context), |c| c.as_str())
+    }
+
+    /// Escape the content of a task and color its project and context tags
+    ///
+    /// Arguments:
+    ///
+    /// * `content` - the content of the task
+    pub fn tags(&self, content : &str) -> String {
+        content.split(' ').map(|word| {
+            match (word.strip_prefix('+'), word.strip_prefix('@')) {
+                (Some(project), _) if !project.is_empty() => colored(&escape(word), self.project_color(project)),
+                (_, Some(context)) if !context.is_empty() => colored(&escape(word), self.context_color(context)),
+                _ => escape(word)
+            }
+        }).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Return the color of `PALETTE` of a tag, always the same for a name
+///
+/// Arguments:
+///
+/// * `name` - the name of the tag
+pub fn palette_color(name : &str) -> &'static str {
+    // FNV-1a, which doesn't change between the runs and the versions of Rust
+    let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    PALETTE[(hash % PALETTE.len() as u64) as usize]
+}
+
+/// Escape a `String` so it can be inserted in Pango markup
+///
+/// Arguments:
+///
+/// * `text` - the text to escape
+pub fn escape(text : &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => s.push_str("&amp;"),
+            '<' => s.push_str("&lt;"),
+            '>' => s.push_str("&gt;"),
+            '\'' => s.push_str("&apos;"),
+            '"' => s.push_str("&quot;"),
+            c => s.push(c)
+        }
+    }
+    s
+}
+
+/// Return the text of Pango markup, without the tags and with the entities unescaped
+///
+/// Arguments:
+///
+/// * `markup` - the markup
+pub fn strip(markup : &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut in_tag = false;
+    for c in markup.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => ()
+        }
+    }
+    // `&amp;` is the last one so `&amp;lt;` gives `&lt;`
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&apos;", "'").replace("&quot;", "\"").replace("&amp;", "&")
+}
+
+/// Wrap a text in a span with a foreground color
+///
+/// Arguments:
+///
+/// * `text` - the markup to color
+/// * `color` - the Pango color (name or `#rrggbb`)
+pub fn colored(text : &str, color : &str) -> String {
+    format!("<span foreground=\"{}\">{}</span>", escape(color), text)
+}
+
+/// Wrap a text in a half transparent span, for the tasks which can't be done yet
+///
+/// Arguments:
+///
+/// * `text` - the markup to dim
+pub fn dimmed(text : &str) -> String {
+    format!("<span alpha=\"50%\">{}</span>", text)
+}
+
+/// Return the number of columns a character takes: 0 for the combining marks, the joiners and the variation selectors,
+/// 2 for the emoji and the wide East Asian characters, 1 for the others
+///
+/// Arguments:
+///
+/// * `c` - the character
+pub fn char_width(c : char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x0483..=0x0489 | 0x0591..=0x05BD | 0x0610..=0x061A | 0x064B..=0x065F | 0x0E31 | 0x0E34..=0x0E3A
+        | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F
+        | 0x1F3FB..=0x1F3FF | 0xE0000..=0xE007F => 0,
+        0x1100..=0x115F | 0x231A..=0x231B | 0x23E9..=0x23EC | 0x23F0..=0x23F3 | 0x25FD..=0x25FE | 0x2614..=0x2615
+        | 0x2648..=0x2653 | 0x26A1 | 0x26AA..=0x26AB | 0x26BD..=0x26BE | 0x26C4..=0x26C5 | 0x26D4 | 0x26EA | 0x26F2..=0x26F5
+        | 0x26FA | 0x26FD | 0x2705 | 0x270A..=0x270B | 0x2728 | 0x274C | 0x2753..=0x2755 | 0x2757 | 0x2795..=0x2797 | 0x27B0
+        | 0x2B1B..=0x2B1C | 0x2B50 | 0x2B55 | 0x2E80..=0x303E | 0x3041..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F680..=0x1F6FF | 0x1F900..=0x1F9FF
+        | 0x1FA70..=0x1FAFF | 0x20000..=0x3FFFD => 2,
+        _ => 1
+    }
+}
+
+/// Return the number of columns of the text of Pango markup, see `char_width`
+///
+/// A character joined to the previous one by a zero width joiner, like in 👩‍💻, takes no column
+///
+/// Arguments:
+///
+/// * `markup` - the markup
+pub fn width(markup : &str) -> usize {
+    let mut joined = false;
+    strip(markup).chars().map(|c| {
+        let width = if joined { 0 } else { char_width(c) };
+        joined = c == '\u{200d}';
+        width
+    }).sum()
+}
+
+/// Cut Pango markup wider than `max_width` columns and end it with `…`, the markup tags left open are closed
+///
+/// The end of the text is cut, never inside a tag, an entity or a character with its combining marks
+///
+/// Arguments:
+///
+/// * `markup` - the markup
+/// * `max_width` - the maximum number of columns, 0 to never cut
+pub fn truncate(markup : &str, max_width : usize) -> String {
+    if max_width == 0 || width(markup) <= max_width {
+        return markup.to_string();
+    }
+    // A column is left for the ellipsis
+    let budget = max_width - 1;
+    let mut text = String::with_capacity(markup.len());
+    let mut open_tags = Vec::new();
+    let mut used = 0;
+    let mut joined = false;
+    let mut rest = markup;
+    while let Some(c) = rest.chars().next() {
+        let end = match c {
+            '<' => rest.find('>').map_or(rest.len(), |i| i + 1),
+            // An entity like `&amp;` is a single character
+            '&' => rest.find(';').filter(|i| rest[1..*i].chars().all(|c| c.is_ascii_alphanumeric() || c == '#')).map_or(1, |i| i + 1),
+            c => c.len_utf8()
+        };
+        let (token, after) = rest.split_at(end);
+        if c == '<' {
+            match token.strip_prefix("</") {
+                Some(_) => { open_tags.pop(); },
+                None if !token.ends_with("/>") => open_tags.push(token[1..].split(|c : char| c.is_whitespace() || c == '>').next().unwrap_or("").to_string()),
+                None => ()
+            }
+        } else {
+            let width = if joined { 0 } else if c == '&' { 1 } else { char_width(c) };
+            if used + width > budget {
+                break;
+            }
+            used += width;
+            joined = c == '\u{200d}';
+        }
+        text.push_str(token);
+        rest = after;
+    }
+    text.push('…');
+    for tag in open_tags.iter().rev() {
+        text.push_str(&format!("</{}>", tag));
+    }
+    text
+}
+
+#[cfg(test)]
+mod markup_tests {
+    use super::*;
+
+    #[test]
+    fn escape_test() {
+        assert_eq!(escape("a < b && c > d"), "a &lt; b &amp;&amp; c &gt; d");
+        assert_eq!(escape("\"quoted\" 'text'"), "&quot;quoted&quot; &apos;text&apos;");
+        assert_eq!(escape("plain"), "plain");
+    }
+
+    #[test]
+    fn strip_test() {
+        assert_eq!(strip("<b>(A)</b> <span foreground=\"red\">2024-05-09</span> : fix &lt;b&gt; &amp;amp; co"), "(A) 2024-05-09 : fix <b> &amp; co");
+        assert_eq!(strip(&escape("\"it's\" <b>")), "\"it's\" <b>");
+        assert_eq!(strip("plain"), "plain");
+    }
+
+    #[test]
+    fn colored_test() {
+        assert_eq!(colored("text", "red"), "<span foreground=\"red\">text</span>");
+        assert_eq!(colored("text", "#\"><b>"), "<span foreground=\"#&quot;&gt;&lt;b&gt;\">text</span>");
+    }
+
+    #[test]
+    fn tag_colors() {
+        let mut colors = MarkupColors::default();
+        colors.projects.insert(String::from("work"), String::from("#123456"));
+        colors.contexts.insert(String::from("home"), String::from("green"));
+        assert_eq!(colors.tags("call +work @home <now> + @"),
+                   "call <span foreground=\"#123456\">+work</span> <span foreground=\"green\">@home</span> &lt;now&gt; + @");
+        // The other tags get a color of the palette, the same at each run
+        assert_eq!(colors.project_color("garden"), palette_color("garden"));
+        assert_eq!(palette_color("garden"), "#33c7de");
+        assert_eq!(colors.tags("fix +a&b"), format!("fix <span foreground=\"{}\">+a&amp;b</span>", palette_color("a&b")));
+
+        assert_eq!(colors.priority_color('A'), None);
+        colors.priority = Some(String::from("yellow"));
+        colors.priorities.insert('B', String::from("blue"));
+        assert_eq!(colors.priority_color('A'), Some("yellow"));
+        assert_eq!(colors.priority_color('B'), Some("blue"));
+        assert_eq!(colors.priority_color('C'), None);
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(width("plain"), 5);
+        assert_eq!(width("<b>(A)</b> fix &lt;b&gt;"), 11);
+        // An emoji takes two columns, a combining accent none
+        assert_eq!(width("🚀 launch"), 9);
+        assert_eq!(width("cafe\u{301}"), 4);
+        assert_eq!(width("👩‍💻 code"), 7);
+        assert_eq!(width("日本"), 4);
+    }
+
+    #[test]
+    fn truncate_test() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("a long task line", 0), "a long task line");
+        assert_eq!(truncate("a long task line", 8), "a long …");
+        // The tags are closed, the entities kept whole
+        assert_eq!(truncate("<b>(A)</b> <span foreground=\"red\">fix &lt;b&gt; &amp; co</span>", 10), "<b>(A)</b> <span foreground=\"red\">fix &lt;…</span>");
+        // Never in the middle of a character with its accent, nor of a joined emoji
+        assert_eq!(truncate("cafe\u{301} au lait", 5), "cafe\u{301}…");
+        assert_eq!(truncate("👩‍💻👩‍💻 code", 4), "👩‍💻…");
+        assert_eq!(truncate("🚀🚀🚀", 4), "🚀…");
+        assert_eq!(truncate("日本語のタスク", 7), "日本語…");
+        assert!(width(&truncate("日本語のタスク", 6)) <= 6);
+    }
+
+    #[test]
+    fn dimmed_test() {
+        assert_eq!(dimmed("<b>text</b>"), "<span alpha=\"50%\"><b>text</b></span>");
+        assert_eq!(strip(&dimmed("text")), "text");
+    }
+}
@@ -0,0 +1,292 @@
+use std::io;
+use std::path::Path;
+use crate::storage::{self, save_atomic, Cipher};
+use crate::todofile::FileFormat;
+
+/// A line changed both in the task list and in the file since it was loaded
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    /// The line as it was loaded
+    pub base : String,
+    /// The line in the task list, `None` if it was removed
+    pub mine : Option<String>,
+    /// The line in the file, `None` if it was removed
+    pub theirs : Option<String>
+}
+
+/// How to resolve a `Conflict`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    /// Keep the version of RofiTodo
+    KeepMine,
+    /// Keep the version of the task file
+    KeepTheirs
+}
+
+/// What happened to a loaded line in the file
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Fate {
+    /// Unchanged, at this index of the file
+    Same(usize),
+    /// Replaced by the line at this index of the file
+    Changed(usize),
+    /// Removed from the file
+    Removed
+}
+
+/// Find what happened to each line of `base` in `theirs`
+///
+/// The lines in common are found with a longest common subsequence, the other lines between two common lines
+/// are paired in order as changed lines
+fn fates(base : &[String], theirs : &[String]) -> Vec<Fate> {
+    let (n, m) = (base.len(), theirs.len());
+    // lengths[i][j] is the length of the longest common subsequence of base[i..] and theirs[j..]
+    let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if base[i] == theirs[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut fates = vec![Fate::Removed; n];
+    let (mut i, mut j) = (0, 0);
+    let (mut hunk_base, mut hunk_theirs) = (0, 0);
+    while i < n && j < m {
+        if base[i] == theirs[j] {
+            // Pairing the changed lines of the hunk before this common line
+            for (k, fate) in fates[hunk_base..i].iter_mut().enumerate() {
+                if hunk_theirs + k < j {
+                    *fate = Fate::Changed(hunk_theirs + k);
+                }
+            }
+            fates[i] = Fate::Same(j);
+            i += 1;
+            j += 1;
+            hunk_base = i;
+            hunk_theirs = j;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    // Lines left at the end of both files
+    for (k, fate) in fates[hunk_base..].iter_mut().enumerate() {
+        if hunk_theirs + k < m {
+            *fate = Fate::Changed(hunk_theirs + k);
+        }
+    }
+    fates
+}
+
+/// Return the index in `theirs` of each line of `base`, unchanged or changed, `None` for a removed line
+///
+/// Arguments:
+///
+/// * `base` - the lines of the file when it was loaded
+/// * `theirs` - the lines of the file now
+pub(crate) fn line_map(base : &[String], theirs : &[String]) -> Vec<Option<usize>> {
+    fates(base, theirs).into_iter().map(|fate| match fate {
+        Fate::Same(j) | Fate::Changed(j) => Some(j),
+        Fate::Removed => None
+    }).collect()
+}
+
+/// Merge the task list with the file modified since it was loaded
+///
+/// The lines added to the file are kept, the lines changed or removed in the task list replace their loaded line,
+/// and the lines changed differently or changed on a side and removed on the other are resolved by `resolve`.
+/// The lines follow the order of the file, the new lines of the task list are added at the end
+///
+/// Arguments:
+///
+/// * `base` - the lines of the file when it was loaded
+/// * `theirs` - the lines of the file now
+/// * `mine` - the lines of the task list with the index of the loaded line they come from
+/// * `resolve` - a closure choosing the line to keep for each conflict
+pub fn merge(base : &[String], theirs : &[String], mine : &[(Option<usize>, String)], mut resolve : impl FnMut(&Conflict) -> Resolution) -> Vec<String> {
+    let fates = fates(base, theirs);
+    let mut mine_by_base : Vec<Option<&String>> = vec![None; base.len()];
+    let mut new_lines = Vec::new();
+    for (source, line) in mine {
+        match source {
+            Some(i) if *i < base.len() && mine_by_base[*i].is_none() => mine_by_base[*i] = Some(line),
+            _ => new_lines.push(line)
+        }
+    }
+
+    // The merged line of each loaded line
+    let mut merged : Vec<Option<String>> = Vec::with_capacity(base.len());
+    for (i, fate) in fates.iter().enumerate() {
+        let mine = mine_by_base[i].cloned();
+        let theirs = match fate {
+            Fate::Same(j) | Fate::Changed(j) => Some(theirs[*j].clone()),
+            Fate::Removed => None
+        };
+        let line = if mine.as_ref() == Some(&base[i]) {
+            theirs
+        } else if matches!(fate, Fate::Same(_)) || mine == theirs {
+            mine
+        } else {
+            let conflict = Conflict { base : base[i].clone(), mine : mine.clone(), theirs : theirs.clone() };
+            match resolve(&conflict) {
+                Resolution::KeepMine => mine,
+                Resolution::KeepTheirs => theirs
+            }
+        };
+        merged.push(line);
+    }
+
+    let mut lines = Vec::new();
+    let mut base_of_theirs = vec![None; theirs.len()];
+    for (i, fate) in fates.iter().enumerate() {
+        if let Fate::Same(j) | Fate::Changed(j) = fate {
+            base_of_theirs[*j] = Some(i);
+        }
+    }
+    for (j, base_index) in base_of_theirs.iter().enumerate() {
+        match base_index {
+            Some(i) => lines.extend(merged[*i].take()),
+            None => lines.push(theirs[j].clone())
+        }
+    }
+    // Lines removed from the file but kept from the task list
+    for (i, fate) in fates.iter().enumerate() {
+        if *fate == Fate::Removed {
+            lines.extend(merged[i].take());
+        }
+    }
+    lines.extend(new_lines.into_iter().cloned());
+    lines
+}
+
+/// Save the task list, merging it with the file if it was modified since it was loaded
+///
+/// Return the saved lines
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+/// * `base` - the lines of the file when it was loaded
+/// * `mine` - the lines of the task list with the index of the loaded line they come from
+/// * `format` - the format of the file when it was loaded, the format of the file now is kept if it changed
+/// * `cipher` - the encryption of the file if its name ends in `.gpg`
+/// * `resolve` - a closure choosing the line to keep for each conflict
+pub fn save_merging(path : &Path, base : &[String], mine : &[(Option<usize>, String)], format : &FileFormat, cipher : Option<&dyn Cipher>, resolve : impl FnMut(&Conflict) -> Resolution) -> io::Result<Vec<String>> {
+    let mut format = *format;
+    let theirs = match storage::read_to_string(path, cipher) {
+        Ok(content) => {
+            let (lines, file_format) = FileFormat::split(&content);
+            // A file with no lines anymore doesn't tell its format
+            if !lines.is_empty() {
+                format = file_format;
+            }
+            Some(lines)
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e)
+    };
+    let lines = match theirs {
+        Some(theirs) if theirs != base => merge(base, &theirs, mine, resolve),
+        _ => mine.iter().map(|(_, line)| line.clone()).collect()
+    };
+    save_atomic(path, &format.join(lines.iter().map(|l| l.as_str())), cipher)?;
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    fn lines(text : &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    fn unchanged(base : &[String]) -> Vec<(Option<usize>, String)> {
+        base.iter().enumerate().map(|(i, l)| (Some(i), l.clone())).collect()
+    }
+
+    fn no_conflict(conflict : &Conflict) -> Resolution {
+        panic!("unexpected conflict {:?}", conflict)
+    }
+
+    #[test]
+    fn fates_test() {
+        let base = lines("a\nb\nc\nd");
+        assert_eq!(fates(&base, &base), vec![Fate::Same(0), Fate::Same(1), Fate::Same(2), Fate::Same(3)]);
+        assert_eq!(fates(&base, &lines("a\nB\nc\nd\ne")), vec![Fate::Same(0), Fate::Changed(1), Fate::Same(2), Fate::Same(3)]);
+        assert_eq!(fates(&base, &lines("a\nd")), vec![Fate::Same(0), Fate::Removed, Fate::Removed, Fate::Same(1)]);
+        assert_eq!(fates(&base, &lines("x\na\nb\nc\nD")), vec![Fate::Same(1), Fate::Same(2), Fate::Same(3), Fate::Changed(4)]);
+        assert_eq!(fates(&base, &[]), vec![Fate::Removed; 4]);
+    }
+
+    #[test]
+    fn external_additions_are_kept() {
+        let base = lines("a\nb");
+        let theirs = lines("a\nb\nc");
+        let mut mine = unchanged(&base);
+        mine.push((None, String::from("d")));
+        assert_eq!(merge(&base, &theirs, &mine, no_conflict), lines("a\nb\nc\nd"));
+    }
+
+    #[test]
+    fn my_changes_override() {
+        let base = lines("a\nb\nc");
+        let theirs = lines("a\nb\nc\nexternal");
+        let mine = vec![(Some(0), String::from("x a")), (Some(2), String::from("c"))];
+        assert_eq!(merge(&base, &theirs, &mine, no_conflict), lines("x a\nc\nexternal"));
+    }
+
+    #[test]
+    fn their_changes_are_kept() {
+        let base = lines("a\nb\nc");
+        let theirs = lines("a\nB\n");
+        assert_eq!(merge(&base, &theirs, &unchanged(&base), no_conflict), lines("a\nB"));
+    }
+
+    #[test]
+    fn conflicts() {
+        let base = lines("a\nb\nc");
+        let theirs = lines("a\nb theirs");
+        let mine = vec![(Some(0), String::from("a")), (Some(1), String::from("b mine")), (Some(2), String::from("c mine"))];
+        let mut conflicts = vec![];
+        let merged = merge(&base, &theirs, &mine, |c| {
+            conflicts.push(c.clone());
+            if c.theirs.is_some() { Resolution::KeepTheirs } else { Resolution::KeepMine }
+        });
+        assert_eq!(merged, lines("a\nb theirs\nc mine"));
+        assert_eq!(conflicts, vec![
+            Conflict { base : String::from("b"), mine : Some(String::from("b mine")), theirs : Some(String::from("b theirs")) },
+            Conflict { base : String::from("c"), mine : Some(String::from("c mine")), theirs : None }
+        ]);
+        // The same change on both sides is not a conflict
+        assert_eq!(merge(&base, &lines("a\nb mine\nc"), &mine[..2], no_conflict), lines("a\nb mine"));
+    }
+
+    #[test]
+    fn file_changed_before_save() {
+        let path = temp_path("merge.txt");
+        std::fs::write(&path, "a\nb\n").unwrap();
+        let base = lines(&std::fs::read_to_string(&path).unwrap());
+        let mine = vec![(Some(0), String::from("x a")), (Some(1), String::from("b")), (None, String::from("c"))];
+
+        // Not modified: the task list is saved as it is
+        assert_eq!(save_merging(&path, &base, &mine, &FileFormat::default(), None, no_conflict).unwrap(), lines("x a\nb\nc"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x a\nb\nc\n");
+
+        // Modified by another program between the load and the save
+        std::fs::write(&path, "a\nb\nexternal\n").unwrap();
+        save_merging(&path, &base, &mine, &FileFormat::default(), None, no_conflict).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x a\nb\nexternal\nc\n");
+
+        // A conflict resolved with the line of the file
+        std::fs::write(&path, "a edited\nb\n").unwrap();
+        save_merging(&path, &base, &mine, &FileFormat::default(), None, |_| Resolution::KeepTheirs).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a edited\nb\nc\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
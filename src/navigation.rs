@@ -0,0 +1,103 @@
+use crate::TaskId;
+
+/// A menu of the interactive mode, the open menus are kept in a stack whose last menu is shown
+#[derive(Clone, Debug, PartialEq)]
+pub enum Screen {
+    /// The tasks needing attention today
+    Today,
+    /// All the tasks, with the entries of the other menus
+    Main,
+    /// The most urgent task alone
+    Focus,
+    /// The stale tasks one by one, the oldest first
+    Stale,
+    /// The actions of a task, or of a completed task
+    Task(TaskId),
+    /// The input of a new task
+    Add,
+    /// A new task read from the clipboard
+    Paste,
+    /// The search of the listed tasks
+    Search,
+    /// The tasks of the next seven days
+    Week,
+    /// The actions on several tasks
+    Bulk,
+    /// The completed tasks and the archive
+    Done,
+    /// The tasks completed today
+    Recent,
+    /// The deleted tasks
+    Trash,
+    /// The project or context tags, with the prefix of their indexes (`project_` or `context_`)
+    Tags(String),
+    /// The project and context tags of the filter
+    FilterByTag,
+    /// The criteria of the filter
+    Filter,
+    /// The sort order
+    Sort,
+    /// The saved views
+    Views,
+    /// The statistics of the tasks
+    Statistics,
+    /// The other task lists
+    SwitchList,
+    /// The backups of the task file
+    Backups,
+    /// The confirmation of the sort of the task file
+    SortFile,
+    /// The actions of the tasks of a project, `None` for the tasks without project
+    Project(Option<String>)
+}
+
+/// Where to go when a menu is closed
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transition {
+    /// Show another menu over the current one, or go back to it if it is already open
+    Open(Screen),
+    /// Go back to the previous menu, closing the first menu quits
+    Back,
+    /// Close all the menus
+    Quit
+}
+
+/// Return the stack of the open menus after a transition, RofiTodo quits when it is empty
+///
+/// Arguments:
+///
+/// * `stack` - the open menus, the shown menu last
+/// * `transition` - the transition asked by the shown menu
+pub fn navigate(mut stack : Vec<Screen>, transition : Transition) -> Vec<Screen> {
+    match transition {
+        // A menu is never open twice, going to the today menu from the main menu it was opened from goes back to it
+        Transition::Open(screen) => match stack.iter().position(|open| *open == screen) {
+            Some(index) => stack.truncate(index + 1),
+            None => stack.push(screen)
+        },
+        Transition::Back => {
+            stack.pop();
+        },
+        Transition::Quit => stack.clear()
+    }
+    stack
+}
+
+#[cfg(test)]
+mod navigation_tests {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        let stack = navigate(vec![Screen::Today], Transition::Open(Screen::Main));
+        assert_eq!(stack, vec![Screen::Today, Screen::Main]);
+        let stack = navigate(stack, Transition::Open(Screen::Filter));
+        assert_eq!(navigate(stack.clone(), Transition::Back), vec![Screen::Today, Screen::Main]);
+        // An open menu is shown again instead of being opened twice
+        assert_eq!(navigate(stack.clone(), Transition::Open(Screen::Today)), vec![Screen::Today]);
+        assert_eq!(navigate(stack, Transition::Quit), vec![]);
+        // Going back from the first menu quits
+        assert_eq!(navigate(vec![Screen::Main], Transition::Back), vec![]);
+        assert_eq!(navigate(vec![], Transition::Back), vec![]);
+    }
+}
@@ -0,0 +1,168 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use crate::task::Task;
+use crate::storage;
+
+/// The custom tag holding the file name of the note of a task
+pub const NOTE_TAG : &str = "note";
+
+/// Return the directory of the notes of a task list, `notes` next to the list
+///
+/// Arguments:
+///
+/// * `todo_file` - the path of the task list
+pub fn notes_dir(todo_file : &Path) -> PathBuf {
+    todo_file.with_file_name("notes")
+}
+
+/// Return the path of a note, rejecting the names which are not a plain file name of the notes directory
+///
+/// Arguments:
+///
+/// * `todo_file` - the path of the task list
+/// * `name` - the file name of the note, as in the `note:` tag
+pub fn note_path(todo_file : &Path, name : &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(format!("\"{}\" is not a valid note name", name));
+    }
+    Ok(notes_dir(todo_file).join(name))
+}
+
+/// Make a file name from the content of a task: the lowercase words joined by `-`
+///
+/// Arguments:
+///
+/// * `text` - the content of the task
+pub fn slug(text : &str) -> String {
+    let words = text.split(|c : char| !c.is_alphanumeric())
+                    .filter(|w| !w.is_empty())
+                    .map(|w| w.to_lowercase())
+                    .collect::<Vec<_>>();
+    let mut slug = String::new();
+    for word in words {
+        // Long contents are cut between two words
+        if !slug.is_empty() && slug.len() + word.len() >= 40 {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word);
+    }
+    if slug.is_empty() { String::from("note") } else { slug }
+}
+
+/// Return the path of the note of a task, creating the file and the notes directory if they don't exist
+///
+/// A task without a `note:` tag gets a new file named after its content, the name to store in the tag is returned
+/// with the path
+///
+/// Arguments:
+///
+/// * `todo_file` - the path of the task list
+/// * `task` - the task
+pub fn prepare(todo_file : &Path, task : &Task) -> Result<(String, PathBuf), String> {
+    if storage::dry_run() {
+        return Err(String::from("The notes can't be created with --dry-run"));
+    }
+    fs::create_dir_all(notes_dir(todo_file)).map_err(|e| e.to_string())?;
+    if let Some(name) = task.get_custom_tag(NOTE_TAG) {
+        let path = note_path(todo_file, name)?;
+        if !path.exists() {
+            fs::write(&path, format!("# {}\n", task.get_content())).map_err(|e| e.to_string())?;
+        }
+        return Ok((name.to_string(), path));
+    }
+    let slug = slug(task.get_content());
+    for i in 1.. {
+        let name = if i == 1 { format!("{}.md", slug) } else { format!("{}-{}.md", slug, i) };
+        let path = note_path(todo_file, &name)?;
+        // The tasks with the same content have their own note
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(format!("# {}\n", task.get_content()).as_bytes()).map_err(|e| e.to_string())?;
+                return Ok((name, path));
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.to_string())
+        }
+    }
+    unreachable!()
+}
+
+/// Open a note without waiting for it to be closed
+///
+/// The note is opened with `$EDITOR` in a terminal (`$TERMINAL`, `x-terminal-emulator` otherwise) when it is set,
+/// with `xdg-open` otherwise
+///
+/// Arguments:
+///
+/// * `path` - the path of the note
+pub fn open(path : &Path) -> Result<(), String> {
+    let editor = std::env::var("EDITOR").ok().filter(|e| !e.trim().is_empty());
+    let mut command = match editor {
+        Some(editor) => {
+            let terminal = std::env::var("TERMINAL").ok().filter(|t| !t.trim().is_empty()).unwrap_or_else(|| String::from("x-terminal-emulator"));
+            let mut command = Command::new(terminal.trim());
+            command.arg("-e").args(editor.split_whitespace());
+            command
+        },
+        None => Command::new("xdg-open")
+    };
+    command.arg(path)
+           .stdin(Stdio::null())
+           .stdout(Stdio::null())
+           .stderr(Stdio::null())
+           .spawn()
+           .map(|_| ())
+           .map_err(|e| format!("Can't open {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod notes_tests {
+    use super::*;
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slug("Call Mom about the +family trip!"), "call-mom-about-the-family-trip");
+        assert_eq!(slug("été à Paris"), "été-à-paris");
+        assert_eq!(slug("../../"), "note");
+        assert_eq!(slug("write the annual report for the board meeting next month"), "write-the-annual-report-for-the-board");
+    }
+
+    #[test]
+    fn traversal_is_rejected() {
+        let todo = Path::new("/home/me/todo.txt");
+        assert_eq!(note_path(todo, "trip.md").unwrap(), PathBuf::from("/home/me/notes/trip.md"));
+        assert!(note_path(todo, "../../etc/passwd").is_err());
+        assert!(note_path(todo, "..").is_err());
+        assert!(note_path(todo, "sub\\file").is_err());
+        assert!(note_path(todo, "").is_err());
+    }
+
+    #[test]
+    fn create_notes() {
+        let dir = std::env::temp_dir().join(format!("rofitodo_notes_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let todo = dir.join("todo.txt");
+        let task = "plan trip +family".parse::<Task>().unwrap();
+        // The notes directory doesn't exist yet
+        let (name, path) = prepare(&todo, &task).unwrap();
+        assert_eq!(name, "plan-trip-family.md");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# plan trip +family\n");
+        assert_eq!(prepare(&todo, &task).unwrap().0, "plan-trip-family-2.md");
+
+        // An existing note is kept
+        fs::write(&path, "my notes").unwrap();
+        let mut noted = task.clone();
+        noted.set_custom_tag(NOTE_TAG, &name).unwrap();
+        assert_eq!(prepare(&todo, &noted).unwrap(), (name, path.clone()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "my notes");
+
+        noted.set_custom_tag(NOTE_TAG, "..").unwrap();
+        assert!(prepare(&todo, &noted).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::{Duration, NaiveDate};
+use std::process::{Command, Stdio};
+use crate::task::Task;
+
+/// The maximum number of tasks listed in the body of a notification
+const MAX_TASKS : usize = 8;
+/// The maximum number of characters of a task in the body of a notification
+const MAX_TASK_LEN : usize = 60;
+
+/// Send desktop notifications
+pub trait Notifier {
+    /// Send a notification
+    ///
+    /// Arguments:
+    ///
+    /// * `summary` - the title of the notification
+    /// * `body` - the text of the notification
+    fn notify(&self, summary : &str, body : &str) -> Result<(), String>;
+}
+
+/// Send the notifications with `notify-send`, the libnotify command
+pub struct NotifySend;
+
+impl Notifier for NotifySend {
+    fn notify(&self, summary : &str, body : &str) -> Result<(), String> {
+        let status = Command::new("notify-send")
+                        .arg("--app-name=RofiTodo")
+                        .arg(summary)
+                        .arg(body)
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .status()
+                        .map_err(|e| format!("Can't run notify-send: {}", e))?;
+        if status.success() { Ok(()) } else { Err(format!("notify-send failed with the status {}", status)) }
+    }
+}
+
+/// Cut a text to `MAX_TASK_LEN` characters
+fn truncate(text : &str) -> String {
+    if text.chars().count() <= MAX_TASK_LEN {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(MAX_TASK_LEN - 1).collect::<String>())
+    }
+}
+
+/// Return the summary and the body of the notification of the tasks overdue or due soon, `None` if there are none
+///
+/// The completed and hidden tasks are not notified
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks
+/// * `today` - the current date
+/// * `days` - the number of days after today the tasks due are notified too (1 for the tasks due tomorrow)
+pub fn due_notification<'a>(tasks : impl IntoIterator<Item=&'a Task>, today : NaiveDate, days : u32) -> Option<(String, String)> {
+    let limit = today + Duration::days(days as i64);
+    let mut due = tasks.into_iter()
+                       .filter(|t| !t.completion && !t.is_hidden())
+                       .filter_map(|t| (*t.get_due()).filter(|d| *d <= limit).map(|d| (d, t)))
+                       .collect::<Vec<_>>();
+    if due.is_empty() {
+        return None;
+    }
+    due.sort_by_key(|(date, _)| *date);
+    let overdue = due.iter().filter(|(d, _)| *d < today).count();
+    let for_today = due.iter().filter(|(d, _)| *d == today).count();
+    let soon = due.len() - overdue - for_today;
+    let plural = |count : usize| if count == 1 { "task" } else { "tasks" };
+    let mut counts = vec![];
+    if overdue > 0 {
+        counts.push(format!("{} {} overdue", overdue, plural(overdue)));
+    }
+    if for_today > 0 {
+        counts.push(format!("{} {} due today", for_today, plural(for_today)));
+    }
+    if soon > 0 {
+        let when = if days == 1 { String::from("tomorrow") } else { format!("in the next {} days", days) };
+        counts.push(format!("{} {} due {}", soon, plural(soon), when));
+    }
+    let mut lines = due.iter().take(MAX_TASKS).map(|(_, t)| truncate(t.get_content())).collect::<Vec<_>>();
+    if due.len() > MAX_TASKS {
+        lines.push(format!("and {} more", due.len() - MAX_TASKS));
+    }
+    Some((counts.join(", "), lines.join("\n")))
+}
+
+/// Notify the tasks overdue or due soon, nothing is sent if there are none
+///
+/// Return true if a notification was sent
+///
+/// Arguments:
+///
+/// * `notifier` - the way the notification is sent
+/// * `tasks` - the tasks
+/// * `today` - the current date
+/// * `days` - the number of days after today the tasks due are notified too
+pub fn notify_due<'a>(notifier : &impl Notifier, tasks : impl IntoIterator<Item=&'a Task>, today : NaiveDate, days : u32) -> Result<bool, String> {
+    match due_notification(tasks, today, days) {
+        Some((summary, body)) => notifier.notify(&summary, &body).map(|_| true),
+        None => Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod notify_tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Keep the notifications instead of sending them
+    #[derive(Default)]
+    struct MockNotifier {
+        sent : RefCell<Vec<(String, String)>>,
+        fail : bool
+    }
+
+    impl Notifier for MockNotifier {
+        fn notify(&self, summary : &str, body : &str) -> Result<(), String> {
+            if self.fail {
+                return Err(String::from("no notification daemon"));
+            }
+            self.sent.borrow_mut().push((summary.to_string(), body.to_string()));
+            Ok(())
+        }
+    }
+
+    fn tasks(lines : &[&str]) -> Vec<Task> {
+        lines.iter().map(|l| l.parse::<Task>().unwrap()).collect()
+    }
+
+    #[test]
+    fn notify_due_tasks() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let list = tasks(&["pay rent due:2024-05-10", "call mom due:2024-05-08", "water plants due:2024-05-09",
+                           "buy milk due:2024-05-11", "x 2024-05-01 old due:2024-05-01", "secret h:1 due:2024-05-01", "someday"]);
+        let notifier = MockNotifier::default();
+        assert_eq!(notify_due(&notifier, &list, today, 0), Ok(true));
+        assert_eq!(notifier.sent.borrow()[0], (String::from("2 tasks overdue, 1 task due today"), String::from("call mom\nwater plants\npay rent")));
+        notify_due(&notifier, &list, today, 1).unwrap();
+        assert_eq!(notifier.sent.borrow()[1].0, "2 tasks overdue, 1 task due today, 1 task due tomorrow");
+        notify_due(&notifier, &list, today, 7).unwrap();
+        assert_eq!(notifier.sent.borrow()[2].0, "2 tasks overdue, 1 task due today, 1 task due in the next 7 days");
+
+        // Nothing is sent without due tasks
+        let notifier = MockNotifier::default();
+        assert_eq!(notify_due(&notifier, &tasks(&["someday", "later due:2024-06-01"]), today, 1), Ok(false));
+        assert!(notifier.sent.borrow().is_empty());
+        let failing = MockNotifier { fail : true, ..Default::default() };
+        assert!(notify_due(&failing, &list, today, 0).is_err());
+    }
+
+    #[test]
+    fn long_bodies_are_truncated() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let long = format!("{} due:2024-05-10", "a".repeat(100));
+        let mut lines = vec![long.as_str()];
+        lines.extend(["b due:2024-05-10"; 9]);
+        let (summary, body) = due_notification(&tasks(&lines), today, 0).unwrap();
+        assert_eq!(summary, "10 tasks due today");
+        let body = body.lines().collect::<Vec<_>>();
+        assert_eq!(body.len(), MAX_TASKS + 1);
+        assert_eq!(body[0], format!("{}…", "a".repeat(59)));
+        assert_eq!(body[MAX_TASKS], "and 2 more");
+    }
+}
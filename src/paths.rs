@@ -0,0 +1,145 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The environment variable giving the task file
+pub const FILE_VARIABLE : &str = "ROFITODO_FILE";
+
+/// How the task file was chosen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileSource {
+    /// Given with `--file`
+    Flag,
+    /// Given with the `ROFITODO_FILE` environment variable
+    Environment,
+    /// The `todo` of the configuration file
+    ConfigFile,
+    /// `todo.txt` in the data directory, `$XDG_DATA_HOME/rofitodo`
+    DataDir,
+    /// `~/todo.txt`, used because it exists and the task file of the data directory doesn't
+    Home
+}
+
+impl fmt::Display for FileSource {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileSource::Flag => write!(f, "given with --file"),
+            FileSource::Environment => write!(f, "given with ${}", FILE_VARIABLE),
+            FileSource::ConfigFile => write!(f, "given in the configuration file"),
+            FileSource::DataDir => write!(f, "the task file of the data directory"),
+            FileSource::Home => write!(f, "found in the home directory")
+        }
+    }
+}
+
+/// The environment variables choosing the task file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Environment {
+    /// `$ROFITODO_FILE`
+    pub file : Option<OsString>,
+    /// `$XDG_DATA_HOME`
+    pub data_home : Option<OsString>,
+    /// The home directory
+    pub home : Option<PathBuf>
+}
+
+impl Environment {
+    /// Read the environment of the process
+    pub fn current() -> Self {
+        Environment {
+            file : std::env::var_os(FILE_VARIABLE),
+            data_home : std::env::var_os("XDG_DATA_HOME"),
+            home : home::home_dir()
+        }
+    }
+
+    /// Return the data directory of RofiTodo, `$XDG_DATA_HOME/rofitodo` or `~/.local/share/rofitodo`
+    ///
+    /// A relative `$XDG_DATA_HOME` is ignored, as the XDG specification requires
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        self.data_home.as_ref()
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| self.home.as_ref().map(|home| home.join(".local").join("share")))
+            .map(|dir| dir.join("rofitodo"))
+    }
+}
+
+/// Replace a leading `~/` by the home directory
+///
+/// Arguments:
+///
+/// * `path` - the path to expand
+/// * `home` - the home directory
+pub fn expand_tilde(path : &Path, home : Option<&Path>) -> PathBuf {
+    match (path.strip_prefix("~"), home) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf()
+    }
+}
+
+/// Choose the task files: the `--file` paths, else `$ROFITODO_FILE`, else the `todo` of the configuration file,
+/// else the task file of the data directory, unless only `~/todo.txt` exists
+///
+/// Return the paths and how they were chosen, `None` without a home directory to find a default task file
+///
+/// Arguments:
+///
+/// * `flags` - the paths given with `--file`
+/// * `configured` - the `todo` of the configuration file
+/// * `env` - the environment variables
+/// * `exists` - return true if a file exists
+pub fn todo_files(flags : &[PathBuf], configured : Option<&Path>, env : &Environment, exists : impl Fn(&Path) -> bool) -> Option<(Vec<PathBuf>, FileSource)> {
+    let home = env.home.as_deref();
+    if !flags.is_empty() {
+        return Some((flags.iter().map(|p| expand_tilde(p, home)).collect(), FileSource::Flag));
+    }
+    if let Some(file) = env.file.as_ref().filter(|f| !f.is_empty()) {
+        return Some((vec![expand_tilde(Path::new(file), home)], FileSource::Environment));
+    }
+    if let Some(file) = configured {
+        return Some((vec![file.to_path_buf()], FileSource::ConfigFile));
+    }
+    let data_file = env.data_dir().map(|dir| dir.join("todo.txt"));
+    let home_file = home.map(|home| home.join("todo.txt"));
+    match (data_file, home_file) {
+        (Some(data_file), Some(home_file)) if !exists(&data_file) && exists(&home_file) => Some((vec![home_file], FileSource::Home)),
+        (Some(data_file), _) => Some((vec![data_file], FileSource::DataDir)),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod paths_tests {
+    use super::*;
+
+    fn env(file : Option<&str>, data_home : Option<&str>) -> Environment {
+        Environment { file : file.map(OsString::from), data_home : data_home.map(OsString::from), home : Some(PathBuf::from("/home/me")) }
+    }
+
+    #[test]
+    fn precedence() {
+        let none = |_ : &Path| false;
+        let flags = vec![PathBuf::from("work.txt"), PathBuf::from("~/personal.txt")];
+        assert_eq!(todo_files(&flags, Some(Path::new("/c.txt")), &env(Some("/e.txt"), None), none),
+                   Some((vec![PathBuf::from("work.txt"), PathBuf::from("/home/me/personal.txt")], FileSource::Flag)));
+        assert_eq!(todo_files(&[], Some(Path::new("/c.txt")), &env(Some("~/e.txt"), None), none),
+                   Some((vec![PathBuf::from("/home/me/e.txt")], FileSource::Environment)));
+        assert_eq!(todo_files(&[], Some(Path::new("/c.txt")), &env(Some(""), None), none),
+                   Some((vec![PathBuf::from("/c.txt")], FileSource::ConfigFile)));
+        assert_eq!(todo_files(&[], None, &env(None, Some("/data")), none),
+                   Some((vec![PathBuf::from("/data/rofitodo/todo.txt")], FileSource::DataDir)));
+        assert_eq!(todo_files(&[], None, &Environment::default(), none), None);
+    }
+
+    #[test]
+    fn default_files() {
+        let home_only = |p : &Path| p == Path::new("/home/me/todo.txt");
+        assert_eq!(todo_files(&[], None, &env(None, None), home_only),
+                   Some((vec![PathBuf::from("/home/me/todo.txt")], FileSource::Home)));
+        // The task file of the data directory is used once it exists
+        let both = |p : &Path| home_only(p) || p == Path::new("/home/me/.local/share/rofitodo/todo.txt");
+        assert_eq!(todo_files(&[], None, &env(None, Some("relative/dir")), both),
+                   Some((vec![PathBuf::from("/home/me/.local/share/rofitodo/todo.txt")], FileSource::DataDir)));
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{Duration, Months, NaiveDate};
+use std::fmt;
+
+/// The unit of a recurrence interval
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecurrenceUnit {
+    /// `d`
+    Day,
+    /// `w`
+    Week,
+    /// `m`
+    Month,
+    /// `y`
+    Year
+}
+
+/// A recurrence interval, as found in a `rec:` tag (e.g. `rec:2w` or `rec:+1m`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Recurrence {
+    /// The number of units between two occurrences
+    pub amount : u32,
+    /// The unit of the interval
+    pub unit : RecurrenceUnit,
+    /// If `true` (`+` prefix), the next occurrence is computed from the due date instead of the completion date
+    pub strict : bool
+}
+
+impl Recurrence {
+    /// Parse the value of a `rec:` tag
+    ///
+    /// Returns `None` if the value is not a positive number followed by `d`, `w`, `m` or `y`,
+    /// optionally preceded by `+` for a strict recurrence
+    ///
+    /// Arguments:
+    ///
+    /// * `value` - the value of the tag, without the `rec:` key
+    pub fn parse(value : &str) -> Option<Self> {
+        let strict = value.starts_with('+');
+        let value = value.strip_prefix('+').unwrap_or(value);
+        let unit = match value.chars().last()? {
+            'd' => RecurrenceUnit::Day,
+            'w' => RecurrenceUnit::Week,
+            'm' => RecurrenceUnit::Month,
+            'y' => RecurrenceUnit::Year,
+            _ => return None
+        };
+        let digits = &value[..value.len()-1];
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let amount = digits.parse::<u32>().ok()?;
+        if amount == 0 {
+            return None;
+        }
+        Some(Recurrence { amount, unit, strict })
+    }
+
+    /// Return the date one interval after `date`
+    ///
+    /// Month and year intervals are clamped to the last day of the month (Jan 31 + 1m is Feb 28/29)
+    ///
+    /// Arguments:
+    ///
+    /// * `date` - the date to start from
+    pub fn advance(&self, date : NaiveDate) -> NaiveDate {
+        match self.unit {
+            RecurrenceUnit::Day => date + Duration::days(self.amount as i64),
+            RecurrenceUnit::Week => date + Duration::weeks(self.amount as i64),
+            RecurrenceUnit::Month => date + Months::new(self.amount),
+            RecurrenceUnit::Year => date + Months::new(self.amount * 12)
+        }
+    }
+}
+
+/// Format the recurrence as the value of a `rec:` tag
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self.unit {
+            RecurrenceUnit::Day => 'd',
+            RecurrenceUnit::Week => 'w',
+            RecurrenceUnit::Month => 'm',
+            RecurrenceUnit::Year => 'y'
+        };
+        write!(f, "{}{}{}", if self.strict {"+"} else {""}, self.amount, unit)
+    }
+}
+
+#[cfg(test)]
+mod recurrence_tests {
+    use super::*;
+
+    #[test]
+    fn parse_units() {
+        assert_eq!(Recurrence::parse("3d"), Some(Recurrence { amount : 3, unit : RecurrenceUnit::Day, strict : false }));
+        assert_eq!(Recurrence::parse("2w"), Some(Recurrence { amount : 2, unit : RecurrenceUnit::Week, strict : false }));
+        assert_eq!(Recurrence::parse("1m"), Some(Recurrence { amount : 1, unit : RecurrenceUnit::Month, strict : false }));
+        assert_eq!(Recurrence::parse("10y"), Some(Recurrence { amount : 10, unit : RecurrenceUnit::Year, strict : false }));
+        assert_eq!(Recurrence::parse("2w").unwrap().to_string(), "2w");
+    }
+
+    #[test]
+    fn parse_strict() {
+        assert_eq!(Recurrence::parse("+1m"), Some(Recurrence { amount : 1, unit : RecurrenceUnit::Month, strict : true }));
+        assert_eq!(Recurrence::parse("+1m").unwrap().to_string(), "+1m");
+        assert_eq!(Recurrence::parse("+"), None);
+        assert_eq!(Recurrence::parse("++1m"), None);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(Recurrence::parse(""), None);
+        assert_eq!(Recurrence::parse("d"), None);
+        assert_eq!(Recurrence::parse("3x"), None);
+        assert_eq!(Recurrence::parse("0d"), None);
+        assert_eq!(Recurrence::parse("-1w"), None);
+        assert_eq!(Recurrence::parse("2é"), None);
+    }
+
+    #[test]
+    fn advance_dates() {
+        let date = NaiveDate::from_ymd_opt(2021, 1, 31).unwrap();
+        assert_eq!(Recurrence::parse("3d").unwrap().advance(date), NaiveDate::from_ymd_opt(2021, 2, 3).unwrap());
+        assert_eq!(Recurrence::parse("2w").unwrap().advance(date), NaiveDate::from_ymd_opt(2021, 2, 14).unwrap());
+        assert_eq!(Recurrence::parse("1m").unwrap().advance(date), NaiveDate::from_ymd_opt(2021, 2, 28).unwrap());
+        assert_eq!(Recurrence::parse("1y").unwrap().advance(date), NaiveDate::from_ymd_opt(2022, 1, 31).unwrap());
+        let leap = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(Recurrence::parse("1y").unwrap().advance(leap), NaiveDate::from_ymd_opt(2025, 2, 28).unwrap());
+    }
+}
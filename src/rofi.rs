@@ -1,31 +1,481 @@
 use std::process::Command;
 use std::process::Stdio;
-use std::io::Write;
+use std::io::{BufRead, Write};
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::markup;
+use crate::locale::Locale;
 
 pub struct RofiParams {
     pub no_config: bool,
-    pub case_insensitive: bool
+    pub case_insensitive: bool,
+    /// Skip the confirmations of the destructive actions
+    pub no_confirm: bool,
+    /// Show the entries as plain text even if the backend can show markup
+    pub no_color: bool,
+    /// The program showing the menus
+    pub backend: Rc<dyn MenuBackend>,
+    /// The language of the prompts and the entries of the menus
+    pub locale: Rc<Locale>,
+    /// The error which stopped the backend, the next menus are closed at once
+    pub failure: Rc<RefCell<Option<String>>>
 }
 
-pub struct Rofi {
-    rofi: Command,
+/// The options of a menu, each backend uses the ones it supports
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MenuOptions {
+    /// The prompt
+    pub prompt: Option<String>,
+    /// The message under the prompt, as plain text
+    pub message: Option<String>,
+    /// The placeholder of the input zone
+    pub placeholder: Option<String>,
+    /// The text already entered in the input zone
+    pub pretext: Option<String>,
+    /// The range of entries marked as active
+    pub active: Option<(usize, usize)>,
+    /// The entry selected when the menu opens
+    pub selected: Option<u32>,
+    /// The entries are Pango markup
+    pub markup_rows: bool,
+    /// Several entries can be selected
+    pub multi_select: bool,
+    /// Only the entries can be chosen, no typed text
+    pub no_custom: bool,
+    /// Only the input zone is shown, without the entries
+    pub text_only: bool,
+    /// The filter is case insensitive
+    pub case_insensitive: bool,
+    /// The configuration of the program is not loaded
+    pub no_config: bool,
+    /// The custom keys, choosing the highlighted entry with the index of the key
+    pub custom_keys: Vec<String>
 }
 
-impl Rofi {
-    /// Create a new Rofi instance
-    pub fn new() -> Self {
-        let mut r = Rofi { rofi : Command::new("rofi") };
-        r.rofi.arg("-dmenu");
-        r
+/// The features of the menus a backend can show, the menus of the other backends are simplified
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capabilities {
+    /// The entries can be Pango markup, they are shown as plain text otherwise
+    pub markup_rows: bool,
+    /// Several entries can be selected, only one otherwise
+    pub multi_select: bool,
+    /// A message is shown under the prompt, its first line is added to the prompt otherwise
+    pub message: bool
+}
+
+/// An entry chosen in a menu
+#[derive(Clone, Debug, PartialEq)]
+pub enum Choice {
+    /// The index of one of the entries
+    Index(usize),
+    /// A text which is not one of the entries
+    Text(String)
+}
+
+/// A program showing the menus
+pub trait MenuBackend {
+    /// Return what the menus of the program can show
+    fn capabilities(&self) -> Capabilities;
+
+    /// Show a menu and return the chosen entries, none if the menu was closed
+    /// 
+    /// Arguments:
+    /// 
+    /// * `entries` - the entries of the menu
+    /// * `options` - the options of the menu, simplified to the capabilities of the backend
+    fn select(&self, entries: &[String], options: &MenuOptions) -> Result<Vec<Choice>, String>;
+
+    /// Show a menu and return the index of the custom key which chose the entries, `None` for Return, with the
+    /// chosen entries
+    /// 
+    /// The backends without custom keys always return `None`
+    /// 
+    /// Arguments:
+    /// 
+    /// * `entries` - the entries of the menu
+    /// * `options` - the options of the menu, simplified to the capabilities of the backend
+    fn select_with_key(&self, entries: &[String], options: &MenuOptions) -> Result<(Option<usize>, Vec<Choice>), String> {
+        Ok((None, self.select(entries, options)?))
+    }
+}
+
+/// The default rofi bindings of the keys likely to be chosen as custom keys, a key can't have two bindings
+const DEFAULT_BINDINGS: [(&str, &str); 16] = [
+    ("kb-accept-custom", "Control+Return"), ("kb-accept-custom-alt", "Control+Shift+Return"), ("kb-accept-alt", "Shift+Return"),
+    ("kb-remove-char-forward", "Delete,Control+d"), ("kb-remove-char-back", "BackSpace,Shift+BackSpace,Control+h"),
+    ("kb-remove-to-eol", "Control+k"), ("kb-remove-to-sol", "Control+u"), ("kb-clear-line", "Control+w"),
+    ("kb-move-front", "Control+a"), ("kb-move-end", "Control+e"), ("kb-move-char-back", "Left,Control+b"),
+    ("kb-move-char-forward", "Right,Control+f"), ("kb-row-up", "Up,Control+p"), ("kb-row-down", "Down,Control+n"),
+    ("kb-row-select", "Control+space"), ("kb-cancel", "Escape,Control+g,Control+bracketleft")
+];
+
+/// The exit code of rofi for the first custom key, the next keys have the next codes
+const FIRST_CUSTOM_KEY_CODE: i32 = 10;
+
+/// The number of custom keys of rofi
+const CUSTOM_KEY_COUNT: usize = 19;
+
+/// The supported menu programs, all used in their dmenu mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Launcher {
+    Rofi,
+    Wofi,
+    Fuzzel,
+    Bemenu,
+    Dmenu
+}
+
+impl Launcher {
+    /// All the launchers
+    pub const ALL: [Launcher; 5] = [Launcher::Rofi, Launcher::Wofi, Launcher::Fuzzel, Launcher::Bemenu, Launcher::Dmenu];
+
+    /// Return the name of the program
+    pub fn name(&self) -> &'static str {
+        match self {
+            Launcher::Rofi => "rofi",
+            Launcher::Wofi => "wofi",
+            Launcher::Fuzzel => "fuzzel",
+            Launcher::Bemenu => "bemenu",
+            Launcher::Dmenu => "dmenu"
+        }
+    }
+
+    /// Return the launcher of a program name
+    /// 
+    /// Arguments:
+    /// 
+    /// * `name` - the name of the program
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|l| l.name() == name)
+    }
+
+    /// Choose the first installed launcher
+    /// 
+    /// Rofi is always preferred, then the launchers made for Wayland in a Wayland session
+    /// 
+    /// Arguments:
+    /// 
+    /// * `wayland` - true if running in a Wayland session
+    /// * `installed` - return true if a program is installed
+    pub fn detect(wayland: bool, installed: impl Fn(&str) -> bool) -> Option<Self> {
+        let order = if wayland {
+            [Launcher::Rofi, Launcher::Fuzzel, Launcher::Wofi, Launcher::Bemenu, Launcher::Dmenu]
+        } else {
+            [Launcher::Rofi, Launcher::Dmenu, Launcher::Bemenu, Launcher::Fuzzel, Launcher::Wofi]
+        };
+        order.iter().copied().find(|l| installed(l.name()))
+    }
+
+    /// Return the arguments of the program showing a menu
+    /// 
+    /// Arguments:
+    /// 
+    /// * `options` - the options of the menu
+    pub fn args(&self, options: &MenuOptions) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        let mut push = |values: &[&str]| args.extend(values.iter().map(|v| v.to_string()));
+        match self {
+            Launcher::Rofi => {
+                push(&["-dmenu"]);
+                if options.no_config {
+                    push(&["-no-config"]);
+                }
+                if options.case_insensitive {
+                    push(&["-i"]);
+                }
+                // The indexes tell apart the entries with the same text
+                if options.no_custom {
+                    push(&["-format", "i", "-no-custom"]);
+                } else {
+                    push(&["-format", "i f"]);
+                }
+                if options.multi_select {
+                    push(&["-multi-select"]);
+                }
+                if options.markup_rows {
+                    push(&["-markup-rows"]);
+                }
+                if let Some(message) = &options.message {
+                    // Pango markup is disabled because user tasks content is interpreted
+                    push(&["-theme-str", "textbox { markup: false; }", "-mesg", message]);
+                }
+                if let Some((start, end)) = options.active {
+                    push(&["-a", &format!("{}-{}", start, end)]);
+                }
+                if let Some(text) = &options.pretext {
+                    push(&["-filter", text]);
+                }
+                if let Some(prompt) = &options.prompt {
+                    push(&["-p", prompt]);
+                }
+                if let Some(index) = options.selected {
+                    push(&["-selected-row", &index.to_string()]);
+                }
+                if let Some(placeholder) = &options.placeholder {
+                    push(&["-theme-str", &format!("entry {{ placeholder: \"{}\"; }}", placeholder)]);
+                }
+                if options.text_only {
+                    push(&["-l", "0"]);
+                }
+                for (i, key) in options.custom_keys.iter().take(CUSTOM_KEY_COUNT).enumerate() {
+                    push(&[&format!("-kb-custom-{}", i + 1), key]);
+                }
+                // The custom keys are removed from the default bindings
+                for (name, keys) in DEFAULT_BINDINGS.iter() {
+                    let kept = keys.split(',').filter(|key| !options.custom_keys.iter().any(|custom| custom.eq_ignore_ascii_case(key))).collect::<Vec<_>>();
+                    if kept.len() < keys.split(',').count() {
+                        push(&[&format!("-{}", name), &kept.join(",")]);
+                    }
+                }
+            },
+            Launcher::Wofi => {
+                push(&["--dmenu"]);
+                if options.case_insensitive {
+                    push(&["--insensitive"]);
+                }
+                if options.markup_rows {
+                    push(&["--allow-markup"]);
+                }
+                if let Some(text) = &options.pretext {
+                    push(&["--search", text]);
+                }
+                if let Some(prompt) = &options.prompt {
+                    push(&["--prompt", prompt]);
+                }
+            },
+            Launcher::Fuzzel => {
+                push(&["--dmenu"]);
+                if let Some(prompt) = &options.prompt {
+                    push(&["--prompt", &format!("{} ", prompt)]);
+                }
+                if let Some(placeholder) = &options.placeholder {
+                    push(&["--placeholder", placeholder]);
+                }
+                if options.text_only {
+                    push(&["--lines", "0"]);
+                }
+            },
+            Launcher::Bemenu | Launcher::Dmenu => {
+                if options.case_insensitive {
+                    push(&["-i"]);
+                }
+                if let Some(prompt) = &options.prompt {
+                    push(&["-p", prompt]);
+                }
+                if !options.text_only {
+                    push(&["-l", "20"]);
+                }
+            }
+        }
+        args
+    }
+}
+
+impl MenuBackend for Launcher {
+    fn capabilities(&self) -> Capabilities {
+        match self {
+            Launcher::Rofi => Capabilities { markup_rows: true, multi_select: true, message: true },
+            Launcher::Wofi => Capabilities { markup_rows: true, multi_select: false, message: false },
+            _ => Capabilities { markup_rows: false, multi_select: false, message: false }
+        }
+    }
+
+    fn select(&self, entries: &[String], options: &MenuOptions) -> Result<Vec<Choice>, String> {
+        Ok(self.select_with_key(entries, options)?.1)
+    }
+
+    fn select_with_key(&self, entries: &[String], options: &MenuOptions) -> Result<(Option<usize>, Vec<Choice>), String> {
+        let mut proc = Command::new(self.name())
+            .args(self.args(options))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Can't run {} : {}", self.name(), e))?;
+
+        let entry_list = vec2str(entries);
+        proc.stdin.as_mut().unwrap().write_all(entry_list.as_bytes()).map_err(|e| format!("Can't write the menu to {} : {}", self.name(), e))?;
+
+        let output = proc.wait_with_output().map_err(|e| e.to_string())?;
+        let code = match output.status.code() {
+            Some(code) => code,
+            None => return Err(format!("{} was stopped by a signal", self.name()))
+        };
+        let key = match *self {
+            Launcher::Rofi => match exit_key(code, options.custom_keys.len()) {
+                Some(key) => key,
+                None => return Ok((None, vec![]))
+            },
+            _ => None
+        };
+        let mut retour = String::from_utf8_lossy(&output.stdout).to_string();
+        trim_newline(&mut retour);
+        let choices = if *self == Launcher::Rofi && options.no_custom {
+            // Rofi gives the indexes of the entries
+            parse_indexes(&retour, entries.len())?.into_iter().map(Choice::Index).collect()
+        } else if *self == Launcher::Rofi {
+            parse_choices(&retour, entries.len())?
+        } else {
+            choices_from_lines(&retour, entries)
+        };
+        Ok((key, choices))
     }
+}
+
+/// Return the launcher chosen with the `--backend` flag, `auto` for the first installed launcher
+/// 
+/// Fails with a message saying what to install when the launcher is not installed
+/// 
+/// Arguments:
+/// 
+/// * `name` - the name of the launcher, or `auto`
+/// * `wayland` - true if running in a Wayland session
+/// * `installed` - return true if a program is installed
+pub fn choose_launcher(name: &str, wayland: bool, installed: impl Fn(&str) -> bool) -> Result<Launcher, String> {
+    if name == "auto" {
+        return Launcher::detect(wayland, installed).ok_or_else(|| String::from(
+            "RofiTodo shows its menus with rofi, which is not installed.\n\
+             Install rofi, or one of wofi, fuzzel, bemenu or dmenu, or use --stdio to answer the menus in the terminal"));
+    }
+    match Launcher::from_name(name) {
+        Some(launcher) if installed(launcher.name()) => Ok(launcher),
+        Some(launcher) => Err(format!("{} is not installed, install it or choose another launcher with --backend", launcher.name())),
+        None => Err(format!("Unknown launcher '{}'", name))
+    }
+}
+
+/// Show the menus as text and read the choices line by line, to use RofiTodo without a display
+/// 
+/// An entry is chosen by its number or its text, another text is typed, an empty line or the end of the input closes the menu
+pub struct StdioBackend<R: BufRead, W: Write> {
+    input: RefCell<R>,
+    output: RefCell<W>
+}
+
+impl<R: BufRead, W: Write> StdioBackend<R, W> {
+    /// Create a backend reading the choices from `input` and writing the menus to `output`
+    pub fn new(input: R, output: W) -> Self {
+        StdioBackend { input: RefCell::new(input), output: RefCell::new(output) }
+    }
+
+    /// Read a line of the input, `None` at the end of the input
+    fn read_line(&self) -> Result<Option<String>, String> {
+        let mut line = String::new();
+        match self.input.borrow_mut().read_line(&mut line) {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                trim_newline(&mut line);
+                Ok(Some(line))
+            },
+            Err(e) => Err(format!("Can't read the choice : {}", e))
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> MenuBackend for StdioBackend<R, W> {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { markup_rows: false, multi_select: false, message: true }
+    }
+
+    fn select(&self, entries: &[String], options: &MenuOptions) -> Result<Vec<Choice>, String> {
+        let mut menu = String::new();
+        if let Some(message) = &options.message {
+            menu.push_str(message);
+            menu.push('\n');
+        }
+        for (i, entry) in entries.iter().enumerate() {
+            menu.push_str(&format!("{:>3} {}\n", i + 1, entry));
+        }
+        menu.push_str(options.prompt.as_deref().unwrap_or(""));
+        if let Some(text) = options.pretext.as_ref().filter(|t| !t.is_empty()) {
+            menu.push_str(&format!(" [{}]", text));
+        }
+        menu.push_str("> ");
+        loop {
+            {
+                let mut output = self.output.borrow_mut();
+                output.write_all(menu.as_bytes()).and_then(|_| output.flush()).map_err(|e| format!("Can't show the menu : {}", e))?;
+            }
+            let line = match self.read_line()? {
+                Some(line) if !line.is_empty() => line,
+                _ => return Ok(vec![])
+            };
+            let choice = match line.parse::<usize>() {
+                Ok(n) if n >= 1 && n <= entries.len() => Choice::Index(n - 1),
+                _ => choices_from_lines(&line, entries).remove(0)
+            };
+            match choice {
+                Choice::Text(text) if options.no_custom => {
+                    self.output.borrow_mut().write_all(format!("No entry '{}'\n", text).as_bytes()).map_err(|e| e.to_string())?;
+                },
+                choice => return Ok(vec![choice])
+            }
+        }
+    }
+}
+
+/// Return the choices of the lines printed by a menu program, a line equal to an entry is the choice of the entry
+/// 
+/// Arguments:
+/// 
+/// * `output` - the output of the program
+/// * `entries` - the entries of the menu
+fn choices_from_lines(output: &str, entries: &[String]) -> Vec<Choice> {
+    output.lines()
+          .filter(|l| !l.is_empty())
+          .map(|line| match entries.iter().position(|e| e == line) {
+              Some(index) => Choice::Index(index),
+              None => Choice::Text(line.to_string())
+          })
+          .collect()
+}
+
+/// Return how rofi was closed from its exit code : `Some(None)` for an entry chosen with Return, `Some(Some(index))`
+/// for an entry chosen with the custom key of the index, `None` when nothing was chosen
+/// 
+/// Escape and the custom keys without action choose nothing
+/// 
+/// Arguments:
+/// 
+/// * `code` - the exit code of rofi
+/// * `key_count` - the number of custom keys of the menu
+fn exit_key(code: i32, key_count: usize) -> Option<Option<usize>> {
+    match code {
+        0 => Some(None),
+        code if code >= FIRST_CUSTOM_KEY_CODE && ((code - FIRST_CUSTOM_KEY_CODE) as usize) < key_count.min(CUSTOM_KEY_COUNT) =>
+            Some(Some((code - FIRST_CUSTOM_KEY_CODE) as usize)),
+        _ => None
+    }
+}
+
+/// Return true if a program is in a directory of `$PATH`
+/// 
+/// Arguments:
+/// 
+/// * `program` - the name of the program
+pub fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
 
+pub struct Rofi {
+    backend: Rc<dyn MenuBackend>,
+    failure: Rc<RefCell<Option<String>>>,
+    /// The markup of the entries is removed
+    no_color: bool,
+    /// The language of the prompt and the entries
+    locale: Rc<Locale>,
+    options: MenuOptions
+}
+
+impl Rofi {
     /// Create a new Rofi instance from paramaters
     /// 
     /// Arguments:
     /// 
     /// * `p` - a reference to a `RofiParams` struct
     pub fn from(p : &RofiParams) -> Self {
-        let mut rofi = Self::new();
+        let mut rofi = Rofi { backend: p.backend.clone(), failure: p.failure.clone(), no_color: p.no_color, locale: p.locale.clone(), options: MenuOptions::default() };
         if p.no_config {
             rofi = rofi.no_config();
         }
@@ -35,25 +485,130 @@ impl Rofi {
         rofi
     }
 
+    /// Show the menu, closed at once if the backend failed before
+    /// 
+    /// A failure of the backend is kept in the `RofiParams` and the menu is closed,
+    /// so that the menus go back until RofiTodo exits and saves the changes
+    fn select(&self, entries: &[String]) -> Result<Vec<Choice>, String> {
+        Ok(self.select_with_key(entries)?.1)
+    }
+
+    /// Show the menu like `select` and return the index of the custom key which chose the entries with them
+    fn select_with_key(&self, entries: &[String]) -> Result<(Option<usize>, Vec<Choice>), String> {
+        if self.failure.borrow().is_some() {
+            return Ok((None, vec![]));
+        }
+        self.select_supported(entries).or_else(|e| {
+            *self.failure.borrow_mut() = Some(e);
+            Ok((None, vec![]))
+        })
+    }
+
+    /// Show the menu with the options the backend supports, the others are simplified
+    ///
+    /// The prompt and the entries are translated, the choices are the indexes of the entries so they are the same in
+    /// every language
+    fn select_supported(&self, entries: &[String]) -> Result<(Option<usize>, Vec<Choice>), String> {
+        let capabilities = self.backend.capabilities();
+        let mut options = self.options.clone();
+        options.prompt = options.prompt.map(|prompt| self.locale.prompt(&prompt).to_string());
+        let translated = entries.iter().map(|e| self.locale.entry(e).to_string()).collect::<Vec<_>>();
+        let plain;
+        let mut entries = &translated;
+        if options.markup_rows && (!capabilities.markup_rows || self.no_color) {
+            options.markup_rows = false;
+            plain = entries.iter().map(|e| markup::strip(e)).collect::<Vec<_>>();
+            entries = &plain;
+        }
+        options.multi_select &= capabilities.multi_select;
+        if !capabilities.message {
+            if let Some(message) = options.message.take() {
+                let first = message.lines().next().unwrap_or("").to_string();
+                options.prompt = Some(match options.prompt.take() {
+                    Some(prompt) if !first.is_empty() => format!("{} ({})", prompt, first),
+                    Some(prompt) => prompt,
+                    None => first
+                });
+            }
+        }
+        self.backend.select_with_key(entries, &options)
+    }
+
     /// Launch Rofi with a list of entries
     /// 
     /// Arguments:
     /// 
     /// * `entries` - a vector of `String` to display as options in Rofi
-    pub fn run(mut self, entries: Vec<String>) -> Result<String, String> {
-        let mut proc = self.rofi.stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()          
-            .ok()
-            .expect("failed to spawn rust");
+    pub fn run(self, entries: Vec<String>) -> Result<String, String> {
+        let choices = self.select(&entries)?;
+        Ok(choices.into_iter().map(|c| match c {
+            Choice::Index(i) => entries[i].clone(),
+            Choice::Text(text) => text
+        }).collect::<Vec<_>>().join("\n"))
+    }
 
+    /// Launch Rofi with a list of entries and return the index of the selected entry
+    /// 
+    /// Custom input is disabled, returns `None` if Rofi was closed
+    /// 
+    /// Arguments:
+    /// 
+    /// * `entries` - a vector of `String` to display as options in Rofi
+    pub fn run_index(mut self, entries: Vec<String>) -> Result<Option<usize>, String> {
+        self.options.no_custom = true;
+        Ok(self.select(&entries)?.into_iter().find_map(|c| match c {
+            Choice::Index(i) => Some(i),
+            Choice::Text(_) => None
+        }))
+    }
 
-        let entry_list = vec2str(entries);
-        proc.stdin.as_mut().unwrap().write_all(entry_list.as_bytes()).expect("Erreur avec stdin");
+    /// Launch Rofi with a list of entries and return the index of the custom key which chose an entry, `None` for
+    /// Return, with the selected entry or the typed text
+    /// 
+    /// The choice is `None` if Rofi was closed
+    /// 
+    /// Arguments:
+    /// 
+    /// * `entries` - a vector of `String` to display as options in Rofi
+    pub fn run_key(self, entries: Vec<String>) -> Result<(Option<usize>, Option<Choice>), String> {
+        let (key, choices) = self.select_with_key(&entries)?;
+        Ok((key, choices.into_iter().next()))
+    }
 
-        let mut retour = String::from_utf8(proc.wait_with_output().unwrap().stdout).unwrap();
-        trim_newline(&mut retour);
-        Ok(retour)
+    /// Launch Rofi with a list of entries and return the indexes of the selected entries
+    /// 
+    /// Custom input is disabled, returns no index if Rofi was closed.
+    /// Fails without returning any index if one of the returned lines is not the index of an entry
+    /// 
+    /// Arguments:
+    /// 
+    /// * `entries` - a vector of `String` to display as options in Rofi
+    pub fn run_indexes(mut self, entries: Vec<String>) -> Result<Vec<usize>, String> {
+        self.options.no_custom = true;
+        let mut indexes = Vec::new();
+        for choice in self.select(&entries)? {
+            match choice {
+                Choice::Index(i) => if !indexes.contains(&i) { indexes.push(i) },
+                Choice::Text(text) => return Err(format!("Unexpected selection '{}'", text))
+            }
+        }
+        Ok(indexes)
+    }
+
+    /// Allow selecting several entries with Shift+Enter
+    /// 
+    /// Equivalent to `-multi-select` Rofi flag
+    pub fn multi_select(mut self) -> Self {
+        self.options.multi_select = true;
+        self
+    }
+
+    /// Interpret the entries as Pango markup
+    /// 
+    /// Equivalent to `-markup-rows` Rofi flag
+    pub fn markup_rows(mut self) -> Self {
+        self.options.markup_rows = true;
+        self
     }
 
     /// Print a message under the prompt
@@ -64,7 +619,7 @@ impl Rofi {
     /// 
     /// * `m` - the `String` to display
     pub fn msg(mut self, m: String) -> Self {
-        self.rofi.arg("-theme-str").arg("textbox { markup: false; }").arg("-mesg").arg(m);
+        self.options.message = Some(m);
         self
     }
 
@@ -72,7 +627,7 @@ impl Rofi {
     /// 
     /// Equivalent to `-no-config` Rofi flag
     pub fn no_config(mut self) -> Self {
-        self.rofi.arg("-no-config");
+        self.options.no_config = true;
         self
     }
 
@@ -80,7 +635,7 @@ impl Rofi {
     /// 
     /// Use `-a` Rofi flag
     pub fn select_range(mut self, start: usize, end: usize) -> Self {
-        self.rofi.arg("-a").arg(format!("{}-{}", start, end));
+        self.options.active = Some((start, end));
         self
     }
 
@@ -90,13 +645,33 @@ impl Rofi {
     /// 
     /// * `text` - the `String` to input
     pub fn pretext(mut self, text: String) -> Self {
-        self.rofi.arg("-filter").arg(text);
+        self.options.pretext = Some(text);
+        self
+    }
+
+    /// Add custom keys choosing the highlighted entry, `run_key` returns the index of the key
+    /// 
+    /// Use the `-kb-custom-*` Rofi flags, the default bindings of the keys are removed
+    /// 
+    /// Arguments:
+    /// 
+    /// * `keys` - the keys, like `Control+Return`
+    pub fn custom_keys(mut self, keys: &[String]) -> Self {
+        self.options.custom_keys = keys.to_vec();
+        self
+    }
+
+    /// Disable the custom input, only the entries can be chosen
+    /// 
+    /// Equivalent to `-no-custom` Rofi flag
+    pub fn no_custom(mut self) -> Self {
+        self.options.no_custom = true;
         self
     }
 
     /// Make the filter case insensitive
     pub fn case_insensitive(mut self) -> Self {
-        self.rofi.arg("-i");
+        self.options.case_insensitive = true;
         self
     }
 
@@ -106,8 +681,7 @@ impl Rofi {
     /// 
     /// * `p` - the `String` to prompt
     pub fn prompt(mut self, p: &str) -> Self {
-        self.rofi.arg("-p")
-            .arg(p);
+        self.options.prompt = Some(p.to_string());
         self
     }
 
@@ -117,8 +691,7 @@ impl Rofi {
     /// 
     /// * `index` - the index of the line
     pub fn selected(mut self, index: u32) -> Self {
-        self.rofi.arg("-selected-row")
-            .arg(index.to_string());
+        self.options.selected = Some(index);
         self
     }
 
@@ -128,14 +701,13 @@ impl Rofi {
     /// 
     /// * `placeholder` - the placeholder to show
     pub fn placeholder(mut self, placeholder: &str) -> Self {
-        self.rofi.arg("-theme-str")
-            .arg(format!("entry {{ placeholder: \"{}\"; }}", placeholder));
+        self.options.placeholder = Some(placeholder.to_string());
         self
     }
 
     /// Mask lines under the input
     pub fn text_only(mut self) -> Self {
-        self.rofi.arg("-l").arg("0");
+        self.options.text_only = true;
         self
     }
 }
@@ -154,20 +726,112 @@ fn trim_newline(s: &mut String) {
     }
 }
 
+/// Parse the indexes returned by Rofi, one per line, without duplicates
+/// 
+/// Arguments:
+/// 
+/// * `output` - the output of Rofi
+/// * `count` - the number of entries
+fn parse_indexes(output: &str, count: usize) -> Result<Vec<usize>, String> {
+    let mut indexes = Vec::new();
+    for line in output.lines().filter(|l| !l.trim().is_empty()) {
+        match line.trim().parse::<usize>() {
+            Ok(i) if i < count => if !indexes.contains(&i) { indexes.push(i) },
+            _ => return Err(format!("Unexpected selection '{}'", line))
+        }
+    }
+    Ok(indexes)
+}
+
+/// Return the choices printed by Rofi with `-format 'i f'`, the index of the entry then the typed text
+/// 
+/// The index of a typed text which is not an entry is `-1`, an empty typed text is no choice
+/// 
+/// Arguments:
+/// 
+/// * `output` - the output of Rofi
+/// * `count` - the number of entries
+fn parse_choices(output: &str, count: usize) -> Result<Vec<Choice>, String> {
+    let mut choices = Vec::new();
+    for line in output.lines().filter(|l| !l.trim().is_empty()) {
+        let (index, text) = line.split_once(' ').unwrap_or((line, ""));
+        let choice = match index.parse::<i64>() {
+            Ok(-1) if text.is_empty() => continue,
+            Ok(-1) => Choice::Text(text.to_string()),
+            Ok(i) if i >= 0 && (i as usize) < count => Choice::Index(i as usize),
+            _ => return Err(format!("Unexpected selection '{}'", line))
+        };
+        if !choices.contains(&choice) {
+            choices.push(choice);
+        }
+    }
+    Ok(choices)
+}
+
 /// Transform a `Vec<String>` to a String with line breaks between each entry
 /// 
 /// Arguments:
 /// 
 /// * `tab` - the `Vec<String>` of entries
-fn vec2str(tab : Vec<String>) -> String {
+fn vec2str(tab : &[String]) -> String {
     let mut s = String::new();
     for x in tab {
-        s.push_str(&x);
+        s.push_str(x);
         s.push('\n');
     }
     s
 }
 
+/// A backend answering the menus with texts given in advance, to test the menus
+#[cfg(test)]
+pub struct ScriptedBackend {
+    /// The texts typed or selected in the next menus, in order
+    answers: std::cell::RefCell<std::collections::VecDeque<String>>,
+    /// The entries and options of the menus shown
+    pub shown: std::cell::RefCell<Vec<(Vec<String>, MenuOptions)>>,
+    /// The capabilities of the backend
+    capabilities: Capabilities
+}
+
+#[cfg(test)]
+impl ScriptedBackend {
+    /// Create a backend answering the next menus, a closed menu is an empty answer
+    /// 
+    /// Arguments:
+    /// 
+    /// * `answers` - the texts typed or selected, one per menu
+    /// * `capabilities` - the capabilities of the backend
+    pub fn new(answers: &[&str], capabilities: Capabilities) -> Self {
+        ScriptedBackend {
+            answers: std::cell::RefCell::new(answers.iter().map(|a| a.to_string()).collect()),
+            shown: std::cell::RefCell::new(Vec::new()),
+            capabilities
+        }
+    }
+}
+
+#[cfg(test)]
+impl MenuBackend for ScriptedBackend {
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    fn select(&self, entries: &[String], options: &MenuOptions) -> Result<Vec<Choice>, String> {
+        Ok(self.select_with_key(entries, options)?.1)
+    }
+
+    /// An answer like `[kb-custom-2] pay rent` chooses the entry with the second custom key
+    fn select_with_key(&self, entries: &[String], options: &MenuOptions) -> Result<(Option<usize>, Vec<Choice>), String> {
+        self.shown.borrow_mut().push((entries.to_vec(), options.clone()));
+        let answer = self.answers.borrow_mut().pop_front().ok_or_else(|| String::from("no answer left"))?;
+        let key = answer.strip_prefix("[kb-custom-").and_then(|rest| rest.split_once("] "));
+        match key.and_then(|(number, rest)| Some((number.parse::<usize>().ok()?.checked_sub(1)?, rest))) {
+            Some((key, rest)) if key < options.custom_keys.len() => Ok((Some(key), choices_from_lines(rest, entries))),
+            _ => Ok((None, choices_from_lines(&answer, entries)))
+        }
+    }
+}
+
 #[cfg(test)]
 mod rofiutils_tests {
     use super::*;
@@ -188,7 +852,143 @@ mod rofiutils_tests {
     #[test]
     fn vec2str_test() {
         let test_vec = vec![String::from("foo"), String::from("bar")];
-        assert_eq!(vec2str(test_vec), String::from("foo\nbar\n"));
+        assert_eq!(vec2str(&test_vec), String::from("foo\nbar\n"));
+    }
+
+    #[test]
+    fn parse_indexes_test() {
+        assert_eq!(parse_indexes("", 3), Ok(vec![]));
+        assert_eq!(parse_indexes("2\n0\n2\n", 3), Ok(vec![2, 0]));
+        assert!(parse_indexes("0\n3", 3).is_err());
+        assert!(parse_indexes("1\ncall mom", 3).is_err());
+    }
+
+    #[test]
+    fn parse_choices_test() {
+        assert_eq!(parse_choices("", 3), Ok(vec![]));
+        assert_eq!(parse_choices("2 call\n-1 call mom\n2 \n", 3), Ok(vec![Choice::Index(2), Choice::Text(String::from("call mom"))]));
+        assert_eq!(parse_choices("-1 ", 3), Ok(vec![]));
+        assert!(parse_choices("3 call", 3).is_err());
+        assert!(parse_choices("call mom", 3).is_err());
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn detect_launcher() {
+        let only = |names: &'static [&'static str]| move |p: &str| names.contains(&p);
+        assert_eq!(Launcher::detect(true, only(&["rofi", "fuzzel"])), Some(Launcher::Rofi));
+        assert_eq!(Launcher::detect(true, only(&["dmenu", "wofi", "fuzzel"])), Some(Launcher::Fuzzel));
+        assert_eq!(Launcher::detect(false, only(&["wofi", "dmenu"])), Some(Launcher::Dmenu));
+        assert_eq!(Launcher::detect(false, only(&[])), None);
+        assert_eq!(Launcher::from_name("bemenu"), Some(Launcher::Bemenu));
+        assert_eq!(Launcher::from_name("xmenu"), None);
+    }
+
+    #[test]
+    fn choose_installed_launcher() {
+        let only = |names: &'static [&'static str]| move |p: &str| names.contains(&p);
+        assert_eq!(choose_launcher("auto", false, only(&["dmenu"])), Ok(Launcher::Dmenu));
+        assert!(choose_launcher("auto", false, only(&[])).unwrap_err().contains("--stdio"));
+        assert_eq!(choose_launcher("fuzzel", false, only(&["rofi", "fuzzel"])), Ok(Launcher::Fuzzel));
+        assert_eq!(choose_launcher("wofi", true, only(&["rofi"])),
+                   Err(String::from("wofi is not installed, install it or choose another launcher with --backend")));
+    }
+
+    #[test]
+    fn stdio_menus() {
+        let backend = StdioBackend::new("2\nbuy milk\nother\n\ncall mom\n".as_bytes(), Vec::new());
+        let entries = vec![String::from("+ add"), String::from("call mom")];
+        let options = MenuOptions { prompt: Some(String::from("Todo")), message: Some(String::from("2 tasks")), ..Default::default() };
+        assert_eq!(backend.select(&entries, &options), Ok(vec![Choice::Index(1)]));
+        assert_eq!(backend.select(&[], &options), Ok(vec![Choice::Text(String::from("buy milk"))]));
+        // Only the entries can be chosen, the empty line closes the menu
+        let no_custom = MenuOptions { no_custom: true, ..options.clone() };
+        assert_eq!(backend.select(&entries, &no_custom), Ok(vec![]));
+        assert_eq!(backend.select(&entries, &options), Ok(vec![Choice::Index(1)]));
+        assert_eq!(backend.select(&entries, &options), Ok(vec![]));
+        let output = String::from_utf8(backend.output.into_inner()).unwrap();
+        assert!(output.starts_with("2 tasks\n  1 + add\n  2 call mom\nTodo> 2 tasks\nTodo> "), "{}", output);
+        assert!(output.contains("No entry 'other'\n"));
+    }
+
+    #[test]
+    fn launcher_args() {
+        let options = MenuOptions { prompt: Some(String::from("Edit")), message: Some(String::from("call mom")), no_custom: true,
+                                    markup_rows: true, case_insensitive: true, ..Default::default() };
+        assert_eq!(Launcher::Rofi.args(&options), vec!["-dmenu", "-i", "-format", "i", "-no-custom", "-markup-rows",
+                   "-theme-str", "textbox { markup: false; }", "-mesg", "call mom", "-p", "Edit"]);
+        // The typed texts come with the indexes, the selected row is kept from a menu to the next
+        let custom = MenuOptions { no_custom: false, markup_rows: false, message: None, selected: Some(4), ..options.clone() };
+        assert_eq!(Launcher::Rofi.args(&custom), vec!["-dmenu", "-i", "-format", "i f", "-p", "Edit", "-selected-row", "4"]);
+        // The custom keys are removed from the default bindings using them
+        let keys = MenuOptions { custom_keys: vec![String::from("Control+Return"), String::from("Control+p")], ..custom.clone() };
+        assert_eq!(Launcher::Rofi.args(&keys), vec!["-dmenu", "-i", "-format", "i f", "-p", "Edit", "-selected-row", "4",
+                   "-kb-custom-1", "Control+Return", "-kb-custom-2", "Control+p", "-kb-accept-custom", "", "-kb-row-up", "Up"]);
+        assert_eq!(Launcher::Wofi.args(&options), vec!["--dmenu", "--insensitive", "--allow-markup", "--prompt", "Edit"]);
+        assert_eq!(Launcher::Fuzzel.args(&options), vec!["--dmenu", "--prompt", "Edit "]);
+        assert_eq!(Launcher::Dmenu.args(&MenuOptions { text_only: true, ..options }), vec!["-i", "-p", "Edit"]);
+    }
+
+    #[test]
+    fn exit_key_test() {
+        assert_eq!(exit_key(0, 2), Some(None));
+        assert_eq!(exit_key(10, 2), Some(Some(0)));
+        assert_eq!(exit_key(11, 2), Some(Some(1)));
+        // Escape, a failure and a custom key without action choose nothing
+        assert_eq!(exit_key(1, 2), None);
+        assert_eq!(exit_key(12, 2), None);
+        assert_eq!(exit_key(65, 2), None);
+    }
+
+    #[test]
+    fn failed_backend() {
+        let capabilities = Capabilities { markup_rows: true, multi_select: true, message: true };
+        let backend = Rc::new(ScriptedBackend::new(&["call mom"], capabilities));
+        let params = RofiParams { no_config: false, case_insensitive: false, no_confirm: false, no_color: false, backend: backend.clone(), locale: Default::default(), failure: Default::default() };
+        assert_eq!(Rofi::from(&params).run(vec![]), Ok(String::from("call mom")));
+        // The failure closes the menu, and the next menus without calling the backend
+        assert_eq!(Rofi::from(&params).run_index(vec![String::from("+ add")]), Ok(None));
+        assert_eq!(params.failure.borrow().as_deref(), Some("no answer left"));
+        assert_eq!(Rofi::from(&params).run(vec![]), Ok(String::new()));
+        assert_eq!(backend.shown.borrow().len(), 2);
+    }
+
+    #[test]
+    fn degraded_menus() {
+        let plain = Capabilities { markup_rows: false, multi_select: false, message: false };
+        let backend = Rc::new(ScriptedBackend::new(&["(A) fix <b>", "typed", ""], plain));
+        let params = RofiParams { no_config: false, case_insensitive: false, no_confirm: false, no_color: false, backend: backend.clone(), locale: Default::default(), failure: Default::default() };
+        let entries = vec![String::from("<b>(A)</b> fix &lt;b&gt;"), String::from("other")];
+        // The markup is removed and the message is shown in the prompt
+        assert_eq!(Rofi::from(&params).markup_rows().prompt("Todo").msg(String::from("3 tasks\nmore")).run_index(entries.clone()), Ok(Some(0)));
+        assert_eq!(backend.shown.borrow()[0].0, vec!["(A) fix <b>", "other"]);
+        assert_eq!(backend.shown.borrow()[0].1.prompt.as_deref(), Some("Todo (3 tasks)"));
+        assert_eq!(Rofi::from(&params).run(entries.clone()), Ok(String::from("typed")));
+        assert_eq!(Rofi::from(&params).multi_select().run_indexes(entries), Ok(vec![]));
+        assert!(!backend.shown.borrow()[2].1.multi_select);
+    }
+
+    #[test]
+    fn no_color() {
+        let capabilities = Capabilities { markup_rows: true, multi_select: true, message: true };
+        let backend = Rc::new(ScriptedBackend::new(&["(A) fix <b>"], capabilities));
+        let params = RofiParams { no_config: false, case_insensitive: false, no_confirm: false, no_color: true, backend: backend.clone(), locale: Default::default(), failure: Default::default() };
+        let entries = vec![String::from("<span foreground=\"red\"><b>(A)</b></span> fix &lt;b&gt;")];
+        assert_eq!(Rofi::from(&params).markup_rows().run_index(entries), Ok(Some(0)));
+        assert_eq!(backend.shown.borrow()[0].0, vec!["(A) fix <b>"]);
+        assert!(!backend.shown.borrow()[0].1.markup_rows);
+    }
+
+    #[test]
+    fn translated_menus() {
+        let capabilities = Capabilities { markup_rows: true, multi_select: true, message: true };
+        let backend = Rc::new(ScriptedBackend::new(&["✔ als erledigt markieren", "call mom"], capabilities));
+        let locale = Rc::new(Locale::new("de").unwrap());
+        let params = RofiParams { no_config: false, case_insensitive: false, no_confirm: false, no_color: false, backend: backend.clone(), locale, failure: Default::default() };
+        let entries = vec![String::from("✔ mark as done"), String::from("call mom")];
+        // The translated entry is chosen by its index, the English entry is returned
+        assert_eq!(Rofi::from(&params).prompt("Task").run(entries.clone()), Ok(String::from("✔ mark as done")));
+        assert_eq!(backend.shown.borrow()[0].0, vec!["✔ als erledigt markieren", "call mom"]);
+        assert_eq!(backend.shown.borrow()[0].1.prompt.as_deref(), Some("Aufgabe"));
+        assert_eq!(Rofi::from(&params).run(entries), Ok(String::from("call mom")));
+    }
+}
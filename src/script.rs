@@ -0,0 +1,144 @@
+use serde::{Serialize, Deserialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use crate::storage::save_atomic;
+use crate::markup::escape;
+
+/// The menu shown by the rofi script mode, kept between the calls of rofi
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ScriptState {
+    /// The tasks to do, a typed text is added as a new task
+    #[default]
+    List,
+    /// The actions of the task on a line of the task file
+    Task(usize),
+    /// The input of a new task
+    Add,
+    /// The confirmation of the deletion of the task on a line
+    ConfirmDelete(usize)
+}
+
+impl ScriptState {
+    /// Return the path of the file keeping the state of the script mode of a task list
+    ///
+    /// Arguments:
+    ///
+    /// * `todo_file` - the path of the task list
+    pub fn path_for(todo_file : &Path) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        todo_file.hash(&mut hasher);
+        std::env::temp_dir().join(format!("rofitodo-script-{:x}.json", hasher.finish()))
+    }
+
+    /// Load the state, the list of the tasks if there is no saved state
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the state file
+    pub fn load(path : &Path) -> Self {
+        std::fs::read_to_string(path).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+    }
+
+    /// Save the state for the next call
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the state file
+    pub fn save(&self, path : &Path) -> Result<(), String> {
+        let content = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        save_atomic(path, &content, None).map_err(|e| e.to_string())
+    }
+}
+
+/// What happened in rofi before the script was called
+#[derive(Debug, PartialEq)]
+pub enum ScriptEvent {
+    /// Rofi was opened
+    Start,
+    /// A row was selected, with the info of the row
+    Select(String),
+    /// A text which is not a row was entered
+    Input(String)
+}
+
+impl ScriptEvent {
+    /// Read the event from the values given by rofi
+    ///
+    /// Arguments:
+    ///
+    /// * `retv` - the `ROFI_RETV` environment variable
+    /// * `info` - the `ROFI_INFO` environment variable
+    /// * `arg` - the argument of the script, the selected or entered text
+    pub fn from_rofi(retv : Option<&str>, info : Option<&str>, arg : Option<&str>) -> Self {
+        match (retv, info, arg) {
+            (Some("1"), Some(info), _) => ScriptEvent::Select(info.to_string()),
+            (Some("2"), _, Some(text)) => ScriptEvent::Input(text.to_string()),
+            _ => ScriptEvent::Start
+        }
+    }
+}
+
+/// The rows and options of a menu of the script mode
+#[derive(Debug, Default, PartialEq)]
+pub struct Screen {
+    /// The prompt of rofi
+    pub prompt : String,
+    /// The message shown under the prompt, as plain text
+    pub message : Option<String>,
+    /// The rows as (Pango markup, info) pairs, the info is given back when the row is selected
+    pub rows : Vec<(String, String)>
+}
+
+impl Screen {
+    /// Return the output read by rofi
+    pub fn render(&self) -> String {
+        // The separators of the protocol can't be in the texts
+        let clean = |s : &str| s.replace(['\0', '\x1f', '\n'], " ");
+        let mut output = format!("\0prompt\x1f{}\n\0markup-rows\x1ftrue\n", clean(&self.prompt));
+        if let Some(message) = &self.message {
+            // The message is read as Pango markup, where a line break can be written as an entity
+            output.push_str(&format!("\0message\x1f{}\n", clean(&escape(message).replace('\n', "&#10;"))));
+        }
+        for (text, info) in &self.rows {
+            output.push_str(&format!("{}\0info\x1f{}\n", clean(text), clean(info)));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod script_tests {
+    use super::*;
+
+    #[test]
+    fn events() {
+        assert_eq!(ScriptEvent::from_rofi(None, None, None), ScriptEvent::Start);
+        assert_eq!(ScriptEvent::from_rofi(Some("0"), None, None), ScriptEvent::Start);
+        assert_eq!(ScriptEvent::from_rofi(Some("1"), Some("task:3"), Some("call mom")), ScriptEvent::Select(String::from("task:3")));
+        assert_eq!(ScriptEvent::from_rofi(Some("2"), None, Some("buy milk")), ScriptEvent::Input(String::from("buy milk")));
+        assert_eq!(ScriptEvent::from_rofi(Some("10"), None, Some("x")), ScriptEvent::Start);
+    }
+
+    #[test]
+    fn render() {
+        let screen = Screen {
+            prompt : String::from("Todo"),
+            message : Some(String::from("Completed <b>\nnow")),
+            rows : vec![(String::from("<b>(A)</b> call mom"), String::from("task:1")), (String::from("bad\0row"), String::from("back"))]
+        };
+        assert_eq!(screen.render(), "\0prompt\x1fTodo\n\0markup-rows\x1ftrue\n\0message\x1fCompleted &lt;b&gt;&#10;now\n<b>(A)</b> call mom\0info\x1ftask:1\nbad row\0info\x1fback\n");
+    }
+
+    #[test]
+    fn state_file() {
+        let path = std::env::temp_dir().join(format!("rofitodo_script_{}.json", std::process::id()));
+        assert_eq!(ScriptState::load(&path), ScriptState::List);
+        ScriptState::ConfirmDelete(4).save(&path).unwrap();
+        assert_eq!(ScriptState::load(&path), ScriptState::ConfirmDelete(4));
+        std::fs::write(&path, "broken").unwrap();
+        assert_eq!(ScriptState::load(&path), ScriptState::List);
+        std::fs::remove_file(&path).unwrap();
+        assert_ne!(ScriptState::path_for(Path::new("/a/todo.txt")), ScriptState::path_for(Path::new("/b/todo.txt")));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::task::Task;
+
+/// The score of a matched character
+const MATCH_SCORE : i64 = 1;
+/// The bonus of a character matched right after the previous one
+const CONSECUTIVE_BONUS : i64 = 5;
+/// The bonus of a character matched at the start of a word
+const WORD_START_BONUS : i64 = 8;
+/// The maximum penalty of the characters skipped between two matches
+const MAX_GAP_PENALTY : i64 = 3;
+
+/// Score how well `query` fuzzy matches `text`
+///
+/// The characters of the query must appear in the text in the same order (case insensitive).
+/// Consecutive characters and characters at the start of words score higher.
+/// Return `None` if the text does not match
+///
+/// Arguments:
+///
+/// * `query` - the searched characters
+/// * `text` - the text to search in
+pub fn fuzzy_score(query : &str, text : &str) -> Option<i64> {
+    let mut query = query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()).peekable();
+    let mut score = 0;
+    let mut previous : Option<char> = None;
+    let mut gap : Option<i64> = None;
+    for c in text.chars() {
+        let expected = match query.peek() {
+            Some(expected) => *expected,
+            None => break
+        };
+        let word_start = match previous {
+            None => true,
+            Some(p) => !p.is_alphanumeric() || (p.is_lowercase() && c.is_uppercase())
+        };
+        previous = Some(c);
+        if c.to_lowercase().eq(std::iter::once(expected)) {
+            score += MATCH_SCORE;
+            match gap {
+                Some(0) => score += CONSECUTIVE_BONUS,
+                Some(n) => score -= n.min(MAX_GAP_PENALTY),
+                None => ()
+            }
+            if word_start {
+                score += WORD_START_BONUS;
+            }
+            gap = Some(0);
+            query.next();
+        } else if let Some(n) = gap {
+            gap = Some(n + 1);
+        }
+    }
+    if query.peek().is_some() {
+        None
+    } else {
+        Some(score)
+    }
+}
+
+/// Rank the tasks matching a query with a fuzzy search over their content and tags
+///
+/// Return the matching tasks with their score, the best first.
+/// Tasks with the same score keep their order, so an empty query returns all the tasks unchanged
+///
+/// Arguments:
+///
+/// * `tasks` - the tasks to search
+/// * `query` - the searched characters
+pub fn search<'a>(tasks : impl IntoIterator<Item = &'a Task>, query : &str) -> Vec<(i64, &'a Task)> {
+    let mut results = tasks.into_iter().filter_map(|task| task_score(task, query).map(|score| (score, task))).collect::<Vec<_>>();
+    results.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    results
+}
+
+/// Return the best score of a query over the content and tags of a task
+fn task_score(task : &Task, query : &str) -> Option<i64> {
+    let mut fields = vec![task.get_content().to_string()];
+    fields.extend(task.get_project_tags().iter().map(|p| format!("+{}", p)));
+    fields.extend(task.get_context_tags().iter().map(|c| format!("@{}", c)));
+    fields.extend(task.custom_tags().map(|(_, value)| value.to_string()));
+    fields.iter().filter_map(|field| fuzzy_score(query, field)).max()
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match() {
+        assert!(fuzzy_score("grcry", "@GroceryStore Eskimo pies").is_some());
+        assert!(fuzzy_score("GRCRY", "@GroceryStore Eskimo pies").is_some());
+        assert!(fuzzy_score("", "anything").is_some());
+        assert!(fuzzy_score("yrcrg", "@GroceryStore Eskimo pies").is_none());
+        assert!(fuzzy_score("pies!", "@GroceryStore Eskimo pies").is_none());
+    }
+
+    #[test]
+    fn fuzzy_ranking() {
+        // Consecutive characters score higher
+        assert!(fuzzy_score("pie", "Eskimo pies").unwrap() > fuzzy_score("pie", "pick the mice").unwrap());
+        // Word starts score higher
+        assert!(fuzzy_score("gs", "GroceryStore").unwrap() > fuzzy_score("gs", "eggs").unwrap());
+    }
+
+    #[test]
+    fn search_tasks() {
+        let tasks = vec![
+            "call the bank",
+            "@GroceryStore Eskimo pies",
+            "buy groceries due:2024-01-01",
+            "water the plants",
+        ].into_iter().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let results = search(&tasks, "grcry").into_iter().map(|(_, t)| t.get_content()).collect::<Vec<_>>();
+        assert_eq!(results, vec!["@GroceryStore Eskimo pies"]);
+        let results = search(&tasks, "2024").into_iter().map(|(_, t)| t.get_content()).collect::<Vec<_>>();
+        assert_eq!(results, vec!["buy groceries"]);
+        let results = search(&tasks, "the").into_iter().map(|(_, t)| t.get_content()).collect::<Vec<_>>();
+        assert_eq!(results, vec!["call the bank", "water the plants"]);
+        let results = search(&tasks, "").into_iter().map(|(_, t)| t.get_content()).collect::<Vec<_>>();
+        assert_eq!(results, vec!["call the bank", "@GroceryStore Eskimo pies", "buy groceries", "water the plants"]);
+    }
+}
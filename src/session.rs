@@ -0,0 +1,452 @@
+use chrono::{Local, NaiveDate};
+use crate::task::{Task, TaskId, SortTaskBy, SortDirection, PostponeBy};
+use crate::tasklist::{TaskList, Reload};
+use crate::undo::{History, Op};
+use crate::archive::Archive;
+use crate::dates::Workdays;
+
+/// The task lists open together, the changes of the tasks of the current list are recorded in its history so they
+/// can be undone
+pub struct Session {
+    /// The loaded task lists
+    pub lists : Vec<TaskList>,
+    /// The index of the current task list
+    pub current : usize,
+    /// The sort keys of the current list, by decreasing importance
+    pub sort : Vec<SortTaskBy>,
+    /// The direction of the sort
+    pub direction : SortDirection,
+    /// The days of the weekend skipped by the postponed and recurring due dates
+    pub workdays : Workdays,
+    /// A message about the last changes, like the tasks unblocked by a completed task or the merge of the task file
+    pub notice : Option<String>
+}
+
+impl Session {
+    /// Open task lists, the first one is the current list
+    ///
+    /// Arguments:
+    ///
+    /// * `sort` - the sort keys of the lists
+    /// * `direction` - the direction of the sort
+    /// * `lists` - the task lists, there is at least one
+    pub fn new(sort : Vec<SortTaskBy>, direction : SortDirection, lists : Vec<TaskList>) -> Self {
+        let mut session = Session { sort : vec![], direction, lists, current : 0, workdays : Workdays::default(), notice : None };
+        session.set_sort(sort, direction);
+        session
+    }
+
+    /// The current task list
+    pub fn list(&self) -> &TaskList {
+        &self.lists[self.current]
+    }
+
+    /// The current task list
+    pub fn list_mut(&mut self) -> &mut TaskList {
+        &mut self.lists[self.current]
+    }
+
+    /// Change the sort order of the current list and rebuild its index
+    pub fn set_sort(&mut self, sort : Vec<SortTaskBy>, direction : SortDirection) {
+        self.sort = sort.clone();
+        self.direction = direction;
+        self.list_mut().sort(sort, direction);
+    }
+
+    /// Load the task file of the current list again, replacing its tasks
+    pub fn reload(&mut self) -> Result<(), String> {
+        self.list_mut().reload()?;
+        self.set_sort(self.sort.clone(), self.direction);
+        Ok(())
+    }
+
+    /// Load the changes another program made to the task file of the current list, a merge is shown in `notice`
+    /// with the errors writing the log
+    pub fn refresh(&mut self) {
+        let log_errors = self.lists.iter_mut().filter_map(|l| l.history.take_log_error()).collect::<Vec<_>>();
+        for error in log_errors {
+            self.add_notice(error);
+        }
+        let name = self.list().name();
+        let message = match self.list_mut().reload_if_changed() {
+            Ok(Reload::Unchanged) => return,
+            Ok(Reload::Reloaded) => {
+                self.set_sort(self.sort.clone(), self.direction);
+                return;
+            },
+            Ok(Reload::Merged { added, conflicts }) => {
+                self.set_sort(self.sort.clone(), self.direction);
+                let mut message = format!("{} changed on disk : {} task{} added", name, added, if added == 1 { "" } else { "s" });
+                if conflicts > 0 {
+                    message.push_str(&format!(", {} line{} changed on both sides kept as edited in RofiTodo", conflicts, if conflicts == 1 { "" } else { "s" }));
+                }
+                message
+            },
+            Err(e) => e
+        };
+        self.add_notice(message);
+    }
+
+    /// Add a line to `notice`
+    pub fn add_notice(&mut self, message : String) {
+        self.notice = Some(match self.notice.take() {
+            Some(notice) => format!("{}\n{}", notice, message),
+            None => message
+        });
+    }
+
+    /// Add a task to the current list
+    pub fn add_task(&mut self, task : Task) -> TaskId {
+        self.list_mut().add(task)
+    }
+
+    /// Return a copy of a task of the current list, to record it in an operation
+    pub fn copy(&self, id : TaskId) -> Task {
+        self.list().get(id).cloned().expect("the task is in the current list")
+    }
+
+    /// Modify a task of the current list and record the change so it can be undone
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task to modify
+    /// * `change` - a closure modifying the task
+    pub fn update_task(&mut self, id : TaskId, change : impl FnOnce(&mut Task)) {
+        let op = self.change_task(id, change);
+        self.record(op);
+    }
+
+    /// Modify a task of the current list, return the operation to record
+    pub fn change_task(&mut self, id : TaskId, change : impl FnOnce(&mut Task)) -> Op {
+        let old = self.copy(id);
+        change(&mut self.list_mut().get_mut(id).expect("the task is in the current list"));
+        Op::Replace(old, self.copy(id))
+    }
+
+    /// Postpone a task of the current list, skipping the weekends of `workdays`, return the operation to record
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task
+    /// * `offset` - how far the task is postponed
+    /// * `today` - the current date
+    pub fn postpone_task(&mut self, id : TaskId, offset : PostponeBy, today : NaiveDate) -> Op {
+        let workdays = self.workdays.clone();
+        self.change_task(id, |t| t.postpone_with(offset, today, &workdays))
+    }
+
+    /// Mark a task of the current list as done today and add its next occurrence, return the operation to record
+    ///
+    /// The tasks which can be done now that it is done are shown in `notice`
+    pub fn complete_task(&mut self, id : TaskId) -> Op {
+        let unblocked = self.list().unblocked_by(id).into_iter().map(|id| self.copy(id).get_content().clone()).collect::<Vec<_>>();
+        if !unblocked.is_empty() {
+            self.add_notice(format!("Unblocked : {}", unblocked.join(", ")));
+        }
+        let mut t = self.list_mut().remove(id).expect("the task is in the current list");
+        let old = t.clone();
+        let today = Local::now().date_naive();
+        t.set_completed_on(today);
+        let next = t.next_occurrence_with(today, &self.workdays);
+        let done = self.add_task(t);
+        let mut ops = vec![Op::Replace(old, self.copy(done))];
+        if let Some(next) = next {
+            let next = self.add_task(next);
+            ops.push(Op::Add(self.copy(next)));
+        }
+        Op::Batch(ops)
+    }
+
+    /// Make a task of the current list depend on another one, the prerequisite gets an `id:` tag if it has none
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the task to do after the prerequisite
+    /// * `prerequisite` - the task to do first
+    pub fn add_dependency(&mut self, id : TaskId, prerequisite : TaskId) {
+        let mut ops = vec![];
+        let dependency_id = match self.copy(prerequisite).get_dependency_id() {
+            Some(dependency_id) => dependency_id.to_string(),
+            None => {
+                let dependency_id = self.list().new_dependency_id();
+                ops.push(self.change_task(prerequisite, |t| t.set_custom_tag("id", &dependency_id).expect("a number is a valid tag")));
+                dependency_id
+            }
+        };
+        ops.push(self.change_task(id, |t| t.add_dependency(&dependency_id)));
+        self.record(Op::Batch(ops));
+    }
+
+    /// Record an operation done on the current list so it can be undone
+    pub fn record(&mut self, op : Op) {
+        self.list_mut().history.push(op);
+    }
+
+    /// Undo the last operation done on the current list
+    pub fn undo(&mut self) -> Result<(), String> {
+        let list = self.list_mut();
+        let mut history = std::mem::replace(&mut list.history, History::new(0));
+        let undone = history.undo(list);
+        list.history = history;
+        undone
+    }
+
+    /// Redo the last undone operation of the current list
+    pub fn redo(&mut self) -> Result<(), String> {
+        let list = self.list_mut();
+        let mut history = std::mem::replace(&mut list.history, History::new(0));
+        let redone = history.redo(list);
+        list.history = history;
+        redone
+    }
+
+    /// Move a task of the archive back to the current list, marked as to do
+    ///
+    /// Arguments:
+    ///
+    /// * `archive` - the archive of the current list
+    /// * `index` - the index of the line of the task in the archive
+    pub fn unarchive(&mut self, archive : &mut Archive, index : usize) -> Result<(), String> {
+        let line = archive.line(index).unwrap_or_default().to_string();
+        let mut task = archive.take(index)?;
+        task.set_not_completed();
+        let id = self.add_task(task);
+        self.record(Op::Batch(vec![Op::TakeLine(self.list().archive_path(), line), Op::Add(self.copy(id))]));
+        Ok(())
+    }
+
+    /// Move a task of the current list to its trash
+    ///
+    /// The task is kept in the list if it could not be added to the trash
+    pub fn delete_task(&mut self, id : TaskId) -> Result<(), String> {
+        let op = self.trash_task(id)?;
+        self.record(op);
+        Ok(())
+    }
+
+    /// Move a task of the current list to its trash, return the operation to record
+    pub fn trash_task(&mut self, id : TaskId) -> Result<Op, String> {
+        let trash_path = self.list().trash_path();
+        let line = self.list().trash()?.put(&self.copy(id), Local::now().date_naive())?;
+        let removed = self.list_mut().remove(id).expect("the task is in the current list");
+        Ok(Op::Batch(vec![Op::PutLine(trash_path, line), Op::Remove(removed)]))
+    }
+
+    /// Move a completed task of the current list to its archive, return the operation to record
+    pub fn archive_task(&mut self, id : TaskId) -> Result<Op, String> {
+        let archive_path = self.list().archive_path();
+        let line = self.list().archive()?.put(&self.copy(id))?;
+        let removed = self.list_mut().remove(id).expect("the task is in the current list");
+        Ok(Op::Batch(vec![Op::PutLine(archive_path, line), Op::Remove(removed)]))
+    }
+
+    /// Return the project and context tags of the tasks to do of the current list, with their prefix, sorted
+    pub fn known_tags(&self) -> Vec<String> {
+        let mut tags = self.list().todos.get_index_list().into_iter().filter_map(|name| {
+            match (name.strip_prefix("project_"), name.strip_prefix("context_")) {
+                (Some(project), _) => Some(format!("+{}", project)),
+                (_, Some(context)) => Some(format!("@{}", context)),
+                _ => None
+            }
+        }).collect::<Vec<_>>();
+        tags.sort();
+        tags
+    }
+
+    /// Rename a project or context tag in all the tasks of the current list, recorded as a single operation
+    ///
+    /// Return the number of modified tasks
+    ///
+    /// Arguments:
+    ///
+    /// * `prefix` - `+` for a project tag, `@` for a context tag
+    /// * `old` - the name of the tag, without the prefix
+    /// * `new` - the new name of the tag, without the prefix
+    pub fn rename_tag(&mut self, prefix : char, old : &str, new : &str) -> Result<usize, String> {
+        let tasks = self.list().iter()
+                        .filter(|(_, t)| if prefix == '+' { t.get_project_tags() } else { t.get_context_tags() }.iter().any(|tag| tag == old))
+                        .map(|(id, _)| id)
+                        .collect::<Vec<_>>();
+        let count = tasks.len();
+        self.bulk(tasks, |session, task| Ok(session.change_task(task, |t| { t.rename_tag(prefix, old, new); })))?;
+        Ok(count)
+    }
+
+    /// Apply an action to several tasks of the current list, recorded as a single operation
+    ///
+    /// Nothing is changed if the action fails on one of the tasks
+    ///
+    /// Arguments:
+    ///
+    /// * `tasks` - the ids of the tasks
+    /// * `action` - the action applied to each task, returning the operation to record
+    pub fn bulk(&mut self, tasks : Vec<TaskId>, action : impl FnMut(&mut Self, TaskId) -> Result<Op, String>) -> Result<(), String> {
+        let ops = self.apply_all(tasks, action)?;
+        if !ops.is_empty() {
+            self.record(Op::Batch(ops));
+        }
+        Ok(())
+    }
+
+    /// Apply an action to several tasks of the current list, recorded as a single operation logged as one line
+    ///
+    /// Nothing is changed if the action fails on one of the tasks
+    ///
+    /// Arguments:
+    ///
+    /// * `description` - the line of the log, like `complete project +home (3 tasks)`
+    /// * `tasks` - the ids of the tasks
+    /// * `action` - the action applied to each task, returning the operation to record
+    pub fn group(&mut self, description : String, tasks : Vec<TaskId>, action : impl FnMut(&mut Self, TaskId) -> Result<Op, String>) -> Result<(), String> {
+        let ops = self.apply_all(tasks, action)?;
+        if !ops.is_empty() {
+            self.record(Op::Group(description, ops));
+        }
+        Ok(())
+    }
+
+    /// Apply an action to each task and return the operations done, the tasks already changed are changed back when
+    /// the action fails on a task
+    ///
+    /// Arguments:
+    ///
+    /// * `tasks` - the ids of the tasks
+    /// * `action` - the action applied to each task, returning the operation done
+    fn apply_all(&mut self, tasks : Vec<TaskId>, mut action : impl FnMut(&mut Self, TaskId) -> Result<Op, String>) -> Result<Vec<Op>, String> {
+        let mut ops = Vec::new();
+        for task in tasks {
+            match action(self, task) {
+                Ok(op) => ops.push(op),
+                Err(e) => {
+                    // The tasks already changed are changed back, the last one first
+                    Op::Batch(ops).revert(self.list_mut())?;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(ops)
+    }
+
+    /// Move a task of the current list at the end of another list
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task to move
+    /// * `target` - the index of the list receiving the task
+    pub fn move_task(&mut self, id : TaskId, target : usize) {
+        let mut t = self.list_mut().remove(id).expect("the task is in the current list");
+        self.record(Op::Remove(t.clone()));
+        // The line index of the task is only meaningful in its old file
+        t.set_source_line(None);
+        let list = &mut self.lists[target];
+        let moved = list.add(t);
+        let moved = list.get(moved).cloned().expect("the task was just added");
+        list.history.push(Op::Add(moved));
+    }
+}
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+    use std::fs;
+    use crate::test_files::temp_file;
+
+    #[test]
+    fn undo_operations() {
+        let path = temp_file("undo.txt", "(A) call mom\nwater plants\n");
+        let mut session = Session::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let find = |session : &Session, content : &str| session.list().iter().find(|(_, t)| t.get_content() == content).map(|(id, t)| (id, t.clone()));
+
+        // Completing the wrong task
+        session.update_task(find(&session, "call mom").unwrap().0, |t| t.set_completed());
+        assert!(find(&session, "call mom").unwrap().1.completion);
+        session.undo().unwrap();
+        assert!(!find(&session, "call mom").unwrap().1.completion);
+        session.redo().unwrap();
+        assert!(find(&session, "call mom").unwrap().1.completion);
+        session.undo().unwrap();
+
+        // Deleting goes through the trash
+        let trash = session.list().trash_path();
+        session.delete_task(find(&session, "water plants").unwrap().0).unwrap();
+        assert!(find(&session, "water plants").is_none());
+        session.undo().unwrap();
+        assert_eq!(find(&session, "water plants").unwrap().1.get_source_line(), Some(1));
+        assert_eq!(fs::read_to_string(&trash).unwrap(), "");
+        assert!(!session.list().history.can_undo());
+        fs::remove_file(&trash).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bulk_complete() {
+        let path = temp_file("bulk.txt", "call mom\nbuy milk\nwater plants rec:1w\nsend report\n");
+        let mut session = Session::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let selected = session.list().iter().filter(|(_, t)| t.get_content() != "send report").map(|(id, _)| id).collect::<Vec<_>>();
+        session.bulk(selected, |session, task| Ok(session.complete_task(task))).unwrap();
+
+        let today = Local::now().date_naive();
+        let done = session.list().todos.get_main_index().iter().filter(|t| t.completion).map(|t| t.completion_date).collect::<Vec<_>>();
+        assert_eq!(done, vec![Some(today); 3]);
+        // The next occurrence of the recurring task is added
+        assert_eq!(session.list().todos.get_main_index().iter().filter(|t| !t.completion).count(), 2);
+
+        // The whole batch is undone at once
+        session.undo().unwrap();
+        assert!(session.list().todos.get_main_index().iter().all(|t| !t.completion));
+        assert!(!session.list().history.can_undo());
+
+        // Nothing is changed when the action fails on a task
+        let ids = session.list().iter().map(|(id, _)| id).collect::<Vec<_>>();
+        let last = ids[ids.len() - 1];
+        let failed = session.bulk(ids, |session, task| match task == last {
+            true => Err(String::from("failed")),
+            false => Ok(session.complete_task(task))
+        });
+        assert_eq!(failed, Err(String::from("failed")));
+        assert!(session.list().todos.get_main_index().iter().all(|t| !t.completion));
+        assert_eq!(session.list().iter().count(), 4);
+        assert!(!session.list().history.can_undo());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn archive_and_rename_project() {
+        let path = temp_file("project.txt", "x 2024-01-02 sell the bike +garage\npaint the fence +house +garage\nx 2024-01-03 call mom\n");
+        let mut session = Session::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        let archive_path = session.list().archive_path();
+        let done = session.list().iter().filter(|(_, t)| t.completion && t.get_project_tags().contains(&String::from("garage"))).map(|(id, _)| id).collect::<Vec<_>>();
+        session.bulk(done, |session, task| session.archive_task(task)).unwrap();
+        assert_eq!(fs::read_to_string(&archive_path).unwrap(), "x 2024-01-02 sell the bike +garage\n");
+        assert_eq!(session.list().todos.get_main_index().len(), 2);
+        session.undo().unwrap();
+        assert_eq!(fs::read_to_string(&archive_path).unwrap(), "");
+        assert_eq!(session.list().todos.get_main_index().len(), 3);
+
+        let projects = |session : &Session| session.list().todos.get_main_index().iter().map(|t| t.get_project_tags().join(",")).collect::<Vec<_>>();
+        assert_eq!(session.rename_tag('+', "garage", "yard"), Ok(2));
+        assert_eq!(projects(&session), vec!["", "house,yard", "yard"]);
+        assert_eq!(session.rename_tag('@', "yard", "garden"), Ok(0));
+        // The renaming is undone at once
+        session.undo().unwrap();
+        assert_eq!(projects(&session), vec!["", "garage,house", "garage"]);
+        fs::remove_file(&archive_path).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn postpone_on_workdays() {
+        let path = temp_file("workdays.txt", "send report due:2024-05-10\nwater plants due:2024-05-10 rec:+1d\n");
+        let mut session = Session::new(vec![SortTaskBy::Content], SortDirection::Ascending, vec![TaskList::load(&path, 20).unwrap()]);
+        session.workdays = Workdays { skip_weekends : true, ..Default::default() };
+        let id = |session : &Session, content : &str| session.list().iter().find(|(_, t)| !t.completion && t.get_content() == content).unwrap().0;
+        let due = |session : &Session, content : &str| session.copy(id(session, content)).get_due().unwrap().to_string();
+        // From a Friday, the postponed task and the next occurrence are due on Monday
+        let op = session.postpone_task(id(&session, "send report"), PostponeBy::Days(1), NaiveDate::from_ymd_opt(2024, 5, 6).unwrap());
+        session.record(op);
+        let op = session.complete_task(id(&session, "water plants"));
+        session.record(op);
+        assert_eq!((due(&session, "send report"), due(&session, "water plants")), (String::from("2024-05-13"), String::from("2024-05-13")));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::storage::{self, save_atomic, Cipher};
+
+/// A file of task lines kept next to a task list, like its archive or its trash
+pub(crate) struct SiblingFile {
+    /// The path of the file
+    pub(crate) path : PathBuf,
+    /// The lines of the file, kept as read so unreadable lines are saved back unchanged
+    pub(crate) lines : Vec<String>,
+    /// The encryption of the file if its name ends in `.gpg`
+    cipher : Option<Rc<dyn Cipher>>
+}
+
+impl SiblingFile {
+    /// Return the path of a file next to a task list
+    ///
+    /// `todo.txt` has `done.txt` for the base name `done` in the same directory, the other lists have their own file
+    /// so several lists can share a directory (`work.done.txt` for `work.txt`). An encrypted list has an encrypted
+    /// file, `done.txt.gpg` for `todo.txt.gpg`
+    ///
+    /// Arguments:
+    ///
+    /// * `todo_file` - the path of the task list
+    /// * `base` - the name of the file without its extension, like `done`
+    pub(crate) fn path_for(todo_file : &Path, base : &str) -> PathBuf {
+        if storage::is_encrypted(todo_file) {
+            let mut path = Self::path_for(&todo_file.with_extension(""), base).into_os_string();
+            path.push(".gpg");
+            return PathBuf::from(path);
+        }
+        match todo_file.file_stem().and_then(|s| s.to_str()) {
+            Some("todo") | None => todo_file.with_file_name(format!("{}.txt", base)),
+            Some(stem) => todo_file.with_file_name(format!("{}.{}.txt", stem, base))
+        }
+    }
+
+    /// Load a file, a missing file has no line
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the file
+    /// * `cipher` - the encryption of the file if its name ends in `.gpg`
+    pub(crate) fn load(path : &Path, cipher : Option<Rc<dyn Cipher>>) -> Result<Self, String> {
+        let lines = match storage::read_to_string(path, cipher.as_deref()) {
+            Ok(content) => content.lines().map(String::from).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.to_string())
+        };
+        Ok(SiblingFile { path : path.to_path_buf(), lines, cipher })
+    }
+
+    /// Write the lines to the file
+    pub(crate) fn save(&self) -> Result<(), String> {
+        let mut content = self.lines.join("\n");
+        if !self.lines.is_empty() {
+            content.push('\n');
+        }
+        save_atomic(&self.path, &content, self.cipher.as_deref()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod sibling_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    #[test]
+    fn path_for() {
+        assert_eq!(SiblingFile::path_for(Path::new("/home/me/todo.txt"), "later"), PathBuf::from("/home/me/later.txt"));
+        assert_eq!(SiblingFile::path_for(Path::new("work.txt"), "later"), PathBuf::from("work.later.txt"));
+        assert_eq!(SiblingFile::path_for(Path::new("/home/me/work.txt.gpg"), "later"), PathBuf::from("/home/me/work.later.txt.gpg"));
+    }
+
+    #[test]
+    fn load_and_save() {
+        let path = temp_path("sibling.txt");
+        let mut file = SiblingFile::load(&path, None).unwrap();
+        assert!(file.lines.is_empty());
+        file.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        file.lines.push(String::from("x 2024-13-45 broken"));
+        file.save().unwrap();
+        assert_eq!(SiblingFile::load(&path, None).unwrap().lines, vec!["x 2024-13-45 broken"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
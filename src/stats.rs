@@ -0,0 +1,164 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::BTreeMap;
+use crate::task::Task;
+
+/// The number of weeks counted in `Stats::weeks`
+pub const WEEKS : usize = 8;
+
+/// The statistics of a task list and its archive
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    /// The number of tasks to do
+    pub open : usize,
+    /// The number of completed tasks
+    pub completed : usize,
+    /// The number of tasks to do which are overdue
+    pub overdue : usize,
+    /// The number of tasks completed in each of the last weeks, the oldest first, with the Monday of the week
+    pub weeks : Vec<(NaiveDate, usize)>,
+    /// The number of tasks to do of each project, the biggest project first
+    pub projects : Vec<(String, usize)>,
+    /// The number of tasks to do of each context, the biggest context first
+    pub contexts : Vec<(String, usize)>,
+    /// The average age in days of the tasks to do with a creation date, `None` if there is none
+    pub average_age : Option<f64>
+}
+
+impl Stats {
+    /// Compute the statistics of tasks, the hidden tasks are not counted
+    ///
+    /// Arguments:
+    ///
+    /// * `tasks` - the tasks of the list and of its archive
+    /// * `today` - the current date, in the last week of `weeks`
+    pub fn of(tasks : &[Task], today : NaiveDate) -> Self {
+        let tasks = tasks.iter().filter(|t| !t.is_hidden()).collect::<Vec<_>>();
+        let (done, open) : (Vec<&Task>, Vec<&Task>) = tasks.into_iter().partition(|t| t.completion);
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let mut weeks = (0..WEEKS).rev().map(|w| (monday - Duration::weeks(w as i64), 0)).collect::<Vec<_>>();
+        for date in done.iter().filter_map(|t| t.completion_date) {
+            if let Some(week) = weeks.iter_mut().rev().find(|(start, _)| *start <= date) {
+                if date <= today {
+                    week.1 += 1;
+                }
+            }
+        }
+        let ages = open.iter().filter_map(|t| t.creation_date).map(|d| (today - d).num_days() as f64).collect::<Vec<_>>();
+        Stats {
+            open : open.len(),
+            completed : done.len(),
+            overdue : open.iter().filter(|t| t.is_overdue(today)).count(),
+            weeks,
+            projects : count_tags(open.iter().map(|t| t.get_project_tags())),
+            contexts : count_tags(open.iter().map(|t| t.get_context_tags())),
+            average_age : if ages.is_empty() { None } else { Some(ages.iter().sum::<f64>() / ages.len() as f64) }
+        }
+    }
+
+    /// Return the statistics as a report of several lines
+    pub fn to_report(&self) -> String {
+        let mut lines = vec![format!("Open : {}, completed : {}, overdue : {}", self.open, self.completed, self.overdue)];
+        lines.push(format!("Average age of the open tasks : {}", self.average_age.map(|a| format!("{:.1} days", a)).unwrap_or_else(|| String::from("unknown"))));
+        lines.push(String::from("Completed per week :"));
+        lines.extend(self.weeks.iter().map(|(start, count)| format!("  {} {:>3} {}", start.format("%Y-%m-%d"), count, "#".repeat(*count)).trim_end().to_string()));
+        for (title, prefix, counts) in [("project", '+', &self.projects), ("context", '@', &self.contexts)].iter() {
+            if !counts.is_empty() {
+                lines.push(format!("Open tasks per {} :", title));
+                lines.extend(counts.iter().map(|(tag, count)| format!("  {}{} {}", prefix, tag, count)));
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Return the statistics in three lines, for the message of a menu
+    pub fn to_summary(&self) -> String {
+        let mut lines = vec![format!("{} open, {} completed, {} overdue", self.open, self.completed, self.overdue)];
+        if let Some(age) = self.average_age {
+            lines[0] = format!("{}, {:.0} days old on average", lines[0], age);
+        }
+        lines.push(format!("Completed in the last {} weeks : {}", WEEKS, self.weeks.iter().map(|(_, c)| c.to_string()).collect::<Vec<_>>().join(" ")));
+        let top = self.projects.iter().map(|(tag, count)| format!("+{} {}", tag, count))
+                      .chain(self.contexts.iter().map(|(tag, count)| format!("@{} {}", tag, count)));
+        let top = top.take(6).collect::<Vec<_>>();
+        if !top.is_empty() {
+            lines.push(top.join(", "));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Count the tasks of each tag, the most used tag first then by name
+fn count_tags<'a>(tags : impl Iterator<Item=&'a Vec<String>>) -> Vec<(String, usize)> {
+    let mut counts : BTreeMap<&str, usize> = BTreeMap::new();
+    for task_tags in tags {
+        // A task with a tag twice is counted once
+        for (index, tag) in task_tags.iter().enumerate() {
+            if !task_tags[..index].contains(tag) {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+    }
+    let mut counts = counts.into_iter().map(|(tag, count)| (String::from(tag), count)).collect::<Vec<_>>();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+
+    fn tasks(lines : &[&str]) -> Vec<Task> {
+        lines.iter().map(|l| l.parse::<Task>().unwrap()).collect()
+    }
+
+    fn date(month : u32, day : u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn counts() {
+        // Friday the 10th of May, in the week starting on the 6th
+        let list = tasks(&["2024-05-01 call mom @phone +family due:2024-05-05", "2024-04-21 fix the sink +house +house @home",
+                           "plan the trip +family", "template h:1 +family", "x 2024-05-10 2024-05-01 pay rent",
+                           "x 2024-05-06 2024-05-06 water plants", "x 2024-05-05 2024-04-01 mow the lawn", "x 2024-03-01 2024-02-01 old task",
+                           "x sell the bike"]);
+        let stats = Stats::of(&list, date(5, 10));
+        assert_eq!(stats.open, 3);
+        assert_eq!(stats.completed, 5);
+        assert_eq!(stats.overdue, 1);
+        assert_eq!(stats.weeks.len(), WEEKS);
+        assert_eq!(stats.weeks[0], (date(3, 18), 0));
+        assert_eq!(&stats.weeks[6..], &[(date(4, 29), 1), (date(5, 6), 2)]);
+        assert_eq!(stats.projects, vec![(String::from("family"), 2), (String::from("house"), 1)]);
+        assert_eq!(stats.contexts, vec![(String::from("home"), 1), (String::from("phone"), 1)]);
+        // The task without creation date is not in the average
+        assert_eq!(stats.average_age, Some(14.0));
+    }
+
+    #[test]
+    fn no_creation_dates() {
+        let stats = Stats::of(&tasks(&["call mom", "x 2024-05-02 done"]), date(5, 10));
+        assert_eq!(stats.average_age, None);
+        // A single date is the creation date, the completed task is in no week
+        assert_eq!(stats.completed, 1);
+        assert_eq!(stats.weeks.iter().map(|(_, c)| c).sum::<usize>(), 0);
+        assert_eq!(Stats::of(&[], date(5, 10)).open, 0);
+    }
+
+    #[test]
+    fn rendering() {
+        let list = tasks(&["2024-05-01 call mom @phone +family due:2024-05-05", "x 2024-05-06 2024-05-06 water plants", "someday"]);
+        let stats = Stats::of(&list, date(5, 10));
+        assert_eq!(stats.to_report(), "Open : 2, completed : 1, overdue : 1\n\
+                                       Average age of the open tasks : 9.0 days\n\
+                                       Completed per week :\n\
+                                       \x20 2024-03-18   0\n  2024-03-25   0\n  2024-04-01   0\n  2024-04-08   0\n\
+                                       \x20 2024-04-15   0\n  2024-04-22   0\n  2024-04-29   0\n  2024-05-06   1 #\n\
+                                       Open tasks per project :\n  +family 1\n\
+                                       Open tasks per context :\n  @phone 1");
+        assert_eq!(stats.to_summary(), "2 open, 1 completed, 1 overdue, 9 days old on average\n\
+                                        Completed in the last 8 weeks : 0 0 0 0 0 0 0 1\n\
+                                        +family 1, @phone 1");
+        assert_eq!(Stats::of(&[], date(5, 10)).to_summary(), "0 open, 0 completed, 0 overdue\nCompleted in the last 8 weeks : 0 0 0 0 0 0 0 0");
+    }
+}
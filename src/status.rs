@@ -0,0 +1,112 @@
+use chrono::NaiveDate;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use crate::task::Task;
+use crate::tasklist::TaskList;
+
+/// The counts of tasks shown in a status bar
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Status {
+    /// The number of tasks to do
+    pub open : usize,
+    /// The number of tasks overdue
+    pub overdue : usize,
+    /// The number of tasks due today
+    pub today : usize,
+    /// The most urgent task, with its priority
+    pub top : Option<String>
+}
+
+impl Status {
+    /// Count the tasks to do, the completed and hidden tasks and the tasks whose threshold is not reached are not counted
+    ///
+    /// Arguments:
+    ///
+    /// * `tasks` - the tasks
+    /// * `today` - the current date
+    pub fn of<'a>(tasks : impl IntoIterator<Item=&'a Task>, today : NaiveDate) -> Self {
+        let open = tasks.into_iter().filter(|t| !t.completion && !t.is_hidden() && t.is_visible_on(today)).collect::<Vec<_>>();
+        // The first of the most urgent tasks is kept
+        let top = open.iter().fold(None, |top : Option<&&Task>, task| match top {
+            Some(top) if top.urgency(today) >= task.urgency(today) => Some(top),
+            _ => Some(task)
+        });
+        Status {
+            open : open.len(),
+            overdue : open.iter().filter(|t| t.is_overdue(today)).count(),
+            today : open.iter().filter(|t| t.is_due_today(today)).count(),
+            top : top.map(|t| match t.get_priority() {
+                Some(priority) => format!("({}) {}", priority, t.get_content()),
+                None => t.get_content().to_string()
+            })
+        }
+    }
+
+    /// Count the tasks to do of a task list like `of`, the most urgent task is not blocked by a dependency
+    ///
+    /// Arguments:
+    ///
+    /// * `list` - the task list
+    /// * `today` - the current date
+    pub fn of_list(list : &TaskList, today : NaiveDate) -> Self {
+        let unblocked = Self::of(list.iter().filter(|(id, _)| !list.is_blocked(*id)).map(|(_, t)| t), today);
+        Status { top : unblocked.top, ..Self::of(list.iter().map(|(_, t)| t), today) }
+    }
+
+    /// Return the status as a JSON object on a single line
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a status can always be serialized")
+    }
+
+    /// Return the status as a line of text, `12 open, 2 overdue, 3 today | (A) pay rent`
+    pub fn to_plain(&self) -> String {
+        let counts = format!("{} open, {} overdue, {} today", self.open, self.overdue, self.today);
+        match &self.top {
+            Some(top) => format!("{} | {}", counts, top),
+            None => counts
+        }
+    }
+}
+
+#[cfg(test)]
+mod status_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    fn tasks(lines : &[&str]) -> Vec<Task> {
+        lines.iter().map(|l| l.parse::<Task>().unwrap()).collect()
+    }
+
+    #[test]
+    fn counts() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let list = tasks(&["water plants due:2024-05-10", "(A) pay \"rent\" due:2024-05-12", "call mom due:2024-05-01",
+                           "x 2024-05-02 done due:2024-05-01", "template h:1 due:2024-05-01", "later t:2024-06-01 due:2024-05-01", "someday"]);
+        let status = Status::of(&list, today);
+        assert_eq!(status, Status { open : 4, overdue : 1, today : 1, top : Some(String::from("(A) pay \"rent\"")) });
+        #[cfg(feature = "serde")]
+        assert_eq!(status.to_json(), r#"{"open":4,"overdue":1,"today":1,"top":"(A) pay \"rent\""}"#);
+        assert_eq!(status.to_plain(), "4 open, 1 overdue, 1 today | (A) pay \"rent\"");
+    }
+
+    #[test]
+    fn blocked_top() {
+        let path = temp_path("status.txt");
+        std::fs::write(&path, "(A) call the roofer dep:quote\n(B) get a quote id:quote\n").unwrap();
+        let list = TaskList::load(&path, 0).unwrap();
+        let status = Status::of_list(&list, NaiveDate::from_ymd_opt(2024, 5, 10).unwrap());
+        assert_eq!((status.open, status.top.as_deref()), (2, Some("(B) get a quote")));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn no_tasks() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let status = Status::of(&tasks(&["x 2024-05-02 done"]), today);
+        #[cfg(feature = "serde")]
+        assert_eq!(status.to_json(), r#"{"open":0,"overdue":0,"today":0,"top":null}"#);
+        assert_eq!(status.to_plain(), "0 open, 0 overdue, 0 today");
+    }
+}
@@ -0,0 +1,407 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+/// True when the files must not be written, see `set_dry_run`
+static DRY_RUN : AtomicBool = AtomicBool::new(false);
+
+/// Stop writing the files: the saves and the backups succeed without changing anything
+///
+/// Arguments:
+///
+/// * `dry_run` - true to stop writing the files
+pub fn set_dry_run(dry_run : bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+/// Return true if the files are not written
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Return the path of the temporary file used to save `path` (`todo.txt.tmp` for `todo.txt`)
+///
+/// Arguments:
+///
+/// * `path` - the path of the saved file
+fn temp_path(path : &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// The encryption of the files whose name ends in `.gpg`, given by the program using the library
+pub trait Cipher {
+    /// Decrypt a file and return its content
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the encrypted file
+    fn decrypt(&self, path : &Path) -> Result<String, String>;
+
+    /// Encrypt the new content of a file and return the encrypted data
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the file, it may not exist yet
+    /// * `content` - the text to encrypt
+    fn encrypt(&self, path : &Path, content : &str) -> Result<Vec<u8>, String>;
+}
+
+/// Return true if a file is encrypted: its name ends in `.gpg`
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+pub fn is_encrypted(path : &Path) -> bool {
+    path.extension().is_some_and(|e| e == "gpg")
+}
+
+/// Return the cipher of an encrypted file, an error if there is none
+fn cipher_of<'a>(path : &Path, cipher : Option<&'a dyn Cipher>) -> io::Result<&'a dyn Cipher> {
+    cipher.ok_or_else(|| io::Error::other(format!("{} is encrypted and there is no program to decrypt it", path.display())))
+}
+
+/// Read a file, decrypting it with `cipher` if its name ends in `.gpg`
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+/// * `cipher` - the encryption of the encrypted files, they can't be read without it
+pub fn read_to_string(path : &Path, cipher : Option<&dyn Cipher>) -> io::Result<String> {
+    read_decrypted(path, is_encrypted(path), cipher)
+}
+
+/// Read a file, decrypting it if `encrypted`, like a backup of an encrypted file
+fn read_decrypted(path : &Path, encrypted : bool, cipher : Option<&dyn Cipher>) -> io::Result<String> {
+    if !encrypted {
+        return fs::read_to_string(path);
+    }
+    // A missing file is reported like the files which are not encrypted
+    fs::metadata(path)?;
+    cipher_of(path, cipher)?.decrypt(path).map_err(io::Error::other)
+}
+
+/// Write a file atomically
+///
+/// The contents are written and synced to a temporary file in the same directory which is then renamed over `path`,
+/// so `path` has either its old or its new contents if the program is stopped while saving.
+/// A symbolic link is followed and the file it points to is replaced, the temporary file is created next to it, not
+/// next to the link, and gets its permissions, so the rename stays on the same device and a private file stays private.
+/// A file which still can't be renamed over across devices, like a file mounted alone in a container, is written in
+/// place from the synced temporary file, which is not atomic.
+/// A file whose name ends in `.gpg` is encrypted with `cipher` before it is written, the temporary file is encrypted too.
+/// Nothing is written in a dry run
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+/// * `contents` - the new contents of the file
+/// * `cipher` - the encryption of the encrypted files, they can't be written without it
+pub fn save_atomic(path : &Path, contents : &str, cipher : Option<&dyn Cipher>) -> io::Result<()> {
+    if dry_run() {
+        return Ok(());
+    }
+    let encrypted;
+    let contents = if is_encrypted(path) {
+        encrypted = cipher_of(path, cipher)?.encrypt(path, contents).map_err(io::Error::other)?;
+        &encrypted[..]
+    } else {
+        contents.as_bytes()
+    };
+    // A new file has no link to follow
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let permissions = fs::metadata(&target).ok().map(|metadata| metadata.permissions());
+    let temp = temp_path(&target);
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        // The permissions are set before the contents are written
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    let renamed = written.and_then(|_| match fs::rename(&temp, &target) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => fs::write(&target, contents).and_then(|_| fs::remove_file(&temp)),
+        result => result
+    });
+    if let Err(e) = renamed {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    // Persisting the rename, not every platform can open a directory
+    if let Some(dir) = target.parent().filter(|d| !d.as_os_str().is_empty()) {
+        let _ = fs::File::open(dir).and_then(|d| d.sync_all());
+    }
+    Ok(())
+}
+
+/// Return true if a file exists and can't be written, without write permission or on a read-only mount
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+pub fn is_read_only(path : &Path) -> bool {
+    match fs::metadata(path) {
+        // Opening the file to append to it doesn't change it
+        Ok(metadata) => metadata.permissions().readonly() || fs::OpenOptions::new().append(true).open(path).is_err(),
+        Err(_) => false
+    }
+}
+
+/// Return the path of a backup of a file (`todo.txt.bak.1` for the last backup of `todo.txt`)
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+/// * `number` - the number of the backup, starting from 1 for the most recent
+pub fn backup_path(path : &Path, number : usize) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(format!(".bak.{}", number));
+    path.with_file_name(name)
+}
+
+/// Copy a file to its first backup, shifting the older backups and removing the ones beyond `keep`
+///
+/// Nothing is done if the file does not exist or `keep` is 0
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+/// * `keep` - the number of backups to keep
+pub fn rotate_backups(path : &Path, keep : usize) -> io::Result<()> {
+    if keep == 0 || !path.exists() || dry_run() {
+        return Ok(());
+    }
+    let mut number = keep;
+    while backup_path(path, number).exists() {
+        fs::remove_file(backup_path(path, number))?;
+        number += 1;
+    }
+    for number in (1..keep).rev() {
+        let backup = backup_path(path, number);
+        if backup.exists() {
+            fs::rename(&backup, backup_path(path, number + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+/// Return the backups of a file with their number and modification time, the most recent first
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+pub fn list_backups(path : &Path) -> Vec<(usize, PathBuf, SystemTime)> {
+    let mut backups = Vec::new();
+    let mut number = 1;
+    while let Ok(metadata) = fs::metadata(backup_path(path, number)) {
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        backups.push((number, backup_path(path, number), modified));
+        number += 1;
+    }
+    backups
+}
+
+/// Replace a file with one of its backups
+///
+/// The current file is backed up first so the restore can be undone
+///
+/// Arguments:
+///
+/// * `path` - the path of the file
+/// * `number` - the number of the backup
+/// * `keep` - the number of backups to keep
+/// * `cipher` - the encryption of an encrypted file and its backups
+pub fn restore_backup(path : &Path, number : usize, keep : usize, cipher : Option<&dyn Cipher>) -> io::Result<()> {
+    let contents = read_decrypted(&backup_path(path, number), is_encrypted(path), cipher)?;
+    rotate_backups(path, keep)?;
+    save_atomic(path, &contents, cipher)
+}
+
+#[cfg(test)]
+mod storage_tests {
+    use super::*;
+    use crate::test_files::{temp_dir, FakeCipher};
+
+    #[test]
+    fn temp_path_test() {
+        assert_eq!(temp_path(Path::new("/home/me/todo.txt")), PathBuf::from("/home/me/todo.txt.tmp"));
+        assert_eq!(temp_path(Path::new("todo.txt")), PathBuf::from("todo.txt.tmp"));
+    }
+
+    #[test]
+    fn save_new_and_existing_file() {
+        let dir = temp_dir("storage_save");
+        let path = dir.join("todo.txt");
+        save_atomic(&path, "first\n", None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\n");
+        save_atomic(&path, "second\n", None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+        assert!(!temp_path(&path).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_and_permissions_kept() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = temp_dir("storage_symlink");
+        fs::create_dir(dir.join("dotfiles")).unwrap();
+        let target = dir.join("dotfiles").join("todo.txt");
+        fs::write(&target, "first\n").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        let link = dir.join("todo.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        // The file the link points to is replaced, the link stays a link
+        save_atomic(&link, "second\n", None).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "second\n");
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(!temp_path(&link).exists() && !temp_path(&target).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_write_keeps_original() {
+        let dir = temp_dir("storage_failure");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "original\n").unwrap();
+        // A directory in place of the temporary file makes its creation fail, even when running as root
+        fs::create_dir(temp_path(&path)).unwrap();
+        assert!(save_atomic(&path, "new\n", None).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_only_directory_keeps_original() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = temp_dir("storage_readonly");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "original\n").unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+        let saved = save_atomic(&path, "new\n", None);
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        if saved.is_ok() {
+            // Root can write in read-only directories, `failed_save_keeps_original` covers it
+            eprintln!("read_only_directory_keeps_original skipped: the directory is writable by this user");
+        } else {
+            assert_eq!(fs::read_to_string(&path).unwrap(), "original\n");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_save_keeps_original() {
+        let dir = temp_dir("storage_failed");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "original\n").unwrap();
+        // The temporary file can't be created where a directory is, whoever runs the test
+        fs::create_dir(temp_path(&path)).unwrap();
+        assert!(save_atomic(&path, "new\n", None).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original\n");
+        fs::remove_dir(temp_path(&path)).unwrap();
+        // The temporary file is removed when it can't replace the file
+        let busy = dir.join("busy.txt");
+        fs::create_dir(&busy).unwrap();
+        fs::write(busy.join("task.txt"), "call mom\n").unwrap();
+        assert!(save_atomic(&busy, "new\n", None).is_err());
+        assert!(!temp_path(&busy).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_only_file() {
+        let dir = temp_dir("storage_read_only");
+        let path = dir.join("todo.txt");
+        assert!(!is_read_only(&path));
+        fs::write(&path, "call mom\n").unwrap();
+        assert!(!is_read_only(&path));
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+        assert!(is_read_only(&path));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backup_path_test() {
+        assert_eq!(backup_path(Path::new("/home/me/todo.txt"), 2), PathBuf::from("/home/me/todo.txt.bak.2"));
+    }
+
+    #[test]
+    fn rotating_backups() {
+        let dir = temp_dir("storage_backups");
+        let path = dir.join("todo.txt");
+        rotate_backups(&path, 3).unwrap();
+        assert!(list_backups(&path).is_empty());
+        for version in 1..=5 {
+            fs::write(&path, format!("version {}\n", version)).unwrap();
+            rotate_backups(&path, 3).unwrap();
+        }
+        let backups = list_backups(&path);
+        assert_eq!(backups.iter().map(|b| b.0).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "version 5\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 3)).unwrap(), "version 3\n");
+
+        // Lowering the limit removes the older backups
+        rotate_backups(&path, 1).unwrap();
+        assert_eq!(list_backups(&path).len(), 1);
+
+        // 0 disables the backups
+        fs::write(&path, "version 6\n").unwrap();
+        rotate_backups(&path, 0).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "version 5\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restoring_backup() {
+        let dir = temp_dir("storage_restore");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "old\n").unwrap();
+        rotate_backups(&path, 3).unwrap();
+        fs::write(&path, "new\n").unwrap();
+        restore_backup(&path, 1, 3, None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "new\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "old\n");
+        assert!(restore_backup(&path, 5, 3, None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn encrypted_file() {
+        assert!(is_encrypted(Path::new("/home/me/todo.txt.gpg")));
+        assert!(!is_encrypted(Path::new("/home/me/todo.txt")));
+        assert!(!is_encrypted(Path::new("/home/me/gpg")));
+        let dir = temp_dir("storage_encrypted");
+        let path = dir.join("todo.txt.gpg");
+        fs::write(&path, "FAKEGPG 0123456789ABCDEF\nold\n").unwrap();
+        assert_eq!(read_to_string(&path, Some(&FakeCipher)).unwrap(), "old\n");
+        assert!(read_to_string(&path, None).unwrap_err().to_string().ends_with("is encrypted and there is no program to decrypt it"));
+        rotate_backups(&path, 3).unwrap();
+        save_atomic(&path, "new\n", Some(&FakeCipher)).unwrap();
+        // The file, its temporary file and its backups are never in plain text
+        assert_eq!(fs::read_to_string(&path).unwrap(), "FAKEGPG 0123456789ABCDEF\nnew\n");
+        assert!(!temp_path(&path).exists());
+        restore_backup(&path, 1, 3, Some(&FakeCipher)).unwrap();
+        assert_eq!(read_to_string(&path, Some(&FakeCipher)).unwrap(), "old\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "FAKEGPG 0123456789ABCDEF\nnew\n");
+        assert_eq!(read_to_string(&dir.join("missing.txt.gpg"), Some(&FakeCipher)).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        // A file which can't be encrypted is not written
+        let new = dir.join("new.txt.gpg");
+        assert!(save_atomic(&new, "secret\n", Some(&FakeCipher)).unwrap_err().to_string().contains("no recipient"));
+        assert!(!new.exists() && !temp_path(&new).exists());
+        fs::write(&path, "broken\n").unwrap();
+        assert!(read_to_string(&path, Some(&FakeCipher)).unwrap_err().to_string().starts_with("Could not decrypt"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,17 +1,202 @@
-use chrono::{NaiveDate, Local, Datelike};
+use chrono::{Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime};
 use regex::{Regex, CaptureMatches, Captures};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{self, AtomicUsize};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use crate::recurrence::Recurrence;
+use crate::markup::{self, MarkupColors};
+use crate::urgency::UrgencyWeights;
+use crate::dates::{parse_due, parse_due_time, parse_due_tag, due_tag, parse_estimate, format_estimate, DateStyle, Workdays};
+use crate::links::find_urls;
+use crate::builder::TaskBuilder;
+use crate::template::{Template, Field};
+use crate::locale::Locale;
 
+/// An error returned when a todo.txt line can't be parsed into a `Task`
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaskParseError {
+    /// The line doesn't follow the todo.txt structure
+    MalformedLine,
+    /// A date of the line doesn't exist (e.g. `2021-02-30`)
+    InvalidDate {
+        /// The date of the line, `creation` or `completion`
+        field : &'static str,
+        /// The date as written
+        value : String
+    },
+    /// A custom tag has a value that can't be read
+    InvalidTag {
+        /// The key of the tag
+        key : String,
+        /// The value as written
+        value : String
+    },
+    /// The task has no content
+    EmptyContent
+}
+
+impl fmt::Display for TaskParseError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskParseError::MalformedLine => write!(f, "malformed task"),
+            TaskParseError::InvalidDate { field, value } => write!(f, "invalid {} date {}", field, value),
+            TaskParseError::InvalidTag { key, value } => write!(f, "invalid value {} for tag {}", value, key),
+            TaskParseError::EmptyContent => write!(f, "the task is empty")
+        }
+    }
+}
+
+impl std::error::Error for TaskParseError { }
+
+impl From<TaskParseError> for String {
+    fn from(e : TaskParseError) -> Self {
+        e.to_string()
+    }
+}
+
+/// An error returned when a priority is not an uppercase letter
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidPriority(pub char);
+
+impl fmt::Display for InvalidPriority {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid priority {}, a priority goes from A to Z", self.0)
+    }
+}
+
+impl std::error::Error for InvalidPriority { }
 
-#[derive(Clone)]
+/// A sort key of the tasks, the tasks without a value for the key are last
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
 pub enum SortTaskBy {
+    /// Oldest tasks first
     CreationDate,
+    /// Alphabetical order of the content
     Content,
+    /// Priority A first
     Priority,
-    DueDate
+    /// Earliest due date first
+    DueDate,
+    /// Earliest completion date first
+    CompletionDate,
+    /// Most urgent tasks first, see `Task::urgency`
+    Urgency,
+    /// Shortest `est:` estimate first, see `Task::get_estimate`
+    Estimate
+}
+
+impl SortTaskBy {
+    /// Every sort key, in the order of the menus
+    pub const ALL : [SortTaskBy; 7] = [SortTaskBy::Content, SortTaskBy::CreationDate, SortTaskBy::Priority, SortTaskBy::DueDate, SortTaskBy::CompletionDate, SortTaskBy::Urgency, SortTaskBy::Estimate];
+
+    /// Return the name of the key in the `--sort` option
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortTaskBy::Content         => "content",
+            SortTaskBy::CreationDate    => "creation",
+            SortTaskBy::Priority        => "priority",
+            SortTaskBy::DueDate         => "due",
+            SortTaskBy::CompletionDate  => "completion",
+            SortTaskBy::Urgency         => "urgency",
+            SortTaskBy::Estimate        => "estimate"
+        }
+    }
+
+    /// Return the key with a name of the `--sort` option (case insensitive), `None` if the name is unknown
+    ///
+    /// Arguments:
+    ///
+    /// * `name` - the name of the key
+    pub fn from_name(name : &str) -> Option<Self> {
+        Self::ALL.iter().find(|key| key.name().eq_ignore_ascii_case(name)).cloned()
+    }
+}
+
+/// The direction of a sort
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+pub enum SortDirection {
+    /// In the order of the sort keys
+    Ascending,
+    /// In the reverse order of the sort keys
+    Descending
+}
+
+impl SortDirection {
+    /// Apply the direction to the result of a comparison
+    pub fn apply(self, ordering : std::cmp::Ordering) -> std::cmp::Ordering {
+        match self {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse()
+        }
+    }
 }
 
+/// How far a task is postponed, see `Task::postpone`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PostponeBy {
+    /// A number of days
+    Days(u32),
+    /// A number of weeks
+    Weeks(u32),
+    /// Clamped to the last day of the month (Jan 31 + 1 month is Feb 28/29)
+    Months(u32),
+    /// A given date
+    Until(NaiveDate)
+}
+
+impl PostponeBy {
+    /// The offsets proposed to postpone a task
+    pub const PRESETS : [PostponeBy; 4] = [PostponeBy::Days(1), PostponeBy::Days(3), PostponeBy::Weeks(1), PostponeBy::Months(1)];
+}
+
+impl fmt::Display for PostponeBy {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let (amount, unit) = match self {
+            PostponeBy::Days(n) => (n, "day"),
+            PostponeBy::Weeks(n) => (n, "week"),
+            PostponeBy::Months(n) => (n, "month"),
+            PostponeBy::Until(date) => return write!(f, "{}", date.format("%Y-%m-%d"))
+        };
+        write!(f, "+{} {}{}", amount, unit, if *amount > 1 { "s" } else { "" })
+    }
+}
+
+/// The identity of a task in its task list, kept when the task is modified and whatever the sort order
+///
+/// The ids are given by `TaskList` to the tasks it loads or adds, they are never reused during a session
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(usize);
+
+impl TaskId {
+    /// Return an id never returned before
+    pub(crate) fn next() -> Self {
+        static NEXT_ID : AtomicUsize = AtomicUsize::new(0);
+        TaskId(NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+}
+
+/// A custom `key:value` tag
+#[derive(Clone,Debug,PartialEq,Eq,PartialOrd,Ord,Hash)]
+struct CustomTag {
+    key : String,
+    value : String,
+    /// The index of the word of the content before which the tag was found, `None` to put it at the end
+    position : Option<usize>
+}
+
+/// The fields compared by the equality, the order and the hash of the tasks
+///
+/// The project and context tags are read from the content and the recurrence from the custom tags, they are left out.
+/// The line and the id of the task tell where it is, not what it is, they are left out too
+type TaskFields<'a> = (&'a String, bool, Option<char>, Option<NaiveDate>, Option<NaiveDate>, Option<NaiveDate>,
+                       Option<NaiveDate>, &'a Vec<CustomTag>);
+
 /// A task struct
 #[derive(Clone,Debug)]
 pub struct Task {
@@ -19,6 +204,10 @@ pub struct Task {
     pub content : String,
     /// An optionnal `NaiveDate` corresponding to when the task should be done
     duedate : Option<NaiveDate>,
+    /// The time of the due date, `due:2024-05-06T14:00`
+    duetime : Option<NaiveTime>,
+    /// An optionnal `NaiveDate` before which the task should not be shown
+    threshold : Option<NaiveDate>,
     /// Is the task done
     pub completion : bool,
     /// When the task was completed
@@ -31,8 +220,14 @@ pub struct Task {
     project_tags : Vec<String>,
     /// A list of context tags
     context_tags : Vec<String>,
-    /// Custom tags with key and value
-    custom_tags : HashMap<String,String>
+    /// Custom tags with key and value, in the order they appear in the line
+    custom_tags : Vec<CustomTag>,
+    /// An optionnal `Recurrence` read from the `rec` custom tag
+    recurrence : Option<Recurrence>,
+    /// The index of the line of the task in the task file, the new tasks are numbered after the loaded lines
+    source_line : Option<usize>,
+    /// The id given by the task list holding the task
+    id : Option<TaskId>
 }
 
 impl Task {
@@ -47,13 +242,18 @@ impl Task {
         Task {
             content: String::new(),
             duedate: None,
+            duetime: None,
+            threshold: None,
             completion : false,
             context_tags : vec![],
             project_tags : vec![],
             priority : None,
             creation_date : None,
             completion_date : None,
-            custom_tags : HashMap::new()
+            custom_tags : vec![],
+            recurrence : None,
+            source_line : None,
+            id : None
         }
     }
 
@@ -65,25 +265,19 @@ impl Task {
     /// 
     /// * `content` - the content of the task
     pub fn new(content: String) -> Self {
-        let today = Local::now();
-        let mut task = Self::empty();
-        task.set_content(content);
-        task.creation_date = Some(NaiveDate::from_ymd(today.year(), today.month(), today.day()));
-        task
+        Self::new_with_date(content, Local::now().date_naive())
     }
 
-    /// Create a new `Task`
+    /// Create a new `Task` created at a date
     /// 
-    /// Create a new `Task` from its content and date
+    /// Unlike `TaskBuilder::build` the content is not checked, a line which can't be read is kept as plain text
     /// 
     /// Arguments:
     /// 
     /// * `content` - the content of the task
-    /// * `date` - the date when the task should be done
+    /// * `date` - the creation date of the task
     pub fn new_with_date(content: String, date: NaiveDate) -> Self {
-        let mut t = Self::new(content);
-        t.set_due(Some(date));
-        t
+        TaskBuilder::new().content(&content).created(date).assemble()
     }
 
     /// Change the content of a task
@@ -98,6 +292,101 @@ impl Task {
         self.extract_tags();
     }
 
+    /// Add a project tag at the end of the content, nothing is done if the task already has it
+    /// 
+    /// Arguments:
+    /// 
+    /// * `tag` - the name of the tag, without `+`
+    pub fn add_project_tag(&mut self, tag: &str) {
+        self.add_content_tag('+', tag);
+    }
+
+    /// Add a context tag at the end of the content, nothing is done if the task already has it
+    /// 
+    /// Arguments:
+    /// 
+    /// * `tag` - the name of the tag, without `@`
+    pub fn add_context_tag(&mut self, tag: &str) {
+        self.add_content_tag('@', tag);
+    }
+
+    /// Remove a project tag from the content
+    /// 
+    /// Arguments:
+    /// 
+    /// * `tag` - the name of the tag, without `+`
+    pub fn remove_project_tag(&mut self, tag: &str) {
+        self.remove_content_tag('+', tag);
+    }
+
+    /// Remove a context tag from the content
+    /// 
+    /// Arguments:
+    /// 
+    /// * `tag` - the name of the tag, without `@`
+    pub fn remove_context_tag(&mut self, tag: &str) {
+        self.remove_content_tag('@', tag);
+    }
+
+    /// Return the words of the content, as separated in the todo.txt line
+    fn content_words(&self) -> Vec<&str> {
+        if self.content.is_empty() { vec![] } else { self.content.split(' ').collect() }
+    }
+
+    /// Add a project or context tag at the end of the content if it is not already in it
+    fn add_content_tag(&mut self, prefix: char, tag: &str) {
+        let tag = format!("{}{}", prefix, tag);
+        let words = self.content_words();
+        if words.contains(&tag.as_str()) {
+            return;
+        }
+        // The custom tags at the end of the line stay after the new tag
+        let len = words.len();
+        for custom in self.custom_tags.iter_mut().filter(|t| t.position.is_some_and(|p| p >= len)) {
+            custom.position = None;
+        }
+        let content = if self.content.is_empty() { tag } else { format!("{} {}", self.content, tag) };
+        self.set_content(content);
+    }
+
+    /// Remove every occurrence of a project or context tag from the content
+    fn remove_content_tag(&mut self, prefix: char, tag: &str) {
+        let tag = format!("{}{}", prefix, tag);
+        let mut words = self.content_words().into_iter().map(String::from).collect::<Vec<_>>();
+        while let Some(index) = words.iter().position(|w| *w == tag) {
+            words.remove(index);
+            // The custom tags keep their place among the other words
+            for custom in self.custom_tags.iter_mut() {
+                if let Some(position) = custom.position.as_mut() {
+                    if *position > index {
+                        *position -= 1;
+                    }
+                }
+            }
+        }
+        self.set_content(words.join(" "));
+    }
+
+    /// Rename a project or context tag in the content of the task and extract the new tags
+    /// 
+    /// Only whole tags are renamed: renaming `+garage` doesn't change `+garage2` or the word `garage`.
+    /// Return true if the tag was found
+    /// 
+    /// Arguments:
+    /// 
+    /// * `prefix` - `+` for a project tag, `@` for a context tag
+    /// * `old` - the name of the tag, without the prefix
+    /// * `new` - the new name of the tag, without the prefix
+    pub fn rename_tag(&mut self, prefix: char, old: &str, new: &str) -> bool {
+        let (old, new) = (format!("{}{}", prefix, old), format!("{}{}", prefix, new));
+        if !self.content.split(' ').any(|word| word == old) {
+            return false;
+        }
+        let content = self.content.split(' ').map(|word| if word == old { new.as_str() } else { word }).collect::<Vec<_>>().join(" ");
+        self.set_content(content);
+        true
+    }
+
     /// Get the content of the task
     /// 
     /// Return `&String` to the content of the task
@@ -115,14 +404,90 @@ impl Task {
         &self.project_tags
     }
 
+    /// Get the priority of the task
+    /// 
+    /// For a completed task, the priority is read from the `pri` custom tag
+    pub fn get_priority(&self) -> Option<char> {
+        if self.priority.is_none() && self.completion {
+            if let Some(p) = self.get_custom_tag("pri") {
+                return Self::parse_priority(p);
+            }
+        }
+        self.priority
+    }
+
+    /// Set the priority of the task
+    ///
+    /// For a completed task, the priority is kept in the `pri` custom tag
+    ///
+    /// Arguments:
+    ///
+    /// * `priority` - the priority from A to Z, or `None` to remove it
+    pub fn set_priority(&mut self, priority: Option<char>) -> Result<(), InvalidPriority> {
+        match priority {
+            Some(p) if !p.is_ascii_uppercase() => return Err(InvalidPriority(p)),
+            _ => ()
+        }
+        if self.completion {
+            match priority {
+                Some(p) => self.set_custom_tag("pri", &p.to_string()).expect("a priority is a valid tag"),
+                None => { self.remove_custom_tag("pri"); }
+            }
+        } else {
+            self.priority = priority;
+        }
+        Ok(())
+    }
+
+    /// Increase the priority by one letter, a task without priority gets Z and A stays A
+    pub fn bump_priority(&mut self) {
+        let priority = match self.get_priority() {
+            None => 'Z',
+            Some('A') => 'A',
+            Some(p) => (p as u8 - 1) as char
+        };
+        self.set_priority(Some(priority)).expect("the priority is a letter");
+    }
+
+    /// Decrease the priority by one letter, a task with priority Z loses its priority
+    pub fn lower_priority(&mut self) {
+        let priority = match self.get_priority() {
+            None | Some('Z') => None,
+            Some(p) => Some((p as u8 + 1) as char)
+        };
+        self.set_priority(priority).expect("the priority is a letter");
+    }
+
+    /// Parse a priority from a `String` containing a single uppercase letter
+    fn parse_priority(p : &str) -> Option<char> {
+        let mut chars = p.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_uppercase() => Some(c),
+            _ => None
+        }
+    }
+
     /// Get the due date of the task
     pub fn get_due(&self) -> &Option<NaiveDate> {
         &self.duedate
     }
 
+    /// Get the time of the due date of the task, `None` for a task due any time of its due date
+    pub fn get_due_time(&self) -> Option<NaiveTime> {
+        self.duetime
+    }
+
+    /// Return the moment a timed task is due, the end of its due date for a task without time
+    fn due_moment(&self) -> Option<NaiveDateTime> {
+        self.duedate.map(|date| match self.duetime {
+            Some(time) => date.and_time(time),
+            None => date.and_hms_opt(23, 59, 59).expect("23:59:59 is a valid time")
+        })
+    }
+
     /// Set the due date of a task
     /// 
-    /// Change the due date of the task and store it in a custom tag
+    /// Change the due date of the task and store it in a custom tag, the time of the due date is kept
     /// 
     /// Arguments:
     /// 
@@ -130,97 +495,607 @@ impl Task {
     pub fn set_due(&mut self, date: Option<NaiveDate>) {
         self.duedate = date;
         match date {
-            Some(date) => { self.custom_tags.insert(String::from("due"), format!("{}",date.format("%Y-%m-%d"))); },
-            None => { self.custom_tags.remove_entry(&String::from("due")); }
+            Some(date) => { self.insert_tag("due", due_tag(date, self.duetime)); },
+            None => {
+                self.duetime = None;
+                self.remove_tag("due");
+            }
+        }
+    }
+
+    /// Set the time of the due date of a task, a task without due date stays without
+    /// 
+    /// Arguments:
+    /// 
+    /// * `time` - the time, `None` for a task due any time of its due date
+    pub fn set_due_time(&mut self, time: Option<NaiveTime>) {
+        if let Some(date) = self.duedate {
+            self.duetime = time;
+            self.insert_tag("due", due_tag(date, time));
+        }
+    }
+
+    /// Postpone the due date of a task
+    ///
+    /// The offset is added to the due date, or to `today` for a task without due date
+    ///
+    /// Arguments:
+    ///
+    /// * `offset` - how far the task is postponed
+    /// * `today` - the current date
+    pub fn postpone(&mut self, offset: PostponeBy, today: NaiveDate) {
+        self.postpone_with(offset, today, &Workdays::default());
+    }
+
+    /// Postpone the due date of a task, moved to the next working day if it skips the weekends
+    /// 
+    /// The date is moved once the months are clamped to their last day, a date given with `PostponeBy::Until` is kept
+    /// 
+    /// Arguments:
+    /// 
+    /// * `offset` - how far the task is postponed
+    /// * `today` - the current date
+    /// * `workdays` - the days of the weekend and the tasks skipping them
+    pub fn postpone_with(&mut self, offset: PostponeBy, today: NaiveDate, workdays: &Workdays) {
+        let from = self.duedate.unwrap_or(today);
+        let date = match offset {
+            PostponeBy::Days(n) => from + Duration::days(n as i64),
+            PostponeBy::Weeks(n) => from + Duration::weeks(n as i64),
+            PostponeBy::Months(n) => from + Months::new(n),
+            PostponeBy::Until(date) => {
+                self.set_due(Some(date));
+                return;
+            }
+        };
+        self.set_due(Some(if self.skips_weekends(workdays) { workdays.next_workday(date) } else { date }));
+    }
+
+    /// Return true if the postponed and recurring due dates of the task skip the weekends: with a `wd:1` tag, or when
+    /// all the tasks skip them and it has no `wd:0` tag
+    /// 
+    /// Arguments:
+    /// 
+    /// * `workdays` - the days of the weekend and the tasks skipping them
+    pub fn skips_weekends(&self, workdays: &Workdays) -> bool {
+        match self.get_custom_tag("wd") {
+            Some("1") => true,
+            Some("0") => false,
+            _ => workdays.skip_weekends
+        }
+    }
+
+    /// Get the threshold date of the task
+    pub fn get_threshold(&self) -> &Option<NaiveDate> {
+        &self.threshold
+    }
+
+    /// Return true if the task is not completed and its due date is before `today`, see `is_overdue_at` for the due
+    /// times
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        match self.duedate {
+            Some(due) => !self.completion && due < today,
+            None => false
+        }
+    }
+
+    /// Return true if the task is not completed and due before `now`, a task without time is due until the end of its due date
+    /// 
+    /// Arguments:
+    /// 
+    /// * `now` - the current date and time
+    pub fn is_overdue_at(&self, now: NaiveDateTime) -> bool {
+        match (self.duedate, self.duetime) {
+            (Some(due), Some(time)) => !self.completion && due.and_time(time) < now,
+            (Some(due), None) => !self.completion && due < now.date(),
+            (None, _) => false
+        }
+    }
+
+    /// Return true if the task is not completed and due `today`
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    pub fn is_due_today(&self, today: NaiveDate) -> bool {
+        self.is_due_within(today, 0)
+    }
+
+    /// Return true if the task is not completed and due between `today` and `days` days later (overdue tasks excluded)
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    /// * `days` - the number of days after today
+    pub fn is_due_within(&self, today: NaiveDate, days: i64) -> bool {
+        match self.duedate {
+            Some(due) => !self.completion && due >= today && due <= today + chrono::Duration::days(days),
+            None => false
+        }
+    }
+
+    /// Return true if the task to do needs attention today: overdue, due today, with its threshold date reached or priority A
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    pub fn is_for_today(&self, today: NaiveDate) -> bool {
+        !self.completion && (self.is_overdue(today)
+                             || self.is_due_today(today)
+                             || self.threshold.is_some_and(|date| date <= today)
+                             || self.priority == Some('A'))
+    }
+
+    /// Return the number of days since the creation of the task, `None` if it has no creation date
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    pub fn age_days(&self, today: NaiveDate) -> Option<i64> {
+        self.creation_date.map(|creation| (today - creation).num_days())
+    }
+
+    /// Return true if the task is not completed and was created more than `days` days before `today`
+    /// 
+    /// A task without creation date is never stale, nor any task when `days` is 0
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    /// * `days` - the age after which a task is stale
+    pub fn is_stale(&self, today: NaiveDate, days: u32) -> bool {
+        days > 0 && !self.completion && self.age_days(today).is_some_and(|age| age > days as i64)
+    }
+
+    /// Return the urgency score of the task with the default weights
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    pub fn urgency(&self, today: NaiveDate) -> f64 {
+        self.urgency_with(today, &UrgencyWeights::default())
+    }
+
+    /// Return the urgency score of the task, the higher the more urgent
+    /// 
+    /// Completed tasks have an urgency of 0
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    /// * `weights` - the weights of the criteria
+    pub fn urgency_with(&self, today: NaiveDate, weights: &UrgencyWeights) -> f64 {
+        if self.completion {
+            return 0.0;
+        }
+        let mut urgency = self.project_tags.len() as f64 * weights.project;
+        if let Some(priority) = self.priority {
+            urgency += weights.priority_score(priority);
+        }
+        if let Some(due) = self.duedate {
+            urgency += weights.due + weights.overdue_score((today - due).num_days());
+        }
+        if let Some(age) = self.age_days(today) {
+            urgency += weights.age_score(age);
+        }
+        urgency
+    }
+
+    /// Set the threshold date of a task
+    /// 
+    /// Change the threshold date of the task and store it in a custom tag
+    /// 
+    /// Arguments:
+    /// 
+    /// * `date` - a `Option<NaiveDate>` containing the date or None
+    pub fn set_threshold(&mut self, date: Option<NaiveDate>) {
+        self.threshold = date;
+        match date {
+            Some(date) => { self.insert_tag("t", format!("{}",date.format("%Y-%m-%d"))); },
+            None => { self.remove_tag("t"); }
+        }
+    }
+
+    /// Return true if the task should be shown on a given date
+    /// 
+    /// A task is hidden until its threshold date
+    /// 
+    /// Arguments:
+    /// 
+    /// * `date` - the date to check
+    pub fn is_visible_on(&self, date: NaiveDate) -> bool {
+        match self.threshold {
+            Some(threshold) => threshold <= date,
+            None => true
+        }
+    }
+
+    /// Return true if the task is hidden with a `h:1` custom tag
+    pub fn is_hidden(&self) -> bool {
+        self.get_custom_tag("h") == Some("1")
+    }
+
+    /// Return true if the task is pinned to the top of the lists with a `star:1` custom tag
+    pub fn is_pinned(&self) -> bool {
+        self.get_custom_tag("star") == Some("1")
+    }
+
+    /// Pin the task to the top of the lists with a `star:1` custom tag, or remove the tag
+    ///
+    /// Arguments:
+    ///
+    /// * `pinned` - true to pin the task
+    pub fn set_pinned(&mut self, pinned : bool) {
+        if pinned {
+            self.set_custom_tag("star", "1").expect("star:1 is a valid tag");
+        } else {
+            self.remove_custom_tag("star");
         }
     }
 
     /// Set the task as completed
     /// 
     /// Change the completion to `true` and store the actual date as completion date.
-    /// If there is no creation date for the task, it creates a creation date identical to the completion date
+    /// If there is no creation date for the task, it creates a creation date identical to the completion date.
+    /// The priority is moved to a `pri` custom tag as completed tasks have no priority in todo.txt
     pub fn set_completed(&mut self) {
+        self.set_completed_on(Local::now().date_naive());
+    }
+
+    /// Set the task as completed on a date, like `set_completed`
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the completion date
+    pub fn set_completed_on(&mut self, today: NaiveDate) {
         self.completion = true;
-        let today = Local::now();
-        self.completion_date = Some(NaiveDate::from_ymd(today.year(), today.month(), today.day()));
+        if let Some(p) = self.priority.take() {
+            self.insert_tag("pri", p.to_string());
+        }
+        self.completion_date = Some(today);
         // Adding a creation date to respect the todo.txt specification (no task with a completion date and without a creation date)
-        match self.creation_date {
-            None => self.creation_date = Some(NaiveDate::from_ymd(today.year(), today.month(), today.day())),
-            _ => ()
+        if self.creation_date.is_none() {
+            self.creation_date = Some(today);
         }
     }
 
     /// Set a task as to do
     /// 
-    /// Change the completion status to `false` and remove the completion date
+    /// Change the completion status to `false`, remove the completion date and restore the priority
+    /// from the `pri` custom tag
     pub fn set_not_completed(&mut self) {
+        if let Some(p) = self.remove_tag("pri") {
+            if self.priority.is_none() {
+                self.priority = Self::parse_priority(&p);
+            }
+        }
         self.completion = false;
         self.completion_date = None;
     }
 
-    /// Return a `String` representation of the task
+    /// Build the next occurrence of a recurring task
     /// 
-    /// Show the priority (optionnal), content and due date (optionnal)
-    pub fn to_string(&self) -> String  {
-        let mut s = String::new();
-        if let Some(priority) = self.priority {
-            s.push_str(&format!("({}) ", priority));
+    /// Return a new uncompleted `Task` whose due date is advanced by the recurrence interval.
+    /// A strict recurrence (`rec:+1m`) starts from the due date, otherwise the interval starts
+    /// from the completion date (tasks without a due date always recur from the completion date).
+    /// Return `None` if the task has no valid recurrence.
+    pub fn next_occurrence(&self) -> Option<Task> {
+        self.next_occurrence_with(Local::now().date_naive(), &Workdays::default())
+    }
+
+    /// Build the next occurrence of a recurring task, see `next_occurrence`, its due date is moved to the next working
+    /// day if it skips the weekends
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the creation date of the next occurrence, and its start if the task has no completion date
+    /// * `workdays` - the days of the weekend and the tasks skipping them
+    pub fn next_occurrence_with(&self, today: NaiveDate, workdays: &Workdays) -> Option<Task> {
+        let recurrence = self.recurrence?;
+        let completion = self.completion_date.unwrap_or(today);
+        let base = match self.duedate {
+            Some(due) if recurrence.strict => due,
+            _ => completion
+        };
+        let mut next = self.clone();
+        next.source_line = None;
+        next.id = None;
+        next.set_not_completed();
+        next.creation_date = Some(today);
+        let due = recurrence.advance(base);
+        next.set_due(Some(if self.skips_weekends(workdays) { workdays.next_workday(due) } else { due }));
+        Some(next)
+    }
+
+    /// Return the index of the line of the task in the task file
+    pub fn get_source_line(&self) -> Option<usize> {
+        self.source_line
+    }
+
+    /// Set the index of the line of the task in the task file
+    /// 
+    /// Arguments:
+    /// 
+    /// * `line` - the index of the line, starting from 0
+    pub fn set_source_line(&mut self, line: Option<usize>) {
+        self.source_line = line;
+    }
+
+    /// Return the id given by the task list holding the task, `None` if it is in no list
+    pub fn id(&self) -> Option<TaskId> {
+        self.id
+    }
+
+    /// Set the id of the task
+    pub(crate) fn set_id(&mut self, id : TaskId) {
+        self.id = Some(id);
+    }
+
+    /// Return the line of the task shown in the menus
+    /// 
+    /// Show the priority (optionnal), due date (optionnal) and content, with the default template
+    pub fn display_line(&self) -> String  {
+        self.display_line_with(&Template::default(), Local::now().date_naive(), DateStyle::Absolute)
+    }
+
+    /// Return the line of the task laid out by a template
+    /// 
+    /// Arguments:
+    /// 
+    /// * `template` - the layout of the line
+    /// * `today` - the current date
+    /// * `dates` - how the dates without format are shown
+    pub fn display_line_with(&self, template : &Template, today : NaiveDate, dates : DateStyle) -> String {
+        template.render(|field, spec| self.field_value(field, spec, today, dates, template.date_format()), |t| t.to_string())
+    }
+
+    /// Return the value of a field of a template, `None` when the task has no value
+    /// 
+    /// The dates with a format are written at the time of the due date, or at midnight, a format the date can't be
+    /// written with gives no value. The other dates are written with `date_format` when they are not relative
+    fn field_value(&self, field : &Field, spec : Option<&str>, today : NaiveDate, dates : DateStyle, date_format : &str) -> Option<String> {
+        let date = |date : Option<NaiveDate>, time : Option<NaiveTime>| {
+            let date = date?;
+            match spec {
+                Some(spec) => {
+                    let mut s = String::new();
+                    write!(s, "{}", date.and_time(time.unwrap_or_default()).format(spec)).ok()?;
+                    Some(s)
+                },
+                None => Some(format!("{}{}", dates.format_with(date, today, date_format), time.map(|t| format!(" {}", t.format("%H:%M"))).unwrap_or_default()))
+            }
+        };
+        let tags = |prefix : char, tags : &[String]| Some(tags.iter().map(|t| format!("{}{}", prefix, t)).collect::<Vec<_>>().join(" "));
+        match field {
+            Field::Priority => self.priority.map(|p| format!("({})", p)),
+            Field::Content => Some(self.content.clone()),
+            Field::Due => date(self.duedate, self.duetime),
+            Field::Threshold => date(self.threshold, None),
+            Field::Created => date(self.creation_date, None),
+            Field::Completed => date(self.completion_date, None),
+            Field::Projects => tags('+', &self.project_tags),
+            Field::Contexts => tags('@', &self.context_tags),
+            Field::Estimate => self.get_estimate().map(format_estimate),
+            Field::Recurrence => self.recurrence.map(|r| r.to_string()),
+            Field::Tag(key) => self.get_custom_tag(key).map(String::from)
         }
-        if let Some(date) = self.duedate {
-            s.push_str(&format!("{} : ", date.format("%Y-%m-%d")));
+    }
+
+    /// Return the time of the due date after a space, nothing for a task without time
+    fn time_suffix(&self) -> String {
+        self.duetime.map(|time| format!(" {}", time.format("%H:%M"))).unwrap_or_default()
+    }
+
+    /// Return a Pango markup representation of the task
+    /// 
+    /// Same as `display_line` with the due date colored when overdue or due today, priority A in bold, and the
+    /// priority and the tags in their colors, or the whole line in the color of its first tag
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    /// * `colors` - the colors to use
+    pub fn to_markup(&self, today: NaiveDate, colors: &MarkupColors, dates: DateStyle) -> String {
+        self.to_markup_with(&Template::default(), today, colors, dates)
+    }
+
+    /// Return a Pango markup representation of the task laid out by a template
+    /// 
+    /// Arguments:
+    /// 
+    /// * `template` - the layout of the line
+    /// * `today` - the current date
+    /// * `colors` - the colors to use
+    /// * `dates` - how the dates without format are shown
+    pub fn to_markup_with(&self, template : &Template, today: NaiveDate, colors: &MarkupColors, dates: DateStyle) -> String {
+        let s = template.render(|field, spec| {
+            let value = self.field_value(field, spec, today, dates, template.date_format())?;
+            Some(match field {
+                Field::Priority => {
+                    let text = if self.priority == Some('A') { format!("<b>{}</b>", value) } else { value };
+                    match self.priority.and_then(|p| colors.priority_color(p)) {
+                        Some(color) if !colors.whole_row => markup::colored(&text, color),
+                        _ => text
+                    }
+                },
+                Field::Due if self.is_overdue(today) => markup::colored(&markup::escape(&value), &colors.overdue),
+                Field::Due if self.is_due_today(today) => markup::colored(&markup::escape(&value), &colors.today),
+                Field::Content | Field::Projects | Field::Contexts if !colors.whole_row => colors.tags(&value),
+                _ => markup::escape(&value)
+            })
+        }, markup::escape);
+        if !colors.whole_row {
+            return s;
+        }
+        let row_color = match (self.project_tags.first(), self.context_tags.first()) {
+            (Some(project), _) => Some(colors.project_color(project)),
+            (None, Some(context)) => Some(colors.context_color(context)),
+            (None, None) => self.priority.and_then(|p| colors.priority_color(p))
+        };
+        match row_color {
+            Some(color) => markup::colored(&s, color),
+            None => s
         }
-        s.push_str(&self.content);
-        s
     }
 
-    /// Show a complete description of the task
-    pub fn recap_str(&self) -> String {
-        let mut s = String::new();
-        s.push_str(&format!("𝐓𝐚𝐬𝐤 : {}", self.get_content()));
-        if self.completion {
-            s.push_str("\n𝐒𝐭𝐚𝐭𝐮𝐬 : Done");
-            if let Some(date) = self.completion_date {
-                s.push_str(&format!(" ({})",date.format("%Y-%m-%d")));
-            }
-        } else {
-            s.push_str("\n𝐒𝐭𝐚𝐭𝐮𝐬 : To do");
+    /// Show a complete description of the task, a line per value
+    /// 
+    /// The due date is followed by its relative description when `dates` is relative, and the custom tags without
+    /// a line of their own are listed under `Tags`
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    /// * `dates` - how the due dates are shown in the lists
+    /// * `locale` - the language of the labels and the format of the dates
+    pub fn recap_str(&self, today: NaiveDate, dates: DateStyle, locale: &Locale) -> String {
+        let mut lines = vec![("Task", self.content.clone())];
+        let mut status = match (self.completion, self.completion_date) {
+            (true, Some(date)) => format!("{} ({})", locale.status("Done"), locale.date(date)),
+            (true, None) => locale.status("Done").to_string(),
+            (false, _) => locale.status("To do").to_string()
+        };
+        if self.is_hidden() {
+            status.push_str(&format!(" ({})", locale.status("hidden")));
         }
-        if let Some(p) = self.priority {
-            s.push_str(&format!("\n𝐏𝐫𝐢𝐨𝐫𝐢𝐭𝐲 : {}", p));
+        if self.is_pinned() {
+            status.push_str(&format!(" ({})", locale.status("pinned")));
+        }
+        lines.push(("Status", status));
+        if let Some(p) = self.get_priority() {
+            lines.push(("Priority", p.to_string()));
         }
         if let Some(date) = self.creation_date {
-            s.push_str(&format!("\n𝐂𝐫𝐞𝐚𝐭𝐞𝐝 𝐨𝐧 : {}", date.format("%Y-%m-%d")));
+            lines.push(("Created on", locale.date(date)));
         }
         if let Some(date) = self.duedate {
-            s.push_str(&format!("\n𝐃𝐮𝐞 𝐝𝐚𝐭𝐞 : {}", date.format("%Y-%m-%d")));
+            let mut due = format!("{}{}", locale.date(date), self.time_suffix());
+            let relative = dates.format_with(date, today, locale.date_format());
+            if relative != locale.date(date) {
+                due.push_str(&format!(" ({})", relative));
+            }
+            lines.push(("Due date", due));
         }
-        if self.context_tags.len() > 0 {
-            s.push_str(&format!{"\n𝐂𝐨𝐧𝐭𝐞𝐱𝐭 𝐭𝐚𝐠𝐬 : {}", self.get_context_tags().join(", ")});
+        if let Some(date) = self.threshold {
+            let waiting = if self.is_visible_on(today) { String::new() } else { format!(" ({})", locale.status("not shown until then")) };
+            lines.push(("Threshold", format!("{}{}", locale.date(date), waiting)));
         }
-        if self.project_tags.len() > 0 {
-            s.push_str(&format!{"\n𝐏𝐫𝐨𝐣𝐞𝐜𝐭 𝐭𝐚𝐠𝐬 : {}", self.get_project_tags().join(", ")});
+        if let Some(recurrence) = self.recurrence {
+            lines.push(("Recurrence", recurrence.to_string()));
         }
-        s
+        if !self.context_tags.is_empty() {
+            lines.push(("Context tags", self.get_context_tags().join(", ")));
+        }
+        if !self.project_tags.is_empty() {
+            lines.push(("Project tags", self.get_project_tags().join(", ")));
+        }
+        let estimate = self.get_estimate();
+        if let Some(estimate) = estimate {
+            lines.push(("Estimate", format_estimate(estimate)));
+        }
+        // The tags which have a line, or are shown in the status
+        let shown = |key : &str| match key {
+            "due" | "note" | "h" | "star" => true,
+            "t" => self.threshold.is_some(),
+            "rec" => self.recurrence.is_some(),
+            "est" => estimate.is_some(),
+            _ => false
+        };
+        let tags = self.custom_tags.iter().filter(|t| !shown(&t.key)).map(|t| format!("{}:{}", t.key, t.value)).collect::<Vec<_>>();
+        if !tags.is_empty() {
+            lines.push(("Tags", tags.join(", ")));
+        }
+        if let Some(note) = self.get_custom_tag("note") {
+            lines.push(("Note", note.to_string()));
+        }
+        lines.iter().map(|(label, value)| locale.label(label, value)).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Create a new `Task` from a line typed by the user
+    ///
+    /// The line follows the todo.txt format, but the due and threshold dates can also be relative
+    /// (`due:fri`, `t:+3d`, see `parse_due`) and the creation date is today when it is not given
+    ///
+    /// Arguments:
+    ///
+    /// * `input` - the typed line
+    /// * `today` - the current date
+    pub fn from_quick_add(input: &str, today: NaiveDate) -> Result<Self, TaskParseError> {
+        let mut task = Self::from_typed(input, today)?;
+        if task.creation_date.is_none() {
+            task.creation_date = Some(today);
+        }
+        Ok(task)
+    }
+
+    /// Replace a task by an edited todo.txt line, read like `from_quick_add`
+    ///
+    /// The task keeps its creation date if the line has none, and its line in the task file.
+    /// The task is unchanged if the line can't be read or has no content
+    ///
+    /// Arguments:
+    ///
+    /// * `input` - the edited line
+    /// * `today` - the current date
+    pub fn edit(&mut self, input: &str, today: NaiveDate) -> Result<(), TaskParseError> {
+        let mut task = Self::from_typed(input, today)?;
+        if task.content.trim().is_empty() {
+            return Err(TaskParseError::EmptyContent);
+        }
+        task.creation_date = task.creation_date.or(self.creation_date);
+        task.source_line = self.source_line;
+        task.id = self.id;
+        *self = task;
+        Ok(())
+    }
+
+    /// Read a todo.txt line typed by the user, with relative due and threshold dates
+    fn from_typed(input: &str, today: NaiveDate) -> Result<Self, TaskParseError> {
+        let mut words : Vec<String> = Vec::new();
+        for word in input.trim().split(' ') {
+            match word.split_once(':') {
+                Some((key, value)) if key == "due" || key == "t" => {
+                    let date = if key == "due" { parse_due_time(value, today) } else { parse_due(value, today).map(|date| (date, None)) };
+                    match date {
+                        Some((date, time)) => words.push(format!("{}:{}", key, due_tag(date, time))),
+                        None => return Err(TaskParseError::InvalidTag { key : String::from(key), value : String::from(value) })
+                    }
+                },
+                _ => words.push(String::from(word))
+            }
+        }
+        words.join(" ").parse()
     }
 
     /// Import a `String` containing a todo.txt representation of a task and return a new `Task`
     /// 
+    /// Deprecated, kept for one release: use `todo.parse::<Task>()`
+    /// 
     /// Arguments:
     /// 
     /// * `todo` - a `String` with a task following todo.txt format
-    pub fn from_todotxt(todo: String) -> Result<Self, String> {
+    #[deprecated(note = "use the `FromStr` implementation, `todo.parse::<Task>()`")]
+    pub fn from_todotxt(todo: String) -> Result<Self, TaskParseError> {
+        todo.parse()
+    }
+
+    /// Read a todo.txt line into a new `Task`, see the `FromStr` implementation
+    fn parse_todotxt(todo: &str) -> Result<Self, TaskParseError> {
         lazy_static! {
             static ref RE_TASK : Regex = Regex::new(r"^(?P<completion>x )?(\((?P<priority>[A-Z])\) )?(?P<compdate>\d{4}-\d{2}-\d{2} )?(?P<creadate>\d{4}-\d{2}-\d{2} )?(?P<content>.*)$").unwrap();
         }
-        let cap : Captures;
+        
         // Check if the String respects the todo.txt standard
-        match RE_TASK.captures(&todo) {
-            None => return Err(String::from("malformed task")),
-            Some(result) => cap = result
-        }
+        let cap : Captures = match RE_TASK.captures(todo) {
+            None => return Err(TaskParseError::MalformedLine),
+            Some(result) => result
+        };
 
 
-        let mut task = Self::new(String::new());
+        let mut task = Self::empty();
         match cap.name("completion") {
             Some(_) => task.completion = true,
             None => task.completion = false
@@ -234,10 +1109,10 @@ impl Task {
         // If there are two date, it is a completion date then a creation date
         match cap.name("creadate") {
             Some(creadate) => {
-                task.creation_date = Some(NaiveDate::parse_from_str(&creadate.as_str(), "%Y-%m-%d ").unwrap());
+                task.creation_date = Some(Self::parse_date(creadate.as_str(), "creation")?);
                 match cap.name("compdate") {
                     Some(compdate) => {
-                        task.completion_date = Some(NaiveDate::parse_from_str(&compdate.as_str(), "%Y-%m-%d ").unwrap());
+                        task.completion_date = Some(Self::parse_date(compdate.as_str(), "completion")?);
                     },
                     None => task.completion_date = None
                 }
@@ -245,7 +1120,7 @@ impl Task {
             None => {
                 match cap.name("compdate") {
                     Some(compdate) => {
-                        task.creation_date = Some(NaiveDate::parse_from_str(&compdate.as_str(), "%Y-%m-%d ").unwrap());
+                        task.creation_date = Some(Self::parse_date(compdate.as_str(), "creation")?);
                     },
                     None => {
                         task.completion_date = None;
@@ -258,108 +1133,379 @@ impl Task {
         // Extract content and custom tags
         let content = cap.name("content").unwrap().as_str();
         lazy_static! {
-            static ref RE_ALLTAGS : Regex = Regex::new(r"( ([^:\s]+):([^:\s]+))+$").unwrap();
-            static ref RE_TAG : Regex = Regex::new(r"(?P<key>[^:\s]+):(?P<value>[^:\s]+)").unwrap();
-        }
-        let alltags_result = RE_ALLTAGS.captures(&content);
-        match alltags_result {
-            None => task.content = String::from(content),
-            Some(alltags) => {
-                // Suppressing tags from the content
-                task.content = String::from(&RE_ALLTAGS.replace_all(content, "").into_owned());
-                // Iterate over all found tags
-                for tag in RE_TAG.captures_iter(&alltags[0]) {
-                    task.custom_tags.insert(String::from(tag.name("key").unwrap().as_str()), String::from(tag.name("value").unwrap().as_str()));
-                }
+            // A date with a time (`due:2024-05-06T14:00`) is the only value with a colon
+            static ref RE_TAG : Regex = Regex::new(r"^(?P<key>[^:\s]+):(?P<value>\d{4}-\d{2}-\d{2}T\d{2}:\d{2}|[^:\s]+)$").unwrap();
+        }
+        // Suppressing tags from the content and remembering where they were
+        let mut words : Vec<&str> = Vec::new();
+        for word in content.split(' ') {
+            match RE_TAG.captures(word) {
+                Some(tag) if Self::is_custom_tag(&tag["key"], &tag["value"]) => {
+                    // Repeated keys are kept to write the line back unchanged
+                    task.custom_tags.push(CustomTag { key : String::from(&tag["key"]), value : String::from(&tag["value"]), position : Some(words.len()) });
+                },
+                _ => words.push(word)
             }
         }
+        task.content = words.join(" ");
 
         // Get Projet Tags and Context Tags
         task.extract_tags();
 
-        // Extract the due date from custom tags
-        match task.custom_tags.get(&String::from("due")) {
-            Some(str_date) => task.duedate = match NaiveDate::parse_from_str(str_date.as_str(), "%Y-%m-%d ") {
-                Ok(date) => Some(date),
-                Err(_) => None
+        // Extract the due date and its time, the threshold date and the recurrence from custom tags,
+        // an invalid value is kept as a plain custom tag
+        task.sync_custom_tag("due");
+        task.sync_custom_tag("t");
+        task.sync_custom_tag("rec");
+        Ok(task)
+    }
+
+    /// Parse a date captured by the todo.txt regex
+    /// 
+    /// Return an error for dates that match the `YYYY-MM-DD` shape but do not exist
+    /// 
+    /// Arguments:
+    /// 
+    /// * `date` - the captured date, with its trailing space
+    /// * `field` - the name of the date, used in the error
+    fn parse_date(date : &str, field : &'static str) -> Result<NaiveDate, TaskParseError> {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d ")
+            .map_err(|_| TaskParseError::InvalidDate { field, value : String::from(date.trim_end()) })
+    }
+
+    /// Return true if a `key:value` word is a custom tag
+    /// 
+    /// Times (`14:30`), URLs (`https://...`) and project or context tags are not custom tags
+    pub(crate) fn is_custom_tag(key : &str, value : &str) -> bool {
+        !key.chars().all(|c| c.is_ascii_digit())
+            && !key.starts_with('+')
+            && !key.starts_with('@')
+            && !value.starts_with("//")
+    }
+
+    /// Check that a custom tag is read back unchanged from a todo.txt line
+    /// 
+    /// The key and the value are words without colon, only the time of a due date has one, and the values of the
+    /// `due`, `t` and `rec` tags must be a date, a date and a recurrence
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the tag
+    /// * `value` - the value of the tag
+    pub fn check_custom_tag(key : &str, value : &str) -> Result<(), TaskParseError> {
+        let valid = match key {
+            "due" => parse_due_tag(value).is_some(),
+            "t" => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+            "rec" => Recurrence::parse(value).is_some(),
+            _ => !value.contains(':')
+        };
+        if !valid || !Self::is_custom_tag(key, value) || key.is_empty() || value.is_empty() || key.contains(':')
+                  || format!("{}{}", key, value).contains(char::is_whitespace) {
+            return Err(TaskParseError::InvalidTag { key : String::from(key), value : String::from(value) });
+        }
+        Ok(())
+    }
+
+    /// Get the value of a custom tag (the first one if the key is repeated)
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the tag
+    pub fn get_custom_tag(&self, key : &str) -> Option<&str> {
+        self.custom_tags.iter().find(|t| t.key == key).map(|t| t.value.as_str())
+    }
+
+    /// Set the value of a custom tag
+    /// 
+    /// The due date, threshold date and recurrence are updated when their tag is set. Fails without changing the task
+    /// when the tag could not be read back from the todo.txt line, see `check_custom_tag`
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the tag
+    /// * `value` - the new value of the tag
+    pub fn set_custom_tag(&mut self, key : &str, value : &str) -> Result<(), TaskParseError> {
+        Self::check_custom_tag(key, value)?;
+        self.insert_tag(key, String::from(value));
+        self.sync_custom_tag(key);
+        Ok(())
+    }
+
+    /// Remove a custom tag and return its value
+    /// 
+    /// The due date, threshold date and recurrence are cleared when their tag is removed
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the tag
+    pub fn remove_custom_tag(&mut self, key : &str) -> Option<String> {
+        let value = self.remove_tag(key);
+        self.sync_custom_tag(key);
+        value
+    }
+
+    /// Iterate over the custom tags as `(key, value)` pairs
+    pub fn custom_tags(&self) -> impl Iterator<Item=(&str,&str)> {
+        self.custom_tags.iter().map(|t| (t.key.as_str(), t.value.as_str()))
+    }
+
+    /// Return the time the task should take from its `est:` tag (`30m`, `2h`, `1d`, or minutes), `None` if it has
+    /// no estimate or an estimate which is not understood
+    pub fn get_estimate(&self) -> Option<Duration> {
+        self.get_custom_tag("est").and_then(parse_estimate)
+    }
+
+    /// Return the `id:` tag naming the task in the `dep:` tags of the tasks to do after it
+    pub fn get_dependency_id(&self) -> Option<&str> {
+        self.get_custom_tag("id")
+    }
+
+    /// Return the ids of the tasks to complete before this one, from its `dep:` tags (`dep:a,b`)
+    pub fn get_dependencies(&self) -> Vec<&str> {
+        let mut ids = Vec::new();
+        for id in self.custom_tags().filter(|(key, _)| *key == "dep").flat_map(|(_, value)| value.split(',')) {
+            if !id.is_empty() && !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
+    /// Add a task to complete before this one to the `dep:` tag
+    /// 
+    /// Arguments:
+    /// 
+    /// * `id` - the `id:` tag of the other task
+    pub fn add_dependency(&mut self, id : &str) {
+        let mut ids = self.get_dependencies().into_iter().map(String::from).collect::<Vec<_>>();
+        if !ids.iter().any(|i| i == id) {
+            ids.push(String::from(id));
+            self.set_custom_tag("dep", &ids.join(",")).expect("the ids of the dependencies are valid tags");
+        }
+    }
+
+    /// Return the URLs of the task, found in the content and in the `url:` tags
+    /// 
+    /// A `url:` tag without a scheme is opened as https
+    pub fn urls(&self) -> Vec<String> {
+        let tags = self.custom_tags()
+                       .filter(|(key, _)| *key == "url")
+                       .map(|(_, value)| if value.contains("://") { value.to_string() } else { format!("https://{}", value) });
+        let mut urls : Vec<String> = Vec::new();
+        for url in find_urls(&self.content).into_iter().chain(tags) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        urls
+    }
+
+    /// Update the field depending on a custom tag after the tag changed
+    fn sync_custom_tag(&mut self, key : &str) {
+        let value = self.get_custom_tag(key);
+        match key {
+            "due" => {
+                let due = value.and_then(parse_due_tag);
+                self.duedate = due.map(|(date, _)| date);
+                self.duetime = due.and_then(|(_, time)| time);
             },
-            None => ()
+            "t" => self.threshold = value.and_then(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").ok()),
+            "rec" => self.recurrence = value.and_then(Recurrence::parse),
+            _ => ()
         }
-        Ok(task)
+    }
+
+    /// Set the value of a custom tag, keeping its position if it already exists
+    fn insert_tag(&mut self, key : &str, value : String) {
+        match self.custom_tags.iter_mut().find(|t| t.key == key) {
+            Some(tag) => tag.value = value,
+            None => self.custom_tags.push(CustomTag { key : String::from(key), value, position : None })
+        }
+    }
+
+    /// Remove a custom tag and return its value
+    fn remove_tag(&mut self, key : &str) -> Option<String> {
+        let position = self.custom_tags.iter().position(|t| t.key == key)?;
+        Some(self.custom_tags.remove(position).value)
     }
 
     /// Return the task in a todo.txt format `String`
+    /// 
+    /// Deprecated, kept for one release: use `to_string` or `format!("{}", task)`
+    #[deprecated(note = "use the `Display` implementation, `task.to_string()`")]
     pub fn to_todotxt(&self) -> String {
-        let mut s = String::new();
-        if self.completion {
-            s.push_str("x ");
-        }
-        if let Some(p) = self.priority {
-            s.push_str(&format!("({}) ", p));
-        }
-        if let Some(date) = self.completion_date {
-            s.push_str(&format!("{} ",date.format("%Y-%m-%d")));
+        self.to_string()
+    }
+
+    /// Get project tags and context tags from task content
+    fn extract_tags(&mut self) {
+        lazy_static! {
+            static ref RE_PROJECT_TAGS : Regex = Regex::new(r"((^| )\+(?P<tag>\S+))").unwrap();
+            static ref RE_CONTEXT_TAGS : Regex = Regex::new(r"((^| )@(?P<tag>\S+))").unwrap();
         }
-        if let Some(date) = self.creation_date {
-            s.push_str(&format!("{} ",date.format("%Y-%m-%d")));
+        self.project_tags = Self::get_tags_from_capture(RE_PROJECT_TAGS.captures_iter(&self.content));
+        self.context_tags = Self::get_tags_from_capture(RE_CONTEXT_TAGS.captures_iter(&self.content));
+    }
+
+    /// Extract the tags from a Regex::CaptureMatches
+    /// 
+    /// Return a sorted and deduplicated `Vec<String>` with the tags
+    fn get_tags_from_capture(captures : CaptureMatches) -> Vec<String> {
+        let mut tags : Vec<String> = Vec::new();
+        for tag in captures {
+            tags.push(String::from(tag.name("tag").unwrap().as_str()));
         }
-        s.push_str(&self.content);
-        for (key, value) in &self.custom_tags {
-            s.push_str(&format!(" {}:{}", key, value));
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Compare two `Task`s to sort them according to `sort` order
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare` - a task to compare
+    /// * `sort` - sort order
+    pub fn _comp(&self, compare: &Self, sort: &SortTaskBy) -> std::cmp::Ordering {
+        self.comp_sorted(compare, sort, SortDirection::Ascending)
+    }
+
+    /// Compare two `Task`s to sort them according to `sort` order and `direction`
+    /// 
+    /// Sorting by priority breaks ties with the due date, the urgency is the one of the current day
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare` - a task to compare
+    /// * `sort` - sort order
+    /// * `direction` - sort direction
+    pub fn comp_sorted(&self, compare: &Self, sort: &SortTaskBy, direction: SortDirection) -> std::cmp::Ordering {
+        let today = Local::now().date_naive();
+        match sort {
+            SortTaskBy::Priority => self.comp_multi_directed(compare, &[SortTaskBy::Priority, SortTaskBy::DueDate], direction, today),
+            _ => self.comp_multi_directed(compare, std::slice::from_ref(sort), direction, today)
         }
-        s
     }
 
-    /// Get project tags and context tags from task content
-    fn extract_tags(&mut self) {
-        lazy_static! {
-            static ref RE_PROJECT_TAGS : Regex = Regex::new(r"((^| )\+(?P<tag>\S+))").unwrap();
-            static ref RE_CONTEXT_TAGS : Regex = Regex::new(r"((^| )@(?P<tag>\S+))").unwrap();
+    /// Compare two `Task`s with a list of sort keys
+    /// 
+    /// The first key giving a non equal result decides, the content is used to break the remaining ties.
+    /// The urgency is the one of the current day
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare` - a task to compare
+    /// * `keys` - the sort keys, by decreasing importance
+    pub fn comp_multi(&self, compare: &Self, keys: &[SortTaskBy]) -> std::cmp::Ordering {
+        self.comp_multi_directed(compare, keys, SortDirection::Ascending, Local::now().date_naive())
+    }
+
+    /// Compare two `Task`s with a comparator, always putting the pinned tasks before the other tasks
+    ///
+    /// Wrapped in `comp_completed_last`, the completed pinned tasks are after the tasks to do
+    ///
+    /// Arguments:
+    ///
+    /// * `compare` - a task to compare
+    /// * `comp` - the comparator used for tasks both pinned or both not pinned
+    pub fn comp_pinned_first(&self, compare: &Self, comp: impl FnOnce(&Self, &Self) -> std::cmp::Ordering) -> std::cmp::Ordering {
+        compare.is_pinned().cmp(&self.is_pinned()).then_with(|| comp(self, compare))
+    }
+
+    /// Compare two `Task`s with a comparator, always putting the completed tasks after the tasks to do
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare` - a task to compare
+    /// * `comp` - the comparator used for tasks with the same completion state
+    pub fn comp_completed_last(&self, compare: &Self, comp: impl FnOnce(&Self, &Self) -> std::cmp::Ordering) -> std::cmp::Ordering {
+        self.completion.cmp(&compare.completion).then_with(|| comp(self, compare))
+    }
+
+    /// Compare two `Task`s with a list of sort keys and a `direction`
+    /// 
+    /// The direction only applies to the keys: tasks without a value for a key keep their place
+    /// and the final tie-break on the content is always ascending
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare` - a task to compare
+    /// * `keys` - the sort keys, by decreasing importance
+    /// * `direction` - sort direction
+    /// * `today` - the day the urgency is computed for
+    pub fn comp_multi_directed(&self, compare: &Self, keys: &[SortTaskBy], direction: SortDirection, today: NaiveDate) -> std::cmp::Ordering {
+        keys.iter()
+            .map(|key| self.comp_key(compare, key, direction, today))
+            .find(|ordering| *ordering != std::cmp::Ordering::Equal)
+            .unwrap_or_else(|| self.content.cmp(&compare.content))
+    }
+
+    /// Compare two `Task`s on a single sort key, without breaking ties
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare` - a task to compare
+    /// * `key` - the sort key
+    /// * `direction` - sort direction
+    /// * `today` - the day the urgency is computed for
+    fn comp_key(&self, compare: &Self, key: &SortTaskBy, direction: SortDirection, today: NaiveDate) -> std::cmp::Ordering {
+        match key {
+            SortTaskBy::Content => direction.apply(self.content.cmp(&compare.content)),
+            SortTaskBy::CreationDate => Self::comp_option(self.creation_date, compare.creation_date, false, direction),
+            SortTaskBy::Priority => Self::comp_option(self.priority, compare.priority, true, direction),
+            SortTaskBy::DueDate => Self::comp_option(self.due_moment(), compare.due_moment(), true, direction),
+            SortTaskBy::CompletionDate => {
+                Self::comp_option(self.get_completion_date(), compare.get_completion_date(), true, direction)
+            },
+            SortTaskBy::Urgency => direction.apply(compare.urgency(today).total_cmp(&self.urgency(today))),
+            SortTaskBy::Estimate => Self::comp_option(self.get_estimate(), compare.get_estimate(), true, direction)
         }
-        self.project_tags = Self::get_tags_from_capture(RE_PROJECT_TAGS.captures_iter(&self.content));
-        self.context_tags = Self::get_tags_from_capture(RE_CONTEXT_TAGS.captures_iter(&self.content));
     }
 
-    /// Extract the tags from a Regex::CaptureMatches
+    /// Compare two optional values
     /// 
-    /// Return a sorted and deduplicated `Vec<String>` with the tags
-    fn get_tags_from_capture(captures : CaptureMatches) -> Vec<String> {
-        let mut tags : Vec<String> = Vec::new();
-        for tag in captures {
-            tags.push(String::from(tag.name("tag").unwrap().as_str()));
+    /// Arguments:
+    /// 
+    /// * `a` - the first value
+    /// * `b` - the second value
+    /// * `missing_last` - whether a missing value sorts after the others
+    /// * `direction` - sort direction of the present values
+    fn comp_option<T : Ord>(a : Option<T>, b : Option<T>, missing_last : bool, direction : SortDirection) -> std::cmp::Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => direction.apply(a.cmp(&b)),
+            (Some(_), None) => if missing_last {std::cmp::Ordering::Less} else {std::cmp::Ordering::Greater},
+            (None, Some(_)) => if missing_last {std::cmp::Ordering::Greater} else {std::cmp::Ordering::Less},
+            (None, None) => std::cmp::Ordering::Equal
         }
-        tags.sort();
-        tags.dedup();
-        tags
     }
 
-    /// Compare two `Task`s to sort them according to `sort` order
+    /// Get the completion date of a completed task
+    fn get_completion_date(&self) -> Option<NaiveDate> {
+        if self.completion { self.completion_date } else { None }
+    }
+
+    /// Return true if two tasks have the same content, whatever their dates, priority and custom tags
     /// 
     /// Arguments:
     /// 
-    /// * `compare` - a task to compare
-    /// * `sort` - sort order
-    pub fn _comp(&self, compare: &Self, sort: &SortTaskBy) -> std::cmp::Ordering {
-        match sort {
-            SortTaskBy::Content => {self.comp_content(compare)},
-            SortTaskBy::CreationDate => {self.comp_creation_date(compare)},
-            SortTaskBy::Priority => {self.comp_priority(compare)},
-            SortTaskBy::DueDate => {self.comp_due_date(compare)}
-        }
+    /// * `other` - a task to compare
+    pub fn same_content(&self, other: &Self) -> bool {
+        self.content == other.content
+    }
+
+    /// Return the fields of the task identifying it, see `TaskFields`
+    fn fields(&self) -> TaskFields<'_> {
+        (&self.content, self.completion, self.priority, self.creation_date, self.completion_date, self.duedate,
+         self.threshold, &self.custom_tags)
     }
 
-    /// Compare two `Task`s to sort them by priority
+    /// Compare two `Task`s to sort them by priority, ties are broken with the due date
     /// 
     /// Arguments:
     /// 
     /// * `compare` - a task to compare
     pub fn comp_priority(&self, compare: &Self) -> std::cmp::Ordering {
-        match (self.priority, compare.priority) {
-            (Some(p1), Some(p2)) => if p1 == p2 {self.comp_due_date(compare)} else if p1 < p2 {std::cmp::Ordering::Less} else {std::cmp::Ordering::Greater},
-            (Some(_), None) => std::cmp::Ordering::Less,
-            (None, Some(_)) => std::cmp::Ordering::Greater,
-            (None, None) => self.comp_due_date(compare)
-        }
+        self.comp_multi(compare, &[SortTaskBy::Priority, SortTaskBy::DueDate])
     }
 
     /// Compare two `Task`s to sort them by creation date
@@ -368,12 +1514,7 @@ impl Task {
     /// 
     /// * `compare` - a task to compare
     pub fn comp_creation_date(&self, compare: &Self) -> std::cmp::Ordering {
-        match (self.creation_date, compare.creation_date) {
-            (Some(d1), Some(d2)) => if d1 == d2 {self.comp_content(compare)} else if d1 < d2 {std::cmp::Ordering::Less} else {std::cmp::Ordering::Greater},
-            (Some(_), None) => std::cmp::Ordering::Greater,
-            (None, Some(_)) => std::cmp::Ordering::Less,
-            (None, None) => self.comp_content(compare)
-        }
+        self.comp_multi(compare, &[SortTaskBy::CreationDate])
     }
 
     /// Compare two `Task`s to sort them by due date
@@ -382,57 +1523,111 @@ impl Task {
     /// 
     /// * `compare` - a task to compare
     pub fn comp_due_date(&self, compare: &Self) -> std::cmp::Ordering {
-        match (self.duedate, compare.duedate) {
-            (Some(d1), Some(d2)) => if d1 == d2 {self.comp_content(compare)} else if d1 < d2 {std::cmp::Ordering::Less} else {std::cmp::Ordering::Greater},
-            (Some(_), None) => std::cmp::Ordering::Less,
-            (None, Some(_)) => std::cmp::Ordering::Greater,
-            (None, None) => self.comp_content(compare)
-        }
+        self.comp_multi(compare, &[SortTaskBy::DueDate])
     }
 
+    /// Compare two `Task`s to sort them by completion date
+    /// 
+    /// Uncompleted tasks and tasks completed without a date sort last
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare` - a task to compare
+    pub fn comp_completion_date(&self, compare: &Self) -> std::cmp::Ordering {
+        self.comp_multi(compare, &[SortTaskBy::CompletionDate])
+    }
 
-    // Compare two `Task`s to sort them by content
+    /// Compare two `Task`s to sort them by content
     /// 
     /// Arguments:
     /// 
     /// * `compare` - a task to compare
     pub fn comp_content(&self, compare: &Self) -> std::cmp::Ordering {
-        if self.content == compare.content {
-            std::cmp::Ordering::Equal
-        } else if self.content < compare.content {
-            std::cmp::Ordering::Less
+        self.comp_multi(compare, &[SortTaskBy::Content])
+    }
+}
+
+impl fmt::Display for Task {
+    /// Write the task as a todo.txt line
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        if self.completion {
+            write!(f, "x ")?;
+        }
+        if let Some(p) = self.priority {
+            write!(f, "({}) ", p)?;
+        }
+        if let Some(date) = self.completion_date {
+            write!(f, "{} ", date.format("%Y-%m-%d"))?;
+        }
+        if let Some(date) = self.creation_date {
+            write!(f, "{} ", date.format("%Y-%m-%d"))?;
+        }
+        // Put back the custom tags where they were found, new tags go at the end
+        let mut words : Vec<String> = if self.content.is_empty() {
+            vec![]
         } else {
-            std::cmp::Ordering::Greater
+            self.content.split(' ').map(String::from).collect()
+        };
+        let content_len = words.len();
+        let mut tags = self.custom_tags.iter().collect::<Vec<_>>();
+        tags.sort_by_key(|t| t.position.unwrap_or(content_len).min(content_len));
+        for (inserted, tag) in tags.iter().enumerate() {
+            let position = tag.position.unwrap_or(content_len).min(content_len);
+            words.insert(position + inserted, format!("{}:{}", tag.key, tag.value));
         }
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+impl FromStr for Task {
+    type Err = TaskParseError;
+
+    /// Read a task from its todo.txt line
+    fn from_str(todo : &str) -> Result<Self, Self::Err> {
+        Self::parse_todotxt(todo)
     }
 }
 
 impl Ord for Task {
+    /// Compare every field of the tasks, the content first
+    ///
+    /// Two tasks are equal when they have the same content, dates, completion, priority and tags, wherever they were
+    /// loaded from: a recurring task and its next occurrence are different tasks, two identical lines are the same
+    /// task. `TaskList` tells the identical lines apart by their `TaskId`. Use the `SortTaskBy` comparisons to sort the tasks
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.comp_content(other)
+        self.fields().cmp(&other.fields())
     }
 }
 
 impl PartialOrd for Task {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.comp_content(other))
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for Task {
     fn eq(&self, other: &Self) -> bool {
-        self.comp_content(other) == std::cmp::Ordering::Equal
+        self.fields() == other.fields()
     }
 }
 
 impl Eq for Task { }
 
+impl Hash for Task {
+    fn hash<H : Hasher>(&self, state : &mut H) {
+        self.fields().hash(state);
+    }
+}
+
 
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod task_tests {
     use super::*;
+    use crate::template::Labels;
     #[test]
+    #[allow(deprecated)]
     fn comp_date_nodate() {
         let t1 = Task::from_todotxt(String::from("a task")).unwrap();
         let t2 = Task::from_todotxt(String::from("2021-01-01 another task")).unwrap();
@@ -450,6 +1645,7 @@ mod task_tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn comp_date_date() {
         let t1 = Task::from_todotxt(String::from("a task")).unwrap();
         let t2 = Task::from_todotxt(String::from("another task")).unwrap();
@@ -458,6 +1654,7 @@ mod task_tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn comp_date_due() {
         let t1 = Task::from_todotxt(String::from("a task due:2021-01-02")).unwrap();
         let t2 = Task::from_todotxt(String::from("another task due:2021-01-01")).unwrap();
@@ -469,6 +1666,7 @@ mod task_tests {
 
 
     #[test]
+    #[allow(deprecated)]
     fn completed() {
         let mut t1 = Task::from_todotxt(String::from("a task")).unwrap();
         t1.set_completed();
@@ -485,6 +1683,7 @@ mod task_tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn not_completed() {
         let t1 = Task::from_todotxt(String::from("a task")).unwrap();
         assert_eq!(t1.completion, false);
@@ -498,6 +1697,44 @@ mod task_tests {
     }
 
     #[test]
+    fn structural_equality() {
+        let t1 = "call mom due:2024-01-05".parse::<Task>().unwrap();
+        let t2 = "call mom due:2024-01-06".parse::<Task>().unwrap();
+        assert_ne!(t1, t2);
+        assert!(t1.same_content(&t2));
+        assert_eq!(t1, "call mom due:2024-01-05".parse::<Task>().unwrap());
+        assert_ne!(t1, "(A) call mom due:2024-01-05".parse::<Task>().unwrap());
+        assert_ne!(t1, "call mom due:2024-01-05 est:1h".parse::<Task>().unwrap());
+        // The same task loaded from another line is equal
+        let mut t3 = t1.clone();
+        t3.set_source_line(Some(3));
+        assert_eq!(t1, t3);
+        assert_eq!(vec![t1.clone(), t3].into_iter().collect::<std::collections::HashSet<_>>().len(), 1);
+        // Neither a BTreeSet nor a HashSet loses a task
+        let tasks = vec![t1.clone(), t2.clone(), t1.clone()];
+        assert_eq!(tasks.iter().cloned().collect::<std::collections::BTreeSet<_>>().len(), 2);
+        assert_eq!(tasks.into_iter().collect::<std::collections::HashSet<_>>().len(), 2);
+        // The order is consistent with the equality
+        assert_ne!(t1.cmp(&t2), std::cmp::Ordering::Equal);
+        assert_eq!(t1.comp_content(&t2), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn display_and_parse() {
+        let line = "x 2024-01-02 2024-01-01 water plants +home due:2024-01-05";
+        let task : Task = line.parse().unwrap();
+        assert_eq!(format!("{}", task), line);
+        assert_eq!(task.display_line(), "2024-01-05 : water plants +home");
+        assert_eq!("x 2024-13-01 broken".parse::<Task>().unwrap_err(),
+                   TaskParseError::InvalidDate { field : "creation", value : String::from("2024-13-01") });
+        #[allow(deprecated)]
+        {
+            assert_eq!(Task::from_todotxt(String::from(line)).unwrap().to_todotxt(), line);
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn from_todotxt() {
         let t1 = Task::from_todotxt(String::from("(A) Thank Mom for the aaa @phone")).unwrap();
         assert_eq!(t1.get_content(), "Thank Mom for the aaa @phone");
@@ -535,4 +1772,836 @@ mod task_tests {
         assert_eq!(*t4.get_context_tags(), vec!["GroceryStore"]);
         assert_eq!(*t4.get_project_tags(), Vec::<String>::new());
     }
+
+    #[test]
+    fn recurrence() {
+        let t1 = "water plants rec:3d".parse::<Task>().unwrap();
+        assert_eq!(t1.recurrence.unwrap().amount, 3);
+        let t2 = "water plants rec:3x".parse::<Task>().unwrap();
+        assert!(t2.recurrence.is_none());
+        assert!(t2.to_string().ends_with("rec:3x"));
+        assert!(t2.next_occurrence().is_none());
+    }
+
+    #[test]
+    fn next_occurrence_from_due() {
+        let mut t1 = "2021-01-01 water plants due:2021-01-10 rec:+2w".parse::<Task>().unwrap();
+        t1.set_completed();
+        let next = t1.next_occurrence().unwrap();
+        assert!(t1.completion);
+        assert!(!next.completion);
+        assert_eq!(next.completion_date, None);
+        assert_eq!(next.get_content(), t1.get_content());
+        assert_eq!(*next.get_due(), Some(NaiveDate::from_ymd_opt(2021, 1, 24).unwrap()));
+        assert!(next.to_string().contains("due:2021-01-24"));
+        assert_ne!(next, t1);
+    }
+
+    #[test]
+    fn next_occurrence_from_completion() {
+        let mut t1 = "water plants rec:1w".parse::<Task>().unwrap();
+        t1.set_completed();
+        let next = t1.next_occurrence().unwrap();
+        assert_eq!(*next.get_due(), Some(t1.completion_date.unwrap() + chrono::Duration::weeks(1)));
+    }
+
+    #[test]
+    fn strict_recurrence_completed_late() {
+        let mut t1 = "2021-01-01 pay rent due:2021-01-10 rec:+1m".parse::<Task>().unwrap();
+        t1.set_completed();
+        t1.completion_date = NaiveDate::from_ymd_opt(2021, 1, 24);
+        let next = t1.next_occurrence().unwrap();
+        assert_eq!(*next.get_due(), NaiveDate::from_ymd_opt(2021, 2, 10));
+        assert!(next.to_string().contains("rec:+1m"));
+    }
+
+    #[test]
+    fn non_strict_recurrence_completed_late() {
+        let mut t1 = "2021-01-01 haircut due:2021-01-10 rec:1m".parse::<Task>().unwrap();
+        t1.set_completed();
+        t1.completion_date = NaiveDate::from_ymd_opt(2021, 1, 24);
+        let next = t1.next_occurrence().unwrap();
+        assert_eq!(*next.get_due(), NaiveDate::from_ymd_opt(2021, 2, 24));
+    }
+
+    #[test]
+    fn threshold() {
+        let t1 = "plan holidays t:2021-05-01".parse::<Task>().unwrap();
+        assert_eq!(*t1.get_threshold(), NaiveDate::from_ymd_opt(2021, 5, 1));
+        assert!(!t1.is_visible_on(NaiveDate::from_ymd_opt(2021, 4, 30).unwrap()));
+        assert!(t1.is_visible_on(NaiveDate::from_ymd_opt(2021, 5, 1).unwrap()));
+        assert_eq!(t1.to_string(), "plan holidays t:2021-05-01");
+
+        let t2 = "plan holidays t:someday".parse::<Task>().unwrap();
+        assert_eq!(*t2.get_threshold(), None);
+        assert!(t2.is_visible_on(NaiveDate::from_ymd_opt(2021, 4, 30).unwrap()));
+        assert_eq!(t2.to_string(), "plan holidays t:someday");
+
+        let mut t3 = "plan holidays".parse::<Task>().unwrap();
+        t3.set_threshold(NaiveDate::from_ymd_opt(2021, 5, 1));
+        assert_eq!(t3.to_string(), "plan holidays t:2021-05-01");
+        t3.set_threshold(None);
+        assert_eq!(t3.to_string(), "plan holidays");
+    }
+
+    #[test]
+    fn hidden() {
+        assert!(!"template task".parse::<Task>().unwrap().is_hidden());
+        assert!(!"template task h:0".parse::<Task>().unwrap().is_hidden());
+        assert!("template task h:1".parse::<Task>().unwrap().is_hidden());
+        assert!(!"template task h:yes".parse::<Task>().unwrap().is_hidden());
+        let t1 = "template task h:1".parse::<Task>().unwrap();
+        assert!(t1.recap_str(Local::now().date_naive(), DateStyle::Absolute, &Locale::default()).contains("(hidden)"));
+        assert_eq!(t1.to_string(), "template task h:1");
+    }
+
+    #[test]
+    fn completed_priority() {
+        let mut t1 = "(A) 2024-01-01 task".parse::<Task>().unwrap();
+        t1.set_completed();
+        assert_eq!(t1.priority, None);
+        assert_eq!(t1.get_priority(), Some('A'));
+        let today = t1.completion_date.unwrap().format("%Y-%m-%d");
+        assert_eq!(t1.to_string(), format!("x {} 2024-01-01 task pri:A", today));
+        t1.set_not_completed();
+        assert_eq!(t1.priority, Some('A'));
+        assert_eq!(t1.to_string(), "(A) 2024-01-01 task");
+
+        let mut t2 = "x 2024-01-02 2024-01-01 task pri:A".parse::<Task>().unwrap();
+        assert_eq!(t2.get_priority(), Some('A'));
+        t2.set_not_completed();
+        assert_eq!(t2.to_string(), "(A) 2024-01-01 task");
+        t2.set_completed();
+        let today = t2.completion_date.unwrap().format("%Y-%m-%d");
+        assert_eq!(t2.to_string(), format!("x {} 2024-01-01 task pri:A", today));
+
+        let t3 = "x 2024-01-02 2024-01-01 task pri:foo".parse::<Task>().unwrap();
+        assert_eq!(t3.get_priority(), None);
+    }
+
+    #[test]
+    fn invalid_dates() {
+        assert!("2021-13-01 buy milk".parse::<Task>().is_err());
+        assert!("2021-01-32 buy milk".parse::<Task>().is_err());
+        assert!("2021-02-30 buy milk".parse::<Task>().is_err());
+        assert!("x 2021-02-30 2021-01-01 buy milk".parse::<Task>().is_err());
+        assert!("x 2021-02-01 2021-13-45 buy milk".parse::<Task>().is_err());
+        assert!("2020-02-29 buy milk".parse::<Task>().is_ok());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("2021-02-30 buy milk".parse::<Task>().unwrap_err(),
+            TaskParseError::InvalidDate { field : "creation", value : String::from("2021-02-30") });
+        assert_eq!("x 2021-02-30 2021-01-01 buy milk".parse::<Task>().unwrap_err(),
+            TaskParseError::InvalidDate { field : "completion", value : String::from("2021-02-30") });
+        // An invalid due date is kept as a plain custom tag
+        let task = "call mom due:someday".parse::<Task>().unwrap();
+        assert_eq!((task.get_due(), task.get_custom_tag("due")), (&None, Some("someday")));
+        assert_eq!(task.to_string(), "call mom due:someday");
+        let e = TaskParseError::InvalidTag { key : String::from("due"), value : String::from("tomorrow") };
+        assert_eq!(String::from(e), "invalid value tomorrow for tag due");
+        assert_eq!(TaskParseError::MalformedLine.to_string(), "malformed task");
+    }
+
+    #[test]
+    fn custom_tags_order() {
+        let line = "2024-01-01 task due:2024-01-01 rec:1w t:2023-12-25 h:1 est:2h";
+        let mut todotxt = String::from(line);
+        for _ in 0..5 {
+            todotxt = todotxt.parse::<Task>().unwrap().to_string();
+            assert_eq!(todotxt, line);
+        }
+        let mut t1 = line.parse::<Task>().unwrap();
+        t1.set_due(NaiveDate::from_ymd_opt(2024, 2, 1));
+        t1.set_threshold(None);
+        assert_eq!(t1.to_string(), "2024-01-01 task due:2024-02-01 rec:1w h:1 est:2h");
+    }
+
+    #[test]
+    fn roundtrip_fixtures() {
+        for line in include_str!("../tests/fixtures/roundtrip.txt").lines() {
+            let task = line.parse::<Task>().unwrap();
+            assert_eq!(task.to_string(), line);
+        }
+    }
+
+    #[test]
+    fn roundtrip_generated() {
+        let prefixes = ["", "x ", "(A) ", "x (B) ", "2024-01-01 ", "x 2024-01-02 2024-01-01 ", "(C) 2024-01-01 "];
+        let contents = ["task", "call @mom +family", "+project first", "a  b", "ends with space ", "2+2 = 4"];
+        let suffixes = ["", " due:2024-03-01", " rec:+1m due:2024-03-01", " t:2024-02-01 h:1", " est:2h est:3h"];
+        for prefix in prefixes {
+            for content in contents {
+                for suffix in suffixes {
+                    let line = format!("{}{}{}", prefix, content, suffix);
+                    assert_eq!(line.parse::<Task>().unwrap().to_string(), line);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip_built_tasks() {
+        let mut t1 = Task::new(String::from("call plumber @phone "));
+        t1.set_due(NaiveDate::from_ymd_opt(2024, 5, 1));
+        t1.priority = Some('A');
+        t1.set_threshold(NaiveDate::from_ymd_opt(2024, 4, 1));
+        let mut t2 = t1.clone();
+        t2.set_completed();
+        for task in [t1, t2] {
+            let line = task.to_string();
+            assert_eq!(line.parse::<Task>().unwrap().to_string(), line);
+        }
+    }
+
+    #[test]
+    fn tags_anywhere() {
+        let line = "call mom due:2024-05-01 about the party";
+        let t1 = line.parse::<Task>().unwrap();
+        assert_eq!(t1.get_content(), "call mom about the party");
+        assert_eq!(*t1.get_due(), NaiveDate::from_ymd_opt(2024, 5, 1));
+        assert_eq!(t1.to_string(), line);
+
+        let line = "due:2024-05-01 call mom rec:1w about t:2024-04-01 the party h:1";
+        let t2 = line.parse::<Task>().unwrap();
+        assert_eq!(t2.get_content(), "call mom about the party");
+        assert!(t2.is_hidden());
+        assert_eq!(t2.to_string(), line);
+
+        let mut t3 = t2.clone();
+        t3.set_due(None);
+        t3.set_due(NaiveDate::from_ymd_opt(2024, 6, 1));
+        assert_eq!(t3.to_string(), "call mom rec:1w about t:2024-04-01 the party h:1 due:2024-06-01");
+    }
+
+    #[test]
+    fn not_custom_tags() {
+        let line = "meeting at 14:30 read https://example.com/a:b +project:x @home:y";
+        let t1 = line.parse::<Task>().unwrap();
+        assert_eq!(t1.get_content(), line);
+        assert_eq!(t1.to_string(), line);
+    }
+
+    #[test]
+    fn custom_tags_accessors() {
+        let mut t1 = "task energy:low".parse::<Task>().unwrap();
+        assert_eq!(t1.get_custom_tag("energy"), Some("low"));
+        assert_eq!(t1.get_custom_tag("est"), None);
+        t1.set_custom_tag("est", "2h").unwrap();
+        t1.set_custom_tag("energy", "high").unwrap();
+        assert_eq!(t1.custom_tags().collect::<Vec<_>>(), vec![("energy", "high"), ("est", "2h")]);
+        assert_eq!(t1.remove_custom_tag("energy"), Some(String::from("high")));
+        assert_eq!(t1.remove_custom_tag("energy"), None);
+        assert_eq!(t1.to_string(), "task est:2h");
+
+        // A tag which would not be read back unchanged is refused and the task is left unchanged
+        let invalid = |key : &str, value : &str| Err(TaskParseError::InvalidTag { key : String::from(key), value : String::from(value) });
+        for (key, value) in [("due", "someday"), ("t", "2024-02-30"), ("rec", "weekly"), ("est", "2 h"), ("my key", "1"), ("at", "14:00"),
+                             ("a:b", "1"), ("est", ""), ("", "1"), ("+home", "1"), ("url", "//example.com")] {
+            assert_eq!(t1.set_custom_tag(key, value), invalid(key, value));
+        }
+        assert_eq!(t1.to_string(), "task est:2h");
+        t1.set_custom_tag("due", "2024-05-06T14:00").unwrap();
+        assert_eq!(t1.to_string().parse::<Task>().unwrap().to_string(), "task est:2h due:2024-05-06T14:00");
+    }
+
+    #[test]
+    fn custom_tags_sync() {
+        let mut t1 = "task".parse::<Task>().unwrap();
+        t1.set_custom_tag("due", "2024-05-01").unwrap();
+        assert_eq!(*t1.get_due(), NaiveDate::from_ymd_opt(2024, 5, 1));
+        t1.set_custom_tag("t", "2024-04-01").unwrap();
+        assert_eq!(*t1.get_threshold(), NaiveDate::from_ymd_opt(2024, 4, 1));
+        t1.set_custom_tag("rec", "+1w").unwrap();
+        assert!(t1.recurrence.unwrap().strict);
+        t1.remove_custom_tag("due");
+        assert_eq!(*t1.get_due(), None);
+        t1.remove_custom_tag("rec");
+        assert_eq!(t1.recurrence, None);
+        assert_eq!(t1.to_string(), "task t:2024-04-01");
+    }
+
+    #[test]
+    fn due_status() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let overdue = "task due:2024-05-09".parse::<Task>().unwrap();
+        let due_today = "task due:2024-05-10".parse::<Task>().unwrap();
+        let due_later = "task due:2024-05-13".parse::<Task>().unwrap();
+        let no_due = "task".parse::<Task>().unwrap();
+        let done = "x task due:2024-05-09".parse::<Task>().unwrap();
+
+        assert!(overdue.is_overdue(today));
+        assert!(!overdue.is_due_today(today));
+        assert!(!overdue.is_due_within(today, 7));
+        assert!(!due_today.is_overdue(today));
+        assert!(due_today.is_due_today(today));
+        assert!(due_today.is_due_within(today, 0));
+        assert!(!due_later.is_due_today(today));
+        assert!(!due_later.is_due_within(today, 2));
+        assert!(due_later.is_due_within(today, 3));
+        assert!(!no_due.is_overdue(today) && !no_due.is_due_today(today) && !no_due.is_due_within(today, 7));
+        assert!(!done.is_overdue(today) && !done.is_due_within(today, 7));
+    }
+
+    #[test]
+    fn estimate() {
+        let quick = "answer the mail est:15m".parse::<Task>().unwrap();
+        let long = "write the report est:1h30m".parse::<Task>().unwrap();
+        let unknown = "think est:a-while".parse::<Task>().unwrap();
+        let none = "call mom".parse::<Task>().unwrap();
+        assert_eq!(quick.get_estimate(), Some(Duration::minutes(15)));
+        assert_eq!(unknown.get_estimate(), None);
+        assert!(long.recap_str(NaiveDate::from_ymd_opt(2024, 5, 6).unwrap(), DateStyle::Absolute, &Locale::default()).contains("\n𝐄𝐬𝐭𝐢𝐦𝐚𝐭𝐞 : 1h 30m"));
+
+        // The quick wins first, the tasks without estimate last
+        let mut tasks = vec![none.clone(), long.clone(), unknown.clone(), quick.clone()];
+        tasks.sort_by(|a, b| a.comp_multi(b, &[SortTaskBy::Estimate]));
+        assert_eq!(tasks, vec![quick, long, none, unknown]);
+        assert_eq!(SortTaskBy::from_name("Estimate"), Some(SortTaskBy::Estimate));
+    }
+
+    #[test]
+    fn dependencies() {
+        let mut t1 = "call the roofer id:roof dep:quote,ladder dep:quote".parse::<Task>().unwrap();
+        assert_eq!(t1.get_dependency_id(), Some("roof"));
+        assert_eq!(t1.get_dependencies(), vec!["quote", "ladder"]);
+        t1.add_dependency("ladder");
+        t1.add_dependency("van");
+        assert_eq!(t1.get_dependencies(), vec!["quote", "ladder", "van"]);
+        assert_eq!(t1.to_string(), "call the roofer id:roof dep:quote,ladder,van dep:quote");
+        let mut t2 = "buy a ladder".parse::<Task>().unwrap();
+        assert!(t2.get_dependency_id().is_none() && t2.get_dependencies().is_empty());
+        t2.add_dependency("shop");
+        assert_eq!(t2.to_string(), "buy a ladder dep:shop");
+    }
+
+    #[test]
+    fn due_time() {
+        let line = "call plumber due:2024-05-06T14:00 +house";
+        let mut t1 = line.parse::<Task>().unwrap();
+        assert_eq!(*t1.get_due(), NaiveDate::from_ymd_opt(2024, 5, 6));
+        assert_eq!(t1.get_due_time(), NaiveTime::from_hms_opt(14, 0, 0));
+        assert_eq!(t1.get_content(), "call plumber +house");
+        assert_eq!(t1.to_string(), line);
+        assert_eq!(t1.display_line(), "2024-05-06 14:00 : call plumber +house");
+        // A task without time doesn't gain one
+        let t2 = "call mom due:2024-05-06".parse::<Task>().unwrap();
+        assert_eq!(t2.get_due_time(), None);
+        assert_eq!(t2.to_string(), "call mom due:2024-05-06");
+        assert_eq!("call mom due:2024-05-06T14".parse::<Task>().unwrap().get_due(), &None);
+
+        // The time is kept when the task is postponed
+        t1.postpone(PostponeBy::Days(1), NaiveDate::from_ymd_opt(2024, 5, 1).unwrap());
+        assert_eq!(t1.to_string(), "call plumber due:2024-05-07T14:00 +house");
+        t1.set_due_time(NaiveTime::from_hms_opt(9, 5, 0));
+        assert_eq!(t1.to_string(), "call plumber due:2024-05-07T09:05 +house");
+        t1.set_due_time(None);
+        assert_eq!(t1.to_string(), "call plumber due:2024-05-07 +house");
+        t1.set_custom_tag("due", "2024-05-08T18:30").unwrap();
+        assert_eq!(t1.get_due_time(), NaiveTime::from_hms_opt(18, 30, 0));
+        t1.set_due(None);
+        assert_eq!((t1.get_due_time(), t1.to_string()), (None, String::from("call plumber +house")));
+
+        let typed = Task::from_quick_add("meeting due:2024-05-06T09:30", NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()).unwrap();
+        assert_eq!(typed.get_due_time(), NaiveTime::from_hms_opt(9, 30, 0));
+    }
+
+    #[test]
+    fn due_time_order_and_overdue() {
+        let morning = "meeting due:2024-05-06T09:00".parse::<Task>().unwrap();
+        let afternoon = "call due:2024-05-06T14:00".parse::<Task>().unwrap();
+        let any_time = "report due:2024-05-06".parse::<Task>().unwrap();
+        let next_day = "review due:2024-05-07T08:00".parse::<Task>().unwrap();
+        // The tasks of a day are sorted by time, the tasks due any time of the day last
+        assert_eq!(afternoon._comp(&morning, &SortTaskBy::DueDate), std::cmp::Ordering::Greater);
+        assert_eq!(afternoon._comp(&any_time, &SortTaskBy::DueDate), std::cmp::Ordering::Less);
+        assert_eq!(any_time._comp(&next_day, &SortTaskBy::DueDate), std::cmp::Ordering::Less);
+
+        let noon = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        assert!(morning.is_overdue_at(noon));
+        assert!(!afternoon.is_overdue_at(noon));
+        assert!(!any_time.is_overdue_at(noon));
+        assert!(any_time.is_overdue_at(noon + Duration::days(1)));
+        // The date only is compared without the time of the day
+        let day = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        assert!(!morning.is_overdue(day) && morning.is_due_today(day));
+        assert!(morning.is_overdue(day.succ_opt().unwrap()));
+    }
+
+    #[test]
+    fn markup() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let colors = MarkupColors::default();
+        let t1 = "(A) fix <b> & co due:2024-05-09".parse::<Task>().unwrap();
+        assert_eq!(t1.to_markup(today, &colors, DateStyle::Absolute), "<b>(A)</b> <span foreground=\"red\">2024-05-09</span> : fix &lt;b&gt; &amp; co");
+        assert_eq!(t1.display_line(), "(A) 2024-05-09 : fix <b> & co");
+        let t2 = "(B) task due:2024-05-10".parse::<Task>().unwrap();
+        let custom = MarkupColors { overdue : String::from("#ff0000"), today : String::from("#ffa500"), priority : Some(String::from("yellow")), ..MarkupColors::default() };
+        assert_eq!(t2.to_markup(today, &custom, DateStyle::Absolute), "(B) <span foreground=\"#ffa500\">2024-05-10</span> : task");
+        assert!(t1.to_markup(today, &custom, DateStyle::Absolute).starts_with("<span foreground=\"yellow\"><b>(A)</b></span> "));
+        let t3 = "task due:2024-05-11".parse::<Task>().unwrap();
+        assert_eq!(t3.to_markup(today, &colors, DateStyle::Absolute), "2024-05-11 : task");
+        let relative = DateStyle::Relative { max_days : 60 };
+        assert_eq!(t1.to_markup(today, &colors, relative), "<b>(A)</b> <span foreground=\"red\">1d overdue</span> : fix &lt;b&gt; &amp; co");
+        assert_eq!(t3.to_markup(today, &colors, relative), "tomorrow : task");
+        assert!(t3.recap_str(today, relative, &Locale::default()).contains("2024-05-11 (tomorrow)"));
+        assert!(!t3.recap_str(today, DateStyle::Absolute, &Locale::default()).contains("tomorrow"));
+        let tagged = "(B) call <bob> +work @phone".parse::<Task>().unwrap();
+        let mut tag_colors = MarkupColors::default();
+        tag_colors.projects.insert(String::from("work"), String::from("#5294e2"));
+        tag_colors.priorities.insert('B', String::from("blue"));
+        assert_eq!(tagged.to_markup(today, &tag_colors, DateStyle::Absolute),
+                   format!("<span foreground=\"blue\">(B)</span> call &lt;bob&gt; <span foreground=\"#5294e2\">+work</span> <span foreground=\"{}\">@phone</span>",
+                           markup::palette_color("phone")));
+        tag_colors.whole_row = true;
+        assert_eq!(tagged.to_markup(today, &tag_colors, DateStyle::Absolute), "<span foreground=\"#5294e2\">(B) call &lt;bob&gt; +work @phone</span>");
+        assert_eq!(t2.to_markup(today, &tag_colors, DateStyle::Absolute), "<span foreground=\"blue\">(B) <span foreground=\"orange\">2024-05-10</span> : task</span>");
+        let t4 = "plan trip note:plan-trip.md".parse::<Task>().unwrap();
+        assert!(t4.recap_str(today, DateStyle::Absolute, &Locale::default()).ends_with("\n𝐍𝐨𝐭𝐞 : plan-trip.md"));
+    }
+
+    #[test]
+    fn templates() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let template = |text : &str| text.parse::<Template>().unwrap();
+        let task = "(A) 2024-05-01 call <bob> +work @phone due:2024-05-09T14:30 est:1h30m rec:1w note:bob.md".parse::<Task>().unwrap();
+        let plain = |text : &str| task.display_line_with(&template(text), today, DateStyle::Absolute);
+        assert_eq!(plain("{content} {due:%d %b %H:%M} {projects}"), "call <bob> +work @phone 09 May 14:30 +work");
+        assert_eq!(plain("{content}[ (created {created})] ~{estimate} {recurrence} {tag.note} {contexts}"),
+                   "call <bob> +work @phone (created 2024-05-01) ~1h 30m 1w bob.md @phone");
+        assert_eq!(plain("{due}"), "2024-05-09 14:30");
+        assert_eq!(task.display_line_with(&template("{due}"), today, DateStyle::Relative { max_days : 60 }), "1d overdue 14:30");
+        assert_eq!(task.display_line_with(&template("{due}").with_date_format("%d.%m.%Y"), today, DateStyle::Absolute), "09.05.2024 14:30");
+        let bare = "call mom".parse::<Task>().unwrap();
+        assert_eq!(bare.display_line_with(&template("{priority} {content} {due:%d %b} {projects}"), today, DateStyle::Absolute), "call mom");
+        assert_eq!(bare.display_line_with(&template("{content}[ done {completed}] {threshold}"), today, DateStyle::Absolute), "call mom");
+        let colors = MarkupColors::default();
+        assert_eq!(task.to_markup_with(&template("{content} {due:%d %b}"), today, &colors, DateStyle::Absolute),
+                   format!("call &lt;bob&gt; {} {} <span foreground=\"red\">09 May</span>",
+                           markup::colored("+work", colors.project_color("work")), markup::colored("@phone", colors.context_color("phone"))));
+        assert_eq!(task.to_markup_with(&template("<{priority}> {estimate}"), today, &colors, DateStyle::Absolute), "&lt;<b>(A)</b>&gt; 1h 30m");
+    }
+
+    #[test]
+    fn recap() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let task = "(A) 2024-05-01 paint the fence +house @garden due:2024-05-11T09:00 t:2024-05-12 rec:2w est:3h id:paint color:green h:1 star:1 note:fence.md"
+            .parse::<Task>().unwrap();
+        let relative = DateStyle::Relative { max_days : 60 };
+        let mut ascii = Locale::default();
+        ascii.labels = Labels::Ascii;
+        assert_eq!(task.recap_str(today, relative, &Locale::default()), "𝐓𝐚𝐬𝐤 : paint the fence +house @garden
+𝐒𝐭𝐚𝐭𝐮𝐬 : To do (hidden) (pinned)
+𝐏𝐫𝐢𝐨𝐫𝐢𝐭𝐲 : A
+𝐂𝐫𝐞𝐚𝐭𝐞𝐝 𝐨𝐧 : 2024-05-01
+𝐃𝐮𝐞 𝐝𝐚𝐭𝐞 : 2024-05-11 09:00 (tomorrow)
+𝐓𝐡𝐫𝐞𝐬𝐡𝐨𝐥𝐝 : 2024-05-12 (not shown until then)
+𝐑𝐞𝐜𝐮𝐫𝐫𝐞𝐧𝐜𝐞 : 2w
+𝐂𝐨𝐧𝐭𝐞𝐱𝐭 𝐭𝐚𝐠𝐬 : garden
+𝐏𝐫𝐨𝐣𝐞𝐜𝐭 𝐭𝐚𝐠𝐬 : house
+𝐄𝐬𝐭𝐢𝐦𝐚𝐭𝐞 : 3h
+𝐓𝐚𝐠𝐬 : id:paint, color:green
+𝐍𝐨𝐭𝐞 : fence.md");
+        assert_eq!(task.recap_str(today, relative, &ascii), "Task: paint the fence +house @garden
+Status: To do (hidden) (pinned)
+Priority: A
+Created on: 2024-05-01
+Due date: 2024-05-11 09:00 (tomorrow)
+Threshold: 2024-05-12 (not shown until then)
+Recurrence: 2w
+Context tags: garden
+Project tags: house
+Estimate: 3h
+Tags: id:paint, color:green
+Note: fence.md");
+        let done = "x 2024-05-09 2024-05-01 call mom rec:often".parse::<Task>().unwrap();
+        assert_eq!(done.recap_str(today, DateStyle::Absolute, &ascii), "Task: call mom\nStatus: Done (2024-05-09)\nCreated on: 2024-05-01\nTags: rec:often");
+        assert_eq!("call mom".parse::<Task>().unwrap().recap_str(today, DateStyle::Absolute, &Locale::default()), "𝐓𝐚𝐬𝐤 : call mom\n𝐒𝐭𝐚𝐭𝐮𝐬 : To do");
+        let mut german = Locale::new("de").unwrap();
+        german.set_date_format("%d.%m.%Y").unwrap();
+        german.labels = Labels::Ascii;
+        assert_eq!(task.recap_str(today, DateStyle::Absolute, &german), "Aufgabe: paint the fence +house @garden
+Status: Offen (versteckt) (angeheftet)
+Priorität: A
+Erstellt am: 01.05.2024
+Fällig am: 11.05.2024 09:00
+Ab: 12.05.2024 (bis dahin ausgeblendet)
+Wiederholung: 2w
+Kontexte: garden
+Projekte: house
+Schätzung: 3h
+Tags: id:paint, color:green
+Notiz: fence.md");
+        assert!(done.recap_str(today, DateStyle::Absolute, &german).contains("\nStatus: Erledigt (09.05.2024)\n"));
+        // The dates of the line are still read and written in the ISO format
+        assert_eq!(task.to_string(), "(A) 2024-05-01 paint the fence +house @garden due:2024-05-11T09:00 t:2024-05-12 rec:2w est:3h id:paint color:green h:1 star:1 note:fence.md");
+        assert_eq!("call mom due:06.05.2024".parse::<Task>().unwrap().get_due(), &None);
+    }
+
+    #[test]
+    fn comp_completion_date() {
+        let t1 = "x 2024-01-02 2024-01-01 b".parse::<Task>().unwrap();
+        let t2 = "x 2024-01-03 2024-01-01 a".parse::<Task>().unwrap();
+        let t3 = "x 2024-01-02 2024-01-01 a".parse::<Task>().unwrap();
+        let undated = "x a".parse::<Task>().unwrap();
+        let open = "a".parse::<Task>().unwrap();
+        assert_eq!(t1.comp_completion_date(&t2), std::cmp::Ordering::Less);
+        assert_eq!(t1.comp_completion_date(&undated), std::cmp::Ordering::Less);
+        assert_eq!(t1.comp_completion_date(&open), std::cmp::Ordering::Less);
+        assert_eq!(undated.comp_completion_date(&open), std::cmp::Ordering::Equal);
+        // Same completion date, falls back to content
+        assert_eq!(t3.comp_completion_date(&t1), std::cmp::Ordering::Less);
+    }
+
+    #[test]
+    fn comp_descending() {
+        let desc = SortDirection::Descending;
+        let t1 = "x 2024-01-02 2024-01-01 b".parse::<Task>().unwrap();
+        let t2 = "x 2024-01-03 2024-01-01 a".parse::<Task>().unwrap();
+        let t3 = "x 2024-01-02 2024-01-01 a".parse::<Task>().unwrap();
+        let undated = "x a".parse::<Task>().unwrap();
+        assert_eq!(t1.comp_sorted(&t2, &SortTaskBy::CompletionDate, desc), std::cmp::Ordering::Greater);
+        // Missing values still sort last
+        assert_eq!(t1.comp_sorted(&undated, &SortTaskBy::CompletionDate, desc), std::cmp::Ordering::Less);
+        // Ties still fall back to content in ascending order
+        assert_eq!(t3.comp_sorted(&t1, &SortTaskBy::CompletionDate, desc), std::cmp::Ordering::Less);
+
+        let p1 = "(A) b".parse::<Task>().unwrap();
+        let p2 = "(B) a".parse::<Task>().unwrap();
+        let p3 = "(A) a".parse::<Task>().unwrap();
+        assert_eq!(p1.comp_sorted(&p2, &SortTaskBy::Priority, desc), std::cmp::Ordering::Greater);
+        assert_eq!(p3.comp_sorted(&p1, &SortTaskBy::Priority, desc), std::cmp::Ordering::Less);
+        assert_eq!(p3.comp_sorted(&p1, &SortTaskBy::Content, desc), std::cmp::Ordering::Greater);
+        assert_eq!(p1.comp_sorted(&p1, &SortTaskBy::Content, desc), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn comp_multi() {
+        let t1 = "(B) 2024-01-01 b due:2024-02-01".parse::<Task>().unwrap();
+        let t2 = "(A) 2024-01-02 a due:2024-02-01".parse::<Task>().unwrap();
+        let t3 = "(A) 2024-01-01 c due:2024-02-01".parse::<Task>().unwrap();
+        let t4 = "2024-01-01 d due:2024-01-15".parse::<Task>().unwrap();
+        let keys = [SortTaskBy::DueDate, SortTaskBy::Priority, SortTaskBy::CreationDate];
+        assert_eq!(t4.comp_multi(&t2, &keys), std::cmp::Ordering::Less);
+        assert_eq!(t2.comp_multi(&t1, &keys), std::cmp::Ordering::Less);
+        assert_eq!(t3.comp_multi(&t2, &keys), std::cmp::Ordering::Less);
+        // Content is the implicit last key
+        assert_eq!(t2.comp_multi(&t3, &[SortTaskBy::DueDate, SortTaskBy::Priority]), std::cmp::Ordering::Less);
+        assert_eq!(t1.comp_multi(&t2, &[]), std::cmp::Ordering::Greater);
+        // The single key sorts are chains of keys
+        assert_eq!(t1._comp(&t2, &SortTaskBy::Priority), t1.comp_multi(&t2, &[SortTaskBy::Priority, SortTaskBy::DueDate]));
+        assert_eq!(t3._comp(&t2, &SortTaskBy::CreationDate), t3.comp_multi(&t2, &[SortTaskBy::CreationDate]));
+    }
+
+    #[test]
+    fn urgency() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let overdue = "(C) 2024-01-01 pay the bills due:2024-02-01".parse::<Task>().unwrap();
+        let future = "(A) 2024-01-01 plan the holidays due:2024-08-01".parse::<Task>().unwrap();
+        assert!(overdue.urgency(today) > future.urgency(today));
+        let project = "(A) 2024-01-01 plan the holidays +family due:2024-08-01".parse::<Task>().unwrap();
+        assert!(project.urgency(today) > future.urgency(today));
+        let mut done = overdue.clone();
+        done.set_completed();
+        assert_eq!(done.urgency(today), 0.0);
+        let weights = UrgencyWeights { due : 0.0, overdue_per_week : 0.0, ..UrgencyWeights::default() };
+        assert!(overdue.urgency_with(today, &weights) < future.urgency_with(today, &weights));
+    }
+
+    #[test]
+    fn comp_urgency() {
+        let t1 = "(A) b".parse::<Task>().unwrap();
+        let t2 = "(B) a".parse::<Task>().unwrap();
+        let t3 = "(A) a".parse::<Task>().unwrap();
+        assert_eq!(t1._comp(&t2, &SortTaskBy::Urgency), std::cmp::Ordering::Less);
+        assert_eq!(t3._comp(&t1, &SortTaskBy::Urgency), std::cmp::Ordering::Less);
+        assert_eq!(t1.comp_sorted(&t2, &SortTaskBy::Urgency, SortDirection::Descending), std::cmp::Ordering::Greater);
+    }
+
+    #[test]
+    fn quick_add() {
+        // A friday
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let task = Task::from_quick_add("(A) call plumber @phone +house due:fri", today).unwrap();
+        assert_eq!(task.get_priority(), Some('A'));
+        assert_eq!(task.get_content(), "call plumber @phone +house");
+        assert_eq!(task.get_context_tags(), &vec![String::from("phone")]);
+        assert_eq!(task.get_project_tags(), &vec![String::from("house")]);
+        assert_eq!(task.to_string(), "(A) 2024-03-01 call plumber @phone +house due:2024-03-08");
+
+        let task = Task::from_quick_add("2024-02-01 water plants t:+2w due:2024-03-20", today).unwrap();
+        assert_eq!(task.to_string(), "2024-02-01 water plants t:2024-03-15 due:2024-03-20");
+
+        assert!(Task::from_quick_add("pay rent due:someday", today).is_err());
+        assert!(Task::from_quick_add("pay rent due:2024-02-30", today).is_err());
+        // A lowercase priority is part of the content
+        assert_eq!(Task::from_quick_add("(a) pay rent", today).unwrap().get_content(), "(a) pay rent");
+    }
+
+    #[test]
+    fn postpone() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        let mut task = "call mom".parse::<Task>().unwrap();
+        task.postpone(PostponeBy::Days(1), today);
+        assert_eq!(task.get_due(), &date(2024, 1, 11));
+        assert_eq!(task.to_string(), "call mom due:2024-01-11");
+        // From the due date, even when it is past
+        task.postpone(PostponeBy::Weeks(1), today);
+        assert_eq!(task.get_due(), &date(2024, 1, 18));
+        let mut late = "pay rent due:2023-12-31".parse::<Task>().unwrap();
+        late.postpone(PostponeBy::Days(3), today);
+        assert_eq!(late.get_due(), &date(2024, 1, 3));
+        late.postpone(PostponeBy::Until(today), today);
+        assert_eq!(late.get_due(), &Some(today));
+
+        let cases = [("2024-01-31", 1, "2024-02-29"), ("2023-01-31", 1, "2023-02-28"), ("2024-02-29", 12, "2025-02-28"), ("2024-03-31", 1, "2024-04-30"), ("2024-12-15", 2, "2025-02-15")];
+        for (due, months, expected) in cases {
+            let mut task = format!("water plants due:{}", due).parse::<Task>().unwrap();
+            task.postpone(PostponeBy::Months(months), today);
+            assert_eq!(task.get_due(), &NaiveDate::parse_from_str(expected, "%Y-%m-%d").ok(), "{} + {} months", due, months);
+        }
+    }
+
+    #[test]
+    fn postpone_skipping_weekends() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let workdays = Workdays { skip_weekends : true, ..Default::default() };
+        let postponed = |line : &str, offset : PostponeBy, workdays : &Workdays| {
+            let mut task = line.parse::<Task>().unwrap();
+            task.postpone_with(offset, today, workdays);
+            task.get_due().unwrap().to_string()
+        };
+        // A Friday + 1 day, a Thursday + 3 days, the last Friday of a month + 1 day
+        assert_eq!(postponed("send report due:2024-05-10", PostponeBy::Days(1), &workdays), "2024-05-13");
+        assert_eq!(postponed("send report due:2024-05-09", PostponeBy::Days(3), &workdays), "2024-05-13");
+        assert_eq!(postponed("send report due:2024-05-31", PostponeBy::Days(1), &workdays), "2024-06-03");
+        // The month is clamped to its last day before the weekend is skipped, a Saturday
+        assert_eq!(postponed("send report due:2026-01-31", PostponeBy::Months(1), &workdays), "2026-03-02");
+        assert_eq!(postponed("send report due:2024-05-10", PostponeBy::Until(NaiveDate::from_ymd_opt(2024, 5, 11).unwrap()), &workdays), "2024-05-11");
+        // The tags choose for a task
+        assert_eq!(postponed("send report due:2024-05-10 wd:0", PostponeBy::Days(1), &workdays), "2024-05-11");
+        assert_eq!(postponed("send report due:2024-05-10 wd:1", PostponeBy::Days(1), &Workdays::default()), "2024-05-13");
+        assert_eq!(postponed("send report due:2024-05-10", PostponeBy::Days(1), &Workdays::default()), "2024-05-11");
+        // A weekend on Friday and Saturday
+        let friday_saturday = Workdays { weekend : vec![chrono::Weekday::Fri, chrono::Weekday::Sat], skip_weekends : true };
+        assert_eq!(postponed("send report due:2024-05-09", PostponeBy::Days(1), &friday_saturday), "2024-05-12");
+
+        let mut done = "2024-05-01 send report due:2024-05-10 rec:+1d".parse::<Task>().unwrap();
+        done.set_completed();
+        let next = done.next_occurrence_with(NaiveDate::from_ymd_opt(2024, 5, 10).unwrap(), &workdays).unwrap();
+        assert_eq!((next.get_due(), next.creation_date), (&NaiveDate::from_ymd_opt(2024, 5, 13), NaiveDate::from_ymd_opt(2024, 5, 10)));
+        assert_eq!(done.next_occurrence().unwrap().get_due(), &NaiveDate::from_ymd_opt(2024, 5, 11));
+        let mut monthly = "2024-01-01 pay rent due:2026-01-31 rec:+1m wd:1".parse::<Task>().unwrap();
+        monthly.set_completed();
+        assert_eq!(monthly.next_occurrence().unwrap().get_due(), &NaiveDate::from_ymd_opt(2026, 3, 2));
+    }
+
+    #[test]
+    fn postpone_names() {
+        assert_eq!(PostponeBy::PRESETS.iter().map(|p| p.to_string()).collect::<Vec<_>>(), vec!["+1 day", "+3 days", "+1 week", "+1 month"]);
+    }
+
+    #[test]
+    fn edit() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let mut task = "(B) 2024-01-02 call plumber @phone due:2024-03-10".parse::<Task>().unwrap();
+        task.set_source_line(Some(4));
+        let line = task.to_string();
+        task.edit(&line.replace("plumber", "electrician"), today).unwrap();
+        assert_eq!(task.to_string(), "(B) 2024-01-02 call electrician @phone due:2024-03-10");
+        assert_eq!(task.get_source_line(), Some(4));
+
+        // The creation date is kept when it is removed from the line
+        task.edit("(A) call electrician +house due:tomorrow", today).unwrap();
+        assert_eq!(task.to_string(), "(A) 2024-01-02 call electrician +house due:2024-03-02");
+        assert_eq!(task.get_project_tags(), &vec![String::from("house")]);
+        assert!(!task.completion);
+
+        let before = task.to_string();
+        assert_eq!(task.edit("(A) due:fri", today), Err(TaskParseError::EmptyContent));
+        assert!(task.edit("call due:someday", today).is_err());
+        assert_eq!(task.to_string(), before);
+    }
+
+    #[test]
+    fn set_priority() {
+        let mut task = "call mom".parse::<Task>().unwrap();
+        assert_eq!(task.set_priority(Some('a')), Err(InvalidPriority('a')));
+        assert_eq!(task.set_priority(Some('1')), Err(InvalidPriority('1')));
+        assert_eq!(task.get_priority(), None);
+        task.set_priority(Some('C')).unwrap();
+        assert_eq!(task.to_string(), "(C) call mom");
+        task.set_priority(None).unwrap();
+        assert_eq!(task.to_string(), "call mom");
+
+        let mut done = "x 2024-01-02 call mom".parse::<Task>().unwrap();
+        done.set_priority(Some('B')).unwrap();
+        assert_eq!(done.get_priority(), Some('B'));
+        assert_eq!(done.to_string(), "x 2024-01-02 call mom pri:B");
+    }
+
+    #[test]
+    fn bump_and_lower_priority() {
+        let mut task = "call mom".parse::<Task>().unwrap();
+        task.bump_priority();
+        assert_eq!(task.get_priority(), Some('Z'));
+        task.bump_priority();
+        assert_eq!(task.get_priority(), Some('Y'));
+        task.lower_priority();
+        task.lower_priority();
+        assert_eq!(task.get_priority(), None);
+        task.lower_priority();
+        assert_eq!(task.get_priority(), None);
+
+        let mut task = "(B) call mom".parse::<Task>().unwrap();
+        task.bump_priority();
+        task.bump_priority();
+        assert_eq!(task.get_priority(), Some('A'));
+        task.lower_priority();
+        assert_eq!(task.get_priority(), Some('B'));
+    }
+
+    #[test]
+    fn comp_completed_last() {
+        let todo = "(C) b due:2024-03-01".parse::<Task>().unwrap();
+        let done = "x 2024-01-02 2024-01-01 a pri:A due:2024-01-01".parse::<Task>().unwrap();
+        let sorts = [SortTaskBy::CreationDate, SortTaskBy::Content, SortTaskBy::Priority, SortTaskBy::DueDate, SortTaskBy::CompletionDate, SortTaskBy::Urgency];
+        let today = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        for sort in sorts {
+            for direction in [SortDirection::Ascending, SortDirection::Descending] {
+                let keys = [sort.clone()];
+                assert_eq!(todo.comp_completed_last(&done, |a, b| a.comp_multi_directed(b, &keys, direction, today)), std::cmp::Ordering::Less);
+                assert_eq!(done.comp_completed_last(&todo, |a, b| a.comp_multi_directed(b, &keys, direction, today)), std::cmp::Ordering::Greater);
+            }
+        }
+        // Tasks with the same completion state keep the order of the comparator
+        let other = "(A) c".parse::<Task>().unwrap();
+        assert_eq!(todo.comp_completed_last(&other, |a, b| a.comp_multi(b, &[SortTaskBy::Priority])), std::cmp::Ordering::Greater);
+    }
+
+    #[test]
+    fn comp_pinned_first() {
+        let pinned = "(C) b star:1".parse::<Task>().unwrap();
+        let open = "(A) a due:2024-01-01".parse::<Task>().unwrap();
+        let pinned_done = "x 2024-01-02 c star:1".parse::<Task>().unwrap();
+        assert!(pinned.is_pinned() && !open.is_pinned());
+        let today = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        for sort in SortTaskBy::ALL {
+            for direction in [SortDirection::Ascending, SortDirection::Descending] {
+                let keys = [sort.clone()];
+                let comp = |a : &Task, b : &Task| a.comp_completed_last(b, |a, b| a.comp_pinned_first(b, |a, b| a.comp_multi_directed(b, &keys, direction, today)));
+                let mut tasks = vec![pinned_done.clone(), open.clone(), pinned.clone()];
+                tasks.sort_by(comp);
+                // A completed pinned task is still after the tasks to do
+                assert_eq!(tasks, vec![pinned.clone(), open.clone(), pinned_done.clone()], "{:?} {:?}", sort, direction);
+            }
+        }
+        let mut unpinned = pinned.clone();
+        unpinned.set_pinned(false);
+        assert_eq!(unpinned.to_string(), "(C) b");
+        unpinned.set_pinned(true);
+        assert_eq!(unpinned.to_string(), "(C) b star:1");
+    }
+
+    #[test]
+    fn sort_names() {
+        for key in SortTaskBy::ALL {
+            assert_eq!(SortTaskBy::from_name(key.name()), Some(key));
+        }
+        assert_eq!(SortTaskBy::from_name("Due"), Some(SortTaskBy::DueDate));
+        assert_eq!(SortTaskBy::from_name("size"), None);
+    }
+
+    #[test]
+    fn for_today() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let for_today = |line : &str| line.parse::<Task>().unwrap().is_for_today(today);
+        assert!(for_today("pay bills due:2024-05-01"));
+        assert!(for_today("call mom due:2024-05-10"));
+        assert!(for_today("water plants t:2024-05-10 due:2024-06-01"));
+        assert!(for_today("(A) fix the roof"));
+        assert!(!for_today("(B) fix the roof due:2024-05-11"));
+        assert!(!for_today("water plants t:2024-05-11"));
+        assert!(!for_today("x 2024-05-09 pay bills due:2024-05-01"));
+        assert!(!for_today("x 2024-05-09 fix the roof pri:A"));
+    }
+
+    #[test]
+    fn rename_tag() {
+        let mut task = "(A) sell the bike +garage @shop due:2024-05-01".parse::<Task>().unwrap();
+        assert!(task.rename_tag('+', "garage", "yard"));
+        assert_eq!(task.to_string(), "(A) sell the bike +yard @shop due:2024-05-01");
+        assert_eq!(task.get_project_tags(), &vec![String::from("yard")]);
+        assert!(!task.rename_tag('+', "shop", "store"));
+        assert!(task.rename_tag('@', "shop", "store"));
+        assert_eq!(task.get_context_tags(), &vec![String::from("store")]);
+
+        // At the start of the content
+        let mut task = "2024-01-01 +GarageSale prepare the tables".parse::<Task>().unwrap();
+        assert!(task.rename_tag('+', "GarageSale", "YardSale"));
+        assert_eq!(task.to_string(), "2024-01-01 +YardSale prepare the tables");
+
+        // Longer tags and the name without prefix are not renamed
+        let line = "call about the GarageSale +GarageSale2 @GarageSale";
+        let mut task = line.parse::<Task>().unwrap();
+        assert!(!task.rename_tag('+', "GarageSale", "YardSale"));
+        assert_eq!(task.to_string(), line);
+        let mut task = format!("{} +GarageSale", line).parse::<Task>().unwrap();
+        assert!(task.rename_tag('+', "GarageSale", "YardSale"));
+        assert_eq!(task.to_string(), format!("{} +YardSale", line));
+        assert_eq!(task.get_project_tags(), &vec![String::from("GarageSale2"), String::from("YardSale")]);
+    }
+
+    #[test]
+    fn add_and_remove_tags() {
+        let mut task = "(A) call plumber due:2024-05-01".parse::<Task>().unwrap();
+        task.add_project_tag("house");
+        task.add_context_tag("phone");
+        task.add_project_tag("house");
+        assert_eq!(task.to_string(), "(A) call plumber +house @phone due:2024-05-01");
+        assert_eq!(task.get_project_tags(), &vec![String::from("house")]);
+        assert_eq!(task.get_context_tags(), &vec![String::from("phone")]);
+
+        // At the start, in the middle and at the end
+        let mut task = "+house call the note:x plumber @phone +house2 +house".parse::<Task>().unwrap();
+        task.remove_project_tag("house");
+        assert_eq!(task.to_string(), "call the note:x plumber @phone +house2");
+        assert_eq!(task.get_project_tags(), &vec![String::from("house2")]);
+        task.remove_context_tag("phone");
+        task.remove_context_tag("missing");
+        assert_eq!(task.to_string(), "call the note:x plumber +house2");
+        assert!(task.get_context_tags().is_empty());
+
+        let mut task = "+house".parse::<Task>().unwrap();
+        task.remove_project_tag("house");
+        assert_eq!(task.to_string(), "");
+        task.add_context_tag("home");
+        assert_eq!(task.to_string(), "@home");
+    }
+
+    #[test]
+    fn urls() {
+        let task = "read https://blog.example.com/post. and http://a.org/x @reading url:example.com/doc https://blog.example.com/post".parse::<Task>().unwrap();
+        assert_eq!(task.urls(), vec!["https://blog.example.com/post", "http://a.org/x", "https://example.com/doc"]);
+        let task = "review url:https://example.com/pr/1".parse::<Task>().unwrap();
+        assert_eq!(task.urls(), vec!["https://example.com/pr/1"]);
+        assert!("call mom".parse::<Task>().unwrap().urls().is_empty());
+    }
+
+    #[test]
+    fn stale_tasks() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+        let old = "2024-04-01 call the bank".parse::<Task>().unwrap();
+        let recent = "2024-05-01 buy milk".parse::<Task>().unwrap();
+        assert_eq!((old.age_days(today), recent.age_days(today)), (Some(60), Some(30)));
+        assert!(old.is_stale(today, 30) && !recent.is_stale(today, 30));
+        assert!(!old.is_stale(today, 0) && !old.is_stale(today, 60));
+        // A task without creation date is never stale, nor a completed one
+        let undated = "fix the sink".parse::<Task>().unwrap();
+        assert_eq!(undated.age_days(today), None);
+        assert!(!undated.is_stale(today, 1));
+        assert!(!"x 2024-05-02 2024-01-01 pay rent".parse::<Task>().unwrap().is_stale(today, 30));
+    }
 }
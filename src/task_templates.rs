@@ -0,0 +1,124 @@
+use chrono::NaiveDate;
+use std::str::FromStr;
+use crate::dates::{due_tag, parse_due, parse_due_time};
+
+/// The placeholders of a task template, where the typed text goes
+pub const PLACEHOLDERS : [&str; 2] = ["{cursor}", "{input}"];
+
+/// A todo.txt line offered when adding a task, like `review PR #{input} @work +reviews due:+2d`
+///
+/// The line can have one placeholder for the text typed when the task is added, and relative due and threshold dates
+/// resolved on the day the task is added (`due:+2d`, `t:fri`, see `parse_due`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskTemplate {
+    /// The line of the template, with its placeholder and its relative dates
+    line : String
+}
+
+/// The line of a template on a given day, cut at its placeholder
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expansion {
+    /// The line before the placeholder, the whole line without placeholder
+    pub before : String,
+    /// The line after the placeholder, `None` without placeholder
+    pub after : Option<String>
+}
+
+impl Expansion {
+    /// Return the line of the task once the text of the placeholder is typed
+    ///
+    /// Arguments:
+    ///
+    /// * `typed` - the line typed from the text before the placeholder
+    pub fn complete(&self, typed : &str) -> String {
+        format!("{}{}", typed, self.after.as_deref().unwrap_or("")).trim().to_string()
+    }
+}
+
+impl TaskTemplate {
+    /// Return the line of the template on a day, its relative dates resolved
+    ///
+    /// The dates next to the placeholder are left as they are, they are resolved when the task is added
+    ///
+    /// Arguments:
+    ///
+    /// * `today` - the day the task is added
+    pub fn expand(&self, today : NaiveDate) -> Expansion {
+        let line = self.line.split(' ').map(|word| resolve_date(word, today).unwrap_or_else(|| word.to_string())).collect::<Vec<_>>().join(" ");
+        match PLACEHOLDERS.iter().find_map(|placeholder| line.split_once(placeholder)) {
+            Some((before, after)) => Expansion { before : before.to_string(), after : Some(after.to_string()) },
+            None => Expansion { before : line, after : None }
+        }
+    }
+}
+
+/// Read a template, fails when it has several placeholders or a relative date which is not understood
+impl FromStr for TaskTemplate {
+    type Err = String;
+
+    fn from_str(line : &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Err(String::from("the template is empty"));
+        }
+        if PLACEHOLDERS.iter().map(|placeholder| line.matches(placeholder).count()).sum::<usize>() > 1 {
+            return Err(format!("the template has more than one of the placeholders {}", PLACEHOLDERS.join(", ")));
+        }
+        // Any day tells the dates which are not understood
+        let today = NaiveDate::from_ymd_opt(2000, 1, 1).expect("the date is valid");
+        match line.split(' ').find(|word| is_date_tag(word) && resolve_date(word, today).is_none()) {
+            Some(word) => Err(format!("`{}` is not a date", word)),
+            None => Ok(TaskTemplate { line : line.to_string() })
+        }
+    }
+}
+
+/// Return true if a word is a `due:` or `t:` tag without placeholder
+fn is_date_tag(word : &str) -> bool {
+    (word.starts_with("due:") || word.starts_with("t:")) && !PLACEHOLDERS.iter().any(|placeholder| word.contains(placeholder))
+}
+
+/// Return a `due:` or `t:` tag with its relative date resolved, `None` for the other words and the dates which are not
+/// understood
+///
+/// Arguments:
+///
+/// * `word` - a word of a template
+/// * `today` - the day the task is added
+fn resolve_date(word : &str, today : NaiveDate) -> Option<String> {
+    if !is_date_tag(word) {
+        return None;
+    }
+    let (key, value) = word.split_once(':')?;
+    let (date, time) = if key == "due" { parse_due_time(value, today)? } else { (parse_due(value, today)?, None) };
+    Some(format!("{}:{}", key, due_tag(date, time)))
+}
+
+#[cfg(test)]
+mod task_templates_tests {
+    use super::*;
+
+    #[test]
+    fn expansion() {
+        // A Friday
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let review = "review PR #{input} @work +reviews due:+2d".parse::<TaskTemplate>().unwrap();
+        let expansion = review.expand(today);
+        assert_eq!(expansion, Expansion { before : String::from("review PR #"), after : Some(String::from(" @work +reviews due:2024-05-12")) });
+        assert_eq!(expansion.complete("review PR #42"), "review PR #42 @work +reviews due:2024-05-12");
+        let standup = "(B) standup notes +team t:mon due:tomorrowT09:30".parse::<TaskTemplate>().unwrap();
+        assert_eq!(standup.expand(today), Expansion { before : String::from("(B) standup notes +team t:2024-05-13 due:2024-05-11T09:30"), after : None });
+        // The placeholder can be in the middle of the line or in a date, resolved once typed
+        let call = "call {cursor} due:eow".parse::<TaskTemplate>().unwrap();
+        assert_eq!(call.expand(today).complete("call mom"), "call mom due:2024-05-12");
+        let pay = "pay due:{input}".parse::<TaskTemplate>().unwrap();
+        assert_eq!(pay.expand(today).complete("pay due:fri"), "pay due:fri");
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert_eq!("  ".parse::<TaskTemplate>(), Err(String::from("the template is empty")));
+        assert_eq!("review due:+2x".parse::<TaskTemplate>(), Err(String::from("`due:+2x` is not a date")));
+        assert!("{input} and {input}".parse::<TaskTemplate>().unwrap_err().contains("more than one"));
+    }
+}
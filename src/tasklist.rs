@@ -0,0 +1,1057 @@
+use std::ops::{Deref, DerefMut};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::collections::{BTreeMap, BTreeSet};
+use chrono::{Local, NaiveDate};
+use crate::task::{Task, TaskId, SortTaskBy, SortDirection, TaskParseError};
+use crate::indexer::Indexer;
+use crate::filter::TaskFilter;
+use crate::undo::{History, Op, TaskStore};
+use crate::archive::Archive;
+use crate::trash::Trash;
+use crate::{merge, storage, todofile};
+use crate::storage::Cipher;
+use crate::todofile::FileFormat;
+use crate::merge::{Conflict, Resolution};
+
+/// The lines of the task file that could not be parsed, with their line number
+pub type ParseErrors = Vec<(usize, TaskParseError)>;
+
+/// The name of the index of the tasks in the sort order of the list
+pub const TASKS_INDEX : &str = "tasks";
+
+/// The name of the index of the completed tasks
+const DONE_INDEX : &str = "done";
+
+/// What `TaskList::reload_if_changed` did
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reload {
+    /// The task file didn't change
+    Unchanged,
+    /// The task file changed and the list, without changes, was loaded again
+    Reloaded,
+    /// The task file changed and was merged with the changes of the list
+    Merged {
+        /// The number of tasks added to the file
+        added : usize,
+        /// The number of lines changed both in the list and in the file, the version of the list is kept
+        conflicts : usize
+    }
+}
+
+/// A task file and its tasks
+///
+/// The tasks keep the line of the file they were read from, so saving the list only changes the lines of the modified
+/// tasks, and the lines that could not be parsed are written back unchanged.
+/// Each task gets a `TaskId` when it is loaded or added, to find it again whatever the sort order and even if
+/// another task of the list is identical
+pub struct TaskList {
+    /// The path of the task file
+    pub path : PathBuf,
+    /// The tasks of the list, with an index of the tasks of each context and project tag
+    pub todos : Indexer<Task>,
+    /// The lines of the task file that could not be parsed
+    parse_errors : ParseErrors,
+    /// The lines of the task file when it was loaded
+    loaded_lines : Vec<String>,
+    /// The byte order mark and the line endings of the task file, written back when saving
+    format : FileFormat,
+    /// The line index given to the next new task, after the loaded lines
+    next_line : usize,
+    /// True if the tasks were changed since the task file was loaded
+    pub modified : bool,
+    /// True if the task file must not be written: the saves, the archiving and the undo fail
+    pub read_only : bool,
+    /// The operations which can be undone, forgotten when the file is loaded again
+    pub history : History,
+    /// The archive of the completed tasks, next to the task file if `None`
+    pub done_file : Option<PathBuf>,
+    /// The trash of the deleted tasks, next to the task file if `None`
+    pub trash_file : Option<PathBuf>,
+    /// The encryption of the task file, its archive and its trash when their name ends in `.gpg`
+    pub cipher : Option<Rc<dyn Cipher>>
+}
+
+impl TaskList {
+    /// Load a task file, creating it if it does not exist
+    ///
+    /// The tasks are sorted by content until `sort` is called
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the task file
+    /// * `undo_depth` - the maximum number of operations which can be undone
+    pub fn load(path : &Path, undo_depth : usize) -> Result<Self, String> {
+        Self::load_with(path, undo_depth, None)
+    }
+
+    /// Load a task file, see `load`, an encrypted file is decrypted with `cipher`
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the task file
+    /// * `undo_depth` - the maximum number of operations which can be undone
+    /// * `cipher` - the encryption of the files whose name ends in `.gpg`
+    pub fn load_with(path : &Path, undo_depth : usize, cipher : Option<Rc<dyn Cipher>>) -> Result<Self, String> {
+        let mut todos = new_indexer();
+        let (loaded_lines, parse_errors, format) = load_tasks(path, &mut todos, cipher.as_deref())?;
+        let mut list = TaskList {
+            path : path.to_path_buf(),
+            todos,
+            parse_errors,
+            next_line : loaded_lines.len(),
+            loaded_lines,
+            format,
+            modified : false,
+            read_only : false,
+            history : History::new(undo_depth),
+            done_file : None,
+            trash_file : None,
+            cipher
+        };
+        list.sort(vec![SortTaskBy::Content], SortDirection::Ascending);
+        Ok(list)
+    }
+
+    /// Load the task file again, replacing the tasks
+    ///
+    /// The sort order goes back to the content
+    pub fn reload(&mut self) -> Result<(), String> {
+        let list = TaskList::load_with(&self.path, self.history.depth(), self.cipher.clone())?;
+        let mut history = std::mem::replace(&mut self.history, History::new(0));
+        history.clear();
+        *self = TaskList { done_file : self.done_file.take(), trash_file : self.trash_file.take(), read_only : self.read_only, history, ..list };
+        Ok(())
+    }
+
+    /// Load the changes another program made to the task file since it was loaded
+    ///
+    /// A list without changes is loaded again. The changes of a modified list are kept: the lines added to the file
+    /// become tasks, the tasks only changed in the file are replaced and a line changed on both sides keeps the
+    /// version of the list. The operations which can be undone are forgotten in both cases
+    pub fn reload_if_changed(&mut self) -> Result<Reload, String> {
+        let (theirs, format) = match storage::read_to_string(&self.path, self.cipher.as_deref()) {
+            Ok(content) => FileFormat::split(&content),
+            // The file is created again when the list is saved
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Reload::Unchanged),
+            Err(e) => return Err(format!("Could not read {} : {}", self.path.display(), e))
+        };
+        if theirs == self.loaded_lines {
+            return Ok(Reload::Unchanged);
+        }
+        if !self.modified {
+            self.reload()?;
+            return Ok(Reload::Reloaded);
+        }
+
+        let map = merge::line_map(&self.loaded_lines, &theirs);
+        let tasks = self.todos.get_main_index().iter().map(|t| t.shared()).collect::<Vec<_>>();
+        let tasks = tasks.into_iter().filter_map(|t| self.todos.remove(t)).collect::<Vec<_>>();
+        // The lines of the file now represented by a task, or removed from the list
+        let mut claimed = vec![false; theirs.len()];
+        let mut kept_lines = vec![false; self.loaded_lines.len()];
+        let (mut merged, mut new_tasks, mut conflicts) = (Vec::new(), Vec::new(), 0);
+        for mut task in tasks {
+            let source = match task.get_source_line() {
+                Some(line) if line < self.loaded_lines.len() => line,
+                _ => {
+                    new_tasks.push(task);
+                    continue;
+                }
+            };
+            kept_lines[source] = true;
+            let edited = task.to_string() != self.loaded_lines[source];
+            match map[source] {
+                Some(j) if !edited && theirs[j] != self.loaded_lines[source] => {
+                    // A line which doesn't parse anymore is preserved like the other unrecognised lines
+                    if let Ok(mut changed) = theirs[j].parse::<Task>() {
+                        changed.set_source_line(Some(j));
+                        if let Some(id) = task.id() {
+                            changed.set_id(id);
+                        }
+                        claimed[j] = true;
+                        merged.push(changed);
+                    }
+                },
+                Some(j) => {
+                    if theirs[j] != self.loaded_lines[source] {
+                        conflicts += 1;
+                    }
+                    claimed[j] = true;
+                    task.set_source_line(Some(j));
+                    merged.push(task);
+                },
+                None if edited => {
+                    conflicts += 1;
+                    task.set_source_line(None);
+                    new_tasks.push(task);
+                },
+                None => {}
+            }
+        }
+        // The tasks removed from the list stay removed
+        let preserved = self.parse_errors.iter().map(|(line, _)| line - 1).collect::<Vec<_>>();
+        for (i, j) in map.iter().enumerate() {
+            if let Some(j) = j {
+                if !kept_lines[i] && !preserved.contains(&i) {
+                    if theirs[*j] != self.loaded_lines[i] {
+                        conflicts += 1;
+                    }
+                    claimed[*j] = true;
+                }
+            }
+        }
+
+        let mut loaded = vec![false; theirs.len()];
+        for j in map.iter().flatten() {
+            loaded[*j] = true;
+        }
+        let (mut added, mut parse_errors) = (0, Vec::new());
+        for (j, line) in theirs.iter().enumerate().filter(|(j, _)| !claimed[*j]) {
+            match line.parse::<Task>() {
+                Ok(mut task) => {
+                    task.set_source_line(Some(j));
+                    task.set_id(TaskId::next());
+                    if !loaded[j] {
+                        added += 1;
+                    }
+                    merged.push(task);
+                },
+                Err(e) => parse_errors.push((j + 1, e))
+            }
+        }
+        // The new tasks of the list stay after the lines of the file
+        new_tasks.sort_by_key(|t| t.get_source_line().unwrap_or(usize::MAX));
+        self.next_line = theirs.len();
+        for mut task in new_tasks {
+            task.set_source_line(Some(self.next_line));
+            self.next_line += 1;
+            merged.push(task);
+        }
+        for task in merged {
+            add_task(&mut self.todos, task);
+        }
+        self.loaded_lines = theirs;
+        self.format = format;
+        self.parse_errors = parse_errors;
+        self.history.clear();
+        Ok(Reload::Merged { added, conflicts })
+    }
+
+    /// Return an error if the task file must not be written, see `read_only`
+    pub fn check_writable(&self) -> Result<(), String> {
+        if self.read_only {
+            Err(format!("{} is read-only", self.name()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Write the tasks to the task file, merging them with the file if another program modified it, see `merge::merge`
+    ///
+    /// Arguments:
+    ///
+    /// * `resolve` - a closure choosing the line to keep for each conflict
+    pub fn save_merging(&self, resolve : impl FnMut(&Conflict) -> Resolution) -> Result<(), String> {
+        self.check_writable()?;
+        merge::save_merging(&self.path, &self.loaded_lines, &self.file_lines(), &self.format, self.cipher.as_deref(), resolve)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Write the tasks to the task file, replacing its content
+    pub fn save(&self) -> Result<(), String> {
+        self.check_writable()?;
+        let lines = self.file_lines();
+        let content = self.format.join(lines.iter().map(|(_, line)| line.as_str()));
+        storage::save_atomic(&self.path, &content, self.cipher.as_deref()).map_err(|e| e.to_string())
+    }
+
+    /// The name of the task file shown in the menus
+    pub fn name(&self) -> String {
+        self.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// The path of the archive of the completed tasks of the list
+    pub fn archive_path(&self) -> PathBuf {
+        self.done_file.clone().unwrap_or_else(|| Archive::path_for(&self.path))
+    }
+
+    /// The path of the trash of the deleted tasks of the list
+    pub fn trash_path(&self) -> PathBuf {
+        self.trash_file.clone().unwrap_or_else(|| Trash::path_for(&self.path))
+    }
+
+    /// Load the archive of the completed tasks of the list, encrypted like the task file
+    pub fn archive(&self) -> Result<Archive, String> {
+        Archive::load(&self.archive_path(), self.cipher.clone())
+    }
+
+    /// Load the trash of the deleted tasks of the list, encrypted like the task file
+    pub fn trash(&self) -> Result<Trash, String> {
+        Trash::load(&self.trash_path(), self.cipher.clone())
+    }
+
+    /// Add a task to the list, a new task is numbered to be saved at the end of the task file
+    ///
+    /// Return the id of the task: the task keeps its id if it was removed from the list, a new task gets a new one
+    pub fn add(&mut self, mut task : Task) -> TaskId {
+        if task.get_source_line().is_none() {
+            task.set_source_line(Some(self.next_line));
+            self.next_line += 1;
+        }
+        let id = match task.id() {
+            Some(id) if self.get(id).is_none() => id,
+            _ => TaskId::next()
+        };
+        task.set_id(id);
+        self.modified = true;
+        add_task(&mut self.todos, task);
+        id
+    }
+
+    /// Return a task of the list
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task
+    pub fn get(&self, id : TaskId) -> Option<&Task> {
+        self.todos.get_main_index().iter().find(|t| t.id() == Some(id)).map(|t| t.as_ref())
+    }
+
+    /// Return a task of the list to modify it, the task is indexed again with its changes when the returned value is dropped
+    ///
+    /// The list is marked as modified
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task
+    pub fn get_mut(&mut self, id : TaskId) -> Option<TaskMut<'_>> {
+        let task = self.remove(id)?;
+        Some(TaskMut { list : self, task : Some(task) })
+    }
+
+    /// Remove a task from the list
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task
+    pub fn remove(&mut self, id : TaskId) -> Option<Task> {
+        let task = self.todos.get_main_index().iter().find(|t| t.id() == Some(id)).map(|t| t.shared())?;
+        self.modified = true;
+        self.todos.remove(task)
+    }
+
+    /// Return the tasks in the order of the lines of the task file, the new tasks last
+    pub fn iter(&self) -> impl Iterator<Item = (TaskId, &Task)> {
+        let mut tasks = self.todos.get_main_index().iter().map(|t| t.as_ref()).collect::<Vec<_>>();
+        tasks.sort_by_key(|t| t.get_source_line());
+        tasks.into_iter().filter_map(|t| Some((t.id()?, t)))
+    }
+
+    /// Sort the tasks, the pinned tasks first and the completed tasks after the tasks to do
+    ///
+    /// Only the order of `tasks` and `filter` changes, the task file keeps the order of its lines
+    ///
+    /// Arguments:
+    ///
+    /// * `sort` - the sort keys, by decreasing importance
+    /// * `direction` - the direction of the sort
+    pub fn sort(&mut self, sort : Vec<SortTaskBy>, direction : SortDirection) {
+        // The urgency of the day of the sort, the order of the index can't change while it holds the tasks
+        let today = Local::now().date_naive();
+        self.todos.remove_index(&String::from(TASKS_INDEX));
+        self.todos.new_index(String::from(TASKS_INDEX), |_|true, move |a, b| {
+            a.comp_completed_last(b, |a, b| a.comp_pinned_first(b, |a, b| a.comp_multi_directed(b, &sort, direction, today))).then_with(|| identity(a, b))
+        });
+    }
+
+    /// Return the tasks in the sort order of the list
+    pub fn tasks(&self) -> Vec<(TaskId, &Task)> {
+        self.index(TASKS_INDEX).unwrap_or_default()
+    }
+
+    /// Return the completed tasks, the most recently completed first
+    pub fn done(&self) -> Vec<(TaskId, &Task)> {
+        self.index(DONE_INDEX).unwrap_or_default()
+    }
+
+    /// Return the tasks of an index, `None` if the index doesn't exist
+    ///
+    /// The tasks to do of each context and project tag have an index, `context_` or `project_` followed by the tag
+    ///
+    /// Arguments:
+    ///
+    /// * `name` - the name of the index
+    pub fn index(&self, name : &str) -> Option<Vec<(TaskId, &Task)>> {
+        let index = self.todos.get_index(&name.to_string())?;
+        Some(index.iter().map(|t| t._content()).filter_map(|t| Some((t.id()?, t))).collect())
+    }
+
+    /// Return the tasks matching a filter, in the sort order of the list
+    ///
+    /// Arguments:
+    ///
+    /// * `filter` - the filter of the tasks
+    pub fn filter(&self, filter : &TaskFilter) -> Vec<(TaskId, &Task)> {
+        self.tasks().into_iter().filter(|(_, t)| filter.matches(t)).collect()
+    }
+
+    /// Rewrite the task file in the sort order of the list and load it again
+    ///
+    /// The lines that could not be parsed are kept at the end of the file
+    ///
+    /// Arguments:
+    ///
+    /// * `backups` - the number of backups of the task file to keep
+    pub fn sort_file(&mut self, backups : usize) -> Result<(), String> {
+        self.check_writable()?;
+        let mut lines = self.tasks().into_iter().map(|(_, task)| task.to_string()).collect::<Vec<_>>();
+        lines.extend(self.parse_errors.iter().filter_map(|(line, _)| self.loaded_lines.get(line - 1).cloned()));
+        let content = self.format.join(lines.iter().map(|l| l.as_str()));
+        storage::rotate_backups(&self.path, backups).map_err(|e| e.to_string())?;
+        storage::save_atomic(&self.path, &content, self.cipher.as_deref()).map_err(|e| e.to_string())?;
+        self.reload()
+    }
+
+    /// Move the tasks completed more than `days` days ago to the archive, save the task file and load it again
+    ///
+    /// The tasks completed without a completion date are kept. Return the number of archived tasks, nothing is
+    /// written if there is none
+    ///
+    /// Arguments:
+    ///
+    /// * `today` - the current date
+    /// * `days` - the number of days the completed tasks stay in the task file
+    /// * `backups` - the number of backups of the task file to keep
+    pub fn archive_completed(&mut self, today : NaiveDate, days : u32, backups : usize) -> Result<usize, String> {
+        self.check_writable()?;
+        let old = self.iter()
+                      .filter(|(_, t)| t.completion && t.completion_date.is_some_and(|date| (today - date).num_days() > days as i64))
+                      .map(|(id, _)| id)
+                      .collect::<Vec<_>>();
+        if old.is_empty() {
+            return Ok(0);
+        }
+        let tasks = old.iter().filter_map(|id| self.get(*id).cloned()).collect::<Vec<_>>();
+        // The tasks are in the archive before they leave the task file, an error can't lose them
+        self.archive()?.put_all(&tasks)?;
+        for id in old {
+            self.remove(id);
+        }
+        // Only written to the log, the history is forgotten by the reload
+        self.history.push(Op::Batch(tasks.iter().cloned().map(Op::Remove).collect()));
+        storage::rotate_backups(&self.path, backups).map_err(|e| e.to_string())?;
+        self.save()?;
+        self.reload()?;
+        Ok(tasks.len())
+    }
+
+    /// Return the tasks to do a task depends on through its `dep:` tags
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task
+    pub fn blocking(&self, id : TaskId) -> Vec<TaskId> {
+        let dependencies = match self.get(id) {
+            Some(task) => task.get_dependencies(),
+            None => return vec![]
+        };
+        if dependencies.is_empty() {
+            return vec![];
+        }
+        self.iter()
+            .filter(|(_, t)| !t.completion && t.get_dependency_id().is_some_and(|i| dependencies.contains(&i)))
+            .map(|(other, _)| other)
+            .collect()
+    }
+
+    /// Return true if a task depends on a task not completed yet, see `blocking`
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task
+    pub fn is_blocked(&self, id : TaskId) -> bool {
+        !self.blocking(id).is_empty()
+    }
+
+    /// Return the ids of the `dep:` tags of a task naming no task of the list, they don't block the task
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task
+    pub fn unknown_dependencies(&self, id : TaskId) -> Vec<String> {
+        let task = match self.get(id) {
+            Some(task) => task,
+            None => return vec![]
+        };
+        task.get_dependencies().into_iter()
+            .filter(|dep| !self.iter().any(|(_, t)| t.get_dependency_id() == Some(*dep)))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Return the tasks to do depending on a task which are blocked by no other task, the tasks it unblocks when
+    /// it is completed
+    ///
+    /// Arguments:
+    ///
+    /// * `id` - the id of the task
+    pub fn unblocked_by(&self, id : TaskId) -> Vec<TaskId> {
+        let dependency = match self.get(id).and_then(|t| t.get_dependency_id()) {
+            Some(dependency) => dependency,
+            None => return vec![]
+        };
+        self.iter()
+            .filter(|(other, t)| !t.completion && t.get_dependencies().contains(&dependency) && self.blocking(*other).iter().all(|b| *b == id))
+            .map(|(other, _)| other)
+            .collect()
+    }
+
+    /// Return the cycles of dependencies between the tasks to do, which block their tasks forever
+    ///
+    /// A cycle is the list of its ids, the first id repeated at the end (`a`, `b`, `a` when `a` depends on `b` and
+    /// `b` on `a`)
+    pub fn dependency_cycles(&self) -> Vec<Vec<String>> {
+        let mut graph : BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (_, task) in self.iter().filter(|(_, t)| !t.completion) {
+            if let Some(id) = task.get_dependency_id() {
+                graph.entry(id).or_default().extend(task.get_dependencies());
+            }
+        }
+        let mut cycles = Vec::new();
+        let mut visited = BTreeSet::new();
+        for id in graph.keys() {
+            find_cycles(&graph, id, &mut Vec::new(), &mut visited, &mut cycles);
+        }
+        cycles
+    }
+
+    /// Return an `id:` tag used by no task of the list, the smallest free number
+    pub fn new_dependency_id(&self) -> String {
+        let used = self.iter().filter_map(|(_, t)| t.get_dependency_id()).collect::<BTreeSet<_>>();
+        (1..).map(|n : usize| n.to_string()).find(|id| !used.contains(id.as_str())).expect("a number is free")
+    }
+
+    /// Return the lines to write in the task file with the index of their line
+    ///
+    /// The lines keep the order of the file whatever the sort order of the list,
+    /// and the lines that could not be parsed are written back unchanged
+    pub fn file_lines(&self) -> Vec<(Option<usize>, String)> {
+        let tasks = self.todos.get_main_index().iter().map(|t| (t.get_source_line(), t.to_string())).collect();
+        let preserved = self.parse_errors.iter()
+                            .filter_map(|(line, _)| self.loaded_lines.get(line - 1).map(|l| (line - 1, l.clone())))
+                            .collect::<Vec<_>>();
+        todofile::file_lines(tasks, &preserved)
+    }
+
+    /// The lines of the task file that could not be parsed, with their line number
+    pub fn parse_errors(&self) -> &ParseErrors {
+        &self.parse_errors
+    }
+
+    /// The lines of the task file when it was loaded
+    pub fn loaded_lines(&self) -> &[String] {
+        &self.loaded_lines
+    }
+
+    /// The byte order mark and the line endings of the task file
+    pub fn format(&self) -> FileFormat {
+        self.format
+    }
+
+    /// Describe the lines of the task file that could not be parsed
+    pub fn parse_errors_str(&self) -> String {
+        let mut s = format!("{} unrecognised lines preserved", self.parse_errors.len());
+        for (line, error) in &self.parse_errors {
+            s.push_str(&format!("\nline {} : {}", line, error));
+        }
+        s
+    }
+
+    /// Return the tasks as a JSON array, in the order of the lines of the task file
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&self.iter().map(|(_, t)| t).collect::<Vec<_>>()).map_err(|e| e.to_string())
+    }
+
+    /// Create a list of JSON tasks, written to a task file when the list is saved
+    ///
+    /// The tasks are checked like by `TaskBuilder::build`, the file is not read and is replaced when the list is saved
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the task file
+    /// * `json` - a JSON array of tasks, as written by `to_json`
+    /// * `undo_depth` - the maximum number of operations which can be undone
+    #[cfg(feature = "serde")]
+    pub fn from_json(path : &Path, json : &str, undo_depth : usize) -> Result<Self, String> {
+        let tasks : Vec<Task> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let mut list = TaskList {
+            path : path.to_path_buf(),
+            todos : new_indexer(),
+            parse_errors : vec![],
+            loaded_lines : vec![],
+            format : FileFormat::default(),
+            next_line : 0,
+            modified : false,
+            read_only : false,
+            history : History::new(undo_depth),
+            done_file : None,
+            trash_file : None,
+            cipher : None
+        };
+        list.sort(vec![SortTaskBy::Content], SortDirection::Ascending);
+        for task in tasks {
+            list.add(task);
+        }
+        Ok(list)
+    }
+}
+
+impl TaskStore for TaskList {
+    fn insert(&mut self, task : Task) {
+        self.add(task);
+    }
+
+    fn take(&mut self, task : &Task) -> Option<Task> {
+        let found = self.todos.get_main_index().iter().find(|t| t.id() == task.id() && t.as_ref() == task).and_then(|t| t.id())?;
+        self.remove(found)
+    }
+
+    fn check_writable(&self) -> Result<(), String> {
+        TaskList::check_writable(self)
+    }
+
+    fn cipher(&self) -> Option<&dyn Cipher> {
+        self.cipher.as_deref()
+    }
+}
+
+/// A task taken out of its list to modify it, see `TaskList::get_mut`
+///
+/// The task goes back to the list with its id when this value is dropped
+pub struct TaskMut<'a> {
+    list : &'a mut TaskList,
+    task : Option<Task>
+}
+
+impl Deref for TaskMut<'_> {
+    type Target = Task;
+
+    fn deref(&self) -> &Task {
+        self.task.as_ref().expect("the task is only taken when dropped")
+    }
+}
+
+impl DerefMut for TaskMut<'_> {
+    fn deref_mut(&mut self) -> &mut Task {
+        self.task.as_mut().expect("the task is only taken when dropped")
+    }
+}
+
+impl Drop for TaskMut<'_> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            self.list.add(task);
+        }
+    }
+}
+
+/// Read the tasks of a task file, creating it if it does not exist, and give them an id
+///
+/// Return the lines of the file, the lines that could not be parsed and the format of the file
+fn load_tasks(path : &Path, todos : &mut Indexer<Task>, cipher : Option<&dyn Cipher>) -> Result<(Vec<String>, ParseErrors, FileFormat), String> {
+    if !path.exists() {
+        storage::save_atomic(path, "", cipher).map_err(|e| e.to_string())?;
+    }
+    // A file which can't be read or decrypted is not replaced by an empty list
+    let content = storage::read_to_string(path, cipher).map_err(|e| format!("Could not read {} : {}", path.display(), e))?;
+    let (loaded, format) = FileFormat::split(&content);
+    let (tasks, errors) = todofile::parse_lines(&loaded);
+    for mut task in tasks {
+        task.set_id(TaskId::next());
+        add_task(todos, task);
+    }
+    Ok((loaded, errors.into_iter().map(|(index, e)| (index + 1, e)).collect(), format))
+}
+
+/// Add a task to the indexes, with an index for each of its context and project tags if it is not completed
+fn add_task(idx: &mut Indexer<Task>, tsk: Task) -> Rc<Task> {
+    if !tsk.completion {
+        for tag in tsk.get_context_tags().clone() {
+            let mut idx_name = String::from("context_");
+            idx_name.push_str(&tag);
+            idx.new_autoremove_index(idx_name, move |x|!x.completion && x.get_context_tags().contains(&tag), |a, b| a.comp_content(b).then_with(|| identity(a, b)));
+        }
+        for tag in tsk.get_project_tags().clone() {
+            let mut idx_name = String::from("project_");
+            idx_name.push_str(&tag);
+            idx.new_autoremove_index(idx_name, move |x|!x.completion && x.get_project_tags().contains(&tag), |a, b| a.comp_content(b).then_with(|| identity(a, b)));
+        }
+    }
+    idx.add(tsk)
+}
+
+/// Compare every field of two tasks, then their ids: the identical lines of a task file are different tasks of the list
+fn identity(a : &Task, b : &Task) -> std::cmp::Ordering {
+    a.cmp(b).then_with(|| a.id().cmp(&b.id()))
+}
+
+/// Create an empty task list with the indexes used by the menus
+fn new_indexer() -> Indexer<Task> {
+    let mut todos = Indexer::with_order(identity);
+    todos.new_index(String::from(DONE_INDEX),       |x|x.completion, |a, b| a.comp_sorted(b, &SortTaskBy::CompletionDate, SortDirection::Descending).then_with(|| identity(a, b)));
+    todos
+}
+
+/// Follow the dependencies from an id depth first, adding the cycles found to `cycles`
+///
+/// An id already visited is not followed again, so the walk ends and a cycle is reported once
+///
+/// Arguments:
+///
+/// * `graph` - the ids each id depends on
+/// * `id` - the id to follow
+/// * `path` - the ids followed to reach `id`
+/// * `visited` - the ids already followed
+/// * `cycles` - the cycles found
+fn find_cycles<'a>(graph : &BTreeMap<&'a str, Vec<&'a str>>, id : &'a str, path : &mut Vec<&'a str>, visited : &mut BTreeSet<&'a str>, cycles : &mut Vec<Vec<String>>) {
+    if let Some(start) = path.iter().position(|i| *i == id) {
+        cycles.push(path[start..].iter().chain(std::iter::once(&id)).map(|i| i.to_string()).collect());
+        return;
+    }
+    if !visited.insert(id) {
+        return;
+    }
+    path.push(id);
+    for dependency in graph.get(id).into_iter().flatten() {
+        find_cycles(graph, dependency, path, visited, cycles);
+    }
+    path.pop();
+}
+
+#[cfg(test)]
+mod tasklist_tests {
+    use super::*;
+    use crate::test_files::{temp_file, FakeCipher};
+    use std::fs;
+
+    fn contents<'a>(tasks : &[(TaskId, &'a Task)]) -> Vec<&'a str> {
+        tasks.iter().map(|(_, t)| t.get_content().as_str()).collect()
+    }
+
+    #[test]
+    fn save_keeps_file_order() {
+        let original = "water plants due:2024-03-01\n(B) call mom\nx 2024-13-01 broken\n(A) buy milk +home\nanswer mail @work\n";
+        let path = temp_file("order", original);
+        let mut list = TaskList::load(&path, 20).unwrap();
+
+        list.sort(vec![SortTaskBy::Priority, SortTaskBy::DueDate], SortDirection::Ascending);
+        list.sort(vec![SortTaskBy::Urgency], SortDirection::Descending);
+        let id = list.tasks().into_iter().find(|(_, t)| t.get_content() == "call mom").unwrap().0;
+        list.get_mut(id).unwrap().set_completed();
+        list.save().unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        let changed = original.lines().zip(saved.lines()).filter(|(a, b)| a != b).collect::<Vec<_>>();
+        assert_eq!(saved.lines().count(), original.lines().count());
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].1.starts_with("x "));
+
+        // New tasks are added at the end
+        list.add(Task::new(String::from("a new task")));
+        list.save().unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.lines().last().unwrap().ends_with("a new task"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sort_and_filter() {
+        let path = temp_file("sort", "water plants due:2024-03-01 +home\nx done task\n(B) call mom\n(A) buy milk +home\n");
+        let mut list = TaskList::load(&path, 20).unwrap();
+        assert!(!list.modified);
+        assert_eq!(contents(&list.tasks()), vec!["buy milk +home", "call mom", "water plants +home", "done task"]);
+
+        list.sort(vec![SortTaskBy::Priority], SortDirection::Descending);
+        assert_eq!(contents(&list.tasks()), vec!["call mom", "buy milk +home", "water plants +home", "done task"]);
+        assert_eq!(contents(&list.filter(&TaskFilter::from_query("+home"))), vec!["buy milk +home", "water plants +home"]);
+        assert_eq!(contents(&list.filter(&TaskFilter::todo())).len(), 3);
+
+        // Reloading forgets the unsaved changes
+        list.add(Task::new(String::from("a new task")));
+        assert!(list.modified);
+        list.reload().unwrap();
+        assert!(!list.modified);
+        assert_eq!(list.tasks().len(), 4);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stable_ids() {
+        let path = temp_file("ids", "call mom\n(A) pay rent\ncall mom\nbuy milk +home\n");
+        let mut list = TaskList::load(&path, 20).unwrap();
+        // The identical tasks are both kept
+        let ids = list.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(contents(&list.iter().collect::<Vec<_>>()), vec!["call mom", "pay rent", "call mom", "buy milk +home"]);
+        assert_eq!(contents(&list.tasks()), vec!["buy milk +home", "call mom", "call mom", "pay rent"]);
+        assert_ne!(ids[0], ids[2]);
+
+        // Removing a task leaves its identical twin and the other ids
+        assert_eq!(list.remove(ids[0]).unwrap().get_content(), "call mom");
+        assert!(list.get(ids[0]).is_none() && list.remove(ids[0]).is_none());
+        list.sort(vec![SortTaskBy::Priority], SortDirection::Ascending);
+        assert_eq!(list.tasks().iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![ids[1], ids[3], ids[2]]);
+        assert_eq!(list.filter(&TaskFilter::from_query("+home")), vec![(ids[3], list.get(ids[3]).unwrap())]);
+        assert_eq!(list.get(ids[2]).unwrap().get_content(), "call mom");
+        assert_eq!(list.get(ids[1]).unwrap().priority, Some('A'));
+
+        // A modified task keeps its id and its place in the file
+        list.get_mut(ids[2]).unwrap().set_completed();
+        assert!(list.get(ids[2]).unwrap().completion);
+        assert_eq!(list.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![ids[1], ids[2], ids[3]]);
+        let added = list.add("call mom".parse::<Task>().unwrap());
+        assert!(!ids.contains(&added));
+        list.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("(A) pay rent\n{}\nbuy milk +home\ncall mom\n", list.get(ids[2]).unwrap()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json() {
+        let original = "(B) call mom due:2024-03-01\nx 2024-01-03 2024-01-02 water plants +home\nbuy milk @store est:5m\n";
+        let path = temp_file("json", original);
+        let list = TaskList::load(&path, 20).unwrap();
+        let json = list.to_json().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let imported = TaskList::from_json(&path, &json, 20).unwrap();
+        assert!(imported.modified);
+        assert_eq!(imported.to_json().unwrap(), json);
+        imported.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        assert!(TaskList::from_json(&path, r#"[{ "content" : "" }]"#, 20).err().unwrap().starts_with("the task is empty"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unparsed_lines_are_kept() {
+        let path = temp_file("unparsed", "call mom\nx 2024-13-01 broken\n");
+        let mut list = TaskList::load(&path, 20).unwrap();
+        assert_eq!(list.parse_errors().len(), 1);
+        assert_eq!(list.parse_errors()[0].0, 2);
+        assert_eq!(list.parse_errors_str(), "1 unrecognised lines preserved\nline 2 : invalid creation date 2024-13-01");
+        assert_eq!(list.loaded_lines().len(), 2);
+
+        list.add("answer mail".parse::<Task>().unwrap());
+        list.sort_file(0).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "answer mail\ncall mom\nx 2024-13-01 broken\n");
+        assert_eq!(list.name(), path.file_name().unwrap().to_string_lossy());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn archive_completed() {
+        let path = temp_file("auto_archive", "x 2024-03-01 2024-02-01 old +house
+call mom
+x 2024-03-20 2024-03-01 recent
+\
+                                              x 2024-03-01 undated
+x 2024-13-01 broken
+x 2024-02-15 2024-02-01 older
+");
+        let done = path.with_extension("done");
+        fs::write(&done, "x 2024-01-01 2023-12-01 archived
+").unwrap();
+        let mut list = TaskList::load(&path, 20).unwrap();
+        list.done_file = Some(done.clone());
+        let today = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        assert_eq!(list.archive_completed(today, 30, 0).unwrap(), 2);
+        // The tasks keep the order of the task file
+        assert_eq!(fs::read_to_string(&done).unwrap(), "x 2024-01-01 2023-12-01 archived
+x 2024-03-01 2024-02-01 old +house
+x 2024-02-15 2024-02-01 older
+");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "call mom
+x 2024-03-20 2024-03-01 recent
+x 2024-03-01 undated
+x 2024-13-01 broken
+");
+        assert!(!list.modified);
+        assert_eq!(list.done_file, Some(done.clone()));
+        assert_eq!(list.iter().count(), 3);
+        // The task completed 30 days ago stays, a task completed without date is never archived
+        assert_eq!(list.archive_completed(NaiveDate::from_ymd_opt(2024, 4, 19).unwrap(), 30, 0).unwrap(), 0);
+        // The tasks stay in the task file when the archive can't be written
+        list.done_file = Some(std::env::temp_dir());
+        assert!(list.archive_completed(NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(), 30, 0).is_err());
+        assert_eq!((list.iter().count(), list.modified), (3, false));
+        list.done_file = Some(done.clone());
+        assert_eq!(list.archive_completed(NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(), 30, 0).unwrap(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "call mom
+x 2024-03-01 undated
+x 2024-13-01 broken
+");
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&done).unwrap();
+    }
+
+    #[test]
+    fn reload_external_append() {
+        let path = temp_file("reload_append", "call mom\nbuy milk\n");
+        let mut list = TaskList::load(&path, 20).unwrap();
+        assert_eq!(list.reload_if_changed().unwrap(), Reload::Unchanged);
+        fs::write(&path, "call mom\nbuy milk\nwater plants\n").unwrap();
+        assert_eq!(list.reload_if_changed().unwrap(), Reload::Reloaded);
+        assert_eq!(list.iter().count(), 3);
+        assert!(!list.modified);
+
+        // The new task of the list goes after the line added to the file
+        let id = list.iter().find(|(_, t)| t.get_content() == "buy milk").unwrap().0;
+        list.get_mut(id).unwrap().set_completed();
+        list.add(Task::new(String::from("pay rent")));
+        fs::write(&path, "call mom\nbuy milk\nwater plants\nx 2024-13-01 broken\nfix the sink\n").unwrap();
+        assert_eq!(list.reload_if_changed().unwrap(), Reload::Merged { added : 1, conflicts : 0 });
+        assert!(list.modified);
+        assert!(!list.history.can_undo());
+        assert_eq!(list.parse_errors().iter().map(|(line, _)| *line).collect::<Vec<_>>(), vec![4]);
+        list.save().unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let saved = saved.lines().collect::<Vec<_>>();
+        assert_eq!(saved.len(), 6);
+        assert!(saved[1].starts_with("x ") && saved[1].ends_with(" buy milk"));
+        assert_eq!(saved[2..5], ["water plants", "x 2024-13-01 broken", "fix the sink"]);
+        assert!(saved[5].ends_with(" pay rent"));
+        list.reload().unwrap();
+        assert_eq!(list.reload_if_changed().unwrap(), Reload::Unchanged);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reload_external_edit() {
+        let path = temp_file("reload_edit", "call mom\nbuy milk\nwater plants\nfix the sink\n");
+        let mut list = TaskList::load(&path, 20).unwrap();
+        for (content, new) in [("buy milk", "(A) buy milk"), ("water plants", "water plants +garden")] {
+            let id = list.iter().find(|(_, t)| t.get_content() == content).unwrap().0;
+            *list.get_mut(id).unwrap() = new.parse().unwrap();
+        }
+        let removed = list.iter().find(|(_, t)| t.get_content() == "fix the sink").unwrap().0;
+        list.remove(removed);
+        // The file changes the task changed in the list, a task of the list only and the removed task
+        fs::write(&path, "call mom @phone\nbuy milk +shop\nwater plants\nfix the sink today\n").unwrap();
+        assert_eq!(list.reload_if_changed().unwrap(), Reload::Merged { added : 0, conflicts : 2 });
+        list.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "call mom @phone\n(A) buy milk\nwater plants +garden\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn encrypted_list() {
+        let path = temp_file("encrypted.txt.gpg", "FAKEGPG 0123456789ABCDEF\ncall mom\n");
+        assert!(TaskList::load(&path, 20).is_err());
+        let mut list = TaskList::load_with(&path, 20, Some(Rc::new(FakeCipher))).unwrap();
+        assert_eq!(list.loaded_lines(), ["call mom"]);
+        let stem = path.file_name().unwrap().to_string_lossy().replace(".txt.gpg", "");
+        assert_eq!(list.archive_path(), path.with_file_name(format!("{}.done.txt.gpg", stem)));
+        list.add(Task::new(String::from("pay rent")));
+        list.save().unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.starts_with("FAKEGPG 0123456789ABCDEF\ncall mom\n") && saved.ends_with(" pay rent\n"));
+        // A file which can't be decrypted is an error, not an empty list
+        fs::write(&path, "garbage\n").unwrap();
+        assert!(TaskList::load_with(&path, 20, Some(Rc::new(FakeCipher))).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "garbage\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_only_list() {
+        let content = "call mom\nx 2020-01-02 2020-01-01 pay rent\n";
+        let path = temp_file("read_only", content);
+        let modified = || fs::metadata(&path).unwrap().modified().unwrap();
+        let before = modified();
+        let mut list = TaskList::load(&path, 20).unwrap();
+        list.read_only = true;
+        // The tasks can change in memory, they are never written
+        let id = list.add(Task::new(String::from("water plants")));
+        list.history.push(crate::undo::Op::Add(list.get(id).unwrap().clone()));
+        let message = format!("{} is read-only", list.name());
+        assert_eq!(list.save(), Err(message.clone()));
+        assert_eq!(list.save_merging(|_| Resolution::KeepMine), Err(message.clone()));
+        assert_eq!(list.sort_file(3), Err(message.clone()));
+        assert_eq!(list.archive_completed(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 0, 3), Err(message.clone()));
+        let mut history = std::mem::replace(&mut list.history, History::new(0));
+        assert_eq!(history.undo(&mut list), Err(message));
+        assert!(history.can_undo());
+        list.reload().unwrap();
+        assert!(list.read_only);
+        assert_eq!(modified(), before);
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert!(!storage::backup_path(&path, 1).exists() && !list.archive_path().exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keep_file_format() {
+        // A byte order mark with CRLF line endings, then a file without final line ending
+        for (name, content) in [("format_bom", "\u{feff}call mom\r\nbuy milk\r\n"), ("format_end", "call mom\nbuy milk")] {
+            let path = temp_file(name, content);
+            let mut list = TaskList::load(&path, 20).unwrap();
+            assert_eq!(list.loaded_lines(), ["call mom", "buy milk"]);
+            assert!(!list.format().mixed_endings);
+            let id = list.iter().find(|(_, t)| t.get_content() == "buy milk").unwrap().0;
+            *list.get_mut(id).unwrap() = "(A) buy milk".parse().unwrap();
+            list.save().unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), content.replace("buy milk", "(A) buy milk"));
+            list.reload().unwrap();
+            list.add(Task::new(String::from("water plants")));
+            list.save_merging(|_| Resolution::KeepMine).unwrap();
+            let saved = fs::read_to_string(&path).unwrap();
+            assert_eq!(saved.starts_with('\u{feff}'), content.starts_with('\u{feff}'));
+            assert_eq!(saved.matches("\r\n").count(), if content.contains('\r') { 3 } else { 0 });
+            assert_eq!(saved.ends_with('\n'), content.ends_with('\n'));
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn dependencies() {
+        let path = temp_file("dependencies", "get a quote id:quote\ncall the roofer id:roof dep:quote\nfix the gutter dep:roof,ladder\n\
+                                              x 2024-05-02 2024-05-01 buy a ladder id:ladder\npaint the fence dep:van\n");
+        let mut list = TaskList::load(&path, 20).unwrap();
+        let id = |list : &TaskList, content : &str| list.iter().find(|(_, t)| t.get_content() == content).unwrap().0;
+        let (quote, roof, gutter, fence) = (id(&list, "get a quote"), id(&list, "call the roofer"), id(&list, "fix the gutter"), id(&list, "paint the fence"));
+        assert!(!list.is_blocked(quote));
+        assert_eq!(list.blocking(roof), vec![quote]);
+        // The completed ladder doesn't block the gutter, the roof does
+        assert_eq!(list.blocking(gutter), vec![roof]);
+        // An unknown id doesn't block
+        assert!(!list.is_blocked(fence));
+        assert_eq!(list.unknown_dependencies(fence), vec!["van"]);
+        assert!(list.unknown_dependencies(gutter).is_empty());
+        assert!(list.dependency_cycles().is_empty());
+        assert_eq!(list.new_dependency_id(), "1");
+
+        // Calling the roofer is the only task blocking the gutter
+        assert_eq!(list.unblocked_by(quote), vec![roof]);
+        assert_eq!(list.unblocked_by(roof), vec![gutter]);
+        assert!(list.unblocked_by(fence).is_empty());
+        list.get_mut(quote).unwrap().set_completed();
+        assert!(!list.is_blocked(roof));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn dependency_cycles() {
+        let path = temp_file("cycles", "a id:a dep:b\nb id:b dep:c\nc id:c dep:a\nd id:d dep:d\ne id:e dep:a\nx 2024-05-02 2024-05-01 f id:f dep:g\ng id:g dep:f\n");
+        let list = TaskList::load(&path, 20).unwrap();
+        // The tasks of a cycle are blocked forever, a completed task breaks its cycle
+        assert_eq!(list.dependency_cycles(), vec![vec!["a", "b", "c", "a"], vec!["d", "d"]]);
+        assert!(list.iter().filter(|(_, t)| !t.completion && t.get_content() != "g").all(|(id, _)| list.is_blocked(id)));
+        assert_eq!(list.new_dependency_id(), "1");
+        fs::remove_file(&path).unwrap();
+    }
+}
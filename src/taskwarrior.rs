@@ -0,0 +1,209 @@
+use chrono::{NaiveDate, NaiveDateTime, TimeZone};
+use serde::Deserialize;
+use crate::task::Task;
+use crate::builder::TaskBuilder;
+
+/// A task of `task export`, only the fields read by RofiTodo
+#[derive(Debug, Deserialize)]
+struct Entry {
+    #[serde(default)]
+    uuid : Option<String>,
+    description : String,
+    #[serde(default)]
+    status : Option<String>,
+    #[serde(default)]
+    entry : Option<String>,
+    #[serde(default)]
+    end : Option<String>,
+    #[serde(default)]
+    due : Option<String>,
+    #[serde(default)]
+    wait : Option<String>,
+    #[serde(default)]
+    priority : Option<String>,
+    #[serde(default)]
+    project : Option<String>,
+    #[serde(default)]
+    tags : Vec<String>,
+    #[serde(default)]
+    annotations : Vec<Annotation>
+}
+
+/// A note added to a Taskwarrior task
+#[derive(Debug, Deserialize)]
+struct Annotation {
+    description : String
+}
+
+/// The tasks read from a Taskwarrior export
+#[derive(Debug, Default)]
+pub struct Import {
+    /// The tasks, in their order in the export
+    pub tasks : Vec<Task>,
+    /// The number of deleted tasks and recurrence templates, which are not imported
+    pub ignored : usize,
+    /// The entries which could not be read, as `uuid: error`
+    pub errors : Vec<String>
+}
+
+/// Read the tasks of the JSON array printed by `task export`
+///
+/// The priorities H, M and L become A, B and C, the project becomes a project tag and the tags become context tags.
+/// The annotations are added at the end of the content after ` -- `, and the wait date becomes the threshold date.
+/// A malformed entry is reported in `errors` with its UUID instead of stopping the import
+///
+/// Arguments:
+///
+/// * `json` - the export
+/// * `timezone` - the time zone of the dates, the timestamps of the export are in UTC
+pub fn from_taskwarrior<Tz : TimeZone>(json : &str, timezone : &Tz) -> Result<Import, String> {
+    let values : Vec<serde_json::Value> = serde_json::from_str(json).map_err(|e| format!("Not a Taskwarrior export : {}", e))?;
+    let mut import = Import::default();
+    for (index, value) in values.into_iter().enumerate() {
+        let uuid = value.get("uuid").and_then(|u| u.as_str()).map(String::from).unwrap_or_else(|| format!("entry {}", index + 1));
+        let entry = match serde_json::from_value::<Entry>(value) {
+            Ok(entry) => entry,
+            Err(e) => {
+                import.errors.push(format!("{}: {}", uuid, e));
+                continue;
+            }
+        };
+        if matches!(entry.status.as_deref(), Some("deleted") | Some("recurring")) {
+            import.ignored += 1;
+            continue;
+        }
+        match entry_task(&entry, timezone) {
+            Ok(task) => import.tasks.push(task),
+            Err(e) => import.errors.push(format!("{}: {}", entry.uuid.unwrap_or(uuid), e))
+        }
+    }
+    Ok(import)
+}
+
+/// Build the task of an entry
+fn entry_task<Tz : TimeZone>(entry : &Entry, timezone : &Tz) -> Result<Task, String> {
+    let date = |timestamp : &Option<String>| timestamp.as_deref().map(|t| local_date(t, timezone)).transpose();
+    let mut content = vec![entry.description.as_str()];
+    for annotation in entry.annotations.iter() {
+        content.push("--");
+        content.push(&annotation.description);
+    }
+    // A todo.txt task is a single line
+    let mut builder = TaskBuilder::new().content(&content.join(" ").split_whitespace().collect::<Vec<_>>().join(" "));
+    if let Some(project) = entry.project.as_deref() {
+        builder = builder.project(project);
+    }
+    for tag in entry.tags.iter() {
+        builder = builder.context(tag);
+    }
+    if let Some(priority) = entry.priority.as_deref() {
+        builder = builder.priority(match priority {
+            "H" => 'A',
+            "M" => 'B',
+            "L" => 'C',
+            priority => return Err(format!("invalid priority {}", priority))
+        });
+    }
+    if let Some(due) = date(&entry.due)? {
+        builder = builder.due(due);
+    }
+    if let Some(wait) = date(&entry.wait)? {
+        builder = builder.tag("t", &wait.format("%Y-%m-%d").to_string());
+    }
+    let end = date(&entry.end)?;
+    let created = date(&entry.entry)?;
+    if entry.status.as_deref() == Some("completed") {
+        let end = end.ok_or("the completed task has no end date")?;
+        builder = builder.created(created.unwrap_or(end)).completed(end);
+    } else if let Some(created) = created {
+        builder = builder.created(created);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Return the local date of a Taskwarrior timestamp, `20240115T093000Z`
+fn local_date<Tz : TimeZone>(timestamp : &str, timezone : &Tz) -> Result<NaiveDate, String> {
+    let utc = NaiveDateTime::parse_from_str(timestamp, "%Y%m%dT%H%M%SZ")
+        .or_else(|_| NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%SZ"))
+        .map_err(|_| format!("invalid date {}", timestamp))?;
+    Ok(timezone.from_utc_datetime(&utc).date_naive())
+}
+
+/// Remove the tasks with the same content and due date as an existing task or an earlier task
+///
+/// Return the remaining tasks and the number of removed tasks
+///
+/// Arguments:
+///
+/// * `tasks` - the imported tasks
+/// * `existing` - the tasks of the list
+pub fn skip_duplicates<'a>(tasks : Vec<Task>, existing : impl IntoIterator<Item=&'a Task>) -> (Vec<Task>, usize) {
+    let mut seen = existing.into_iter().map(|t| (t.get_content().clone(), *t.get_due())).collect::<Vec<_>>();
+    let count = tasks.len();
+    let tasks = tasks.into_iter().filter(|task| {
+        let key = (task.get_content().clone(), *task.get_due());
+        if seen.contains(&key) {
+            return false;
+        }
+        seen.push(key);
+        true
+    }).collect::<Vec<_>>();
+    let skipped = count - tasks.len();
+    (tasks, skipped)
+}
+
+#[cfg(test)]
+mod taskwarrior_tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    const EXPORT : &str = r#"[
+        {"id":1,"description":"call the plumber","entry":"20240110T083000Z","due":"20240301T230000Z","priority":"H",
+         "project":"house","tags":["phone","errand"],"status":"pending","uuid":"a1b2c3d4-0000-0000-0000-000000000001",
+         "annotations":[{"entry":"20240111T090000Z","description":"ask about\nthe boiler"}]},
+        {"id":0,"description":"water plants","entry":"20240102T120000Z","end":"20240105T180000Z","priority":"L",
+         "status":"completed","uuid":"a1b2c3d4-0000-0000-0000-000000000002"},
+        {"id":0,"description":"old idea","entry":"20240102T120000Z","status":"deleted","uuid":"a1b2c3d4-0000-0000-0000-000000000003"},
+        {"id":2,"description":"renew passport","entry":"20240103T120000Z","wait":"20240601T000000Z","priority":"X",
+         "status":"waiting","uuid":"a1b2c3d4-0000-0000-0000-000000000004"},
+        {"id":3,"entry":"20240103T120000Z","status":"pending","uuid":"a1b2c3d4-0000-0000-0000-000000000005"},
+        {"id":4,"description":"book dentist","entry":"yesterday","status":"pending","uuid":"a1b2c3d4-0000-0000-0000-000000000006"},
+        {"id":5,"description":"pay rent","entry":"2024-01-03T12:00:00Z","wait":"20240201T000000Z","status":"waiting"}
+    ]"#;
+
+    #[test]
+    fn import() {
+        let import = from_taskwarrior(EXPORT, &Utc).unwrap();
+        assert_eq!(import.tasks.iter().map(|t| t.to_string()).collect::<Vec<_>>(), vec![
+            "(A) 2024-01-10 call the plumber -- ask about the boiler +house @phone @errand due:2024-03-01",
+            "x 2024-01-05 2024-01-02 water plants pri:C",
+            "2024-01-03 pay rent t:2024-02-01"
+        ]);
+        assert_eq!(import.ignored, 1);
+        assert_eq!(import.errors.len(), 3);
+        assert_eq!(import.errors[0], "a1b2c3d4-0000-0000-0000-000000000004: invalid priority X");
+        assert!(import.errors[1].starts_with("a1b2c3d4-0000-0000-0000-000000000005: missing field `description`"), "{}", import.errors[1]);
+        assert_eq!(import.errors[2], "a1b2c3d4-0000-0000-0000-000000000006: invalid date yesterday");
+        assert!(from_taskwarrior("{\"description\":\"call mom\"}", &Utc).is_err());
+    }
+
+    #[test]
+    fn local_dates() {
+        // The plumber is due at 11 PM UTC, the next day two hours east of Greenwich
+        let east = FixedOffset::east_opt(2 * 3600).unwrap();
+        let import = from_taskwarrior(EXPORT, &east).unwrap();
+        assert_eq!(*import.tasks[0].get_due(), NaiveDate::from_ymd_opt(2024, 3, 2));
+        let west = FixedOffset::west_opt(10 * 3600).unwrap();
+        assert_eq!(local_date("20240105T080000Z", &west), Ok(NaiveDate::from_ymd_opt(2024, 1, 4).unwrap()));
+    }
+
+    #[test]
+    fn duplicates() {
+        let existing = ["call mom due:2024-01-10", "pay rent"].iter().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let imported = ["2024-01-02 call mom due:2024-01-10", "call mom due:2024-01-11", "water plants", "water plants"]
+                           .iter().map(|l| l.parse::<Task>().unwrap()).collect::<Vec<_>>();
+        let (tasks, skipped) = skip_duplicates(imported, &existing);
+        assert_eq!(tasks.iter().map(|t| t.to_string()).collect::<Vec<_>>(), vec!["call mom due:2024-01-11", "water plants"]);
+        assert_eq!(skipped, 2);
+    }
+}
@@ -0,0 +1,405 @@
+use std::fmt;
+use std::str::FromStr;
+use chrono::format::{Item, StrftimeItems};
+use crate::dates;
+
+/// The template of the lines of the tasks in the menus, the priority and the due date before the content
+pub const DEFAULT : &str = "[{priority} ][{due} : ]{content}";
+
+/// The names of the placeholders, `tag.<key>` shows a custom tag too
+pub const FIELDS : [&str; 10] = ["priority", "content", "due", "threshold", "created", "completed", "projects", "contexts", "estimate", "recurrence"];
+
+/// A value of a task shown by a placeholder of a `Template`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    /// The priority, `(A)`
+    Priority,
+    /// The content with its project and context tags
+    Content,
+    /// The due date, with its time
+    Due,
+    /// The threshold date
+    Threshold,
+    /// The creation date
+    Created,
+    /// The completion date
+    Completed,
+    /// The project tags, `+work +home`
+    Projects,
+    /// The context tags, `@phone`
+    Contexts,
+    /// The `est:` estimate, `1h 30m`
+    Estimate,
+    /// The `rec:` recurrence
+    Recurrence,
+    /// The value of a custom tag
+    Tag(String)
+}
+
+impl Field {
+    /// Return true for the fields holding a date, which can have a chrono format like `{due:%d %b}`
+    pub fn is_date(&self) -> bool {
+        matches!(self, Field::Due | Field::Threshold | Field::Created | Field::Completed)
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(name : &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "priority" => Field::Priority,
+            "content" => Field::Content,
+            "due" => Field::Due,
+            "threshold" => Field::Threshold,
+            "created" => Field::Created,
+            "completed" => Field::Completed,
+            "projects" => Field::Projects,
+            "contexts" => Field::Contexts,
+            "estimate" => Field::Estimate,
+            "recurrence" => Field::Recurrence,
+            name => match name.strip_prefix("tag.") {
+                Some(key) if !key.is_empty() => Field::Tag(key.to_string()),
+                _ => return Err(format!("unknown placeholder `{{{}}}`, the placeholders are {} and tag.<key>",
+                                        name, FIELDS.iter().map(|f| format!("{{{}}}", f)).collect::<Vec<_>>().join(", ")))
+            }
+        })
+    }
+}
+
+/// A part of a template
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    /// A text written as it is
+    Text(String),
+    /// A value of the task, with the format of a date
+    Placeholder(Field, Option<String>),
+    /// A section between brackets, left out when one of its placeholders is empty
+    Optional(Vec<Part>)
+}
+
+/// The layout of the line of a task, like `{priority} {content}[ due {due:%d %b}]`
+///
+/// `{name}` is replaced by a value of the task, `{name:format}` formats a date with a chrono format.
+/// A section between brackets is left out when one of its placeholders is empty, and an empty placeholder takes one of
+/// the spaces around it away. `{{`, `}}`, `[[` and `]]` write a brace or a bracket
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    /// The parts in their order
+    parts : Vec<Part>,
+    /// The text the template was read from
+    source : String,
+    /// The chrono format of the dates without a format of their own
+    date_format : String
+}
+
+impl Template {
+    /// Read a template, fails on an unknown placeholder, a wrong date format or an unclosed brace or bracket
+    ///
+    /// Arguments:
+    ///
+    /// * `text` - the template
+    pub fn parse(text : &str) -> Result<Self, String> {
+        let mut stack : Vec<Vec<Part>> = vec![vec![]];
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' | '[' | ']' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    literal.push(c);
+                },
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("the placeholder `{{{}` is not closed", name))
+                        }
+                    }
+                    let (name, spec) = match name.split_once(':') {
+                        Some((name, spec)) => (name, Some(spec.to_string())),
+                        None => (name.as_str(), None)
+                    };
+                    let field = name.trim().parse::<Field>()?;
+                    if let Some(spec) = &spec {
+                        if !field.is_date() {
+                            return Err(format!("`{{{}}}` is not a date, only the dates have a format", name));
+                        }
+                        if !is_date_format(spec) {
+                            return Err(format!("`{}` is not a date format of `{{{}}}`", spec, name));
+                        }
+                    }
+                    push_text(&mut stack, &mut literal);
+                    stack.last_mut().expect("the stack has the template").push(Part::Placeholder(field, spec));
+                },
+                '[' => {
+                    if stack.len() > 1 {
+                        return Err(String::from("a section between brackets can't be in another one"));
+                    }
+                    push_text(&mut stack, &mut literal);
+                    stack.push(vec![]);
+                },
+                ']' => {
+                    if stack.len() == 1 {
+                        return Err(String::from("a `]` closes no section, write `]]` for a bracket"));
+                    }
+                    push_text(&mut stack, &mut literal);
+                    let section = stack.pop().expect("a section is open");
+                    stack.last_mut().expect("the stack has the template").push(Part::Optional(section));
+                },
+                '}' => return Err(String::from("a `}` closes no placeholder, write `}}` for a brace")),
+                c => literal.push(c)
+            }
+        }
+        if stack.len() > 1 {
+            return Err(String::from("a section between brackets is not closed"));
+        }
+        push_text(&mut stack, &mut literal);
+        Ok(Template { parts : stack.pop().expect("the stack has the template"), source : text.to_string(), date_format : dates::ISO_FORMAT.to_string() })
+    }
+
+    /// Write the dates without a format of their own with a chrono format, ISO by default
+    ///
+    /// Arguments:
+    ///
+    /// * `date_format` - a valid chrono format, like `%d.%m.%Y`
+    pub fn with_date_format(mut self, date_format : &str) -> Self {
+        self.date_format = date_format.to_string();
+        self
+    }
+
+    /// Return the chrono format of the dates without a format of their own
+    pub fn date_format(&self) -> &str {
+        &self.date_format
+    }
+
+    /// Write the line of a task with the template
+    ///
+    /// Arguments:
+    ///
+    /// * `value` - a closure returning the value of a field with its date format, `None` if the task has no value
+    /// * `text` - a closure writing the texts of the template, like `markup::escape`
+    pub fn render(&self, value : impl Fn(&Field, Option<&str>) -> Option<String>, text : impl Fn(&str) -> String) -> String {
+        let mut line = String::new();
+        let mut skip_space = false;
+        // The line ends with a space of the template, which an empty section only takes after a text
+        let mut after_space = None;
+        for part in &self.parts {
+            let rendered = match part {
+                Part::Optional(section) => {
+                    let mut inner = String::new();
+                    let mut complete = true;
+                    for part in section {
+                        match part {
+                            Part::Text(t) => inner.push_str(&text(t)),
+                            Part::Placeholder(field, spec) => match value(field, spec.as_deref()).filter(|v| !v.is_empty()) {
+                                Some(v) => inner.push_str(&v),
+                                None => complete = false
+                            },
+                            Part::Optional(_) => unreachable!("the sections are not nested")
+                        }
+                    }
+                    if complete { Some(inner) } else { None }
+                },
+                Part::Placeholder(field, spec) => value(field, spec.as_deref()).filter(|v| !v.is_empty()),
+                Part::Text(t) => {
+                    let t = if skip_space { t.strip_prefix(' ').unwrap_or(t) } else { t };
+                    Some(text(t))
+                }
+            };
+            skip_space = false;
+            match rendered {
+                Some(rendered) => {
+                    after_space = match part {
+                        _ if !rendered.ends_with(' ') => None,
+                        Part::Text(_) => Some(true),
+                        Part::Optional(_) => Some(false),
+                        Part::Placeholder(_, _) => None
+                    };
+                    line.push_str(&rendered);
+                },
+                // An empty value takes away the space of the template before it, or the space after it at the start of the line
+                None => match after_space {
+                    Some(after_text) if after_text || matches!(part, Part::Placeholder(_, _)) => {
+                        line.pop();
+                        after_space = None;
+                    },
+                    Some(_) => (),
+                    None => skip_space = line.is_empty()
+                }
+            }
+        }
+        line
+    }
+}
+
+/// Return true if a chrono format can write a date, `%d %b` but not `%Q` nor an empty format
+///
+/// Arguments:
+///
+/// * `format` - the chrono format
+pub fn is_date_format(format : &str) -> bool {
+    !format.is_empty() && StrftimeItems::new(format).all(|item| item != Item::Error)
+}
+
+/// Add the text read so far to the parts of the innermost section
+fn push_text(stack : &mut [Vec<Part>], literal : &mut String) {
+    if !literal.is_empty() {
+        stack.last_mut().expect("the stack has the template").push(Part::Text(std::mem::take(literal)));
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::parse(DEFAULT).expect("the default template is valid")
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(text : &str) -> Result<Self, Self::Err> {
+        Template::parse(text)
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// How the labels of the recap of a task are written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Labels {
+    /// In mathematical bold letters, `𝐃𝐮𝐞 𝐝𝐚𝐭𝐞 : 2024-05-06`
+    #[default]
+    Bold,
+    /// In plain ASCII, for the terminals without these letters and the screen readers, `Due date: 2024-05-06`
+    Ascii
+}
+
+impl Labels {
+    /// Write a line of the recap
+    ///
+    /// Arguments:
+    ///
+    /// * `label` - the name of the value, in ASCII
+    /// * `value` - the value
+    pub fn line(self, label : &str, value : &str) -> String {
+        match self {
+            Labels::Bold => format!("{} : {}", bold(label), value),
+            Labels::Ascii => format!("{}: {}", label, value)
+        }
+    }
+}
+
+/// Write the ASCII letters and digits of a text in mathematical bold letters, `Task` as `𝐓𝐚𝐬𝐤`
+fn bold(text : &str) -> String {
+    text.chars().map(|c| {
+        let code = match c {
+            'A'..='Z' => 0x1D400 + (c as u32 - 'A' as u32),
+            'a'..='z' => 0x1D41A + (c as u32 - 'a' as u32),
+            '0'..='9' => 0x1D7CE + (c as u32 - '0' as u32),
+            _ => return c
+        };
+        char::from_u32(code).unwrap_or(c)
+    }).collect()
+}
+
+#[cfg(test)]
+mod template_tests {
+    use super::*;
+
+    /// Render a template with the values of a map, the texts in uppercase to tell them from the values
+    fn render(template : &str, values : &[(&str, &str)]) -> String {
+        let template = Template::parse(template).unwrap();
+        template.render(|field, spec| {
+            let name = match field {
+                Field::Tag(key) => format!("tag.{}", key),
+                field => FIELDS.iter().find(|name| name.parse::<Field>().as_ref() == Ok(field)).unwrap().to_string()
+            };
+            values.iter().find(|(n, _)| *n == name).map(|(_, v)| match spec {
+                Some(spec) => format!("{}|{}", v, spec),
+                None => v.to_string()
+            })
+        }, |t| t.to_uppercase())
+    }
+
+    #[test]
+    fn placeholders() {
+        let values = [("priority", "(A)"), ("content", "call mom"), ("due", "2024-05-06"), ("tag.note", "n.md")];
+        assert_eq!(render(DEFAULT, &values), "(A) 2024-05-06 : call mom");
+        assert_eq!(render("{content} due {due:%d %b}", &values), "call mom DUE 2024-05-06|%d %b");
+        assert_eq!(render("{content} {tag.note}", &values), "call mom n.md");
+        assert_eq!(render("{{{content}}} [[x]]", &values), "{call mom} [X]");
+        for field in FIELDS {
+            assert!(Template::parse(&format!("{{{}}}", field)).is_ok(), "{}", field);
+        }
+    }
+
+    #[test]
+    fn optional_sections() {
+        let values = [("content", "call mom"), ("projects", "+family")];
+        assert_eq!(render(DEFAULT, &values), "call mom");
+        assert_eq!(render("{content}[ due {due}]", &values), "call mom");
+        assert_eq!(render("{content}[ due {due}]", &[("content", "a"), ("due", "fri")]), "a DUE fri");
+        // A section is left out when one of its placeholders is empty
+        assert_eq!(render("[{priority} {projects} ]{content}", &values), "call mom");
+        assert_eq!(render("[{projects} ]{content}", &values), "+family call mom");
+    }
+
+    #[test]
+    fn no_double_spaces() {
+        let values = [("content", "call mom"), ("projects", "+family")];
+        assert_eq!(render("{priority} {content} {due} {projects}", &values), "call mom +family");
+        assert_eq!(render("{content} {due}", &values), "call mom");
+        assert_eq!(render("{due} {priority} {content}", &values), "call mom");
+        assert_eq!(render("{content} {due} {contexts} {projects}", &values), "call mom +family");
+        assert_eq!(render("{content} [due {due}] {projects}", &values), "call mom +family");
+        assert_eq!(render("[{projects} ]{due} {content}", &values), "+family call mom");
+        assert_eq!(render("[{projects} ][{due} ]{content}", &values), "+family call mom");
+        assert_eq!(render("{content} - {due}", &values), "call mom -");
+        // The spaces of the values are kept
+        assert_eq!(render("{content}{due}", &[("content", "two  spaces ")]), "two  spaces ");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text : &str| Template::parse(text).unwrap_err();
+        assert!(error("{content} {size}").starts_with("unknown placeholder `{size}`, the placeholders are {priority}, {content}"));
+        assert_eq!(error("{content:%d}"), "`{content}` is not a date, only the dates have a format");
+        assert_eq!(error("{due:%Q}"), "`%Q` is not a date format of `{due}`");
+        assert_eq!(error("{due:}"), "`` is not a date format of `{due}`");
+        assert_eq!(error("{content"), "the placeholder `{content` is not closed");
+        assert_eq!(error("content}"), "a `}` closes no placeholder, write `}}` for a brace");
+        assert_eq!(error("[{due} [{priority}]]"), "a section between brackets can't be in another one");
+        assert_eq!(error("[{due}"), "a section between brackets is not closed");
+        assert_eq!(error("{due}]"), "a `]` closes no section, write `]]` for a bracket");
+        assert_eq!(error("{tag.}").split(',').next(), Some("unknown placeholder `{tag.}`"));
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(Labels::Bold.line("Due date", "2024-05-06"), "𝐃𝐮𝐞 𝐝𝐚𝐭𝐞 : 2024-05-06");
+        assert_eq!(Labels::Ascii.line("Due date", "2024-05-06"), "Due date: 2024-05-06");
+        assert_eq!(bold("Top 10, x"), "𝐓𝐨𝐩 𝟏𝟎, 𝐱");
+        assert_eq!(Labels::default(), Labels::Bold);
+    }
+
+    #[test]
+    fn date_formats() {
+        assert!(is_date_format("%d.%m.%Y") && is_date_format("%a %H:%M"));
+        assert!(!is_date_format("%Q") && !is_date_format(""));
+        let template = Template::default().with_date_format("%d.%m.%Y");
+        assert_eq!((template.date_format(), Template::default().date_format()), ("%d.%m.%Y", "%Y-%m-%d"));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Template::default().to_string(), DEFAULT);
+        assert_eq!("{content}".parse::<Template>().unwrap(), Template::parse("{content}").unwrap());
+    }
+}
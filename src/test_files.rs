@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::storage::Cipher;
+
+/// Return a path of the temporary directory used by no other test, `rofitodo_undo_1234_5.txt` for `undo.txt`
+///
+/// The PID keeps apart the test runs, the counter the tests of a run which use the same name
+///
+/// Arguments:
+///
+/// * `name` - the name of the file, its extension is kept at the end of the path
+pub(crate) fn temp_path(name : &str) -> PathBuf {
+    static NEXT : AtomicUsize = AtomicUsize::new(0);
+    let (stem, extension) = match name.split_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (name, String::new())
+    };
+    let number = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("rofitodo_{}_{}_{}{}", stem, std::process::id(), number, extension))
+}
+
+/// Write a file used by no other test and return its path, see `temp_path`
+///
+/// Arguments:
+///
+/// * `name` - the name of the file
+/// * `content` - the content of the file
+pub(crate) fn temp_file(name : &str, content : &str) -> PathBuf {
+    let path = temp_path(name);
+    fs::write(&path, content).unwrap();
+    path
+}
+
+/// Create an empty directory used by no other test and return its path, see `temp_path`
+///
+/// Arguments:
+///
+/// * `name` - the name of the directory
+pub(crate) fn temp_dir(name : &str) -> PathBuf {
+    let dir = temp_path(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A cipher "encrypting" a file with a `FAKEGPG <key>` first line, the new content is encrypted for the key of the file
+pub(crate) struct FakeCipher;
+
+impl Cipher for FakeCipher {
+    fn decrypt(&self, path : &Path) -> Result<String, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match content.split_once('\n') {
+            Some((key, text)) if key.starts_with("FAKEGPG ") => Ok(text.to_string()),
+            _ => Err(format!("Could not decrypt {} : no secret key", path.display()))
+        }
+    }
+
+    fn encrypt(&self, path : &Path, content : &str) -> Result<Vec<u8>, String> {
+        let key = fs::read_to_string(path).ok().and_then(|c| c.lines().next().filter(|l| l.starts_with("FAKEGPG ")).map(String::from));
+        match key {
+            Some(key) => Ok(format!("{}\n{}", key, content).into_bytes()),
+            None => Err(format!("Could not encrypt {} : no recipient", path.display()))
+        }
+    }
+}
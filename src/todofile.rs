@@ -0,0 +1,193 @@
+use crate::task::{Task, TaskParseError};
+
+/// Parse the lines of a task file
+///
+/// Return the tasks, with the index of their line as source line,
+/// and the index of the lines which could not be parsed with their error
+///
+/// Arguments:
+///
+/// * `lines` - the lines of the file
+pub fn parse_lines(lines : &[String]) -> (Vec<Task>, Vec<(usize, TaskParseError)>) {
+    let mut tasks = Vec::new();
+    let mut errors = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        match line.parse::<Task>() {
+            Ok(mut task) => {
+                task.set_source_line(Some(index));
+                tasks.push(task);
+            },
+            Err(e) => errors.push((index, e))
+        }
+    }
+    (tasks, errors)
+}
+
+/// Return the lines to write in a task file, in the order of their line index
+///
+/// The preserved lines are put back at their original index and the tasks without index are added at the end,
+/// so the file keeps its order whatever the order of `tasks`
+///
+/// Arguments:
+///
+/// * `tasks` - the lines of the tasks with the index of their line
+/// * `preserved` - the lines which could not be parsed with their index
+pub fn file_lines(tasks : Vec<(Option<usize>, String)>, preserved : &[(usize, String)]) -> Vec<(Option<usize>, String)> {
+    // The preserved lines come first so they stay before a task numbered like them
+    let mut lines = preserved.iter().map(|(index, line)| (Some(*index), line.clone())).collect::<Vec<_>>();
+    lines.extend(tasks);
+    lines.sort_by_key(|(index, _)| index.unwrap_or(usize::MAX));
+    lines
+}
+
+/// How a task file is written, read from the file so a save doesn't change more than the tasks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileFormat {
+    /// True if the file starts with a UTF-8 byte order mark
+    pub bom : bool,
+    /// True if the lines end with `\r\n` rather than `\n`
+    pub crlf : bool,
+    /// True if the last line ends with a line ending
+    pub final_newline : bool,
+    /// True if the file had both line endings, they are all written like the most common one
+    pub mixed_endings : bool
+}
+
+impl Default for FileFormat {
+    /// The format of a new file: UTF-8 without byte order mark, `\n` line endings and a final line ending
+    fn default() -> Self {
+        FileFormat { bom : false, crlf : false, final_newline : true, mixed_endings : false }
+    }
+}
+
+impl FileFormat {
+    /// Split the content of a task file into lines and return its format
+    ///
+    /// An empty file has the default format
+    ///
+    /// Arguments:
+    ///
+    /// * `content` - the content of the file
+    pub fn split(content : &str) -> (Vec<String>, Self) {
+        let (bom, text) = match content.strip_prefix('\u{feff}') {
+            Some(text) => (true, text),
+            None => (false, content)
+        };
+        let endings = text.matches('\n').count();
+        let crlf_endings = text.matches("\r\n").count();
+        let format = FileFormat {
+            bom,
+            crlf : crlf_endings * 2 > endings,
+            final_newline : text.is_empty() || text.ends_with('\n'),
+            mixed_endings : crlf_endings > 0 && crlf_endings < endings
+        };
+        (text.lines().map(String::from).collect(), format)
+    }
+
+    /// Return the content of a task file of this format
+    ///
+    /// Arguments:
+    ///
+    /// * `lines` - the lines of the file
+    pub fn join<'a>(&self, lines : impl IntoIterator<Item = &'a str>) -> String {
+        let ending = if self.crlf { "\r\n" } else { "\n" };
+        let mut content = String::from(if self.bom { "\u{feff}" } else { "" });
+        let mut lines = lines.into_iter().peekable();
+        while let Some(line) = lines.next() {
+            content.push_str(line);
+            if lines.peek().is_some() || self.final_newline {
+                content.push_str(ending);
+            }
+        }
+        content
+    }
+}
+
+#[cfg(test)]
+mod todofile_tests {
+    use super::*;
+
+    fn lines(text : &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let (tasks, errors) = parse_lines(&lines("first\nx 2024-13-01 broken\n2024-04-31 call\nsecond"));
+        assert_eq!(tasks.iter().map(|t| t.get_source_line()).collect::<Vec<_>>(), vec![Some(0), Some(3)]);
+        assert_eq!(errors.iter().map(|e| e.0).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn preserve_unparsed_lines() {
+        let file = lines("(A) call mom\nx 2024-13-01 from another tool\nbuy milk\n2024-04-31 call\nwater plants\n2024-02-30 impossible date");
+        let (mut tasks, errors) = parse_lines(&file);
+        let preserved = errors.iter().map(|(index, _)| (*index, file[*index].clone())).collect::<Vec<_>>();
+        assert_eq!(preserved.len(), 3);
+
+        tasks[1].set_completed();
+        let saved = file_lines(tasks.iter().map(|t| (t.get_source_line(), t.to_string())).collect(), &preserved);
+        let saved = saved.into_iter().map(|(_, line)| line).collect::<Vec<_>>();
+        assert_eq!(saved.len(), file.len());
+        assert_eq!(saved[1], "x 2024-13-01 from another tool");
+        assert!(saved[2].starts_with("x "));
+        assert_eq!(saved[3], "2024-04-31 call");
+        assert_eq!(saved[5], "2024-02-30 impossible date");
+        assert_eq!(saved[4], "water plants");
+
+        // The preserved lines keep their order when tasks are removed or added
+        tasks.remove(0);
+        tasks.push(Task::new(String::from("new task")));
+        let saved = file_lines(tasks.iter().map(|t| (t.get_source_line(), t.to_string())).collect(), &preserved);
+        let preserved_lines = preserved.iter().map(|(_, l)| l.as_str()).collect::<Vec<_>>();
+        let saved_preserved = saved.iter().map(|(_, l)| l.as_str()).filter(|l| preserved_lines.contains(l)).collect::<Vec<_>>();
+        assert_eq!(saved_preserved, preserved_lines);
+    }
+
+    #[test]
+    fn keep_file_order() {
+        let file = lines("b\na\nx 2024-13-01 broken\nc");
+        let (tasks, errors) = parse_lines(&file);
+        let preserved = errors.iter().map(|(index, _)| (*index, file[*index].clone())).collect::<Vec<_>>();
+        let mut shuffled = tasks.iter().rev().map(|t| (t.get_source_line(), t.to_string())).collect::<Vec<_>>();
+        shuffled.insert(1, (Some(7), String::from("new")));
+        let saved = file_lines(shuffled, &preserved).into_iter().map(|(_, line)| line).collect::<Vec<_>>();
+        assert_eq!(saved, lines("b\na\nx 2024-13-01 broken\nc\nnew"));
+    }
+
+    #[test]
+    fn bom_and_crlf() {
+        let content = "\u{feff}(A) call mom\r\nbuy milk\r\n";
+        let (lines, format) = FileFormat::split(content);
+        assert_eq!(lines, vec!["(A) call mom", "buy milk"]);
+        assert_eq!(format, FileFormat { bom : true, crlf : true, final_newline : true, mixed_endings : false });
+        assert_eq!(format.join(lines.iter().map(|l| l.as_str())), content);
+    }
+
+    #[test]
+    fn lf_only() {
+        let (lines, format) = FileFormat::split("call mom\nbuy milk\n");
+        assert_eq!(format, FileFormat::default());
+        assert_eq!(format.join(lines.iter().map(|l| l.as_str()).chain(["water plants"])), "call mom\nbuy milk\nwater plants\n");
+        assert_eq!(FileFormat::split(""), (vec![], FileFormat::default()));
+    }
+
+    #[test]
+    fn no_trailing_newline() {
+        let (lines, format) = FileFormat::split("call mom\r\nbuy milk");
+        assert!(format.crlf && !format.final_newline && !format.mixed_endings);
+        assert_eq!(format.join(lines.iter().map(|l| l.as_str())), "call mom\r\nbuy milk");
+        assert_eq!(format.join(["call mom", "buy milk", "water plants"]), "call mom\r\nbuy milk\r\nwater plants");
+        assert_eq!(format.join([]), "");
+    }
+
+    #[test]
+    fn mixed_endings() {
+        let (lines, format) = FileFormat::split("a\r\nb\nc\r\n");
+        assert_eq!(lines, vec!["a", "b", "c"]);
+        assert!(format.crlf && format.mixed_endings);
+        assert_eq!(format.join(lines.iter().map(|l| l.as_str())), "a\r\nb\r\nc\r\n");
+        let (_, format) = FileFormat::split("a\nb\r\nc\n");
+        assert!(!format.crlf && format.mixed_endings);
+    }
+}
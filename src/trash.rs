@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use chrono::NaiveDate;
+use crate::task::Task;
+use crate::sibling::SiblingFile;
+use crate::storage::Cipher;
+
+/// The custom tag holding the date a task was deleted
+const DELETED_TAG : &str = "deleted";
+
+/// The `trash.txt` file where the deleted tasks are kept until they are purged
+pub struct Trash {
+    /// The lines of the trash and its path
+    file : SiblingFile
+}
+
+impl Trash {
+    /// Return the path of the trash of a task list
+    ///
+    /// `todo.txt` has its deleted tasks in `trash.txt` in the same directory, the other lists have their own trash
+    /// (`work.trash.txt` for `work.txt`)
+    ///
+    /// Arguments:
+    ///
+    /// * `todo_file` - the path of the task list
+    pub fn path_for(todo_file : &Path) -> PathBuf {
+        SiblingFile::path_for(todo_file, "trash")
+    }
+
+    /// Load a trash, a missing file is an empty trash
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the trash
+    /// * `cipher` - the encryption of the trash if its name ends in `.gpg`, see `TaskList::trash`
+    pub fn load(path : &Path, cipher : Option<Rc<dyn Cipher>>) -> Result<Self, String> {
+        Ok(Trash { file : SiblingFile::load(path, cipher)? })
+    }
+
+    /// Add a deleted task at the end of the trash, with the date of the deletion, and save it
+    ///
+    /// Return the line added to the trash
+    ///
+    /// Arguments:
+    ///
+    /// * `task` - the deleted task
+    /// * `today` - the date of the deletion
+    pub fn put(&mut self, task : &Task, today : NaiveDate) -> Result<String, String> {
+        let mut deleted = task.clone();
+        deleted.set_custom_tag(DELETED_TAG, &today.format("%Y-%m-%d").to_string()).expect("a date is a valid tag");
+        self.file.lines.push(deleted.to_string());
+        self.file.save()?;
+        Ok(deleted.to_string())
+    }
+
+    /// Return a line of the trash
+    ///
+    /// Arguments:
+    ///
+    /// * `index` - the index of the line
+    pub fn line(&self, index : usize) -> Option<&str> {
+        self.file.lines.get(index).map(|l| l.as_str())
+    }
+
+    /// Return the deleted tasks with their line index, the last deleted first
+    pub fn tasks(&self) -> Vec<(usize, Task)> {
+        let mut tasks = self.file.lines.iter().enumerate()
+                            .filter_map(|(index, line)| line.parse::<Task>().ok().map(|task| (index, task)))
+                            .collect::<Vec<_>>();
+        // Sorting by line index first since the tasks deleted the same day keep their order
+        tasks.reverse();
+        tasks.sort_by_key(|(_, task)| std::cmp::Reverse(deletion_date(task)));
+        tasks
+    }
+
+    /// Remove a task from the trash and save it
+    ///
+    /// Return the removed task without its deletion date
+    ///
+    /// Arguments:
+    ///
+    /// * `index` - the line index of the task
+    pub fn take(&mut self, index : usize) -> Result<Task, String> {
+        let line = self.file.lines.get(index).ok_or_else(|| String::from("no such deleted task"))?;
+        let mut task = line.parse::<Task>()?;
+        task.remove_custom_tag(DELETED_TAG);
+        self.file.lines.remove(index);
+        self.file.save()?;
+        Ok(task)
+    }
+
+    /// Remove the tasks deleted more than `days` days ago and save the trash if some were removed
+    ///
+    /// Return the number of removed tasks, the lines which can not be parsed are kept
+    ///
+    /// Arguments:
+    ///
+    /// * `today` - the current date
+    /// * `days` - the number of days the deleted tasks are kept
+    pub fn purge(&mut self, today : NaiveDate, days : u32) -> Result<usize, String> {
+        let count = self.file.lines.len();
+        self.file.lines.retain(|line| match line.parse::<Task>().ok().as_ref().and_then(deletion_date) {
+            Some(date) => (today - date).num_days() <= days as i64,
+            None => true
+        });
+        let removed = count - self.file.lines.len();
+        if removed > 0 {
+            self.file.save()?;
+        }
+        Ok(removed)
+    }
+}
+
+/// Return the date a task of the trash was deleted
+fn deletion_date(task : &Task) -> Option<NaiveDate> {
+    task.get_custom_tag(DELETED_TAG).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+}
+
+#[cfg(test)]
+mod trash_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    fn date(y : i32, m : u32, d : u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn path_for() {
+        assert_eq!(Trash::path_for(Path::new("/home/me/todo.txt")), PathBuf::from("/home/me/trash.txt"));
+        assert_eq!(Trash::path_for(Path::new("./work.txt")), PathBuf::from("./work.trash.txt"));
+        assert_eq!(Trash::path_for(Path::new("/home/me/todo.txt.gpg")), PathBuf::from("/home/me/trash.txt.gpg"));
+    }
+
+    #[test]
+    fn delete_and_restore() {
+        let path = temp_path("trash_restore.txt");
+        let line = "(B) 2024-01-02 call mom +family @phone due:2024-02-01 t:2024-01-25 rec:+1w note:birthday";
+        let task = line.parse::<Task>().unwrap();
+        let mut trash = Trash::load(&path, None).unwrap();
+        trash.put(&task, date(2024, 1, 10)).unwrap();
+        trash.put(&"x 2024-01-05 done task".parse::<Task>().unwrap(), date(2024, 1, 11)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().next().unwrap(), format!("{} deleted:2024-01-10", line));
+
+        let mut trash = Trash::load(&path, None).unwrap();
+        let tasks = trash.tasks();
+        assert_eq!(tasks.iter().map(|(_, t)| t.get_content()).collect::<Vec<_>>(), vec!["done task", "call mom +family @phone"]);
+        let restored = trash.take(tasks[1].0).unwrap();
+        assert_eq!(restored.to_string(), line);
+        assert_eq!(restored.get_due(), task.get_due());
+        assert_eq!(restored.get_threshold(), task.get_threshold());
+        assert_eq!(restored.get_custom_tag("rec"), Some("+1w"));
+        assert_eq!(Trash::load(&path, None).unwrap().tasks().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn purge() {
+        let path = temp_path("trash_purge.txt");
+        std::fs::write(&path, "old deleted:2024-01-01\nrecent deleted:2024-01-25\nundated\nx 2024-13-45 broken\n").unwrap();
+        let mut trash = Trash::load(&path, None).unwrap();
+        assert_eq!(trash.purge(date(2024, 1, 31), 30).unwrap(), 0);
+        assert_eq!(trash.purge(date(2024, 2, 1), 30).unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "recent deleted:2024-01-25\nundated\nx 2024-13-45 broken\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
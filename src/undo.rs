@@ -0,0 +1,425 @@
+use std::path::{Path, PathBuf};
+use chrono::Local;
+use crate::audit::AuditLog;
+use crate::task::Task;
+use crate::storage::{self, save_atomic, Cipher};
+
+/// A list of tasks the operations are applied to
+pub trait TaskStore {
+    /// Add a task
+    fn insert(&mut self, task : Task);
+    /// Remove the task equal to `task` and return it
+    fn take(&mut self, task : &Task) -> Option<Task>;
+    /// Return an error if the store must not be changed, nothing can be undone or redone then
+    fn check_writable(&self) -> Result<(), String> {
+        Ok(())
+    }
+    /// The encryption of the files the lines are taken from and put in, when their name ends in `.gpg`
+    fn cipher(&self) -> Option<&dyn Cipher> {
+        None
+    }
+}
+
+/// An operation done on a task list, which can be reverted
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    /// A task was added
+    Add(Task),
+    /// A task was removed
+    Remove(Task),
+    /// A task was replaced by a modified version (edited, completed, postponed…)
+    Replace(Task, Task),
+    /// A line was removed from a file, like a task restored from `done.txt`
+    TakeLine(PathBuf, String),
+    /// A line was added at the end of a file, like a task moved to the trash
+    PutLine(PathBuf, String),
+    /// Several operations done at once, in order
+    Batch(Vec<Op>),
+    /// Several operations done at once, in order, logged as a single line like `complete project +home (3 tasks)`
+    Group(String, Vec<Op>)
+}
+
+impl Op {
+    /// Do the operation
+    ///
+    /// Arguments:
+    ///
+    /// * `store` - the task list
+    pub fn apply(&self, store : &mut impl TaskStore) -> Result<(), String> {
+        match self {
+            Op::Add(task) => {
+                store.insert(task.clone());
+                Ok(())
+            },
+            Op::Remove(task) => take(store, task).map(|_| ()),
+            Op::Replace(old, new) => {
+                take(store, old)?;
+                store.insert(new.clone());
+                Ok(())
+            },
+            Op::TakeLine(path, line) => remove_line(path, line, store.cipher()),
+            Op::PutLine(path, line) => append_line(path, line, store.cipher()),
+            Op::Batch(ops) | Op::Group(_, ops) => ops.iter().try_for_each(|op| op.apply(store))
+        }
+    }
+
+    /// Undo the operation
+    ///
+    /// Arguments:
+    ///
+    /// * `store` - the task list
+    pub fn revert(&self, store : &mut impl TaskStore) -> Result<(), String> {
+        match self {
+            Op::Add(task) => take(store, task).map(|_| ()),
+            Op::Remove(task) => {
+                store.insert(task.clone());
+                Ok(())
+            },
+            Op::Replace(old, new) => {
+                take(store, new)?;
+                store.insert(old.clone());
+                Ok(())
+            },
+            Op::TakeLine(path, line) => append_line(path, line, store.cipher()),
+            Op::PutLine(path, line) => remove_line(path, line, store.cipher()),
+            Op::Batch(ops) | Op::Group(_, ops) => ops.iter().rev().try_for_each(|op| op.revert(store))
+        }
+    }
+}
+
+/// Describe operations in a few words, `complete 'pay rent', add 2 tasks`, `None` if there is no change of task
+///
+/// The changes of each kind are counted, the task is named when it is the only one of its kind
+///
+/// Arguments:
+///
+/// * `ops` - the operations, the oldest first
+pub fn summary(ops : &[Op]) -> Option<String> {
+    let mut changes : Vec<(&str, Vec<&Task>)> = Vec::new();
+    for (verb, task) in ops.iter().flat_map(changes_of) {
+        match changes.iter_mut().find(|(v, _)| *v == verb) {
+            Some((_, tasks)) => tasks.push(task),
+            None => changes.push((verb, vec![task]))
+        }
+    }
+    if changes.is_empty() {
+        return None;
+    }
+    Some(changes.iter().map(|(verb, tasks)| match tasks.as_slice() {
+        [task] => format!("{} '{}'", verb, task.get_content()),
+        tasks => format!("{} {} tasks", verb, tasks.len())
+    }).collect::<Vec<_>>().join(", "))
+}
+
+/// Return the changes of tasks of an operation, with the verb describing each change
+fn changes_of(op : &Op) -> Vec<(&'static str, &Task)> {
+    match op {
+        Op::Add(task) => vec![("add", task)],
+        Op::Remove(task) => vec![("remove", task)],
+        Op::Replace(old, new) => vec![(match (old.completion, new.completion) {
+            (false, true) => "complete",
+            (true, false) => "reopen",
+            _ => "edit"
+        }, new)],
+        Op::TakeLine(_, _) | Op::PutLine(_, _) => vec![],
+        Op::Batch(ops) | Op::Group(_, ops) => ops.iter().flat_map(changes_of).collect()
+    }
+}
+
+/// Remove a task from the store, failing if it is not there anymore
+fn take(store : &mut impl TaskStore, task : &Task) -> Result<Task, String> {
+    store.take(task).ok_or_else(|| format!("The task \"{}\" is not in the list anymore", task.get_content()))
+}
+
+/// Read the lines of a file, a missing file has no lines
+fn read_lines(path : &Path, cipher : Option<&dyn Cipher>) -> Result<Vec<String>, String> {
+    match storage::read_to_string(path, cipher) {
+        Ok(content) => Ok(content.lines().map(String::from).collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.to_string())
+    }
+}
+
+/// Write the lines of a file
+fn write_lines(path : &Path, lines : &[String], cipher : Option<&dyn Cipher>) -> Result<(), String> {
+    let mut content = lines.join("\n");
+    if !lines.is_empty() {
+        content.push('\n');
+    }
+    save_atomic(path, &content, cipher).map_err(|e| e.to_string())
+}
+
+/// Add a line at the end of a file
+fn append_line(path : &Path, line : &str, cipher : Option<&dyn Cipher>) -> Result<(), String> {
+    let mut lines = read_lines(path, cipher)?;
+    lines.push(line.to_string());
+    write_lines(path, &lines, cipher)
+}
+
+/// Remove the last occurence of a line from a file
+fn remove_line(path : &Path, line : &str, cipher : Option<&dyn Cipher>) -> Result<(), String> {
+    let mut lines = read_lines(path, cipher)?;
+    let index = lines.iter().rposition(|l| l == line).ok_or_else(|| format!("\"{}\" is not in {} anymore", line, path.display()))?;
+    lines.remove(index);
+    write_lines(path, &lines, cipher)
+}
+
+/// The operations which can be undone and redone
+pub struct History {
+    /// The operations to undo, the last one at the end
+    undo : Vec<Op>,
+    /// The undone operations to redo, the last undone at the end
+    redo : Vec<Op>,
+    /// The maximum number of operations which can be undone
+    depth : usize,
+    /// The log the operations are written to, see `set_log`
+    log : Option<AuditLog>,
+    /// The last error writing the log, see `take_log_error`
+    log_error : Option<String>
+}
+
+impl History {
+    /// Create an empty history
+    ///
+    /// Arguments:
+    ///
+    /// * `depth` - the maximum number of operations which can be undone, 0 disables the history
+    pub fn new(depth : usize) -> Self {
+        History { undo : vec![], redo : vec![], depth, log : None, log_error : None }
+    }
+
+    /// Write the operations done, undone and redone to a log, even when the history is disabled
+    ///
+    /// Arguments:
+    ///
+    /// * `log` - the log, `None` to stop logging
+    pub fn set_log(&mut self, log : Option<AuditLog>) {
+        self.log = log;
+    }
+
+    /// Return the log the operations are written to
+    pub fn log(&self) -> Option<&AuditLog> {
+        self.log.as_ref()
+    }
+
+    /// Return the last error writing the log and forget it
+    pub fn take_log_error(&mut self) -> Option<String> {
+        self.log_error.take()
+    }
+
+    /// Forget the operations which can be undone and redone, the log is kept
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Write an operation to the log, keeping the error
+    fn write_log(&mut self, prefix : &str, op : &Op) {
+        if let Some(Err(e)) = self.log.as_ref().map(|log| log.write(Local::now().naive_local(), prefix, op)) {
+            self.log_error = Some(e);
+        }
+    }
+
+    /// Return the maximum number of operations which can be undone
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Record an operation which was done, forgetting the undone operations
+    ///
+    /// Arguments:
+    ///
+    /// * `op` - the operation
+    pub fn push(&mut self, op : Op) {
+        self.write_log("", &op);
+        if self.depth == 0 {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push(op);
+        if self.undo.len() > self.depth {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Return the operations which can be undone, the oldest first
+    pub fn done(&self) -> &[Op] {
+        &self.undo
+    }
+
+    /// Return true if there is an operation to undo
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Return true if there is an operation to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Revert the last operation
+    ///
+    /// An operation which can not be reverted is forgotten
+    ///
+    /// Arguments:
+    ///
+    /// * `store` - the task list
+    pub fn undo(&mut self, store : &mut impl TaskStore) -> Result<(), String> {
+        store.check_writable()?;
+        let op = self.undo.pop().ok_or_else(|| String::from("Nothing to undo"))?;
+        op.revert(store)?;
+        self.write_log("undo ", &op);
+        self.redo.push(op);
+        Ok(())
+    }
+
+    /// Do again the last undone operation
+    ///
+    /// An operation which can not be done again is forgotten
+    ///
+    /// Arguments:
+    ///
+    /// * `store` - the task list
+    pub fn redo(&mut self, store : &mut impl TaskStore) -> Result<(), String> {
+        store.check_writable()?;
+        let op = self.redo.pop().ok_or_else(|| String::from("Nothing to redo"))?;
+        op.apply(store)?;
+        self.write_log("redo ", &op);
+        self.undo.push(op);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod undo_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    impl TaskStore for Vec<Task> {
+        fn insert(&mut self, task : Task) {
+            self.push(task);
+        }
+
+        fn take(&mut self, task : &Task) -> Option<Task> {
+            let index = self.iter().position(|t| t == task)?;
+            Some(self.remove(index))
+        }
+    }
+
+    fn task(line : &str) -> Task {
+        line.parse::<Task>().unwrap()
+    }
+
+    fn lines(store : &[Task]) -> Vec<String> {
+        let mut lines = store.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn apply_and_revert() {
+        let mut store = vec![task("call mom"), task("(B) buy milk due:2024-01-10")];
+        let initial = lines(&store);
+        let mut done = task("call mom");
+        done.set_completed();
+        let ops = vec![
+            Op::Add(task("water plants")),
+            Op::Remove(task("(B) buy milk due:2024-01-10")),
+            Op::Replace(task("call mom"), done),
+            Op::Batch(vec![Op::Add(task("a")), Op::Replace(task("a"), task("(A) a"))])
+        ];
+        for op in &ops {
+            let before = lines(&store);
+            op.apply(&mut store).unwrap();
+            assert_ne!(lines(&store), before);
+            op.revert(&mut store).unwrap();
+            assert_eq!(lines(&store), before);
+        }
+        assert_eq!(lines(&store), initial);
+        assert!(Op::Remove(task("missing")).apply(&mut store).is_err());
+    }
+
+    #[test]
+    fn file_lines() {
+        let path = temp_path("undo.txt");
+        std::fs::write(&path, "x 2024-01-02 first\nx 2024-01-03 second\n").unwrap();
+        let mut store : Vec<Task> = vec![];
+        // A task restored from done.txt goes back to the archive when undone
+        let restore = Op::Batch(vec![Op::TakeLine(path.clone(), String::from("x 2024-01-02 first")), Op::Add(task("first"))]);
+        restore.apply(&mut store).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x 2024-01-03 second\n");
+        restore.revert(&mut store).unwrap();
+        assert!(store.is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x 2024-01-03 second\nx 2024-01-02 first\n");
+        assert!(Op::TakeLine(path.clone(), String::from("missing")).apply(&mut store).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn history() {
+        let mut store = vec![task("call mom")];
+        let mut history = History::new(2);
+        assert!(!history.can_undo());
+        for name in ["a", "b", "c"] {
+            store.push(task(name));
+            history.push(Op::Add(task(name)));
+        }
+        history.undo(&mut store).unwrap();
+        history.undo(&mut store).unwrap();
+        // Only the last two operations are kept
+        assert!(history.undo(&mut store).is_err());
+        assert_eq!(lines(&store), vec!["a", "call mom"]);
+
+        assert!(history.can_redo());
+        history.redo(&mut store).unwrap();
+        assert_eq!(lines(&store), vec!["a", "b", "call mom"]);
+        history.push(Op::Remove(task("a")));
+        assert!(!history.can_redo());
+
+        let mut disabled = History::new(0);
+        disabled.push(Op::Add(task("d")));
+        assert!(!disabled.can_undo());
+    }
+
+    #[test]
+    fn logged_history() {
+        let path = temp_path("undo_log.log");
+        let mut store = vec![];
+        let mut history = History::new(0);
+        history.set_log(Some(AuditLog::new(&path, 1024)));
+        // The operations are logged even when they can't be undone
+        store.push(task("a"));
+        history.push(Op::Add(task("a")));
+        history.depth = 1;
+        history.push(Op::Remove(task("a")));
+        store.clear();
+        history.undo(&mut store).unwrap();
+        history.redo(&mut store).unwrap();
+        let lines = std::fs::read_to_string(&path).unwrap();
+        let changes = lines.lines().map(|l| l.split_once(' ').unwrap().1).collect::<Vec<_>>();
+        assert_eq!(changes, vec!["add \"a\"", "delete \"a\"", "undo delete \"a\"", "redo delete \"a\""]);
+        assert_eq!(history.take_log_error(), None);
+
+        // The history goes on when the log can't be written
+        history.set_log(Some(AuditLog::new(&path.join("missing"), 1024)));
+        history.push(Op::Add(task("b")));
+        assert!(history.can_undo());
+        assert!(history.take_log_error().unwrap().starts_with("Could not write the log"));
+        assert_eq!(history.take_log_error(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn summaries() {
+        let mut done = task("pay rent");
+        done.set_completed();
+        assert_eq!(summary(&[Op::Replace(task("pay rent"), done.clone())]), Some(String::from("complete 'pay rent'")));
+        assert_eq!(summary(&[Op::Add(task("a")), Op::Batch(vec![Op::PutLine(PathBuf::from("done.txt"), String::from("x b")), Op::Add(task("b"))])]),
+                   Some(String::from("add 2 tasks")));
+        let ops = [Op::Replace(task("pay rent"), done.clone()), Op::Add(task("a")), Op::Add(task("b")),
+                   Op::Replace(task("(B) a"), task("(A) a")), Op::Replace(done, task("pay rent")), Op::Remove(task("c"))];
+        assert_eq!(summary(&ops), Some(String::from("complete 'pay rent', add 2 tasks, edit 'a', reopen 'pay rent', remove 'c'")));
+        assert_eq!(summary(&[Op::TakeLine(PathBuf::from("done.txt"), String::from("x b"))]), None);
+        assert_eq!(summary(&[]), None);
+    }
+}
@@ -0,0 +1,94 @@
+/// The weights used to compute the urgency score of a task
+///
+/// The urgency of a task is the sum of:
+///
+/// * the priority contribution: `priority` for `(A)`, decreasing linearly down to `priority / 26` for `(Z)`
+/// * `due` if the task has a due date
+/// * `overdue_per_week` for each week (fractions included) the task is overdue, up to `overdue_max`
+/// * `age_per_week` for each week since the creation of the task, up to `age_max`
+/// * `project` for each project tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct UrgencyWeights {
+    /// The contribution of a `(A)` priority
+    pub priority : f64,
+    /// The contribution of having a due date
+    pub due : f64,
+    /// The contribution of each week overdue
+    pub overdue_per_week : f64,
+    /// The maximum contribution of being overdue
+    pub overdue_max : f64,
+    /// The contribution of each week since creation
+    pub age_per_week : f64,
+    /// The maximum contribution of the age
+    pub age_max : f64,
+    /// The contribution of each project tag
+    pub project : f64
+}
+
+impl Default for UrgencyWeights {
+    fn default() -> Self {
+        UrgencyWeights {
+            priority : 6.0,
+            due : 2.0,
+            overdue_per_week : 1.0,
+            overdue_max : 5.0,
+            age_per_week : 0.1,
+            age_max : 2.0,
+            project : 0.5
+        }
+    }
+}
+
+impl UrgencyWeights {
+    /// Return the contribution of a priority letter
+    ///
+    /// Arguments:
+    ///
+    /// * `priority` - the priority letter, from `A` to `Z`
+    pub fn priority_score(&self, priority : char) -> f64 {
+        let rank = (priority as u32).saturating_sub('A' as u32).min(25);
+        self.priority * (26 - rank) as f64 / 26.0
+    }
+
+    /// Return the contribution of a number of days overdue
+    ///
+    /// Arguments:
+    ///
+    /// * `days` - the number of days since the due date
+    pub fn overdue_score(&self, days : i64) -> f64 {
+        (self.overdue_per_week * days.max(0) as f64 / 7.0).min(self.overdue_max)
+    }
+
+    /// Return the contribution of the age of a task
+    ///
+    /// Arguments:
+    ///
+    /// * `days` - the number of days since the creation date
+    pub fn age_score(&self, days : i64) -> f64 {
+        (self.age_per_week * days.max(0) as f64 / 7.0).min(self.age_max)
+    }
+}
+
+#[cfg(test)]
+mod urgency_tests {
+    use super::*;
+
+    #[test]
+    fn priority_score() {
+        let weights = UrgencyWeights::default();
+        assert_eq!(weights.priority_score('A'), 6.0);
+        assert!(weights.priority_score('B') < weights.priority_score('A'));
+        assert!(weights.priority_score('Z') > 0.0);
+        assert!(weights.priority_score('Z') < weights.priority_score('Y'));
+    }
+
+    #[test]
+    fn capped_scores() {
+        let weights = UrgencyWeights::default();
+        assert_eq!(weights.overdue_score(-3), 0.0);
+        assert_eq!(weights.overdue_score(14), 2.0);
+        assert_eq!(weights.overdue_score(700), 5.0);
+        assert_eq!(weights.age_score(0), 0.0);
+        assert_eq!(weights.age_score(7000), 2.0);
+    }
+}
@@ -0,0 +1,237 @@
+use serde::{Serialize, Deserialize};
+use std::path::{Path, PathBuf};
+use crate::filter::TaskFilter;
+use crate::task::{SortTaskBy, SortDirection};
+use crate::storage::save_atomic;
+
+/// A named combination of a filter and a sort order of the main menu
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct View {
+    /// The name of the view
+    pub name : String,
+    /// The filter of the tasks
+    #[serde(default)]
+    pub filter : TaskFilter,
+    /// The sort keys, by decreasing importance
+    pub sort : Vec<SortTaskBy>,
+    /// The direction of the sort
+    pub direction : SortDirection
+}
+
+/// The saved views and the last one used
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ViewStore {
+    /// The saved views
+    #[serde(default)]
+    pub views : Vec<View>,
+    /// The name of the view used last, `None` for the default view
+    #[serde(default)]
+    pub last : Option<String>,
+    /// Show the completed tasks after the tasks to do in the main menu
+    #[serde(default)]
+    pub show_completed : bool,
+    /// The sort keys chosen last in the main menu, by their name in the `--sort` option
+    ///
+    /// The names are kept as text so a key unknown to this version is skipped instead of discarding the file
+    #[serde(default)]
+    pub sort : Vec<String>,
+    /// The direction of the sort chosen last
+    #[serde(default)]
+    pub reverse : bool
+}
+
+impl ViewStore {
+    /// Return the path of the views file of a task list (`todo.txt` has its views in `todo.views.json`)
+    ///
+    /// Arguments:
+    ///
+    /// * `todo_file` - the path of the task list
+    pub fn path_for(todo_file : &Path) -> PathBuf {
+        todo_file.with_extension("views.json")
+    }
+
+    /// Load the views from a file
+    ///
+    /// Return no views if the file is missing or can not be read
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the views file
+    pub fn load(path : &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the views to a file
+    ///
+    /// Arguments:
+    ///
+    /// * `path` - the path of the views file
+    pub fn save(&self, path : &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        save_atomic(path, &content, None).map_err(|e| e.to_string())
+    }
+
+    /// Return the view named `name`
+    ///
+    /// Arguments:
+    ///
+    /// * `name` - the name of the view
+    pub fn get(&self, name : &str) -> Option<&View> {
+        self.views.iter().find(|v| v.name == name)
+    }
+
+    /// Add a view, replacing the view with the same name
+    ///
+    /// Arguments:
+    ///
+    /// * `view` - the view to add
+    pub fn set(&mut self, view : View) {
+        match self.views.iter_mut().find(|v| v.name == view.name) {
+            Some(old) => *old = view,
+            None => self.views.push(view)
+        }
+    }
+
+    /// Rename a view, return false if there is no view named `name` or a view is already named `new_name`
+    ///
+    /// Arguments:
+    ///
+    /// * `name` - the name of the view
+    /// * `new_name` - the new name of the view
+    pub fn rename(&mut self, name : &str, new_name : &str) -> bool {
+        if self.get(new_name).is_some() {
+            return false;
+        }
+        match self.views.iter_mut().find(|v| v.name == name) {
+            Some(view) => {
+                view.name = new_name.to_string();
+                if self.last.as_deref() == Some(name) {
+                    self.last = Some(new_name.to_string());
+                }
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Remove a view
+    ///
+    /// Arguments:
+    ///
+    /// * `name` - the name of the view
+    pub fn remove(&mut self, name : &str) {
+        self.views.retain(|v| v.name != name);
+        if self.last.as_deref() == Some(name) {
+            self.last = None;
+        }
+    }
+
+    /// Remember the sort order chosen in the main menu
+    ///
+    /// Arguments:
+    ///
+    /// * `sort` - the sort keys
+    /// * `direction` - the direction of the sort
+    pub fn set_last_sort(&mut self, sort : &[SortTaskBy], direction : SortDirection) {
+        self.sort = sort.iter().map(|key| key.name().to_string()).collect();
+        self.reverse = direction == SortDirection::Descending;
+    }
+
+    /// Return the sort order chosen last, without the unknown keys, `None` if no known key was saved
+    pub fn last_sort(&self) -> Option<(Vec<SortTaskBy>, SortDirection)> {
+        let sort = self.sort.iter().filter_map(|name| SortTaskBy::from_name(name)).collect::<Vec<_>>();
+        if sort.is_empty() {
+            return None;
+        }
+        Some((sort, if self.reverse { SortDirection::Descending } else { SortDirection::Ascending }))
+    }
+
+    /// Return the view used last if it still exists
+    pub fn last_view(&self) -> Option<&View> {
+        self.last.as_ref().and_then(|name| self.get(name))
+    }
+}
+
+#[cfg(test)]
+mod views_tests {
+    use super::*;
+    use crate::test_files::temp_path;
+
+    fn view(name : &str) -> View {
+        View {
+            name : name.to_string(),
+            filter : TaskFilter::from_query("+work -@home"),
+            sort : vec![SortTaskBy::DueDate, SortTaskBy::Priority],
+            direction : SortDirection::Descending
+        }
+    }
+
+    #[test]
+    fn path_for() {
+        assert_eq!(ViewStore::path_for(Path::new("/home/me/todo.txt")), PathBuf::from("/home/me/todo.views.json"));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = temp_path("views_roundtrip.json");
+        let mut store = ViewStore::default();
+        store.set(view("work"));
+        store.set(view("someday"));
+        store.last = Some(String::from("work"));
+        store.save(&path).unwrap();
+        assert_eq!(ViewStore::load(&path), store);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_or_corrupt_file() {
+        let path = temp_path("views_corrupt.json");
+        assert_eq!(ViewStore::load(&path), ViewStore::default());
+        std::fs::write(&path, "{ not json").unwrap();
+        assert_eq!(ViewStore::load(&path), ViewStore::default());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn last_sort() {
+        let mut store = ViewStore::default();
+        assert_eq!(store.last_sort(), None);
+        store.set_last_sort(&[SortTaskBy::DueDate, SortTaskBy::Urgency], SortDirection::Descending);
+        assert_eq!(store.last_sort(), Some((vec![SortTaskBy::DueDate, SortTaskBy::Urgency], SortDirection::Descending)));
+
+        // A file of an older or newer version
+        let path = temp_path("views_sort.json");
+        std::fs::write(&path, r#"{ "last" : null, "sort" : ["size", "due"], "reverse" : false }"#).unwrap();
+        assert_eq!(ViewStore::load(&path).last_sort(), Some((vec![SortTaskBy::DueDate], SortDirection::Ascending)));
+        std::fs::write(&path, r#"{ "sort" : ["size"] }"#).unwrap();
+        assert_eq!(ViewStore::load(&path).last_sort(), None);
+        std::fs::write(&path, r#"{ "views" : [] }"#).unwrap();
+        assert_eq!(ViewStore::load(&path), ViewStore::default());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn edit_views() {
+        let mut store = ViewStore::default();
+        store.set(view("work"));
+        store.set(view("today"));
+        let mut updated = view("work");
+        updated.sort = vec![SortTaskBy::Urgency];
+        store.set(updated.clone());
+        assert_eq!(store.views.len(), 2);
+        assert_eq!(store.get("work"), Some(&updated));
+
+        store.last = Some(String::from("work"));
+        assert!(!store.rename("work", "today"));
+        assert!(!store.rename("missing", "other"));
+        assert!(store.rename("work", "office"));
+        assert_eq!(store.last_view().unwrap().name, "office");
+
+        store.remove("office");
+        assert_eq!(store.last, None);
+        assert_eq!(store.views.len(), 1);
+    }
+}
@@ -0,0 +1,571 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Create an empty directory for a test, used by no other test even with the same name
+fn temp_dir(name : &str) -> PathBuf {
+    static NEXT : AtomicUsize = AtomicUsize::new(0);
+    let number = NEXT.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("rofitodo_cli_{}_{}_{}", name, std::process::id(), number));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run RofiTodo on a task file, with the configuration file of the directory of the task file
+fn rofitodo(todo : &Path, args : &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rofitodo")).arg("-c").arg(todo).arg("--backups").arg("0").args(args)
+        .env("XDG_CONFIG_HOME", todo.parent().unwrap()).output().unwrap()
+}
+
+fn stdout(output : &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn add_list_done_rm() {
+    let dir = temp_dir("commands");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "(B) 2024-01-02 call mom @phone\nwater plants +garden due:2024-05-01\n").unwrap();
+
+    let added = stdout(&rofitodo(&todo, &["add", "(A) buy milk @store due:fri"]));
+    assert!(added.starts_with("3 (A) "), "{}", added);
+    assert!(added.contains(" buy milk @store due:20"), "{}", added);
+    assert_eq!(fs::read_to_string(&todo).unwrap().lines().count(), 3);
+
+    let listed = stdout(&rofitodo(&todo, &["list"]));
+    assert_eq!(listed.lines().map(|l| l.split(' ').next().unwrap()).collect::<Vec<_>>(), vec!["1", "2", "3"]);
+    // The numbers don't change with the filter and the sort order
+    assert_eq!(stdout(&rofitodo(&todo, &["list", "--filter", "+garden"])), "2 water plants +garden due:2024-05-01\n");
+    let sorted = stdout(&rofitodo(&todo, &["list", "--sort", "priority"]));
+    assert_eq!(sorted.lines().map(|l| l.split(' ').next().unwrap()).collect::<Vec<_>>(), vec!["3", "1", "2"]);
+
+    let done = stdout(&rofitodo(&todo, &["done", "2"]));
+    assert!(done.starts_with("2 x "), "{}", done);
+    assert_eq!(stdout(&rofitodo(&todo, &["list"])).lines().count(), 2);
+    assert_eq!(stdout(&rofitodo(&todo, &["list", "--all"])).lines().count(), 3);
+
+    assert_eq!(stdout(&rofitodo(&todo, &["rm", "1"])), "1 (B) 2024-01-02 call mom @phone\n");
+    assert!(!fs::read_to_string(&todo).unwrap().contains("call mom"));
+    assert!(fs::read_to_string(dir.join("trash.txt")).unwrap().starts_with("(B) 2024-01-02 call mom @phone deleted:"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn errors() {
+    let dir = temp_dir("errors");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "x 2024-01-03 done task\ncall mom\n").unwrap();
+    let before = fs::read_to_string(&todo).unwrap();
+
+    for args in [&["done", "5"][..], &["done", "0"], &["done", "1"], &["rm", "3"], &["add", " "], &["add", "x 2024-13-45 broken"]] {
+        let output = rofitodo(&todo, args);
+        assert!(!output.status.success(), "{:?}", args);
+        assert!(output.stdout.is_empty());
+        assert!(!output.stderr.is_empty());
+    }
+    assert_eq!(fs::read_to_string(&todo).unwrap(), before);
+    // Listing an empty result is not an error
+    assert_eq!(stdout(&rofitodo(&todo, &["list", "--filter", "+none"])), "");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn status() {
+    let dir = temp_dir("status");
+    let todo = dir.join("todo.txt");
+    assert_eq!(stdout(&rofitodo(&todo, &["status", "--format", "json"])), "{\"open\":0,\"overdue\":0,\"today\":0,\"top\":null}\n");
+    fs::write(&todo, "(A) pay \"rent\" due:2000-01-01\ncall mom\n").unwrap();
+    assert_eq!(stdout(&rofitodo(&todo, &["status", "--format", "json"])), "{\"open\":2,\"overdue\":1,\"today\":0,\"top\":\"(A) pay \\\"rent\\\"\"}\n");
+    assert_eq!(stdout(&rofitodo(&todo, &["status"])), "2 open, 1 overdue, 0 today | (A) pay \"rent\"\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn export_ics() {
+    let dir = temp_dir("export");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "(A) 2024-01-02 call mom, then dad @phone due:2024-01-10\nx 2024-01-05 2024-01-03 water plants\n").unwrap();
+    let printed = stdout(&rofitodo(&todo, &["export", "--format", "ics"]));
+    assert_eq!(printed.matches("BEGIN:VTODO").count(), 2);
+    assert!(printed.contains("SUMMARY:call mom\\, then dad @phone\r\nDUE;VALUE=DATE:20240110\r\nPRIORITY:1\r\n"), "{}", printed);
+
+    let ics = dir.join("todo.ics");
+    assert_eq!(stdout(&rofitodo(&todo, &["export", "--output", ics.to_str().unwrap()])), "");
+    let exported = fs::read_to_string(&ics).unwrap();
+    assert_eq!(exported.trim_end(), printed.trim_end());
+    // The tasks keep their UID from an export to the next one
+    let uids = |ics : &str| ics.lines().filter(|l| l.starts_with("UID:")).map(String::from).collect::<Vec<_>>();
+    stdout(&rofitodo(&todo, &["done", "1"]));
+    assert_eq!(uids(&stdout(&rofitodo(&todo, &["export"]))), uids(&exported));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn report() {
+    let dir = temp_dir("report");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "2024-01-02 call mom @phone +family due:2024-01-10\nfix the sink +house\n").unwrap();
+    fs::write(dir.join("done.txt"), "x 2024-01-05 2024-01-03 water plants +house\n").unwrap();
+    let printed = stdout(&rofitodo(&todo, &["report"]));
+    assert!(printed.starts_with("Open : 2, completed : 1, overdue : 1\n"), "{}", printed);
+    assert!(printed.contains("Open tasks per project :\n  +family 1\n  +house 1\n"), "{}", printed);
+    assert_eq!(printed.lines().filter(|l| l.starts_with("  20")).count(), 8);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn auto_archive() {
+    let dir = temp_dir("auto_archive");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "x 2020-01-05 2020-01-01 water plants\ncall mom\nx sell the bike\n").unwrap();
+    let output = rofitodo(&todo, &["--auto-archive-days", "30", "list"]);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "todo.txt : archived 1 tasks\n");
+    // The lines are numbered in the rewritten task file
+    assert_eq!(stdout(&output), "1 call mom\n");
+    assert_eq!(fs::read_to_string(dir.join("done.txt")).unwrap(), "x 2020-01-05 2020-01-01 water plants\n");
+    assert_eq!(fs::read_to_string(&todo).unwrap(), "call mom\nx sell the bike\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn git_autocommit() {
+    let dir = temp_dir("git");
+    let git = |args : &[&str]| Command::new("git").arg("-C").arg(&dir).args(args).output();
+    if git(&["init", "--quiet"]).map(|o| !o.status.success()).unwrap_or(true) {
+        return;
+    }
+    git(&["config", "user.name", "RofiTodo"]).unwrap();
+    git(&["config", "user.email", "rofitodo@example.com"]).unwrap();
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "").unwrap();
+    stdout(&rofitodo(&todo, &["--git-autocommit", "add", "pay rent"]));
+    stdout(&rofitodo(&todo, &["--git-autocommit", "add", "call mom"]));
+    stdout(&rofitodo(&todo, &["--git-autocommit", "done", "1"]));
+    // Without the flag nothing is committed
+    stdout(&rofitodo(&todo, &["add", "water plants"]));
+    stdout(&rofitodo(&todo, &["--git-autocommit", "--dry-run", "done", "2"]));
+    let log = String::from_utf8(git(&["log", "--format=%s"]).unwrap().stdout).unwrap();
+    assert_eq!(log, "rofitodo: complete 'pay rent'\nrofitodo: add 'call mom'\nrofitodo: add 'pay rent'\n");
+
+    // A directory which isn't a repository is only a warning
+    let other = temp_dir("no_git");
+    let output = rofitodo(&other.join("todo.txt"), &["--git-autocommit", "add", "pay rent"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Could not commit"));
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&other).unwrap();
+}
+
+#[test]
+fn encrypted_list() {
+    let dir = temp_dir("gpg");
+    // A fake gpg: the encrypted files start with a `FAKEGPG <key>` line
+    let gpg = dir.join("gpg");
+    fs::write(&gpg, "#!/bin/sh\nfor last; do :; done\ncase \" $* \" in\n\
+                     *\" --decrypt \"*) tail -n +2 \"$last\" ;;\n\
+                     *\" --list-packets \"*) echo \":pubkey enc packet: version 3, algo 1, keyid $(head -n 1 \"$last\" | cut -d ' ' -f 2)\" ;;\n\
+                     *\" --encrypt \"*) echo FAKEGPG KEY; cat ;;\nesac\n").unwrap();
+    Command::new("chmod").arg("+x").arg(&gpg).status().unwrap();
+    while let Err(e) = Command::new(&gpg).output() {
+        assert_eq!(e.kind(), std::io::ErrorKind::ExecutableFileBusy);
+    }
+    let todo = dir.join("todo.txt.gpg");
+    fs::write(&todo, "FAKEGPG KEY\ncall mom\n").unwrap();
+    let gpg = gpg.to_str().unwrap();
+    stdout(&rofitodo(&todo, &["--gpg-program", gpg, "add", "pay rent"]));
+    assert_eq!(stdout(&rofitodo(&todo, &["--gpg-program", gpg, "list"])).lines().count(), 2);
+    let saved = fs::read_to_string(&todo).unwrap();
+    assert!(saved.starts_with("FAKEGPG KEY\ncall mom\n") && saved.ends_with(" pay rent\n"), "{}", saved);
+
+    // The archive created for the first archived task is encrypted for the keys of the task file
+    fs::write(&todo, "FAKEGPG KEY\nx 2024-01-02 2024-01-01 call mom\npay rent\n").unwrap();
+    stdout(&rofitodo(&todo, &["--gpg-program", gpg, "--auto-archive-days", "0", "list"]));
+    assert_eq!(fs::read_to_string(dir.join("done.txt.gpg")).unwrap(), "FAKEGPG KEY\nx 2024-01-02 2024-01-01 call mom\n");
+    let saved = fs::read_to_string(&todo).unwrap();
+    assert_eq!(saved, "FAKEGPG KEY\npay rent\n");
+
+    let output = rofitodo(&todo, &["--gpg-program", "rofitodo_no_gpg", "add", "water plants"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("rofitodo_no_gpg is not installed"));
+    assert_eq!(fs::read_to_string(&todo).unwrap(), saved);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn read_only() {
+    let dir = temp_dir("read_only");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom\n").unwrap();
+    let output = rofitodo(&todo, &["--read-only", "add", "pay rent"]);
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr).trim(), "todo.txt is read-only");
+    assert_eq!(stdout(&rofitodo(&todo, &["--read-only", "list"])), "1 call mom\n");
+    // A file which can't be written is read-only without the flag
+    let mut permissions = fs::metadata(&todo).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&todo, permissions).unwrap();
+    assert!(!rofitodo(&todo, &["done", "1"]).status.success());
+    assert_eq!(fs::read_to_string(&todo).unwrap(), "call mom\n");
+    assert!(!dir.join("done.txt").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn export_csv() {
+    let dir = temp_dir("csv");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "(A) 2024-01-02 call mom, then dad @phone due:2024-01-10\nx 2024-01-05 2024-01-03 water plants\n").unwrap();
+    assert_eq!(stdout(&rofitodo(&todo, &["export", "--format", "csv", "--columns", "content,due,completed"])),
+               "content,due,completed\r\n\"call mom, then dad @phone\",2024-01-10,\r\nwater plants,,2024-01-05\n");
+    let output = rofitodo(&todo, &["export", "--format", "csv", "--columns", "content,tags"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown column tags, the columns are content, priority, due"));
+    assert!(!rofitodo(&todo, &["export", "--format", "md", "--columns", "content"]).status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn markdown() {
+    let dir = temp_dir("markdown");
+    let todo = dir.join("todo.txt");
+    fs::copy("tests/fixtures/tasks.txt", &todo).unwrap();
+    assert_eq!(stdout(&rofitodo(&todo, &["export", "--format", "md"])), include_str!("fixtures/export.md"));
+
+    // Nothing is added without a confirmation
+    let before = fs::read_to_string(&todo).unwrap();
+    stdout(&rofitodo(&todo, &["import", "--format", "md", "tests/fixtures/meeting.md"]));
+    assert_eq!(fs::read_to_string(&todo).unwrap(), before);
+    let imported = stdout(&rofitodo(&todo, &["--no-confirm", "import", "tests/fixtures/meeting.md"]));
+    assert_eq!(imported.lines().count(), 9);
+    assert!(imported.starts_with("6 "), "{}", imported);
+    assert!(imported.contains(" Kitchen: order the tiles +house\n"), "{}", imported);
+    assert_eq!(fs::read_to_string(&todo).unwrap().lines().count(), 14);
+
+    let empty = dir.join("empty.md");
+    fs::write(&empty, "# Nothing to do\n").unwrap();
+    assert!(!rofitodo(&todo, &["--no-confirm", "import", empty.to_str().unwrap()]).status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn import_taskwarrior() {
+    let dir = temp_dir("taskwarrior");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom due:2024-03-01\n").unwrap();
+    // The timestamps are at noon, the dates are the same in every time zone of the tests
+    let export = dir.join("export.json");
+    fs::write(&export, r#"[
+        {"description":"call mom","entry":"20240110T120000Z","due":"20240301T120000Z","status":"pending","uuid":"0001"},
+        {"description":"fix the sink","entry":"20240110T120000Z","priority":"M","project":"house","tags":["home"],"status":"pending","uuid":"0002"},
+        {"description":"sell the bike","entry":"20240110T120000Z","status":"completed","uuid":"0003"}
+    ]"#).unwrap();
+    let imported = stdout(&rofitodo(&todo, &["--no-confirm", "import", "--format", "taskwarrior", export.to_str().unwrap()]));
+    assert_eq!(imported, "2 (B) 2024-01-10 fix the sink +house @home\nSkipped 1 tasks already in the list\n\
+                          Could not read 1 entries :\n  0003: the completed task has no end date\n");
+    assert_eq!(fs::read_to_string(&todo).unwrap(), "call mom due:2024-03-01\n(B) 2024-01-10 fix the sink +house @home\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Run the menus of RofiTodo on a task file, answering them with `input`
+fn menus(todo : &Path, args : &[&str], input : &str, path : &str) -> Output {
+    use std::io::Write;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rofitodo")).arg("-c").arg(todo).arg("--backups").arg("0").arg("--start-view").arg("all")
+                        .args(args).env("PATH", path).env("XDG_CONFIG_HOME", todo.parent().unwrap())
+                        .stdin(std::process::Stdio::piped()).stdout(std::process::Stdio::piped()).stderr(std::process::Stdio::piped())
+                        .spawn().unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn add_from_stdin() {
+    use std::io::Write;
+    let dir = temp_dir("add_stdin");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom\n").unwrap();
+    let add = |input : &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rofitodo")).arg("-c").arg(&todo).arg("--backups").arg("0").args(["add", "-"])
+                            .env("XDG_CONFIG_HOME", &dir)
+                            .stdin(std::process::Stdio::piped()).stdout(std::process::Stdio::piped()).stderr(std::process::Stdio::piped())
+                            .spawn().unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    };
+    let added = stdout(&add("(A) reply to\nthe landlord @home\n"));
+    assert!(added.starts_with("2 (A) ") && added.ends_with(" reply to the landlord @home\n"), "{}", added);
+    let output = add(" \n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "The standard input is empty, no task added\n");
+    assert_eq!(fs::read_to_string(&todo).unwrap().lines().count(), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn stdio_menus() {
+    let dir = temp_dir("stdio");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom\n").unwrap();
+    // Add a task, then the end of the input closes the main menu
+    let output = stdout(&menus(&todo, &["--stdio"], "+ add\nbuy milk\n1\n", ""));
+    assert!(output.contains("  1 + add\n"), "{}", output);
+    assert!(output.contains("Task> "), "{}", output);
+    assert!(fs::read_to_string(&todo).unwrap().lines().last().unwrap().ends_with(" buy milk"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn locked_file() {
+    let dir = temp_dir("locked");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom\n").unwrap();
+    // This test is the other RofiTodo holding the lock
+    fs::write(dir.join("todo.txt.lock"), format!("{}\n", std::process::id())).unwrap();
+    let lock = fs::File::open(dir.join("todo.txt.lock")).unwrap();
+    lock.lock().unwrap();
+    let output = menus(&todo, &["--stdio"], "+ add\nbuy milk\n1\n", "");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("todo.txt is open in another RofiTodo (process "));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("the changes won't be saved"));
+    assert_eq!(fs::read_to_string(&todo).unwrap(), "call mom\n");
+    // The lock of a process which is not running anymore is taken over and emptied at exit
+    drop(lock);
+    fs::write(dir.join("todo.txt.lock"), "4194305\n").unwrap();
+    stdout(&menus(&todo, &["--stdio"], "+ add\nbuy milk\n1\n", ""));
+    assert!(fs::read_to_string(&todo).unwrap().contains("buy milk"));
+    assert_eq!(fs::read_to_string(dir.join("todo.txt.lock")).unwrap(), "");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn missing_launcher() {
+    let dir = temp_dir("missing");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom\n").unwrap();
+    for args in [&[][..], &["--backend", "fuzzel"]] {
+        let output = menus(&todo, args, "", dir.to_str().unwrap());
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("not installed"));
+    }
+    // The commands don't need a launcher
+    assert_eq!(stdout(&menus(&todo, &["list"], "", "")), "1 call mom\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn stopped_launcher() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = temp_dir("stopped");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom\n").unwrap();
+    // A rofi adding a task, then killed when the main menu is shown again
+    let rofi = dir.join("rofi");
+    fs::write(&rofi, format!("#!/bin/sh\ncat > /dev/null\nn=$(cat {0}/count 2>/dev/null || echo 0)\necho $((n + 1)) > {0}/count\n\
+                              case $n in 0) echo 0;; 1) echo '-1 buy milk';; 2) echo '0 ';; *) kill -9 $$;; esac\n", dir.display())).unwrap();
+    fs::set_permissions(&rofi, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = menus(&todo, &[], "", &format!("{}:/usr/bin:/bin", dir.display()));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "rofi was stopped by a signal\n");
+    assert!(fs::read_to_string(&todo).unwrap().contains("buy milk"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn config_file() {
+    let dir = temp_dir("config");
+    let todo = dir.join("todo.txt");
+    let configured = dir.join("configured.txt");
+    fs::write(&todo, "call mom\n").unwrap();
+    fs::write(&configured, "water plants\n").unwrap();
+    fs::create_dir(dir.join("rofitodo")).unwrap();
+    let config = dir.join("rofitodo").join("config.toml");
+    let run = |args : &[&str]| Command::new(env!("CARGO_BIN_EXE_rofitodo")).args(args).env("XDG_CONFIG_HOME", &dir).output().unwrap();
+
+    fs::write(&config, format!("todo = \"{}\"\nbackups = 0\ntheme = \"dark\"\n", configured.display())).unwrap();
+    let output = run(&["list"]);
+    assert_eq!(stdout(&output), "1 water plants\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("config.toml : unknown key `theme`, it is ignored"));
+    // The command line wins over the configuration file
+    assert_eq!(stdout(&run(&["-c", todo.to_str().unwrap(), "list"])), "1 call mom\n");
+
+    fs::write(&config, "backups = \"many\"\n").unwrap();
+    let output = run(&["list"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 | backups = \"many\""));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn default_locations() {
+    let dir = temp_dir("locations");
+    let run = |home : &Path, args : &[&str], file : Option<&Path>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_rofitodo"));
+        command.args(args).env("HOME", home).env("XDG_CONFIG_HOME", home).env_remove("XDG_DATA_HOME").env_remove("ROFITODO_FILE");
+        if let Some(file) = file {
+            command.env("ROFITODO_FILE", file);
+        }
+        command.output().unwrap()
+    };
+    // The task file of the data directory is created on the first run
+    stdout(&run(&dir, &["--backups", "0", "add", "call mom"], None));
+    let data_file = dir.join(".local/share/rofitodo/todo.txt");
+    assert!(fs::read_to_string(&data_file).unwrap().ends_with(" call mom\n"));
+    let output = run(&dir, &["--verbose", "list"], None);
+    assert!(String::from_utf8_lossy(&output.stderr).contains(&format!("Task file : {} (the task file of the data directory)", data_file.display())));
+
+    // $ROFITODO_FILE, then --file, win over the default file
+    let other = dir.join("other.txt");
+    fs::write(&other, "water plants\n").unwrap();
+    assert_eq!(stdout(&run(&dir, &["list"], Some(&other))), "1 water plants\n");
+    assert!(stdout(&run(&dir, &["--file", data_file.to_str().unwrap(), "list"], Some(&other))).contains("call mom"));
+
+    // ~/todo.txt is used if it exists and the task file of the data directory doesn't
+    let home = dir.join("home");
+    fs::create_dir(&home).unwrap();
+    fs::write(home.join("todo.txt"), "buy milk\n").unwrap();
+    assert_eq!(stdout(&run(&home, &["list"], None)), "1 buy milk\n");
+    assert!(!home.join(".local").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dry_run() {
+    let dir = temp_dir("dry_run");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom\nwater plants\n").unwrap();
+    let run = |args : &[&str]| Command::new(env!("CARGO_BIN_EXE_rofitodo")).arg("-c").arg(&todo).arg("--dry-run").args(args)
+                                   .env("XDG_CONFIG_HOME", &dir).output().unwrap();
+    assert!(stdout(&run(&["add", "buy milk"])).starts_with("3 "));
+    assert!(stdout(&run(&["done", "1"])).starts_with("1 x "));
+    assert_eq!(stdout(&run(&["rm", "2"])), "2 water plants\n");
+    let output = menus(&todo, &["--dry-run", "--stdio"], "+ add\nbuy milk\n1\n", "");
+    assert!(output.status.success());
+    // Nothing is written, not even the backups and the trash
+    assert_eq!(fs::read_to_string(&todo).unwrap(), "call mom\nwater plants\n");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalid_options() {
+    let dir = temp_dir("options");
+    let todo = dir.join("todo.txt");
+    let stderr = |output : Output| {
+        assert!(!output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    };
+    let error = stderr(rofitodo(&todo, &["--sort", "size"]));
+    assert!(error.contains("[possible values: completion, content, creation, due, estimate, priority, urgency]"), "{}", error);
+    assert!(stderr(rofitodo(&todo, &["--backend", "xmenu"])).contains("possible values"));
+    assert_eq!(stderr(menus(&todo, &["--stdio", "--view", "work"], "", "")), "There is no view 'work', the saved views are : none\n");
+    let version = stdout(&rofitodo(&todo, &["--version"]));
+    assert_eq!(version, format!("rofitodo {}\n", env!("CARGO_PKG_VERSION")));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn audit_log() {
+    let dir = temp_dir("audit_log");
+    let todo = dir.join("todo.txt");
+    let log = dir.join("rofitodo.log");
+    fs::write(&todo, "pay rent\n").unwrap();
+    let log_arg = log.to_str().unwrap();
+    stdout(&rofitodo(&todo, &["--audit-log", log_arg, "add", "(A) call plumber due:2024-05-10"]));
+    stdout(&rofitodo(&todo, &["--audit-log", log_arg, "done", "1"]));
+    stdout(&rofitodo(&todo, &["--audit-log", log_arg, "rm", "2"]));
+    // Reading the tasks is not logged
+    stdout(&rofitodo(&todo, &["--audit-log", log_arg, "list"]));
+
+    let printed = stdout(&rofitodo(&todo, &["--audit-log", log_arg, "log", "--since", "2024-05-01"]));
+    let changes = printed.lines().map(|l| l.split_once(' ').unwrap().1).collect::<Vec<_>>();
+    assert!(changes[0].starts_with("add \"(A) 20"), "{}", printed);
+    assert!(changes[0].ends_with(" call plumber due:2024-05-10\""), "{}", printed);
+    assert_eq!(changes[1], "complete \"pay rent\"");
+    assert!(changes[2].starts_with("delete \"(A) 20"), "{}", printed);
+    assert_eq!(changes.len(), 3);
+    assert_eq!(fs::read_to_string(&log).unwrap(), printed);
+    // No change after that day
+    assert_eq!(stdout(&rofitodo(&todo, &["--audit-log", log_arg, "log", "--since", "2999-01-01"])), "");
+    assert!(!rofitodo(&todo, &["log"]).status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dependencies() {
+    let dir = temp_dir("dependencies");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "(A) paint walls dep:1\nbuy paint id:1\nclean brushes dep:1,9\n").unwrap();
+    // The blocked task is not the most urgent one
+    assert_eq!(stdout(&rofitodo(&todo, &["status"])), "3 open, 0 overdue, 0 today | buy paint\n");
+    assert_eq!(stdout(&rofitodo(&todo, &["--show-blocked", "status"])), "3 open, 0 overdue, 0 today | (A) paint walls\n");
+
+    let done = stdout(&rofitodo(&todo, &["done", "2"]));
+    let lines = done.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("2 x "), "{}", done);
+    // The unknown task 9 doesn't block the third task
+    assert_eq!(&lines[1..], ["Unblocked : 1 (A) paint walls dep:1", "Unblocked : 3 clean brushes dep:1,9"]);
+    assert_eq!(stdout(&rofitodo(&todo, &["status"])), "2 open, 0 overdue, 0 today | (A) paint walls\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn row_format() {
+    let dir = temp_dir("row_format");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "(B) call mom +family due:2099-05-06\nwater plants\n").unwrap();
+    let output = stdout(&menus(&todo, &["--stdio", "--row-format", "{content}[ ({due:%d %b})] {priority}"], "", ""));
+    assert!(output.contains(" call mom +family (06 May) (B)\n"), "{}", output);
+    assert!(output.contains(" water plants\n"), "{}", output);
+    let output = menus(&todo, &["--stdio", "--row-format", "{content} {size}"], "", "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown placeholder `{size}`"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn localized_menus() {
+    let dir = temp_dir("localized");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "call mom due:2099-05-06\n").unwrap();
+    let output = stdout(&menus(&todo, &["--stdio", "--lang", "de", "--date-format", "%d.%m.%Y"], "", ""));
+    assert!(output.contains("  1 + hinzufügen\n"), "{}", output);
+    assert!(output.contains(" 06.05.2099 : call mom\n"), "{}", output);
+    assert!(output.contains("  7 ~ Sammelaktionen\n") && output.ends_with("Aufgaben ⇅ content> "), "{}", output);
+    // The translations of the configuration replace the texts of the language, the others stay in German
+    fs::create_dir(dir.join("rofitodo")).unwrap();
+    fs::write(dir.join("rofitodo").join("config.toml"), "lang = \"de\"\ntranslations = \"de.toml\"\n").unwrap();
+    fs::write(dir.join("rofitodo").join("de.toml"), "[menu]\nadd = \"+ neu\"\nfly = \"fliegen\"\n").unwrap();
+    let output = menus(&todo, &["--stdio"], "+ neu\nbuy milk\n1\n", "");
+    assert!(stdout(&output).contains("  1 + neu\n"), "{}", stdout(&output));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("de.toml : unknown text `menu.fly`, it is ignored"));
+    // The new task is written with ISO dates
+    assert_eq!(fs::read_to_string(&todo).unwrap().lines().next(), Some("call mom due:2099-05-06"));
+    assert!(fs::read_to_string(&todo).unwrap().lines().last().unwrap().ends_with(" buy milk"));
+    let output = menus(&todo, &["--stdio", "--date-format", "%Q"], "", "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`%Q` is not a date format"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn numbered_rows() {
+    let dir = temp_dir("numbered_rows");
+    let todo = dir.join("todo.txt");
+    fs::write(&todo, "wash car\n(B) renew passport\n").unwrap();
+    // The rows are numbered in the order of the menu, a number typed to add a task opens this task
+    let output = stdout(&menus(&todo, &["--stdio", "--numbered-rows", "--sort", "content"], "+ add\n1\n", ""));
+    assert!(output.contains(" 1 │ (B) renew passport\n") && output.contains(" 2 │ wash car\n"), "{}", output);
+    assert!(output.contains("✔ mark as done"), "{}", output);
+    assert_eq!(fs::read_to_string(&todo).unwrap(), "wash car\n(B) renew passport\n");
+    // `done` takes the number shown by `list`, whatever the sort order
+    let listed = stdout(&rofitodo(&todo, &["list", "--sort", "priority"]));
+    let first = listed.lines().next().unwrap();
+    assert!(first.ends_with("(B) renew passport"), "{}", listed);
+    let number = first.split(' ').next().unwrap();
+    assert!(stdout(&rofitodo(&todo, &["done", number])).contains("x "));
+    assert!(fs::read_to_string(&todo).unwrap().lines().any(|l| l.starts_with("x ") && l.contains("renew passport")));
+    fs::remove_dir_all(&dir).unwrap();
+}